@@ -8,8 +8,8 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     let mut bufmgr = BufferPoolManager::new(disk, pool);
 
     let btree = BTree::new(PageId(0));
-    let mut iter = btree.search(&mut bufmgr, SearchMode::Start)?;
-    while let Some((key, value)) = iter.next(&mut bufmgr)? {
+    for pair in btree.cursor(&mut bufmgr, SearchMode::Start)? {
+        let (key, value) = pair?;
         println!("{:02x?} = {:02x?}", key, value);
     }
 
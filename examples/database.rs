@@ -0,0 +1,32 @@
+use putty_db::sql::Value;
+use putty_db::{Database, Options};
+use std::error::Error;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut db = Database::open("database.db", Options::default())?;
+
+    if db.catalog().table("users").is_none() {
+        db.execute(
+            "CREATE TABLE users (id INT PRIMARY KEY, first_name TEXT, last_name TEXT);
+             INSERT INTO users VALUES
+                 (1, 'Charlie', 'MUNGER'), (2, 'Brian', 'LEE'), (3, 'Alice', 'SMITH');",
+        )?;
+    }
+
+    let mut users = db.table("users")?;
+    if users.get(&[Value::Int(4)])?.is_none() {
+        users.insert(&[
+            Value::Int(4),
+            Value::Text("John".to_string()),
+            Value::Text("BAKERY".to_string()),
+        ])?;
+    }
+
+    let rows = db.query("SELECT first_name, last_name FROM users WHERE id >= 2 ORDER BY id")?;
+    println!("{rows}");
+    for row in rows.iter() {
+        println!("{:?}", row.values());
+    }
+
+    Ok(db.close()?)
+}
@@ -15,10 +15,12 @@ fn main() -> Result<(), Box<dyn Error>> {
         table_meta_page_id: PageId(0),
         search_mode: TupleSearchMode::Key(&[b"a"]),
         while_cond: &|pk| pk[0].as_slice() < b"e",
+        condition: "(#0 < 'e')",
     };
     let query_plan = Filter {
         inner_plan: &query_plan,
-        cond: &|record| record[1].as_slice() < b"John",
+        cond: &|record| Ok(record[1].as_slice() < b"John"),
+        condition: "(#1 < 'John')",
     };
 
     let mut exec = query_plan.start(&mut bufmgr)?;
@@ -0,0 +1,25 @@
+//! A server accepting PostgreSQL clients, such as `psql`, over a database file.
+//!
+//! Usage: `putty-server <database file> [address]`. The file is created if it does not exist,
+//! and the address defaults to `127.0.0.1:5432`.
+
+use std::env;
+use std::error::Error;
+use std::net::TcpListener;
+
+use putty_db::pgwire::Server;
+use putty_db::{Database, Options};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut args = env::args().skip(1);
+    let path = args
+        .next()
+        .ok_or("usage: putty-server <database file> [address]")?;
+    let addr = args.next().unwrap_or_else(|| "127.0.0.1:5432".to_string());
+    let database = Database::open(&path, Options::default())?;
+
+    let listener = TcpListener::bind(&addr)?;
+    eprintln!("listening on {}", listener.local_addr()?);
+    Server::new(database).serve(&listener)?;
+    Ok(())
+}
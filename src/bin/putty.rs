@@ -0,0 +1,141 @@
+//! An interactive SQL shell over a database file.
+//!
+//! Usage: `putty <database file>`. The file is created if it does not exist. Statements end with
+//! a semicolon and may span several lines; lines starting with a dot are meta-commands, listed
+//! by `.help`.
+
+use std::env;
+use std::error::Error;
+use std::path::PathBuf;
+
+use putty_db::sql::{self, ast::Statement, Output};
+use putty_db::{Database, Options};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+
+const HELP: &str = "\
+.tables            List the tables
+.schema [TABLE]    Show the statements creating TABLE, or every table
+.explain QUERY     Show the plan chosen for QUERY
+.help              Show this message
+.quit              Exit";
+
+struct Shell {
+    database: Database,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let path = env::args().nth(1).ok_or("usage: putty <database file>")?;
+    let database = Database::open(&path, Options::default())?;
+    let mut shell = Shell { database };
+
+    let mut editor = DefaultEditor::new()?;
+    let history = env::var_os("HOME").map(|home| PathBuf::from(home).join(".putty_history"));
+    if let Some(history) = &history {
+        // There is no history before the first session.
+        let _ = editor.load_history(history);
+    }
+    let mut sql = String::new();
+    loop {
+        let prompt = if sql.is_empty() { "putty> " } else { "  ...> " };
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => {
+                sql.clear();
+                continue;
+            }
+            Err(ReadlineError::Eof) => break,
+            Err(err) => return Err(err.into()),
+        };
+        if sql.is_empty() {
+            let command = line.trim();
+            if command.is_empty() {
+                continue;
+            }
+            if command.starts_with('.') {
+                editor.add_history_entry(command)?;
+                match shell.meta_command(command) {
+                    Ok(true) => continue,
+                    Ok(false) => break,
+                    Err(err) => {
+                        eprintln!("error: {err}");
+                        continue;
+                    }
+                }
+            }
+        }
+        sql += &line;
+        sql.push('\n');
+        if sql::is_complete(&sql) {
+            editor.add_history_entry(sql.trim_end())?;
+            if let Err(err) = shell.run(&sql) {
+                eprintln!("error: {err}");
+            }
+            sql.clear();
+        }
+    }
+    if let Some(history) = &history {
+        editor.save_history(history)?;
+    }
+    Ok(shell.database.close()?)
+}
+
+impl Shell {
+    /// Runs the statements in `sql`, stopping at the first error, and writes the changes to
+    /// the file.
+    fn run(&mut self, sql: &str) -> Result<(), Box<dyn Error>> {
+        for statement in sql::parse_statements(sql)? {
+            self.execute(&statement)?;
+        }
+        Ok(self.database.flush()?)
+    }
+
+    fn execute(&mut self, statement: &Statement) -> Result<(), Box<dyn Error>> {
+        match self.database.run(statement)? {
+            Output::Rows(result_set) => println!("{result_set}\n"),
+            Output::Affected(1) => println!("1 row affected"),
+            Output::Affected(n) => println!("{n} rows affected"),
+            Output::Done => println!("OK"),
+        }
+        Ok(())
+    }
+
+    /// Runs a meta-command, returning whether the shell should go on.
+    fn meta_command(&mut self, command: &str) -> Result<bool, Box<dyn Error>> {
+        let (name, arg) = command.split_once(' ').unwrap_or((command, ""));
+        let arg = arg.trim();
+        match name {
+            ".tables" => {
+                for info in &self.database.catalog().tables {
+                    println!("{}", info.name);
+                }
+            }
+            ".schema" if arg.is_empty() => {
+                for info in &self.database.catalog().tables {
+                    println!("{}", sql::schema(info));
+                }
+            }
+            ".schema" => {
+                let info = self
+                    .database
+                    .catalog()
+                    .table(arg)
+                    .ok_or_else(|| format!("table {arg} does not exist"))?;
+                println!("{}", sql::schema(info));
+            }
+            ".explain" => {
+                let Statement::Select(select) = sql::parse(arg)? else {
+                    return Err("usage: .explain SELECT ...".into());
+                };
+                self.execute(&Statement::Explain {
+                    analyze: false,
+                    select,
+                })?;
+            }
+            ".help" => println!("{HELP}"),
+            ".quit" | ".exit" => return Ok(false),
+            _ => return Err(format!("unknown command {name}, see .help").into()),
+        }
+        Ok(true)
+    }
+}
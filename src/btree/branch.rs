@@ -57,9 +57,38 @@ impl<B: ByteSlice> Branch<B> {
         Pair::from_bytes(&self.body[slot_id])
     }
 
+    /// Returns the key and left child of every pair, checking the slots instead of trusting
+    /// them as a possibly corrupted page requires. Fails with the slot id of the first
+    /// unreadable pair.
+    pub fn checked_pairs(&self) -> Result<Vec<(&[u8], PageId)>, usize> {
+        (0..self.num_pairs())
+            .map(|slot_id| {
+                let pair = self
+                    .body
+                    .checked_get(slot_id)
+                    .and_then(Pair::try_from_bytes)
+                    .ok_or(slot_id)?;
+                let child = PageId::read_from(pair.value).ok_or(slot_id)?;
+                Ok((pair.key, child))
+            })
+            .collect()
+    }
+
+    pub fn right_child(&self) -> PageId {
+        self.header.right_child
+    }
+
     pub fn max_pair_size(&self) -> usize {
         self.body.capacity() / 2 - size_of::<Pointer>()
     }
+
+    pub fn capacity(&self) -> usize {
+        self.body.capacity()
+    }
+
+    pub fn free_space(&self) -> usize {
+        self.body.free_space()
+    }
 }
 
 impl<B: ByteSliceMut> Branch<B> {
@@ -79,6 +108,15 @@ impl<B: ByteSliceMut> Branch<B> {
         key_vec
     }
 
+    /// Appends `page_id` as the new right child. `key` is the smallest key of the new child
+    /// and becomes the separator in front of the current right child.
+    #[must_use = "insertion may fail"]
+    pub fn push_right_child(&mut self, key: &[u8], page_id: PageId) -> Option<()> {
+        self.insert(self.num_pairs(), key, self.header.right_child)?;
+        self.header.right_child = page_id;
+        Some(())
+    }
+
     #[must_use = "insertion may fail"]
     pub fn insert(&mut self, slot_id: usize, key: &[u8], page_id: PageId) -> Option<()> {
         let pair = Pair {
@@ -92,49 +130,69 @@ impl<B: ByteSliceMut> Branch<B> {
         Some(())
     }
 
-    fn is_half_full(&self) -> bool {
-        self.body.free_space() * 2 < self.body.capacity()
-    }
-
+    /// Splits the full branch, moving the smaller keys to `new_branch`, and returns the key
+    /// separating the two, which moves up to the parent.
+    ///
+    /// Among the split points leaving both branches at least a quarter full, the one moving up
+    /// the shortest key is chosen, which keeps the keys of the parent short.
     pub fn split_insert(
         &mut self,
         new_branch: &mut Branch<impl ByteSliceMut>,
         new_key: &[u8],
         new_page_id: PageId,
     ) -> Vec<u8> {
+        let index = self
+            .search_slot_id(new_key)
+            .expect_err("key must be unique");
+        let mut pairs: Vec<(Vec<u8>, PageId)> = (0..self.num_pairs())
+            .map(|slot_id| {
+                let Pair { key, value } = self.pair_at(slot_id);
+                (key.to_vec(), value.into())
+            })
+            .collect();
+        pairs.insert(index, (new_key.to_vec(), new_page_id));
+
+        // `ends[at]` is the size of the pairs before `at`.
+        let ends: Vec<usize> = std::iter::once(0)
+            .chain(pairs.iter().scan(0, |end, (key, _)| {
+                *end += Pair::encoded_size(key.len(), size_of::<PageId>()) + size_of::<Pointer>();
+                Some(*end)
+            }))
+            .collect();
+        let total = ends[pairs.len()];
+        let capacity = self.capacity();
+        // The key at `at` moves up, and its child becomes the right child of `new_branch`.
+        let candidates: Vec<(usize, usize, usize)> = (1..pairs.len() - 1)
+            .map(|at| (at, ends[at], total - ends[at + 1]))
+            .filter(|&(_, left, right)| left <= capacity && right <= capacity)
+            .collect();
+        let balanced = candidates
+            .iter()
+            .filter(|&&(_, left, right)| left.min(right) >= capacity / 4)
+            .min_by_key(|&&(at, left, right)| (pairs[at].0.len(), left.max(right)));
+        let &(split_at, _, _) = balanced
+            .or_else(|| {
+                candidates
+                    .iter()
+                    .min_by_key(|&&(_, left, right)| left.max(right))
+            })
+            .expect("split branches must have space");
+
+        let right_child = self.header.right_child;
         new_branch.body.initialize();
-        loop {
-            if new_branch.is_half_full() {
-                let index = self
-                    .search_slot_id(new_key)
-                    .expect_err("key must be unique");
-                self.insert(index, new_key, new_page_id)
-                    .expect("old branch must have space");
-                break;
-            }
-            if self.pair_at(0).key < new_key {
-                self.transfer(new_branch);
-            } else {
-                new_branch
-                    .insert(new_branch.num_pairs(), new_key, new_page_id)
-                    .expect("new branch must have space");
-                while !new_branch.is_half_full() {
-                    self.transfer(new_branch);
-                }
-                break;
-            }
+        for (slot_id, (key, page_id)) in pairs[..split_at].iter().enumerate() {
+            new_branch
+                .insert(slot_id, key, *page_id)
+                .expect("new branch must have space");
         }
-        new_branch.fill_right_child()
-    }
-
-    pub fn transfer(&mut self, dest: &mut Branch<impl ByteSliceMut>) {
-        let next_index = dest.num_pairs();
-        let pair = &self.body[0];
-        dest.body
-            .insert(next_index, pair.len())
-            .expect("dest branch must have space");
-        dest.body[next_index].copy_from_slice(pair);
-        self.body.remove(0);
+        new_branch.header.right_child = pairs[split_at].1;
+        self.body.initialize();
+        for (slot_id, (key, page_id)) in pairs[split_at + 1..].iter().enumerate() {
+            self.insert(slot_id, key, *page_id)
+                .expect("old branch must have space");
+        }
+        self.header.right_child = right_child;
+        pairs.swap_remove(split_at).0
     }
 }
 
@@ -0,0 +1,390 @@
+use crate::btree::branch::Branch;
+use crate::btree::error::BTreeError;
+use crate::btree::leaf::Leaf;
+use crate::btree::node::{self, Node};
+use crate::btree::pair::Pair;
+use crate::btree::prefix::shortest_separator;
+use crate::btree::{meta, BTree};
+use crate::buffer::{Buffer, BufferPoolManager};
+use crate::disk::PageId;
+use crate::slotted::Pointer;
+use std::cmp::Ordering;
+use std::mem::size_of;
+use std::rc::Rc;
+use zerocopy::AsBytes;
+
+/// The pages of a tree built by [`BTree::prepare_load`], which are not reachable from the tree
+/// until [`BTree::finish_load`] points its meta page at them.
+pub struct PendingLoad {
+    root_page_id: Option<PageId>,
+}
+
+impl BTree {
+    /// Builds a new tree from pairs sorted by key in ascending order.
+    ///
+    /// Pages are filled bottom-up up to `fill_factor` (in `(0, 1]`) of their capacity, so that
+    /// later insertions have room before splitting.
+    pub fn bulk_load(
+        bufmgr: &mut BufferPoolManager,
+        pairs: impl IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+        fill_factor: f64,
+    ) -> Result<Self, BTreeError> {
+        let btree = Self::create(bufmgr)?;
+        btree.load(bufmgr, pairs, fill_factor)?;
+        Ok(btree)
+    }
+
+    /// Fills an empty tree from pairs sorted by key in ascending order. See [`BTree::bulk_load`].
+    ///
+    /// The pairs are streamed into new pages that become part of the tree only once all of them
+    /// are written, so unsorted or duplicate keys and keys too large leave the tree empty.
+    pub fn load(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        pairs: impl IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+        fill_factor: f64,
+    ) -> Result<(), BTreeError> {
+        let pending = self.prepare_load(bufmgr, pairs, fill_factor)?;
+        self.finish_load(bufmgr, pending)
+    }
+
+    /// Writes the pages [`load`](Self::load) would fill the empty tree with, without making
+    /// them part of it, so that several trees can be loaded all or nothing. The pages written
+    /// are left unused if this fails or the result is dropped.
+    pub fn prepare_load(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        pairs: impl IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+        fill_factor: f64,
+    ) -> Result<PendingLoad, BTreeError> {
+        if !(fill_factor > 0.0 && fill_factor <= 1.0) {
+            return Err(BTreeError::InvalidArgument(format!(
+                "fill factor {fill_factor} is not in (0, 1]"
+            )));
+        }
+        let meta_buffer = bufmgr.fetch_page(self.meta_page_id)?;
+        let root_page_id = {
+            let meta_page = meta_buffer.page.borrow();
+            meta::Meta::new(meta_page.as_slice()).root_page_id()?
+        };
+        let root_buffer = bufmgr.fetch_page(root_page_id)?;
+        {
+            let root_page = root_buffer.page.borrow();
+            let root = Node::new(root_page.as_slice());
+            match node::Body::new(root.header.node_type, root.body) {
+                node::Body::Leaf(leaf) if leaf.num_pairs() == 0 => {}
+                _ => return Err(BTreeError::NotEmpty),
+            }
+        }
+
+        let mut level = Self::build_leaves(bufmgr, pairs, fill_factor)?;
+        while level.len() > 1 {
+            level = Self::build_branches(bufmgr, level, fill_factor)?;
+        }
+        Ok(PendingLoad {
+            root_page_id: level.first().map(|(_, page_id)| *page_id),
+        })
+    }
+
+    /// Makes the pages written by [`prepare_load`](Self::prepare_load) the tree. The empty
+    /// root leaf they replace is left unused.
+    pub fn finish_load(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        pending: PendingLoad,
+    ) -> Result<(), BTreeError> {
+        let Some(root_page_id) = pending.root_page_id else {
+            return Ok(());
+        };
+        let meta_buffer = bufmgr.fetch_page(self.meta_page_id)?;
+        let mut meta_page = meta_buffer.page.borrow_mut();
+        let mut meta = meta::Meta::new(meta_page.as_mut_slice());
+        meta.header.root_page_id = root_page_id;
+        meta_buffer.is_dirty.set(true);
+        Ok(())
+    }
+
+    /// Fills new leaves with pairs, checking as they come that their keys are sorted and
+    /// unique, and returns the page id of each leaf with the shortest key separating it from
+    /// the previous one.
+    fn build_leaves(
+        bufmgr: &mut BufferPoolManager,
+        pairs: impl IntoIterator<Item = (Vec<u8>, Vec<u8>)>,
+        fill_factor: f64,
+    ) -> Result<Vec<(Vec<u8>, PageId)>, BTreeError> {
+        let mut leaves = vec![];
+        let mut leaf_buffer: Option<Rc<Buffer>> = None;
+        let mut prev_key: Option<Vec<u8>> = None;
+        for (key, value) in pairs {
+            if let Some(prev_key) = &prev_key {
+                match prev_key.cmp(&key) {
+                    Ordering::Less => {}
+                    Ordering::Equal => return Err(BTreeError::DuplicateKey),
+                    Ordering::Greater => return Err(BTreeError::UnsortedInput),
+                }
+            }
+            let buffer = match leaf_buffer.take() {
+                Some(buffer) => buffer,
+                None => {
+                    let buffer = Self::create_leaf(bufmgr, None)?;
+                    leaves.push((vec![], buffer.page_id));
+                    buffer
+                }
+            };
+            let max_pair_size = {
+                let page = buffer.page.borrow();
+                Leaf::new(Node::new(page.as_slice()).body).max_pair_size()
+            };
+            let value = Self::encode_value(bufmgr, &key, &value, max_pair_size)?;
+
+            let pushed = {
+                let mut page = buffer.page.borrow_mut();
+                let mut leaf = Leaf::new(Node::new(page.as_mut_slice()).body);
+                let max_used = match leaf.num_pairs() {
+                    0 => leaf.capacity(),
+                    _ => (leaf.capacity() as f64 * fill_factor) as usize,
+                };
+                leaf.insert_within(leaf.num_pairs(), &key, &value, max_used)
+                    .is_some()
+            };
+            buffer.is_dirty.set(true);
+            if pushed {
+                leaf_buffer = Some(buffer);
+            } else {
+                let new_buffer = Self::create_leaf(bufmgr, Some(&buffer))?;
+                let mut new_page = new_buffer.page.borrow_mut();
+                Leaf::new(Node::new(new_page.as_mut_slice()).body)
+                    .insert(0, &key, &value)
+                    .expect("new leaf must have space");
+                drop(new_page);
+                let prev_key = prev_key.as_ref().expect("first leaf must take a pair");
+                leaves.push((shortest_separator(prev_key, &key), new_buffer.page_id));
+                leaf_buffer = Some(new_buffer);
+            }
+            prev_key = Some(key);
+        }
+        Ok(leaves)
+    }
+
+    /// Creates an empty leaf, linked after `prev_leaf_buffer` if any.
+    fn create_leaf(
+        bufmgr: &mut BufferPoolManager,
+        prev_leaf_buffer: Option<&Rc<Buffer>>,
+    ) -> Result<Rc<Buffer>, BTreeError> {
+        let buffer = bufmgr.create_page()?;
+        let mut page = buffer.page.borrow_mut();
+        let mut node = Node::new(page.as_mut_slice());
+        node.initialize_as_leaf();
+        let mut leaf = Leaf::new(node.body);
+        leaf.initialize();
+        if let Some(prev_leaf_buffer) = prev_leaf_buffer {
+            let mut prev_page = prev_leaf_buffer.page.borrow_mut();
+            Leaf::new(Node::new(prev_page.as_mut_slice()).body)
+                .set_next_page_id(Some(buffer.page_id));
+            prev_leaf_buffer.is_dirty.set(true);
+            leaf.set_prev_page_id(Some(prev_leaf_buffer.page_id));
+        }
+        buffer.is_dirty.set(true);
+        drop(page);
+        Ok(buffer)
+    }
+
+    /// Builds one level of branches over `children` and returns the smallest key and the page id
+    /// of each branch.
+    fn build_branches(
+        bufmgr: &mut BufferPoolManager,
+        children: Vec<(Vec<u8>, PageId)>,
+        fill_factor: f64,
+    ) -> Result<Vec<(Vec<u8>, PageId)>, BTreeError> {
+        let mut branches: Vec<(Vec<u8>, PageId)> = vec![];
+        let mut branch_buffer: Option<Rc<Buffer>> = None;
+        let mut pending: Option<(Vec<u8>, PageId)> = None;
+        for (key, page_id) in children {
+            if let Some(buffer) = &branch_buffer {
+                let mut page = buffer.page.borrow_mut();
+                let mut branch = Branch::new(Node::new(page.as_mut_slice()).body);
+                let max_used = (branch.capacity() as f64 * fill_factor) as usize;
+                let used =
+                    branch.capacity() - branch.free_space() + pair_size(&key, page_id.as_bytes());
+                // Each branch keeps at least two pairs so that a lone child left over at the end
+                // can take over the right child of the previous branch.
+                if (branch.num_pairs() < 2 || used <= max_used)
+                    && branch.push_right_child(&key, page_id).is_some()
+                {
+                    continue;
+                }
+                drop(page);
+                branch_buffer = None;
+            }
+            match pending.take() {
+                None => pending = Some((key, page_id)),
+                Some((first_key, first_page_id)) => {
+                    let buffer = bufmgr.create_page()?;
+                    let mut page = buffer.page.borrow_mut();
+                    let mut node = Node::new(page.as_mut_slice());
+                    node.initialize_as_branch();
+                    let mut branch = Branch::new(node.body);
+                    branch.initialize(&key, first_page_id, page_id);
+                    buffer.is_dirty.set(true);
+                    drop(page);
+                    branches.push((first_key, buffer.page_id));
+                    branch_buffer = Some(buffer);
+                }
+            }
+        }
+
+        if let Some((key, page_id)) = pending {
+            let (_, last_page_id) = branches.last().expect("children must not be a single page");
+            let last_buffer = bufmgr.fetch_page(*last_page_id)?;
+            let mut last_page = last_buffer.page.borrow_mut();
+            let mut last_branch = Branch::new(Node::new(last_page.as_mut_slice()).body);
+            let stolen_page_id = last_branch.child_at(last_branch.num_pairs());
+            let stolen_key = last_branch.fill_right_child();
+            last_buffer.is_dirty.set(true);
+
+            let buffer = bufmgr.create_page()?;
+            let mut page = buffer.page.borrow_mut();
+            let mut node = Node::new(page.as_mut_slice());
+            node.initialize_as_branch();
+            let mut branch = Branch::new(node.body);
+            branch.initialize(&key, stolen_page_id, page_id);
+            buffer.is_dirty.set(true);
+            branches.push((stolen_key, buffer.page_id));
+        }
+        Ok(branches)
+    }
+}
+
+fn pair_size(key: &[u8], value: &[u8]) -> usize {
+    Pair { key, value }.to_bytes().len() + size_of::<Pointer>()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::btree::SearchMode;
+    use crate::buffer::BufferPool;
+    use crate::disk::DiskManager;
+    use tempfile::tempfile;
+
+    #[test]
+    fn test_bulk_load() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+
+        let pairs = (0u64..5000).map(|i| (i.to_be_bytes().to_vec(), vec![i as u8; 100]));
+        let btree = BTree::bulk_load(&mut bufmgr, pairs, 0.9).unwrap();
+
+        let mut iter = btree.search(&mut bufmgr, SearchMode::Start).unwrap();
+        for i in 0u64..5000 {
+            let (key, value) = iter.next(&mut bufmgr).unwrap().unwrap();
+            assert_eq!(&i.to_be_bytes(), key.as_slice());
+            assert_eq!(vec![i as u8; 100], value);
+        }
+        assert!(iter.next(&mut bufmgr).unwrap().is_none());
+
+        for i in [0u64, 1, 777, 2500, 4999] {
+            let (key, _) = btree
+                .search(&mut bufmgr, SearchMode::Key(i.to_be_bytes().to_vec()))
+                .unwrap()
+                .next(&mut bufmgr)
+                .unwrap()
+                .unwrap();
+            assert_eq!(&i.to_be_bytes(), key.as_slice());
+        }
+
+        // The tree stays usable for ordinary insertions.
+        btree
+            .insert(&mut bufmgr, &5000u64.to_be_bytes(), b"tail")
+            .unwrap();
+        assert!(matches!(
+            btree.load(&mut bufmgr, vec![(vec![1], vec![1])], 1.0),
+            Err(BTreeError::NotEmpty)
+        ));
+    }
+
+    #[test]
+    fn test_bulk_load_long_keys() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+
+        // Long keys make narrow branches, so several levels and leftover children show up.
+        let key = |i: u64| {
+            let mut key = i.to_be_bytes().to_vec();
+            key.resize(600, 0);
+            key
+        };
+        for num_pairs in 1u64..120 {
+            let pairs = (0..num_pairs).map(|i| (key(i), i.to_be_bytes().to_vec()));
+            let btree = BTree::bulk_load(&mut bufmgr, pairs, 1.0).unwrap();
+            let mut iter = btree.search(&mut bufmgr, SearchMode::Start).unwrap();
+            for i in 0..num_pairs {
+                let (_, value) = iter.next(&mut bufmgr).unwrap().unwrap();
+                assert_eq!(&i.to_be_bytes(), value.as_slice());
+            }
+            assert!(iter.next(&mut bufmgr).unwrap().is_none());
+            for i in 0..num_pairs {
+                let (_, value) = btree
+                    .search(&mut bufmgr, SearchMode::Key(key(i)))
+                    .unwrap()
+                    .next(&mut bufmgr)
+                    .unwrap()
+                    .unwrap();
+                assert_eq!(&i.to_be_bytes(), value.as_slice());
+            }
+        }
+    }
+
+    #[test]
+    fn test_bulk_load_unsorted() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+
+        let pairs = vec![(b"b".to_vec(), vec![]), (b"a".to_vec(), vec![])];
+        assert!(matches!(
+            BTree::bulk_load(&mut bufmgr, pairs, 1.0),
+            Err(BTreeError::UnsortedInput)
+        ));
+        let pairs = vec![(b"a".to_vec(), vec![]), (b"a".to_vec(), vec![])];
+        assert!(matches!(
+            BTree::bulk_load(&mut bufmgr, pairs, 1.0),
+            Err(BTreeError::DuplicateKey)
+        ));
+
+        // A bad key after enough pairs to fill several leaves leaves the tree empty.
+        let btree = BTree::create(&mut bufmgr).unwrap();
+        let pairs = (0u64..1000)
+            .chain([0])
+            .map(|i| (i.to_be_bytes().to_vec(), vec![0; 100]));
+        assert!(matches!(
+            btree.load(&mut bufmgr, pairs, 1.0),
+            Err(BTreeError::UnsortedInput)
+        ));
+        let mut iter = btree.search(&mut bufmgr, SearchMode::Start).unwrap();
+        assert!(iter.next(&mut bufmgr).unwrap().is_none());
+
+        // So does a key too large for a leaf after the others are written.
+        let pairs = (0u64..1000)
+            .map(|i| (i.to_be_bytes().to_vec(), vec![0; 100]))
+            .chain([(vec![0xff; 4096], vec![])]);
+        assert!(matches!(
+            btree.load(&mut bufmgr, pairs, 1.0),
+            Err(BTreeError::KeyTooLarge)
+        ));
+        let mut iter = btree.search(&mut bufmgr, SearchMode::Start).unwrap();
+        assert!(iter.next(&mut bufmgr).unwrap().is_none());
+
+        for fill_factor in [0.0, 1.5, f64::NAN] {
+            assert!(matches!(
+                btree.load(&mut bufmgr, vec![(b"a".to_vec(), vec![])], fill_factor),
+                Err(BTreeError::InvalidArgument(_))
+            ));
+        }
+        btree
+            .load(&mut bufmgr, vec![(b"a".to_vec(), b"b".to_vec())], 1.0)
+            .unwrap();
+    }
+}
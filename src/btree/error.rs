@@ -5,6 +5,24 @@ use thiserror::Error;
 pub enum BTreeError {
     #[error("duplicate key")]
     DuplicateKey,
+    #[error("key not found")]
+    KeyNotFound,
+    /// Only values move to overflow pages, so a key must fit in a leaf along with a reference
+    /// to an overflow chain.
+    #[error("key is too large")]
+    KeyTooLarge,
+    /// An argument out of its range, like a fill factor above 1.
+    #[error("{0}")]
+    InvalidArgument(String),
+    #[error("tree is not empty")]
+    NotEmpty,
+    #[error("input is not sorted by key")]
+    UnsortedInput,
+    /// A page holds bytes that cannot be decoded.
+    #[error("B+ tree is corrupted: {0}")]
+    Corrupted(String),
+    #[error("unsupported B+ tree format version {0}")]
+    UnsupportedVersion(u64),
     #[error(transparent)]
     Buffer(#[from] buffer::BufferError),
 }
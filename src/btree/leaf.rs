@@ -1,7 +1,10 @@
 use crate::btree::bsearch::binary_search_by;
 use crate::btree::pair::Pair;
+use crate::btree::prefix::{common_prefix_len, shortest_separator};
 use crate::disk::PageId;
 use crate::slotted::{Pointer, Slotted};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BinaryHeap};
 use std::mem::size_of;
 use zerocopy::{AsBytes, ByteSlice, ByteSliceMut, FromBytes, FromZeroes, Ref};
 
@@ -12,6 +15,15 @@ pub struct Header {
     next_page_id: PageId,
 }
 
+/// The slot holding the prefix shared by every key of the leaf. The pairs follow it, with the
+/// prefix cut off their keys.
+const PREFIX_SLOT_ID: usize = 0;
+
+/// A leaf page. Its keys are prefix compressed: the longest prefix they all share is stored
+/// once, and only the rest of each key is stored with its value.
+///
+/// The prefix is only recomputed when the leaf runs out of space or a new key does not share
+/// it, so a leaf filled one key at a time stays uncompressed until it is first full.
 pub struct Leaf<B> {
     header: Ref<B, Header>,
     body: Slotted<B>,
@@ -33,27 +45,88 @@ impl<B: ByteSlice> Leaf<B> {
     }
 
     pub fn num_pairs(&self) -> usize {
-        self.body.num_slots()
+        self.body.num_slots() - 1
+    }
+
+    /// The prefix cut off every key stored in the leaf.
+    pub fn prefix(&self) -> &[u8] {
+        &self.body[PREFIX_SLOT_ID]
     }
 
     pub fn search_slot_id(&self, key: &[u8]) -> Result<usize, usize> {
+        let prefix = self.prefix();
+        let len = prefix.len().min(key.len());
+        // A key without the prefix sorts before or after every key of the leaf.
+        match prefix[..len].cmp(&key[..len]) {
+            Ordering::Less => return Err(self.num_pairs()),
+            Ordering::Greater => return Err(0),
+            Ordering::Equal if key.len() < prefix.len() => return Err(0),
+            Ordering::Equal => {}
+        }
+        let suffix = &key[prefix.len()..];
         binary_search_by(self.num_pairs(), |slot_id| {
-            self.pair_at(slot_id).key.cmp(key)
+            self.stored_pair_at(slot_id).key.cmp(suffix)
         })
     }
 
     #[cfg(test)]
-    pub fn search_pair(&self, key: &[u8]) -> Option<Pair> {
+    pub fn search_value(&self, key: &[u8]) -> Option<&[u8]> {
         let slot_id = self.search_slot_id(key).ok()?;
-        Some(self.pair_at(slot_id))
+        Some(self.value_at(slot_id))
+    }
+
+    /// Returns the whole key at `slot_id`, prefix included.
+    pub fn key_at(&self, slot_id: usize) -> Vec<u8> {
+        let mut key = self.prefix().to_vec();
+        key.extend_from_slice(self.stored_pair_at(slot_id).key);
+        key
+    }
+
+    pub fn value_at(&self, slot_id: usize) -> &[u8] {
+        self.stored_pair_at(slot_id).value
     }
 
-    pub fn pair_at(&self, slot_id: usize) -> Pair {
-        Pair::from_bytes(&self.body[slot_id])
+    /// Returns the pair at `slot_id` as stored, with the prefix cut off its key.
+    fn stored_pair_at(&self, slot_id: usize) -> Pair {
+        Pair::from_bytes(&self.body[slot_id + 1])
     }
 
+    /// The largest encoded pair, with its whole key, that a leaf accepts. Any two such pairs
+    /// fit in a leaf along with the prefix, so that a split always leaves room.
     pub fn max_pair_size(&self) -> usize {
-        self.body.capacity() / 2 - size_of::<Pointer>()
+        self.capacity() / 2 - size_of::<Pointer>()
+    }
+
+    /// The bytes available for the prefix and the pairs.
+    pub fn capacity(&self) -> usize {
+        self.body.capacity() - size_of::<Pointer>()
+    }
+
+    pub fn free_space(&self) -> usize {
+        self.body.free_space()
+    }
+
+    /// Returns every pair with its whole key, checking the slots instead of trusting them as a
+    /// possibly corrupted page requires. Fails with the slot id of the first unreadable pair.
+    #[allow(clippy::type_complexity)]
+    pub fn checked_pairs(&self) -> Result<Vec<(Vec<u8>, &[u8])>, usize> {
+        let prefix = self.body.checked_get(PREFIX_SLOT_ID).ok_or(0usize)?;
+        (0..self.body.num_slots().saturating_sub(1))
+            .map(|slot_id| {
+                let pair = self
+                    .body
+                    .checked_get(slot_id + 1)
+                    .and_then(Pair::try_from_bytes)
+                    .ok_or(slot_id)?;
+                Ok(([prefix, pair.key].concat(), pair.value))
+            })
+            .collect()
+    }
+
+    fn pairs(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        (0..self.num_pairs())
+            .map(|slot_id| (self.key_at(slot_id), self.value_at(slot_id).to_vec()))
+            .collect()
     }
 }
 
@@ -62,6 +135,9 @@ impl<B: ByteSliceMut> Leaf<B> {
         self.header.prev_page_id = PageId::INVALID_PAGE_ID;
         self.header.next_page_id = PageId::INVALID_PAGE_ID;
         self.body.initialize();
+        self.body
+            .insert(PREFIX_SLOT_ID, 0)
+            .expect("leaf must have space for the prefix");
     }
 
     pub fn set_prev_page_id(&mut self, prev_page_id: Option<PageId>) {
@@ -74,54 +150,157 @@ impl<B: ByteSliceMut> Leaf<B> {
 
     #[must_use = "insertion may fail"]
     pub fn insert(&mut self, slot_id: usize, key: &[u8], value: &[u8]) -> Option<()> {
+        self.insert_within(slot_id, key, value, self.capacity())
+    }
+
+    /// Like [`insert`](Self::insert), but fails if the leaf would use more than `max_used`
+    /// bytes of its capacity.
+    #[must_use = "insertion may fail"]
+    pub fn insert_within(
+        &mut self,
+        slot_id: usize,
+        key: &[u8],
+        value: &[u8],
+        max_used: usize,
+    ) -> Option<()> {
+        assert!(Pair::encoded_size(key.len(), value.len()) <= self.max_pair_size());
+        let prefix_len = self.prefix().len();
+        if key.starts_with(self.prefix()) {
+            let used = self.capacity() - self.free_space()
+                + Pair::encoded_size(key.len() - prefix_len, value.len())
+                + size_of::<Pointer>();
+            if used <= max_used {
+                return self.insert_stored(slot_id, &key[prefix_len..], value);
+            }
+        }
+        // Recompute the prefix, which makes room if the keys now share more bytes, and makes
+        // the key fit the prefix if it did not.
+        let mut pairs = self.pairs();
+        pairs.insert(slot_id, (key.to_vec(), value.to_vec()));
+        self.rebuild(&pairs, max_used)
+    }
+
+    fn insert_stored(&mut self, slot_id: usize, key: &[u8], value: &[u8]) -> Option<()> {
         let pair_bytes = Pair { key, value }.to_bytes();
-        assert!(pair_bytes.len() <= self.max_pair_size());
-        self.body.insert(slot_id, pair_bytes.len())?;
-        self.body[slot_id].copy_from_slice(&pair_bytes);
+        self.body.insert(slot_id + 1, pair_bytes.len())?;
+        self.body[slot_id + 1].copy_from_slice(&pair_bytes);
         Some(())
     }
 
-    fn is_half_full(&self) -> bool {
-        self.body.free_space() * 2 < self.body.capacity()
+    pub fn remove(&mut self, slot_id: usize) {
+        self.body.remove(slot_id + 1);
     }
 
+    /// Replaces the pairs of the leaf with `pairs`, sorted by key, compressed with the longest
+    /// prefix they share. Leaves the leaf as it was if they would use more than `max_used`
+    /// bytes.
+    #[must_use = "rebuilding may fail"]
+    fn rebuild(&mut self, pairs: &[(Vec<u8>, Vec<u8>)], max_used: usize) -> Option<()> {
+        let prefix_len = shared_prefix_len(pairs);
+        if rebuilt_size(pairs, prefix_len) > max_used {
+            return None;
+        }
+        self.body.initialize();
+        let prefix = pairs.first().map_or(&[][..], |(key, _)| &key[..prefix_len]);
+        self.body.insert(PREFIX_SLOT_ID, prefix_len)?;
+        self.body[PREFIX_SLOT_ID].copy_from_slice(prefix);
+        for (slot_id, (key, value)) in pairs.iter().enumerate() {
+            self.insert_stored(slot_id, &key[prefix_len..], value)?;
+        }
+        Some(())
+    }
+
+    /// Splits the full leaf, moving the smaller keys to the empty `new_leaf` so that both are
+    /// about as full, and returns the shortest key separating the two leaves.
     pub fn split_insert(
         &mut self,
         new_leaf: &mut Leaf<impl ByteSliceMut>,
         new_key: &[u8],
         new_value: &[u8],
     ) -> Vec<u8> {
-        loop {
-            if new_leaf.is_half_full() {
-                let index = self
-                    .search_slot_id(new_key)
-                    .expect_err("key must be unique");
-                self.insert(index, new_key, new_value)
-                    .expect("old leaf must have space");
-                break;
-            }
-            if self.pair_at(0).key < new_key {
-                self.transfer(new_leaf);
-            } else {
-                new_leaf
-                    .insert(new_leaf.num_pairs(), new_key, new_value)
-                    .expect("new leaf must have space");
-                while !new_leaf.is_half_full() {
-                    self.transfer(new_leaf);
+        let index = self
+            .search_slot_id(new_key)
+            .expect_err("key must be unique");
+        let mut pairs = self.pairs();
+        pairs.insert(index, (new_key.to_vec(), new_value.to_vec()));
+
+        let capacity = self.capacity();
+        let left_sizes = leading_sizes(&pairs);
+        let right_sizes = leading_sizes(pairs.iter().rev());
+        let split_at = (1..pairs.len())
+            .map(|at| (at, left_sizes[at - 1], right_sizes[pairs.len() - at - 1]))
+            .filter(|&(_, left, right)| left <= capacity && right <= capacity)
+            .min_by_key(|&(_, left, right)| left.max(right))
+            .map(|(at, _, _)| at)
+            .expect("split leaves must have space");
+        let (left, right) = pairs.split_at(split_at);
+        new_leaf
+            .rebuild(left, capacity)
+            .expect("new leaf must have space");
+        self.rebuild(right, capacity)
+            .expect("old leaf must have space");
+        shortest_separator(&left[left.len() - 1].0, &right[0].0)
+    }
+}
+
+/// The length of the longest prefix shared by the keys of `pairs`, sorted by key.
+fn shared_prefix_len(pairs: &[(Vec<u8>, Vec<u8>)]) -> usize {
+    match (pairs.first(), pairs.last()) {
+        (Some((first, _)), Some((last, _))) => common_prefix_len(first, last),
+        _ => 0,
+    }
+}
+
+/// The bytes of its capacity a leaf holding `pairs` compressed with a prefix of `prefix_len`
+/// uses.
+fn rebuilt_size(pairs: &[(Vec<u8>, Vec<u8>)], prefix_len: usize) -> usize {
+    let pairs_size: usize = pairs
+        .iter()
+        .map(|(key, value)| {
+            Pair::encoded_size(key.len() - prefix_len, value.len()) + size_of::<Pointer>()
+        })
+        .sum();
+    prefix_len + pairs_size
+}
+
+/// The bytes of its capacity a leaf holding the first `n` of `pairs` uses, for every `n`, as
+/// [`rebuilt_size`] would compute them. `pairs` must be sorted by key, in either order.
+///
+/// Each added pair can only shorten the shared prefix, making every stored key longer. The
+/// stored keys are grouped by the bytes their length takes, so that only those whose length
+/// grows past a varint boundary are looked at again, instead of summing all pairs for every `n`.
+fn leading_sizes<'a>(pairs: impl IntoIterator<Item = &'a (Vec<u8>, Vec<u8>)>) -> Vec<usize> {
+    let mut first = None;
+    // The sizes of the pairs but for the lengths of their keys, as if they had no prefix.
+    let mut unprefixed_size = 0;
+    let mut len_sizes = 0;
+    // The key lengths by the bytes they take stored, longest first.
+    let mut by_len_size: BTreeMap<usize, BinaryHeap<usize>> = BTreeMap::new();
+    let mut sizes = vec![];
+    for (key, value) in pairs {
+        let first = *first.get_or_insert(key);
+        let prefix_len = common_prefix_len(first, key);
+        unprefixed_size +=
+            key.len() + Pair::len_size(value.len()) + value.len() + size_of::<Pointer>();
+        let mut grown = vec![(Pair::len_size(key.len() - prefix_len), key.len())];
+        for (&len_size, key_lens) in by_len_size.iter_mut() {
+            while let Some(&key_len) = key_lens.peek() {
+                let new_len_size = Pair::len_size(key_len - prefix_len);
+                if new_len_size == len_size {
+                    break;
                 }
-                break;
+                key_lens.pop();
+                len_sizes -= len_size;
+                grown.push((new_len_size, key_len));
             }
         }
-        self.pair_at(0).key.to_vec()
-    }
-
-    pub fn transfer(&mut self, dest: &mut Leaf<impl ByteSliceMut>) {
-        let next_index = dest.num_pairs();
-        let Pair { key, value } = self.pair_at(0);
-        dest.insert(next_index, key, value)
-            .expect("dest leaf must have space");
-        self.body.remove(0);
+        for (len_size, key_len) in grown {
+            len_sizes += len_size;
+            by_len_size.entry(len_size).or_default().push(key_len);
+        }
+        sizes.push(prefix_len + unprefixed_size - (sizes.len() + 1) * prefix_len + len_sizes);
     }
+    sizes
 }
 
 #[cfg(test)]
@@ -138,31 +317,31 @@ mod tests {
         assert_eq!(0, id);
         leaf_page.insert(id, b"deadbeef", b"world").unwrap();
         assert_eq!(1, leaf_page.num_pairs());
-        assert_eq!(b"deadbeef", leaf_page.pair_at(0).key);
+        assert_eq!(b"deadbeef".to_vec(), leaf_page.key_at(0));
 
         let id = leaf_page.search_slot_id(b"facebook").unwrap_err();
         assert_eq!(1, id);
         leaf_page.insert(id, b"facebook", b"!").unwrap();
         assert_eq!(2, leaf_page.num_pairs());
-        assert_eq!(b"deadbeef", leaf_page.pair_at(0).key);
-        assert_eq!(b"facebook", leaf_page.pair_at(1).key);
+        assert_eq!(b"deadbeef".to_vec(), leaf_page.key_at(0));
+        assert_eq!(b"facebook".to_vec(), leaf_page.key_at(1));
 
         let id = leaf_page.search_slot_id(b"beefdead").unwrap_err();
         assert_eq!(0, id);
         leaf_page.insert(id, b"beefdead", b"hello").unwrap();
         assert_eq!(3, leaf_page.num_pairs());
-        assert_eq!(b"beefdead", leaf_page.pair_at(0).key);
-        assert_eq!(b"deadbeef", leaf_page.pair_at(1).key);
-        assert_eq!(b"facebook", leaf_page.pair_at(2).key);
+        assert_eq!(b"beefdead".to_vec(), leaf_page.key_at(0));
+        assert_eq!(b"deadbeef".to_vec(), leaf_page.key_at(1));
+        assert_eq!(b"facebook".to_vec(), leaf_page.key_at(2));
 
-        assert_eq!(b"hello", leaf_page.search_pair(b"beefdead").unwrap().value);
-        assert_eq!(b"world", leaf_page.search_pair(b"deadbeef").unwrap().value);
-        assert_eq!(b"!", leaf_page.search_pair(b"facebook").unwrap().value);
+        assert_eq!(Some(&b"hello"[..]), leaf_page.search_value(b"beefdead"));
+        assert_eq!(Some(&b"world"[..]), leaf_page.search_value(b"deadbeef"));
+        assert_eq!(Some(&b"!"[..]), leaf_page.search_value(b"facebook"));
     }
 
     #[test]
     fn test_leaf_split_insert() {
-        let mut page_data = vec![0; 62];
+        let mut page_data = vec![0; 80];
         let mut leaf_page = Leaf::new(page_data.as_mut_slice());
         leaf_page.initialize();
 
@@ -171,20 +350,72 @@ mod tests {
         let id = leaf_page.search_slot_id(b"facebook").unwrap_err();
         leaf_page.insert(id, b"facebook", b"!").unwrap();
         let id = leaf_page.search_slot_id(b"beefdead").unwrap_err();
-        assert!(leaf_page.insert(id, b"beefdead", b"hello").is_none()); // not enough space
+        leaf_page.insert(id, b"beefdead", b"hi").unwrap();
+        let id = leaf_page.search_slot_id(b"cafebabe").unwrap_err();
+        assert!(leaf_page.insert(id, b"cafebabe", b"bye").is_none()); // not enough space
 
-        let mut page_data_new = vec![0; 62];
+        let mut page_data_new = vec![0; 80];
         let mut leaf_page_new = Leaf::new(page_data_new.as_mut_slice());
         leaf_page_new.initialize();
 
-        let split_key = leaf_page.split_insert(&mut leaf_page_new, b"beefdead", b"hello");
-        assert_eq!(b"facebook".to_vec(), split_key);
+        let split_key = leaf_page.split_insert(&mut leaf_page_new, b"cafebabe", b"bye");
+        assert_eq!(b"d".to_vec(), split_key);
 
         assert_eq!(2, leaf_page_new.num_pairs());
         assert_eq!(Ok(0), leaf_page_new.search_slot_id(b"beefdead"));
-        assert_eq!(Ok(1), leaf_page_new.search_slot_id(b"deadbeef"));
+        assert_eq!(Ok(1), leaf_page_new.search_slot_id(b"cafebabe"));
 
-        assert_eq!(1, leaf_page.num_pairs());
-        assert_eq!(Ok(0), leaf_page.search_slot_id(b"facebook"));
+        assert_eq!(2, leaf_page.num_pairs());
+        assert_eq!(Ok(0), leaf_page.search_slot_id(b"deadbeef"));
+        assert_eq!(Ok(1), leaf_page.search_slot_id(b"facebook"));
+    }
+
+    #[test]
+    fn test_leaf_prefix_compression() {
+        let mut page_data = vec![0; 128];
+        let mut leaf_page = Leaf::new(page_data.as_mut_slice());
+        leaf_page.initialize();
+
+        // Keys sharing a tenant prefix fit only once it is stored a single time.
+        let key = |i: u8| [&b"tenant-0042/"[..], &[b'a' + i]].concat();
+        for i in 0..6 {
+            leaf_page.insert(i as usize, &key(i), &[i]).unwrap();
+        }
+        assert_eq!(b"tenant-0042/", leaf_page.prefix());
+        assert_eq!(key(3), leaf_page.key_at(3));
+        assert_eq!(Ok(5), leaf_page.search_slot_id(&key(5)));
+        assert_eq!(Err(0), leaf_page.search_slot_id(b"tenant-0041/z"));
+        assert_eq!(Err(0), leaf_page.search_slot_id(b"tenant"));
+        assert_eq!(Err(6), leaf_page.search_slot_id(b"tenant-0043/"));
+
+        // A key without the prefix shortens it, as long as the longer keys still fit.
+        leaf_page.remove(5);
+        leaf_page.remove(4);
+        leaf_page.insert(4, b"tenant-0043/a", b"").unwrap();
+        assert_eq!(b"tenant-004", leaf_page.prefix());
+        assert_eq!(key(3), leaf_page.key_at(3));
+        assert_eq!(b"tenant-0043/a".to_vec(), leaf_page.key_at(4));
+    }
+
+    #[test]
+    fn test_leading_sizes() {
+        // Long keys whose stored length crosses a varint boundary as the prefix shortens.
+        let long = |tail: &[u8]| [&[b'k'; 300][..], tail].concat();
+        let pairs = vec![
+            (long(b"a"), b"1".to_vec()),
+            (long(b"b"), vec![7; 260]),
+            ([&[b'k'; 100][..], b"x"].concat(), vec![]),
+            (b"l".to_vec(), b"2".to_vec()),
+            ([&b"l"[..], &[b'm'; 400]].concat(), b"3".to_vec()),
+        ];
+        let expected: Vec<usize> = (1..=pairs.len())
+            .map(|n| rebuilt_size(&pairs[..n], shared_prefix_len(&pairs[..n])))
+            .collect();
+        assert_eq!(expected, leading_sizes(&pairs));
+        let expected: Vec<usize> = (0..pairs.len())
+            .rev()
+            .map(|at| rebuilt_size(&pairs[at..], shared_prefix_len(&pairs[at..])))
+            .collect();
+        assert_eq!(expected, leading_sizes(pairs.iter().rev()));
     }
 }
@@ -1,10 +1,19 @@
+use crate::btree::error::BTreeError;
 use crate::disk::PageId;
-use zerocopy::{AsBytes, ByteSlice, FromBytes, FromZeroes, Ref};
+use zerocopy::{AsBytes, ByteSlice, ByteSliceMut, FromBytes, FromZeroes, Ref};
+
+/// The version of the page layout written by this code. Trees created before versioning read
+/// as version 0, which covers two layouts that cannot be told apart: leaves storing values as
+/// is, and leaves storing them wrapped in an `overflow::Value`.
+///
+/// - 1: leaves store a shared key prefix in their first slot.
+pub const FORMAT_VERSION: u64 = 1;
 
 #[derive(Debug, FromZeroes, FromBytes, AsBytes)]
 #[repr(C)]
 pub struct Header {
     pub root_page_id: PageId,
+    pub format_version: u64,
 }
 
 pub struct Meta<B> {
@@ -17,4 +26,19 @@ impl<B: ByteSlice> Meta<B> {
         let (header, _unused) = Ref::new_from_prefix(bytes).expect("meta page must be aligned");
         Self { header, _unused }
     }
+
+    /// Returns the root page, or an error if the tree was written in another format.
+    pub fn root_page_id(&self) -> Result<PageId, BTreeError> {
+        match self.header.format_version {
+            FORMAT_VERSION => Ok(self.header.root_page_id),
+            version => Err(BTreeError::UnsupportedVersion(version)),
+        }
+    }
+}
+
+impl<B: ByteSliceMut> Meta<B> {
+    pub fn initialize(&mut self, root_page_id: PageId) {
+        self.header.root_page_id = root_page_id;
+        self.header.format_version = FORMAT_VERSION;
+    }
 }
@@ -4,18 +4,27 @@ use crate::btree::node::Node;
 use crate::btree::pair::Pair;
 use crate::buffer::{Buffer, BufferPoolManager};
 use crate::disk::PageId;
-use error::BTreeError;
 use std::convert::identity;
 use std::rc::Rc;
 use zerocopy::ByteSlice;
 
 mod branch;
 mod bsearch;
+mod bulk;
 mod error;
 mod leaf;
 mod meta;
 mod node;
+mod overflow;
 mod pair;
+mod prefix;
+mod stats;
+mod verify;
+
+pub use bulk::PendingLoad;
+pub use error::BTreeError;
+pub use stats::Stats;
+pub use verify::{VerifyReport, Violation};
 
 pub struct BTree {
     pub meta_page_id: PageId,
@@ -32,7 +41,7 @@ impl BTree {
         root.initialize_as_leaf();
         let mut leaf = Leaf::new(root.body);
         leaf.initialize();
-        meta.header.root_page_id = root_buffer.page_id;
+        meta.initialize(root_buffer.page_id);
         Ok(Self::new(buffer.page_id))
     }
 
@@ -44,7 +53,7 @@ impl BTree {
         let meta_buffer = bufmgr.fetch_page(self.meta_page_id)?;
         let meta_page = meta_buffer.page.borrow();
         let meta = meta::Meta::new(meta_page.as_slice());
-        let root_buffer = bufmgr.fetch_page(meta.header.root_page_id)?;
+        let root_buffer = bufmgr.fetch_page(meta.root_page_id()?)?;
         Ok(root_buffer)
     }
 
@@ -88,6 +97,17 @@ impl BTree {
         Self::search_internal(bufmgr, root_page, search_mode)
     }
 
+    /// Like [`search`](Self::search), returning a cursor that holds `bufmgr` so that it can be
+    /// used as an [`Iterator`].
+    pub fn cursor<'a>(
+        &self,
+        bufmgr: &'a mut BufferPoolManager,
+        search_mode: SearchMode,
+    ) -> Result<Cursor<'a>, BTreeError> {
+        let iter = self.search(bufmgr, search_mode)?;
+        Ok(iter.into_cursor(bufmgr))
+    }
+
     fn insert_internal(
         bufmgr: &mut BufferPoolManager,
         node_buffer: Rc<Buffer>,
@@ -104,6 +124,7 @@ impl BTree {
                     Ok(_) => return Err(BTreeError::DuplicateKey),
                     Err(slot_id) => slot_id,
                 };
+                let value = &Self::encode_value(bufmgr, key, value, leaf.max_pair_size())?;
 
                 if leaf.insert(slot_id, key, value).is_some() {
                     node_buffer.is_dirty.set(true);
@@ -178,6 +199,28 @@ impl BTree {
         }
     }
 
+    /// Encodes `value` as it is stored in a leaf, moving it to overflow pages when the pair
+    /// would not fit inline. Keys are always stored inline, so a key too large for the pair to
+    /// fit even with an overflowing value is rejected.
+    fn encode_value(
+        bufmgr: &mut BufferPoolManager,
+        key: &[u8],
+        value: &[u8],
+        max_pair_size: usize,
+    ) -> Result<Vec<u8>, BTreeError> {
+        let pair_size = |value: &[u8]| Pair { key, value }.to_bytes().len();
+        if pair_size(&overflow::Value::max_overflow_bytes()) > max_pair_size {
+            return Err(BTreeError::KeyTooLarge);
+        }
+        let inline = overflow::Value::Inline(value).to_bytes();
+        if pair_size(&inline) <= max_pair_size {
+            return Ok(inline);
+        }
+        let page_id = overflow::write(bufmgr, value)?;
+        let len = value.len() as u64;
+        Ok(overflow::Value::Overflow { page_id, len }.to_bytes())
+    }
+
     pub fn insert(
         &self,
         bufmgr: &mut BufferPoolManager,
@@ -187,7 +230,7 @@ impl BTree {
         let meta_buffer = bufmgr.fetch_page(self.meta_page_id)?;
         let mut meta_page = meta_buffer.page.borrow_mut();
         let mut meta = meta::Meta::new(meta_page.as_mut_slice());
-        let root_buffer = bufmgr.fetch_page(meta.header.root_page_id)?;
+        let root_buffer = bufmgr.fetch_page(meta.root_page_id()?)?;
         if let Some((key, child_page_id)) = Self::insert_internal(bufmgr, root_buffer, key, value)?
         {
             let new_root_buffer = bufmgr.create_page()?;
@@ -201,6 +244,33 @@ impl BTree {
         }
         Ok(())
     }
+
+    /// Removes `key` and its value from the tree.
+    ///
+    /// Nodes are never merged: a leaf emptied by deletes stays in the tree, where iterators skip
+    /// it and later inserts may fill it again. The overflow pages of the value are not reused.
+    pub fn delete(&self, bufmgr: &mut BufferPoolManager, key: &[u8]) -> Result<(), BTreeError> {
+        let mut node_buffer = self.fetch_root_page(bufmgr)?;
+        loop {
+            let child_page_id = {
+                let mut node_page = node_buffer.page.borrow_mut();
+                let node = Node::new(node_page.as_mut_slice());
+                let node_type = node.header.node_type;
+                match node::Body::new(node_type, node.body) {
+                    node::Body::Leaf(mut leaf) => {
+                        let slot_id = leaf
+                            .search_slot_id(key)
+                            .map_err(|_| BTreeError::KeyNotFound)?;
+                        leaf.remove(slot_id);
+                        node_buffer.is_dirty.set(true);
+                        return Ok(());
+                    }
+                    node::Body::Branch(branch) => branch.search_child(key),
+                }
+            };
+            node_buffer = bufmgr.fetch_page(child_page_id)?;
+        }
+    }
 }
 
 pub struct Iter {
@@ -213,34 +283,46 @@ impl Iter {
         Self { buffer, slot_id }
     }
 
-    fn get(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+    #[allow(clippy::type_complexity)]
+    fn get(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+    ) -> Result<Option<(Vec<u8>, Vec<u8>)>, BTreeError> {
         let page = self.buffer.page.borrow();
         let node = Node::new(page.as_slice());
         let leaf = Leaf::new(node.body);
-        if self.slot_id < leaf.num_pairs() {
-            let Pair { key, value } = leaf.pair_at(self.slot_id);
-            Some((key.to_vec(), value.to_vec()))
-        } else {
-            None
+        if self.slot_id >= leaf.num_pairs() {
+            return Ok(None);
         }
+        let key = leaf.key_at(self.slot_id);
+        let value = match overflow::Value::from_bytes(leaf.value_at(self.slot_id))? {
+            overflow::Value::Inline(value) => value.to_vec(),
+            overflow::Value::Overflow { page_id, len } => {
+                overflow::read(bufmgr, page_id, len as usize)?
+            }
+        };
+        Ok(Some((key, value)))
     }
 
+    /// Moves to the next pair, skipping leaves left empty by deletes.
     fn advance(&mut self, bufmgr: &mut BufferPoolManager) -> Result<(), BTreeError> {
         self.slot_id += 1;
-        let next_page_id = {
-            let page = self.buffer.page.borrow();
-            let node = Node::new(page.as_slice());
-            let leaf = Leaf::new(node.body);
-            if self.slot_id < leaf.num_pairs() {
+        loop {
+            let next_page_id = {
+                let page = self.buffer.page.borrow();
+                let node = Node::new(page.as_slice());
+                let leaf = Leaf::new(node.body);
+                if self.slot_id < leaf.num_pairs() {
+                    return Ok(());
+                }
+                leaf.next_page_id()
+            };
+            let Some(next_page_id) = next_page_id else {
                 return Ok(());
-            }
-            leaf.next_page_id()
-        };
-        if let Some(next_page_id) = next_page_id {
+            };
             self.buffer = bufmgr.fetch_page(next_page_id)?;
             self.slot_id = 0;
         }
-        Ok(())
     }
 
     #[allow(clippy::type_complexity)]
@@ -248,10 +330,39 @@ impl Iter {
         &mut self,
         bufmgr: &mut BufferPoolManager,
     ) -> Result<Option<(Vec<u8>, Vec<u8>)>, BTreeError> {
-        let value = self.get();
+        let value = self.get(bufmgr)?;
         self.advance(bufmgr)?;
         Ok(value)
     }
+
+    pub fn into_cursor(self, bufmgr: &mut BufferPoolManager) -> Cursor<'_> {
+        Cursor {
+            iter: self,
+            bufmgr,
+            done: false,
+        }
+    }
+}
+
+/// An [`Iter`] with the buffer pool it reads pages through, yielding the `(key, value)` pairs as
+/// an [`Iterator`]. It ends after the first error.
+pub struct Cursor<'a> {
+    iter: Iter,
+    bufmgr: &'a mut BufferPoolManager,
+    done: bool,
+}
+
+impl<'a> Iterator for Cursor<'a> {
+    type Item = Result<(Vec<u8>, Vec<u8>), BTreeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.iter.next(self.bufmgr).transpose();
+        self.done = !matches!(result, Some(Ok(_)));
+        result
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -305,17 +416,45 @@ mod tests {
         let (_, value) = btree
             .search(&mut bufmgr, SearchMode::Key(3u64.to_be_bytes().to_vec()))
             .unwrap()
-            .get()
+            .get(&mut bufmgr)
+            .unwrap()
             .unwrap();
         assert_eq!(b"hello", &value[..]);
         let (_, value) = btree
             .search(&mut bufmgr, SearchMode::Key(8u64.to_be_bytes().to_vec()))
             .unwrap()
-            .get()
+            .get(&mut bufmgr)
+            .unwrap()
             .unwrap();
         assert_eq!(b"!", &value[..]);
     }
 
+    #[test]
+    fn test_cursor() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let btree = BTree::create(&mut bufmgr).unwrap();
+        for i in 0u64..16 {
+            btree
+                .insert(&mut bufmgr, &i.to_be_bytes(), &[0; 1024])
+                .unwrap();
+        }
+
+        let keys: Vec<u64> = btree
+            .cursor(&mut bufmgr, SearchMode::Key(10u64.to_be_bytes().to_vec()))
+            .unwrap()
+            .map(|pair| u64::from_be_bytes(pair.unwrap().0.try_into().unwrap()))
+            .collect();
+        assert_eq!(vec![10, 11, 12, 13, 14, 15], keys);
+        let count = btree
+            .cursor(&mut bufmgr, SearchMode::Start)
+            .unwrap()
+            .filter(|pair| pair.as_ref().is_ok_and(|(key, _)| key[7] % 2 == 0))
+            .count();
+        assert_eq!(8, count);
+    }
+
     #[test]
     fn test_search_iter() {
         let disk = DiskManager::new(tempfile().unwrap()).unwrap();
@@ -336,7 +475,8 @@ mod tests {
                     SearchMode::Key((i * 2 + 1).to_be_bytes().to_vec()),
                 )
                 .unwrap()
-                .get()
+                .get(&mut bufmgr)
+                .unwrap()
                 .unwrap();
 
             assert_eq!(key.as_slice(), &((i + 1) * 2).to_be_bytes());
@@ -366,10 +506,163 @@ mod tests {
             let (k, v) = btree
                 .search(&mut bufmgr, SearchMode::Key(data.clone()))
                 .unwrap()
-                .get()
+                .get(&mut bufmgr)
+                .unwrap()
                 .unwrap();
             assert_eq!(data, &k);
             assert_eq!(data, &v);
         }
     }
+
+    #[test]
+    fn test_overflow() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let btree = BTree::create(&mut bufmgr).unwrap();
+
+        let large_values: Vec<Vec<u8>> = (0u8..8)
+            .map(|i| (0..20_000).map(|j| (j as u8).wrapping_mul(i)).collect())
+            .collect();
+        for (i, value) in large_values.iter().enumerate() {
+            btree
+                .insert(&mut bufmgr, &(i as u64).to_be_bytes(), value)
+                .unwrap();
+        }
+        btree
+            .insert(&mut bufmgr, &100u64.to_be_bytes(), b"small")
+            .unwrap();
+
+        let mut iter = btree.search(&mut bufmgr, SearchMode::Start).unwrap();
+        for (i, value) in large_values.iter().enumerate() {
+            let (k, v) = iter.next(&mut bufmgr).unwrap().unwrap();
+            assert_eq!(&(i as u64).to_be_bytes(), k.as_slice());
+            assert_eq!(value, &v);
+        }
+        let (_, v) = iter.next(&mut bufmgr).unwrap().unwrap();
+        assert_eq!(b"small", v.as_slice());
+
+        assert!(matches!(
+            btree.insert(&mut bufmgr, &[0xFF; 4000], b"key is too large"),
+            Err(BTreeError::KeyTooLarge)
+        ));
+
+        // A value that does not decode is reported instead of panicking.
+        let btree = BTree::create(&mut bufmgr).unwrap();
+        let iter = btree.search(&mut bufmgr, SearchMode::Start).unwrap();
+        {
+            let mut page = iter.buffer.page.borrow_mut();
+            let mut leaf = Leaf::new(Node::new(page.as_mut_slice()).body);
+            leaf.insert(0, b"key", &[0xFF]).unwrap();
+        }
+        let mut iter = btree.search(&mut bufmgr, SearchMode::Start).unwrap();
+        assert!(matches!(
+            iter.next(&mut bufmgr),
+            Err(BTreeError::Corrupted(_))
+        ));
+    }
+
+    #[test]
+    fn test_delete() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let btree = BTree::create(&mut bufmgr).unwrap();
+
+        for i in 0u64..16 {
+            btree
+                .insert(&mut bufmgr, &i.to_be_bytes(), &[0; 1024])
+                .unwrap();
+        }
+        // Deleting keys 2 to 13 empties the leaves in the middle of the tree.
+        for i in 2u64..14 {
+            btree.delete(&mut bufmgr, &i.to_be_bytes()).unwrap();
+        }
+        assert!(matches!(
+            btree.delete(&mut bufmgr, &5u64.to_be_bytes()),
+            Err(BTreeError::KeyNotFound)
+        ));
+        assert!(btree.verify(&mut bufmgr).unwrap().is_ok());
+
+        let mut iter = btree.search(&mut bufmgr, SearchMode::Start).unwrap();
+        for i in [0u64, 1, 14, 15] {
+            let (key, _) = iter.next(&mut bufmgr).unwrap().unwrap();
+            assert_eq!(&i.to_be_bytes(), key.as_slice());
+        }
+        assert!(iter.next(&mut bufmgr).unwrap().is_none());
+
+        let mut iter = btree
+            .search(&mut bufmgr, SearchMode::Key(5u64.to_be_bytes().to_vec()))
+            .unwrap();
+        let (key, _) = iter.next(&mut bufmgr).unwrap().unwrap();
+        assert_eq!(&14u64.to_be_bytes(), key.as_slice());
+
+        btree
+            .insert(&mut bufmgr, &7u64.to_be_bytes(), b"again")
+            .unwrap();
+        let (_, value) = btree
+            .search(&mut bufmgr, SearchMode::Key(7u64.to_be_bytes().to_vec()))
+            .unwrap()
+            .next(&mut bufmgr)
+            .unwrap()
+            .unwrap();
+        assert_eq!(b"again", value.as_slice());
+    }
+
+    #[test]
+    fn test_prefix_compression() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let btree = BTree::create(&mut bufmgr).unwrap();
+
+        // Composite keys of one tenant share most of their bytes.
+        let key = |i: u64| [&b"tenant-00000042/orders/"[..], &i.to_be_bytes()].concat();
+        for i in (0u64..2000).rev() {
+            btree
+                .insert(&mut bufmgr, &key(i), &i.to_be_bytes())
+                .unwrap();
+        }
+        assert!(btree.verify(&mut bufmgr).unwrap().is_ok());
+        let stats = btree.stats(&mut bufmgr).unwrap();
+        assert_eq!(2000 * key(0).len(), stats.key_bytes);
+        // Uncompressed, a pair takes 45 bytes and a full leaf holds about 90 of them.
+        assert!(stats.num_leaf_pages < 2000 / 90, "{stats:?}");
+
+        let mut iter = btree.search(&mut bufmgr, SearchMode::Start).unwrap();
+        for i in 0u64..2000 {
+            let (k, v) = iter.next(&mut bufmgr).unwrap().unwrap();
+            assert_eq!(key(i), k);
+            assert_eq!(&i.to_be_bytes(), v.as_slice());
+        }
+        let (k, _) = btree
+            .search(&mut bufmgr, SearchMode::Key(b"tenant-00000042/".to_vec()))
+            .unwrap()
+            .next(&mut bufmgr)
+            .unwrap()
+            .unwrap();
+        assert_eq!(key(0), k);
+    }
+
+    #[test]
+    fn test_format_version() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let btree = BTree::create(&mut bufmgr).unwrap();
+
+        // Trees written before the format was versioned have zeroes in its place.
+        let meta_buffer = bufmgr.fetch_page(btree.meta_page_id).unwrap();
+        meta::Meta::new(meta_buffer.page.borrow_mut().as_mut_slice())
+            .header
+            .format_version = 0;
+        assert!(matches!(
+            btree.search(&mut bufmgr, SearchMode::Start),
+            Err(BTreeError::UnsupportedVersion(0))
+        ));
+        assert!(matches!(
+            btree.insert(&mut bufmgr, b"key", b"value"),
+            Err(BTreeError::UnsupportedVersion(0))
+        ));
+    }
 }
@@ -0,0 +1,128 @@
+use crate::btree::error::BTreeError;
+use crate::buffer::BufferPoolManager;
+use crate::disk::{PageId, PAGE_SIZE};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use std::mem::size_of;
+use zerocopy::{AsBytes, ByteSlice, ByteSliceMut, FromBytes, FromZeroes, Ref};
+
+#[derive(Debug, FromZeroes, FromBytes, AsBytes)]
+#[repr(C)]
+pub struct Header {
+    next_page_id: PageId,
+}
+
+/// A page in a chain holding the bytes of a value too large to be stored inline in a leaf.
+pub struct Overflow<B> {
+    header: Ref<B, Header>,
+    body: B,
+}
+
+impl<B: ByteSlice> Overflow<B> {
+    pub fn new(bytes: B) -> Self {
+        let (header, body) = Ref::new_from_prefix(bytes).expect("overflow header must be aligned");
+        Self { header, body }
+    }
+
+    pub fn next_page_id(&self) -> Option<PageId> {
+        self.header.next_page_id.valid()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.body.len()
+    }
+}
+
+impl<B: ByteSliceMut> Overflow<B> {
+    pub fn initialize(&mut self) {
+        self.header.next_page_id = PageId::INVALID_PAGE_ID;
+    }
+
+    pub fn set_next_page_id(&mut self, next_page_id: Option<PageId>) {
+        self.header.next_page_id = next_page_id.into();
+    }
+}
+
+/// Returns the number of overflow pages needed to hold `len` bytes.
+pub fn num_pages(len: usize) -> usize {
+    len.div_ceil(PAGE_SIZE - size_of::<Header>())
+}
+
+/// The value part of a pair stored in a leaf.
+#[derive(Serialize, Deserialize)]
+pub enum Value<'a> {
+    Inline(&'a [u8]),
+    Overflow { page_id: PageId, len: u64 },
+}
+
+impl<'a> Value<'a> {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        bincode::options().serialize(&self).unwrap()
+    }
+
+    pub fn from_bytes(bytes: &'a [u8]) -> Result<Self, BTreeError> {
+        bincode::options()
+            .deserialize(bytes)
+            .map_err(|err| BTreeError::Corrupted(format!("leaf value: {err}")))
+    }
+
+    /// The largest possible encoding of `Value::Overflow`.
+    pub fn max_overflow_bytes() -> Vec<u8> {
+        Value::Overflow {
+            page_id: PageId(u64::MAX - 1),
+            len: u64::MAX,
+        }
+        .to_bytes()
+    }
+}
+
+/// Writes `data` to a newly allocated chain of overflow pages and returns the first page id.
+pub fn write(bufmgr: &mut BufferPoolManager, mut data: &[u8]) -> Result<PageId, BTreeError> {
+    let first_buffer = bufmgr.create_page()?;
+    let first_page_id = first_buffer.page_id;
+    let mut buffer = first_buffer;
+    loop {
+        let next_buffer = {
+            let mut page = buffer.page.borrow_mut();
+            let mut overflow = Overflow::new(page.as_mut_slice());
+            overflow.initialize();
+            let len = data.len().min(overflow.capacity());
+            overflow.body[..len].copy_from_slice(&data[..len]);
+            data = &data[len..];
+            buffer.is_dirty.set(true);
+            if data.is_empty() {
+                break;
+            }
+            let next_buffer = bufmgr.create_page()?;
+            overflow.set_next_page_id(Some(next_buffer.page_id));
+            next_buffer
+        };
+        buffer = next_buffer;
+    }
+    Ok(first_page_id)
+}
+
+/// Reads `len` bytes from the chain of overflow pages starting at `page_id`.
+pub fn read(
+    bufmgr: &mut BufferPoolManager,
+    page_id: PageId,
+    len: usize,
+) -> Result<Vec<u8>, BTreeError> {
+    let mut data = Vec::with_capacity(len);
+    let mut next_page_id = Some(page_id);
+    while data.len() < len {
+        let page_id = next_page_id.ok_or_else(|| {
+            BTreeError::Corrupted(format!(
+                "overflow chain ends {} bytes early",
+                len - data.len()
+            ))
+        })?;
+        let buffer = bufmgr.fetch_page(page_id)?;
+        let page = buffer.page.borrow();
+        let overflow = Overflow::new(page.as_slice());
+        let copy_len = (len - data.len()).min(overflow.capacity());
+        data.extend_from_slice(&overflow.body[..copy_len]);
+        next_page_id = overflow.next_page_id();
+    }
+    Ok(data)
+}
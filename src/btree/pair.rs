@@ -15,4 +15,19 @@ impl<'a> Pair<'a> {
     pub fn from_bytes(bytes: &'a [u8]) -> Self {
         bincode::options().deserialize(bytes).unwrap()
     }
+
+    /// Like [`from_bytes`](Self::from_bytes), but returns `None` if `bytes` is not a pair.
+    pub fn try_from_bytes(bytes: &'a [u8]) -> Option<Self> {
+        bincode::options().deserialize(bytes).ok()
+    }
+
+    /// The length of [`to_bytes`](Self::to_bytes) for a key and a value of the given lengths.
+    pub fn encoded_size(key_len: usize, value_len: usize) -> usize {
+        Self::len_size(key_len) + Self::len_size(value_len) + key_len + value_len
+    }
+
+    /// The bytes [`to_bytes`](Self::to_bytes) uses for the length of a key or value of `len`.
+    pub fn len_size(len: usize) -> usize {
+        bincode::options().serialized_size(&(len as u64)).unwrap() as usize
+    }
 }
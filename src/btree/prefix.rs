@@ -0,0 +1,31 @@
+//! Helpers for the prefix compression of leaf keys and the suffix truncation of separators.
+
+/// The length of the longest common prefix of `a` and `b`.
+pub fn common_prefix_len(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+/// Returns the shortest key `s` with `left < s <= right`, which separates the two in a branch
+/// just like `right` does. `left` must be smaller than `right`.
+pub fn shortest_separator(left: &[u8], right: &[u8]) -> Vec<u8> {
+    debug_assert!(left < right);
+    let len = common_prefix_len(left, right);
+    right[..len + 1].to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shortest_separator() {
+        assert_eq!(b"d".to_vec(), shortest_separator(b"beefdead", b"deadbeef"));
+        assert_eq!(
+            b"tenant1/c".to_vec(),
+            shortest_separator(b"tenant1/bob", b"tenant1/carol")
+        );
+        // A left key that is a prefix of the right one needs one more byte.
+        assert_eq!(b"abc".to_vec(), shortest_separator(b"ab", b"abcd"));
+        assert_eq!(3, common_prefix_len(b"abc", b"abc"));
+    }
+}
@@ -0,0 +1,114 @@
+use crate::btree::error::BTreeError;
+use crate::btree::node::{self, Node};
+use crate::btree::{meta, overflow, BTree};
+use crate::buffer::BufferPoolManager;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    /// The number of levels, counting a root leaf as one.
+    pub height: usize,
+    pub num_branch_pages: usize,
+    pub num_leaf_pages: usize,
+    pub num_overflow_pages: usize,
+    pub num_keys: usize,
+    /// The average fraction of leaf space in use, from 0 to 1.
+    pub avg_leaf_fill: f64,
+    /// The smallest fraction of leaf space in use, from 0 to 1.
+    pub min_leaf_fill: f64,
+    pub key_bytes: usize,
+    /// The total length of values, including those stored in overflow pages.
+    pub value_bytes: usize,
+}
+
+impl BTree {
+    /// Walks the whole tree and collects its statistics.
+    pub fn stats(&self, bufmgr: &mut BufferPoolManager) -> Result<Stats, BTreeError> {
+        let root_page_id = {
+            let meta_buffer = bufmgr.fetch_page(self.meta_page_id)?;
+            let meta_page = meta_buffer.page.borrow();
+            let meta = meta::Meta::new(meta_page.as_slice());
+            meta.root_page_id()?
+        };
+
+        let mut stats = Stats {
+            min_leaf_fill: 1.0,
+            ..Default::default()
+        };
+        let mut total_leaf_fill = 0.0;
+        let mut level = vec![root_page_id];
+        while !level.is_empty() {
+            stats.height += 1;
+            let mut next_level = vec![];
+            for page_id in level {
+                let buffer = bufmgr.fetch_page(page_id)?;
+                let page = buffer.page.borrow();
+                let node = Node::new(page.as_slice());
+                match node::Body::new(node.header.node_type, node.body) {
+                    node::Body::Leaf(leaf) => {
+                        stats.num_leaf_pages += 1;
+                        let fill = 1.0 - leaf.free_space() as f64 / leaf.capacity() as f64;
+                        total_leaf_fill += fill;
+                        stats.min_leaf_fill = stats.min_leaf_fill.min(fill);
+                        for slot_id in 0..leaf.num_pairs() {
+                            stats.num_keys += 1;
+                            stats.key_bytes += leaf.key_at(slot_id).len();
+                            let value = leaf.value_at(slot_id);
+                            stats.value_bytes += match overflow::Value::from_bytes(value)? {
+                                overflow::Value::Inline(value) => value.len(),
+                                overflow::Value::Overflow { len, .. } => {
+                                    stats.num_overflow_pages += overflow::num_pages(len as usize);
+                                    len as usize
+                                }
+                            };
+                        }
+                    }
+                    node::Body::Branch(branch) => {
+                        stats.num_branch_pages += 1;
+                        next_level.extend((0..=branch.num_pairs()).map(|idx| branch.child_at(idx)));
+                    }
+                }
+            }
+            level = next_level;
+        }
+        stats.avg_leaf_fill = total_leaf_fill / stats.num_leaf_pages as f64;
+        Ok(stats)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::BufferPool;
+    use crate::disk::DiskManager;
+    use tempfile::tempfile;
+
+    #[test]
+    fn test_stats() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+
+        let btree = BTree::create(&mut bufmgr).unwrap();
+        let stats = btree.stats(&mut bufmgr).unwrap();
+        assert_eq!(1, stats.height);
+        assert_eq!(1, stats.num_leaf_pages);
+        assert_eq!(0, stats.num_keys);
+        assert_eq!(0.0, stats.min_leaf_fill);
+
+        let pairs = (0u64..1000).map(|i| (i.to_be_bytes().to_vec(), vec![0; 100]));
+        let btree = BTree::bulk_load(&mut bufmgr, pairs, 1.0).unwrap();
+        btree
+            .insert(&mut bufmgr, &1000u64.to_be_bytes(), &[0; 10_000])
+            .unwrap();
+        let stats = btree.stats(&mut bufmgr).unwrap();
+        assert_eq!(2, stats.height);
+        assert_eq!(1, stats.num_branch_pages);
+        assert!(stats.num_leaf_pages > 1);
+        assert_eq!(3, stats.num_overflow_pages);
+        assert_eq!(1001, stats.num_keys);
+        assert_eq!(1001 * 8, stats.key_bytes);
+        assert_eq!(1000 * 100 + 10_000, stats.value_bytes);
+        assert!(stats.avg_leaf_fill > 0.9);
+        assert!(stats.min_leaf_fill <= stats.avg_leaf_fill);
+    }
+}
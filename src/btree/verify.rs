@@ -0,0 +1,358 @@
+use crate::btree::error::BTreeError;
+use crate::btree::node::{self, Node};
+use crate::btree::{meta, overflow, BTree};
+use crate::buffer::BufferPoolManager;
+use crate::disk::PageId;
+use std::collections::HashSet;
+
+/// A structural problem found by [`BTree::verify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The page is not a leaf or a branch.
+    UnknownNodeType { page_id: PageId },
+    /// The key at `slot_id` is not greater than the key before it.
+    UnorderedKey { page_id: PageId, slot_id: usize },
+    /// The key at `slot_id` is outside the range given by the separators in the parent branches.
+    KeyOutOfBounds { page_id: PageId, slot_id: usize },
+    /// The leaf is not as deep as the leftmost leaf.
+    UnequalLeafDepth {
+        page_id: PageId,
+        depth: usize,
+        expected: usize,
+    },
+    /// `prev_page_id` or `next_page_id` of the leaf does not point to its neighbor.
+    BrokenLeafChain { page_id: PageId },
+    /// The chain of overflow pages holding a value is shorter than the value.
+    BrokenOverflowChain { page_id: PageId },
+    /// The page is reachable from more than one place.
+    PageReachedTwice { page_id: PageId },
+    /// A page id points past the end of the file.
+    PageOutOfRange { page_id: PageId },
+    /// The slot at `slot_id` points outside the page, or holds bytes that do not decode.
+    UnreadableSlot { page_id: PageId, slot_id: usize },
+}
+
+#[derive(Debug, Default)]
+pub struct VerifyReport {
+    /// The number of distinct pages reached from the meta page, including it.
+    pub num_pages: usize,
+    pub violations: Vec<Violation>,
+}
+
+impl VerifyReport {
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+#[derive(Default)]
+struct Verifier {
+    report: VerifyReport,
+    visited: HashSet<PageId>,
+    leaf_depth: Option<usize>,
+    /// The page ids of leaves with their `prev_page_id` and `next_page_id` in key order.
+    leaves: Vec<(PageId, Option<PageId>, Option<PageId>)>,
+}
+
+impl BTree {
+    /// Checks the structure of the tree and reports every violation found.
+    pub fn verify(&self, bufmgr: &mut BufferPoolManager) -> Result<VerifyReport, BTreeError> {
+        let mut verifier = Verifier::default();
+        verifier.visit(self.meta_page_id);
+        let root_page_id = {
+            let meta_buffer = bufmgr.fetch_page(self.meta_page_id)?;
+            let meta_page = meta_buffer.page.borrow();
+            let meta = meta::Meta::new(meta_page.as_slice());
+            meta.root_page_id()?
+        };
+        verifier.verify_node(bufmgr, root_page_id, 0, None, None)?;
+        verifier.verify_leaf_chain();
+        Ok(verifier.report)
+    }
+}
+
+impl Verifier {
+    /// Marks the page as visited and returns whether it was not visited before.
+    fn visit(&mut self, page_id: PageId) -> bool {
+        if self.visited.insert(page_id) {
+            self.report.num_pages += 1;
+            true
+        } else {
+            self.violate(Violation::PageReachedTwice { page_id });
+            false
+        }
+    }
+
+    fn violate(&mut self, violation: Violation) {
+        self.report.violations.push(violation);
+    }
+
+    /// Returns whether `page_id` is in the file, reporting it if not.
+    fn check_in_range(&mut self, bufmgr: &BufferPoolManager, page_id: PageId) -> bool {
+        let in_range = page_id.to_u64() < bufmgr.num_pages();
+        if !in_range {
+            self.violate(Violation::PageOutOfRange { page_id });
+        }
+        in_range
+    }
+
+    /// Verifies the subtree at `page_id`, whose keys must be in `lower..upper`.
+    fn verify_node(
+        &mut self,
+        bufmgr: &mut BufferPoolManager,
+        page_id: PageId,
+        depth: usize,
+        lower: Option<&[u8]>,
+        upper: Option<&[u8]>,
+    ) -> Result<(), BTreeError> {
+        if !self.check_in_range(bufmgr, page_id) || !self.visit(page_id) {
+            return Ok(());
+        }
+        let buffer = bufmgr.fetch_page(page_id)?;
+        let page = buffer.page.borrow();
+        let node = Node::new(page.as_slice());
+        let node_type = node.header.node_type;
+        if node_type != node::NODE_TYPE_LEAF && node_type != node::NODE_TYPE_BRANCH {
+            self.violate(Violation::UnknownNodeType { page_id });
+            return Ok(());
+        }
+        match node::Body::new(node_type, node.body) {
+            node::Body::Leaf(leaf) => {
+                match self.leaf_depth {
+                    None => self.leaf_depth = Some(depth),
+                    Some(expected) if expected != depth => {
+                        self.violate(Violation::UnequalLeafDepth {
+                            page_id,
+                            depth,
+                            expected,
+                        });
+                    }
+                    Some(_) => {}
+                }
+                self.leaves
+                    .push((page_id, leaf.prev_page_id(), leaf.next_page_id()));
+                let pairs = match leaf.checked_pairs() {
+                    Ok(pairs) => pairs,
+                    Err(slot_id) => {
+                        self.violate(Violation::UnreadableSlot { page_id, slot_id });
+                        return Ok(());
+                    }
+                };
+                self.verify_keys(
+                    page_id,
+                    pairs.iter().map(|(key, _)| key.as_slice()),
+                    lower,
+                    upper,
+                );
+                let mut overflows = vec![];
+                for (slot_id, (_, value)) in pairs.iter().enumerate() {
+                    match overflow::Value::from_bytes(value) {
+                        Ok(overflow::Value::Overflow { page_id, len }) => {
+                            overflows.push((page_id, len as usize));
+                        }
+                        Ok(overflow::Value::Inline(_)) => {}
+                        Err(_) => self.violate(Violation::UnreadableSlot { page_id, slot_id }),
+                    }
+                }
+                drop(page);
+                for (page_id, len) in overflows {
+                    self.verify_overflow(bufmgr, page_id, len)?;
+                }
+            }
+            node::Body::Branch(branch) => {
+                let pairs = match branch.checked_pairs() {
+                    Ok(pairs) => pairs,
+                    Err(slot_id) => {
+                        self.violate(Violation::UnreadableSlot { page_id, slot_id });
+                        return Ok(());
+                    }
+                };
+                let keys: Vec<Vec<u8>> = pairs.iter().map(|(key, _)| key.to_vec()).collect();
+                self.verify_keys(page_id, keys.iter().map(Vec::as_slice), lower, upper);
+                let children: Vec<PageId> = pairs
+                    .iter()
+                    .map(|&(_, child)| child)
+                    .chain([branch.right_child()])
+                    .collect();
+                drop(page);
+                for (child_idx, child_page_id) in children.into_iter().enumerate() {
+                    let child_lower = match child_idx {
+                        0 => lower,
+                        _ => Some(keys[child_idx - 1].as_slice()),
+                    };
+                    let child_upper = keys.get(child_idx).map(Vec::as_slice).or(upper);
+                    self.verify_node(bufmgr, child_page_id, depth + 1, child_lower, child_upper)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn verify_keys<'a>(
+        &mut self,
+        page_id: PageId,
+        keys: impl Iterator<Item = &'a [u8]>,
+        lower: Option<&[u8]>,
+        upper: Option<&[u8]>,
+    ) {
+        let mut prev_key: Option<&[u8]> = None;
+        for (slot_id, key) in keys.enumerate() {
+            if prev_key.is_some_and(|prev_key| prev_key >= key) {
+                self.violate(Violation::UnorderedKey { page_id, slot_id });
+            }
+            if lower.is_some_and(|lower| key < lower) || upper.is_some_and(|upper| key >= upper) {
+                self.violate(Violation::KeyOutOfBounds { page_id, slot_id });
+            }
+            prev_key = Some(key);
+        }
+    }
+
+    fn verify_overflow(
+        &mut self,
+        bufmgr: &mut BufferPoolManager,
+        first_page_id: PageId,
+        len: usize,
+    ) -> Result<(), BTreeError> {
+        let mut remaining = len;
+        let mut next_page_id = Some(first_page_id);
+        while remaining > 0 {
+            let Some(page_id) = next_page_id else {
+                self.violate(Violation::BrokenOverflowChain {
+                    page_id: first_page_id,
+                });
+                break;
+            };
+            if !self.check_in_range(bufmgr, page_id) || !self.visit(page_id) {
+                break;
+            }
+            let buffer = bufmgr.fetch_page(page_id)?;
+            let page = buffer.page.borrow();
+            let overflow = overflow::Overflow::new(page.as_slice());
+            remaining = remaining.saturating_sub(overflow.capacity());
+            next_page_id = overflow.next_page_id();
+        }
+        Ok(())
+    }
+
+    fn verify_leaf_chain(&mut self) {
+        let leaves = std::mem::take(&mut self.leaves);
+        for (idx, &(page_id, prev_page_id, next_page_id)) in leaves.iter().enumerate() {
+            let expected_prev = idx.checked_sub(1).map(|idx| leaves[idx].0);
+            let expected_next = leaves.get(idx + 1).map(|leaf| leaf.0);
+            if prev_page_id != expected_prev || next_page_id != expected_next {
+                self.violate(Violation::BrokenLeafChain { page_id });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::btree::leaf::Leaf;
+    use crate::buffer::BufferPool;
+    use crate::disk::DiskManager;
+    use tempfile::tempfile;
+
+    #[test]
+    fn test_verify() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let btree = BTree::create(&mut bufmgr).unwrap();
+        for i in (0u64..200).rev() {
+            let value = vec![i as u8; if i % 50 == 0 { 10_000 } else { 100 }];
+            btree.insert(&mut bufmgr, &i.to_be_bytes(), &value).unwrap();
+        }
+        let report = btree.verify(&mut bufmgr).unwrap();
+        assert!(report.is_ok(), "{:?}", report.violations);
+
+        let key = |i: u64| {
+            let mut key = i.to_be_bytes().to_vec();
+            key.resize(600, 0);
+            key
+        };
+        let pairs = (0..100).map(|i| (key(i), vec![]));
+        let btree = BTree::bulk_load(&mut bufmgr, pairs, 1.0).unwrap();
+        let report = btree.verify(&mut bufmgr).unwrap();
+        assert!(report.is_ok(), "{:?}", report.violations);
+    }
+
+    #[test]
+    fn test_verify_broken_leaf_chain() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let btree = BTree::create(&mut bufmgr).unwrap();
+        for i in 0u64..16 {
+            btree
+                .insert(&mut bufmgr, &i.to_be_bytes(), &[0; 1024])
+                .unwrap();
+        }
+
+        let iter = btree
+            .search(&mut bufmgr, crate::btree::SearchMode::Start)
+            .unwrap();
+        let page_id = iter.buffer.page_id;
+        {
+            let mut page = iter.buffer.page.borrow_mut();
+            let mut leaf = Leaf::new(Node::new(page.as_mut_slice()).body);
+            leaf.set_next_page_id(None);
+            iter.buffer.is_dirty.set(true);
+        }
+
+        let report = btree.verify(&mut bufmgr).unwrap();
+        assert_eq!(
+            vec![Violation::BrokenLeafChain { page_id }],
+            report.violations
+        );
+    }
+
+    #[test]
+    fn test_verify_corrupted_pages() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let btree = BTree::create(&mut bufmgr).unwrap();
+        for i in 0u64..16 {
+            btree
+                .insert(&mut bufmgr, &i.to_be_bytes(), &[0; 1024])
+                .unwrap();
+        }
+        let root_page_id = {
+            let meta_buffer = bufmgr.fetch_page(btree.meta_page_id).unwrap();
+            let meta_page = meta_buffer.page.borrow();
+            meta::Meta::new(meta_page.as_slice())
+                .root_page_id()
+                .unwrap()
+        };
+
+        let iter = btree
+            .search(&mut bufmgr, crate::btree::SearchMode::Start)
+            .unwrap();
+        let leaf_page_id = iter.buffer.page_id;
+        {
+            // The pointer of the first pair follows the node, leaf and slotted headers and the
+            // pointer of the prefix.
+            let mut page = iter.buffer.page.borrow_mut();
+            page[36..38].copy_from_slice(&u16::MAX.to_ne_bytes());
+            iter.buffer.is_dirty.set(true);
+        }
+        {
+            // The right child of the root follows the node header.
+            let root_buffer = bufmgr.fetch_page(root_page_id).unwrap();
+            let mut page = root_buffer.page.borrow_mut();
+            page[8..16].copy_from_slice(&(u64::MAX - 1).to_ne_bytes());
+            root_buffer.is_dirty.set(true);
+        }
+
+        let report = btree.verify(&mut bufmgr).unwrap();
+        assert!(report.violations.contains(&Violation::UnreadableSlot {
+            page_id: leaf_page_id,
+            slot_id: 0,
+        }));
+        assert!(report.violations.contains(&Violation::PageOutOfRange {
+            page_id: PageId(u64::MAX - 1),
+        }));
+    }
+}
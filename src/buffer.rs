@@ -105,6 +105,7 @@ pub struct BufferPoolManager {
     disk: DiskManager,
     pool: BufferPool,
     page_table: HashMap<PageId, BufferId>,
+    num_fetches: u64,
 }
 
 impl BufferPoolManager {
@@ -113,10 +114,23 @@ impl BufferPoolManager {
             disk,
             pool,
             page_table: HashMap::new(),
+            num_fetches: 0,
         }
     }
 
+    /// The number of calls to [`fetch_page`](Self::fetch_page) so far, whether the page was
+    /// already in the pool or not.
+    pub fn num_fetches(&self) -> u64 {
+        self.num_fetches
+    }
+
+    /// The number of pages allocated in the file.
+    pub fn num_pages(&self) -> u64 {
+        self.disk.num_pages()
+    }
+
     pub fn fetch_page(&mut self, page_id: PageId) -> Result<Rc<Buffer>, BufferError> {
+        self.num_fetches += 1;
         if let Some(&buffer_id) = self.page_table.get(&page_id) {
             let frame = &mut self.pool[buffer_id];
             frame.usage_count += 1;
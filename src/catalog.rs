@@ -0,0 +1,415 @@
+use crate::btree::{BTree, BTreeError, SearchMode};
+use crate::buffer::BufferPoolManager;
+use crate::disk::PageId;
+use crate::error::DbError;
+use crate::table::{IndexKind, Table, UniqueIndex};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+/// The meta page of the catalog tree in a database file, which is the first page allocated in it.
+pub const CATALOG_META_PAGE_ID: PageId = PageId(0);
+
+/// The value stored under the empty key of every catalog tree, which no table can be named
+/// with. It tells a database file from other files starting with a B+ tree, like those of the
+/// examples.
+const CATALOG_MAGIC: &[u8] = b"putty_db catalog";
+
+/// The tables known to the query optimizer, with their column names and statistics.
+///
+/// A catalog opened from a database file keeps the definitions of its tables in a B+ tree
+/// mapping table names to their columns and pages, updated by
+/// [`create_table`](Self::create_table) and [`create_index`](Self::create_index). One built with
+/// `Default` and [`add_table`](Self::add_table) lives only in memory.
+#[derive(Default)]
+pub struct Catalog {
+    /// The meta page of the catalog tree, or `None` if the catalog is not stored.
+    pub meta_page_id: Option<PageId>,
+    pub tables: Vec<TableInfo>,
+}
+
+pub struct TableInfo {
+    pub name: String,
+    pub columns: Vec<Column>,
+    pub table: Table,
+    pub stats: TableStats,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Column {
+    pub name: String,
+    pub data_type: DataType,
+}
+
+/// How the bytes of a column are interpreted by SQL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DataType {
+    /// An integer encoded with [`tuple::encode_int`](crate::tuple::encode_int).
+    Int,
+    /// UTF-8 text.
+    Text,
+    /// [`TRUE`](crate::query::expr::TRUE) or [`FALSE`](crate::query::expr::FALSE).
+    Bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TableStats {
+    pub num_rows: usize,
+    pub num_leaf_pages: usize,
+    /// The height of the clustered tree, also used for the unique indexes.
+    pub height: usize,
+}
+
+/// A table as stored in the catalog tree.
+#[derive(Serialize, Deserialize)]
+struct TableEntry {
+    columns: Vec<Column>,
+    meta_page_id: PageId,
+    num_key_elems: usize,
+    unique_index: Vec<IndexEntry>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexEntry {
+    kind: IndexKind,
+    meta_page_id: PageId,
+    skey: Vec<usize>,
+    include: Vec<usize>,
+}
+
+impl Default for TableStats {
+    /// Guesses used for a table until [`Catalog::analyze`] is run.
+    fn default() -> Self {
+        Self {
+            num_rows: 1000,
+            num_leaf_pages: 10,
+            height: 2,
+        }
+    }
+}
+
+impl Column {
+    pub fn new(name: &str, data_type: DataType) -> Self {
+        Self {
+            name: name.to_string(),
+            data_type,
+        }
+    }
+}
+
+impl Display for DataType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            DataType::Int => f.write_str("INT"),
+            DataType::Text => f.write_str("TEXT"),
+            DataType::Bool => f.write_str("BOOLEAN"),
+        }
+    }
+}
+
+impl Catalog {
+    /// Creates an empty catalog stored in a new tree.
+    pub fn create(bufmgr: &mut BufferPoolManager) -> Result<Self, DbError> {
+        let btree = BTree::create(bufmgr)?;
+        btree.insert(bufmgr, b"", CATALOG_MAGIC)?;
+        Ok(Self {
+            meta_page_id: Some(btree.meta_page_id),
+            tables: vec![],
+        })
+    }
+
+    /// Reads the catalog stored in the tree at `meta_page_id`. The statistics of the tables are
+    /// not stored, so they are the defaults until [`analyze`](Self::analyze) is run. Fails with
+    /// [`DbError::NotADatabase`] if the tree is not a catalog.
+    pub fn open(bufmgr: &mut BufferPoolManager, meta_page_id: PageId) -> Result<Self, DbError> {
+        let mut tables = vec![];
+        let mut iter = BTree::new(meta_page_id).search(bufmgr, SearchMode::Start)?;
+        match iter.next(bufmgr)? {
+            Some((key, magic)) if key.is_empty() && magic == CATALOG_MAGIC => {}
+            _ => return Err(DbError::NotADatabase),
+        }
+        while let Some((name, entry)) = iter.next(bufmgr)? {
+            let entry: TableEntry = bincode::options()
+                .deserialize(&entry)
+                .map_err(|err| DbError::Corrupted(format!("catalog entry: {err}")))?;
+            let name = String::from_utf8(name)
+                .map_err(|_| DbError::Corrupted("table name is not UTF-8".to_string()))?;
+            let unique_index = entry
+                .unique_index
+                .into_iter()
+                .map(|index| UniqueIndex {
+                    name: index_name(&name, &entry.columns, &index.skey),
+                    kind: index.kind,
+                    meta_page_id: index.meta_page_id,
+                    skey: index.skey,
+                    include: index.include,
+                })
+                .collect();
+            tables.push(TableInfo {
+                name,
+                columns: entry.columns,
+                table: Table {
+                    meta_page_id: entry.meta_page_id,
+                    num_key_elems: entry.num_key_elems,
+                    unique_index,
+                },
+                stats: TableStats::default(),
+            });
+        }
+        Ok(Self {
+            meta_page_id: Some(meta_page_id),
+            tables,
+        })
+    }
+
+    /// Opens the catalog of a database file, creating it first if the file is empty.
+    pub fn open_or_create(bufmgr: &mut BufferPoolManager) -> Result<Self, DbError> {
+        if bufmgr.num_pages() > 0 {
+            return Self::open(bufmgr, CATALOG_META_PAGE_ID);
+        }
+        let catalog = Self::create(bufmgr)?;
+        assert_eq!(Some(CATALOG_META_PAGE_ID), catalog.meta_page_id);
+        Ok(catalog)
+    }
+
+    pub fn add_table(
+        &mut self,
+        name: &str,
+        columns: Vec<Column>,
+        table: Table,
+    ) -> Result<(), DbError> {
+        if self.table(name).is_some() {
+            return Err(DbError::AlreadyExists(format!("table {name}")));
+        }
+        if columns.len() < table.num_key_elems {
+            return Err(DbError::InvalidSchema(format!(
+                "table {name} has fewer columns than key elements"
+            )));
+        }
+        self.tables.push(TableInfo {
+            name: name.to_string(),
+            columns,
+            table,
+            stats: TableStats::default(),
+        });
+        Ok(())
+    }
+
+    /// Creates an empty table whose primary key is its first `num_key_elems` columns, and
+    /// stores its definition.
+    pub fn create_table(
+        &mut self,
+        bufmgr: &mut BufferPoolManager,
+        name: &str,
+        columns: Vec<Column>,
+        num_key_elems: usize,
+    ) -> Result<(), DbError> {
+        if self.table(name).is_some() {
+            return Err(DbError::AlreadyExists(format!("table {name}")));
+        }
+        if num_key_elems == 0 {
+            return Err(DbError::InvalidSchema(format!(
+                "table {name} has no primary key"
+            )));
+        }
+        let mut table = Table {
+            meta_page_id: PageId::INVALID_PAGE_ID,
+            num_key_elems,
+            unique_index: vec![],
+        };
+        table.create(bufmgr)?;
+        self.add_table(name, columns, table)?;
+        self.store(bufmgr, name)
+    }
+
+    /// Builds a unique index of `kind` on the `skey` columns of a table, also holding the
+    /// `include` columns, and stores it with the table definition.
+    pub fn create_index(
+        &mut self,
+        bufmgr: &mut BufferPoolManager,
+        table_name: &str,
+        kind: IndexKind,
+        skey: Vec<usize>,
+        include: Vec<usize>,
+    ) -> Result<(), DbError> {
+        let info = self
+            .table_mut(table_name)
+            .ok_or_else(|| DbError::NotFound(format!("table {table_name}")))?;
+        let name = index_name(table_name, &info.columns, &skey);
+        match kind {
+            IndexKind::BTree => info
+                .table
+                .add_unique_index_including(bufmgr, &name, skey, include)?,
+            IndexKind::Hash if include.is_empty() => {
+                info.table.add_hash_index(bufmgr, &name, skey)?
+            }
+            IndexKind::Hash => {
+                return Err(DbError::Unsupported(
+                    "hash indexes with included columns".to_string(),
+                ))
+            }
+        }
+        self.store(bufmgr, table_name)
+    }
+
+    /// Writes the definition of a table to the catalog tree, if the catalog is stored.
+    fn store(&self, bufmgr: &mut BufferPoolManager, name: &str) -> Result<(), DbError> {
+        let Some(meta_page_id) = self.meta_page_id else {
+            return Ok(());
+        };
+        let info = self
+            .table(name)
+            .ok_or_else(|| DbError::NotFound(format!("table {name}")))?;
+        let entry = TableEntry {
+            columns: info.columns.clone(),
+            meta_page_id: info.table.meta_page_id,
+            num_key_elems: info.table.num_key_elems,
+            unique_index: info
+                .table
+                .unique_index
+                .iter()
+                .map(|index| IndexEntry {
+                    kind: index.kind,
+                    meta_page_id: index.meta_page_id,
+                    skey: index.skey.clone(),
+                    include: index.include.clone(),
+                })
+                .collect(),
+        };
+        let btree = BTree::new(meta_page_id);
+        match btree.delete(bufmgr, name.as_bytes()) {
+            Ok(()) | Err(BTreeError::KeyNotFound) => {}
+            Err(err) => return Err(err.into()),
+        }
+        btree.insert(
+            bufmgr,
+            name.as_bytes(),
+            &bincode::options()
+                .serialize(&entry)
+                .map_err(|err| DbError::Invalid(err.to_string()))?,
+        )?;
+        Ok(())
+    }
+
+    pub fn table(&self, name: &str) -> Option<&TableInfo> {
+        self.tables.iter().find(|info| info.name == name)
+    }
+
+    pub fn table_mut(&mut self, name: &str) -> Option<&mut TableInfo> {
+        self.tables.iter_mut().find(|info| info.name == name)
+    }
+
+    /// Recomputes the statistics of every table by walking its clustered tree.
+    pub fn analyze(&mut self, bufmgr: &mut BufferPoolManager) -> Result<(), DbError> {
+        for info in &mut self.tables {
+            let stats = BTree::new(info.table.meta_page_id).stats(bufmgr)?;
+            info.stats = TableStats {
+                num_rows: stats.num_keys,
+                num_leaf_pages: stats.num_leaf_pages,
+                height: stats.height,
+            };
+        }
+        Ok(())
+    }
+}
+
+/// Names a unique index after its table and columns, like `users_email_key`. Index names are
+/// not stored, so they are derived again when the catalog is read.
+fn index_name(table: &str, columns: &[Column], skey: &[usize]) -> String {
+    let mut name = table.to_string();
+    for &index in skey {
+        name += "_";
+        name += &columns[index].name;
+    }
+    name + "_key"
+}
+
+impl TableInfo {
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|column| column.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::BufferPool;
+    use crate::disk::DiskManager;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_catalog_persistence() {
+        let file = NamedTempFile::new().unwrap();
+        let columns = vec![
+            Column::new("id", DataType::Int),
+            Column::new("name", DataType::Text),
+            Column::new("email", DataType::Text),
+        ];
+        {
+            let disk = DiskManager::open(file.path()).unwrap();
+            let mut bufmgr = BufferPoolManager::new(disk, BufferPool::new(10));
+            let mut catalog = Catalog::open_or_create(&mut bufmgr).unwrap();
+            catalog
+                .create_table(&mut bufmgr, "users", columns.clone(), 1)
+                .unwrap();
+            catalog
+                .create_index(&mut bufmgr, "users", IndexKind::BTree, vec![2], vec![1])
+                .unwrap();
+            catalog
+                .create_index(&mut bufmgr, "users", IndexKind::Hash, vec![1], vec![])
+                .unwrap();
+            assert!(matches!(
+                catalog.create_table(&mut bufmgr, "users", columns.clone(), 1),
+                Err(DbError::AlreadyExists(_))
+            ));
+            assert!(matches!(
+                catalog.create_index(&mut bufmgr, "users", IndexKind::Hash, vec![2], vec![0]),
+                Err(DbError::Unsupported(_))
+            ));
+            assert!(matches!(
+                catalog.store(&mut bufmgr, "orders"),
+                Err(DbError::NotFound(_))
+            ));
+            bufmgr.flush().unwrap();
+        }
+
+        let disk = DiskManager::open(file.path()).unwrap();
+        let mut bufmgr = BufferPoolManager::new(disk, BufferPool::new(10));
+        let catalog = Catalog::open_or_create(&mut bufmgr).unwrap();
+        let info = catalog.table("users").unwrap();
+        assert_eq!(columns, info.columns);
+        assert_eq!(1, info.table.num_key_elems);
+        assert_eq!(vec![2], info.table.unique_index[0].skey);
+        assert_eq!(vec![1], info.table.unique_index[0].include);
+        assert_eq!("users_email_key", info.table.unique_index[0].name);
+        assert_eq!(IndexKind::Hash, info.table.unique_index[1].kind);
+        assert_eq!(Some(2), info.column_index("email"));
+    }
+
+    #[test]
+    fn test_not_a_database() {
+        // A file of the examples starts with the tree of a table, not a catalog.
+        let file = NamedTempFile::new().unwrap();
+        {
+            let disk = DiskManager::open(file.path()).unwrap();
+            let mut bufmgr = BufferPoolManager::new(disk, BufferPool::new(10));
+            let mut table = Table {
+                meta_page_id: PageId::INVALID_PAGE_ID,
+                num_key_elems: 1,
+                unique_index: vec![],
+            };
+            table.create(&mut bufmgr).unwrap();
+            table
+                .insert(&mut bufmgr, &[b"a", b"Charlie", b"MUNGER"])
+                .unwrap();
+            bufmgr.flush().unwrap();
+        }
+
+        let disk = DiskManager::open(file.path()).unwrap();
+        let mut bufmgr = BufferPoolManager::new(disk, BufferPool::new(10));
+        assert!(matches!(
+            Catalog::open_or_create(&mut bufmgr),
+            Err(DbError::NotADatabase)
+        ));
+    }
+}
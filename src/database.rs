@@ -0,0 +1,645 @@
+use std::fmt::{self, Display, Formatter};
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::btree::{BTree, SearchMode};
+use crate::buffer::{BufferPool, BufferPoolManager};
+use crate::catalog::{Catalog, Column, DataType, TableInfo};
+use crate::disk::DiskManager;
+use crate::error::DbError;
+use crate::query::TupleSlice;
+use crate::sql::{self, ast, MappingError, Output, Plan, ResultSet, Value};
+use crate::tuple;
+
+/// How a [`Database`] is opened.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// The number of pages cached in memory.
+    pub pool_size: usize,
+    /// Whether to compute the statistics of the tables when the database is opened, which
+    /// reads every table. Otherwise the optimizer uses guesses until `ANALYZE` is run.
+    pub analyze: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            pool_size: 1024,
+            analyze: false,
+        }
+    }
+}
+
+/// A database file with its buffer pool and catalog, run through SQL.
+///
+/// Changes are written to the file by [`flush`](Self::flush) and when the database is closed
+/// or dropped.
+pub struct Database {
+    catalog: Catalog,
+    bufmgr: BufferPoolManager,
+    /// Whether the database was closed by [`close`](Self::close), which flushed it already.
+    closed: bool,
+}
+
+/// A statement parsed and planned once by [`Database::prepare`], then run any number of times
+/// with values for its parameters.
+///
+/// It must be run on the database that prepared it.
+pub struct Statement {
+    plan: Plan,
+    /// The names of the parameters if they are written `:name`, in the order of their numbers.
+    names: Vec<String>,
+}
+
+/// The rows returned by [`Database::query`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rows {
+    result_set: ResultSet,
+}
+
+/// A row of [`Rows`].
+#[derive(Debug, Clone, Copy)]
+pub struct Row<'a> {
+    columns: &'a [Column],
+    values: TupleSlice<'a>,
+}
+
+/// A table of a [`Database`], read and written by primary key without SQL.
+pub struct TableHandle<'a> {
+    info: &'a TableInfo,
+    bufmgr: &'a mut BufferPoolManager,
+}
+
+impl Database {
+    /// Opens the database in the file at `path`, creating it if it does not exist.
+    pub fn open(path: impl AsRef<Path>, options: Options) -> Result<Self, DbError> {
+        Self::with_disk(DiskManager::open(path)?, options)
+    }
+
+    /// Opens the database in the file of `disk`.
+    pub fn with_disk(disk: DiskManager, options: Options) -> Result<Self, DbError> {
+        let mut bufmgr = BufferPoolManager::new(disk, BufferPool::new(options.pool_size));
+        let mut catalog = Catalog::open_or_create(&mut bufmgr)?;
+        if options.analyze {
+            catalog.analyze(&mut bufmgr)?;
+        }
+        Ok(Self {
+            catalog,
+            bufmgr,
+            closed: false,
+        })
+    }
+
+    /// Runs the statements in `sql`, returning the number of rows they inserted, updated or
+    /// deleted. The rows returned by queries are discarded.
+    pub fn execute(&mut self, sql: &str) -> Result<usize, DbError> {
+        let mut count = 0;
+        for statement in sql::parse_statements(sql)? {
+            if let Output::Affected(n) = self.run(&statement)? {
+                count += n;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Runs a single statement returning rows, like `SELECT` or `EXPLAIN`.
+    pub fn query(&mut self, sql: &str) -> Result<Rows, DbError> {
+        self.prepare(sql)?.query(self, &[])
+    }
+
+    /// Runs a single statement returning rows like [`query`](Self::query), reading each row into
+    /// a `T` by column name, see [`sql::from_row`].
+    pub fn query_as<T: DeserializeOwned>(&mut self, sql: &str) -> Result<Vec<T>, DbError> {
+        Ok(self.query(sql)?.deserialize()?)
+    }
+
+    /// Runs a parsed statement.
+    pub fn run(&mut self, statement: &ast::Statement) -> Result<Output, DbError> {
+        sql::execute(&mut self.catalog, &mut self.bufmgr, statement)
+    }
+
+    /// Parses and plans a single statement, whose parameters are written `?`, `$n` or `:name`.
+    /// The type of each parameter is inferred from where it is used.
+    pub fn prepare(&self, sql: &str) -> Result<Statement, DbError> {
+        let (statement, names) = sql::parse_with_param_names(sql)?;
+        Ok(Statement {
+            plan: sql::prepare(&self.catalog, &statement)?,
+            names,
+        })
+    }
+
+    pub fn catalog(&self) -> &Catalog {
+        &self.catalog
+    }
+
+    /// Returns the catalog and the buffer pool, to use the lower layers directly.
+    pub fn parts_mut(&mut self) -> (&mut Catalog, &mut BufferPoolManager) {
+        (&mut self.catalog, &mut self.bufmgr)
+    }
+
+    pub fn table(&mut self, name: &str) -> Result<TableHandle<'_>, DbError> {
+        let info = self
+            .catalog
+            .table(name)
+            .ok_or_else(|| DbError::NotFound(format!("table {name}")))?;
+        Ok(TableHandle {
+            info,
+            bufmgr: &mut self.bufmgr,
+        })
+    }
+
+    /// Writes the changes made so far to the file.
+    pub fn flush(&mut self) -> Result<(), DbError> {
+        self.bufmgr.flush()?;
+        Ok(())
+    }
+
+    /// Flushes and closes the database, returning the errors that dropping it would ignore.
+    pub fn close(mut self) -> Result<(), DbError> {
+        self.flush()?;
+        self.closed = true;
+        Ok(())
+    }
+}
+
+impl Drop for Database {
+    fn drop(&mut self) {
+        if !self.closed {
+            let _ = self.bufmgr.flush();
+        }
+    }
+}
+
+impl Statement {
+    pub fn param_types(&self) -> &[DataType] {
+        self.plan.param_types()
+    }
+
+    /// Runs the statement with `params` as the values of its parameters in order, returning
+    /// the number of rows it inserted, updated or deleted.
+    pub fn execute(&self, db: &mut Database, params: &[Value]) -> Result<usize, DbError> {
+        match self.plan.run(&mut db.catalog, &mut db.bufmgr, params)? {
+            Output::Affected(count) => Ok(count),
+            _ => Ok(0),
+        }
+    }
+
+    /// Runs the statement, which must return rows, with `params` as the values of its
+    /// parameters in order.
+    pub fn query(&self, db: &mut Database, params: &[Value]) -> Result<Rows, DbError> {
+        if !self.plan.returns_rows() {
+            return Err(DbError::Invalid(
+                "statement does not return rows".to_string(),
+            ));
+        }
+        match self.plan.run(&mut db.catalog, &mut db.bufmgr, params)? {
+            Output::Rows(result_set) => Ok(Rows { result_set }),
+            _ => Err(DbError::Invalid(
+                "statement does not return rows".to_string(),
+            )),
+        }
+    }
+
+    /// Like [`query`](Self::query), reading each row into a `T`.
+    pub fn query_as<T: DeserializeOwned>(
+        &self,
+        db: &mut Database,
+        params: &[Value],
+    ) -> Result<Vec<T>, DbError> {
+        Ok(self.query(db, params)?.deserialize()?)
+    }
+
+    /// Like [`execute`](Self::execute), with the values of `:name` parameters given by name.
+    pub fn execute_named(
+        &self,
+        db: &mut Database,
+        params: &[(&str, Value)],
+    ) -> Result<usize, DbError> {
+        self.execute(db, &self.positional(params)?)
+    }
+
+    /// Like [`query`](Self::query), with the values of `:name` parameters given by name.
+    pub fn query_named(
+        &self,
+        db: &mut Database,
+        params: &[(&str, Value)],
+    ) -> Result<Rows, DbError> {
+        self.query(db, &self.positional(params)?)
+    }
+
+    /// Orders named parameters by their numbers.
+    fn positional(&self, params: &[(&str, Value)]) -> Result<Vec<Value>, DbError> {
+        let mut values = vec![None; self.names.len()];
+        for (name, value) in params {
+            let name = name.strip_prefix(':').unwrap_or(name).to_ascii_lowercase();
+            let index = self
+                .names
+                .iter()
+                .position(|other| *other == name)
+                .ok_or_else(|| DbError::NotFound(format!("parameter :{name}")))?;
+            if values[index].replace(value.clone()).is_some() {
+                return Err(DbError::Invalid(format!(
+                    "parameter :{name} is given more than once"
+                )));
+            }
+        }
+        values
+            .into_iter()
+            .zip(&self.names)
+            .map(|(value, name)| {
+                value.ok_or_else(|| DbError::Invalid(format!("parameter :{name} has no value")))
+            })
+            .collect()
+    }
+}
+
+impl Rows {
+    pub fn columns(&self) -> &[Column] {
+        &self.result_set.columns
+    }
+
+    pub fn len(&self) -> usize {
+        self.result_set.rows.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.result_set.rows.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<Row<'_>> {
+        let values = self.result_set.rows.get(index)?;
+        Some(Row {
+            columns: &self.result_set.columns,
+            values,
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = Row<'_>> {
+        self.result_set.rows.iter().map(|values| Row {
+            columns: &self.result_set.columns,
+            values,
+        })
+    }
+
+    /// Reads every row into a `T`.
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<Vec<T>, MappingError> {
+        self.iter().map(|row| row.deserialize()).collect()
+    }
+
+    pub fn into_result_set(self) -> ResultSet {
+        self.result_set
+    }
+}
+
+impl Display for Rows {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.result_set.fmt(f)
+    }
+}
+
+impl<'a> Row<'a> {
+    /// Returns the value of the column at `index`, or `None` if it is NULL or out of range.
+    pub fn get(&self, index: usize) -> Option<Value> {
+        Value::decode(self.values.get(index)?, self.columns[index].data_type)
+    }
+
+    /// Returns the value of the column named `name`, or `None` if it is NULL or there is no
+    /// such column.
+    pub fn get_by_name(&self, name: &str) -> Option<Value> {
+        let index = self.columns.iter().position(|column| column.name == name)?;
+        self.get(index)
+    }
+
+    pub fn values(&self) -> Vec<Option<Value>> {
+        (0..self.values.len()).map(|i| self.get(i)).collect()
+    }
+
+    /// Reads the row into a `T`, which may borrow text from it.
+    pub fn deserialize<T: Deserialize<'a>>(&self) -> Result<T, MappingError> {
+        sql::from_row(self.columns, self.values)
+    }
+
+    /// Returns the bytes of the columns, as stored.
+    pub fn raw(&self) -> TupleSlice<'a> {
+        self.values
+    }
+}
+
+impl<'a> TableHandle<'a> {
+    pub fn columns(&self) -> &[Column] {
+        &self.info.columns
+    }
+
+    /// Inserts a row with a value for every column.
+    pub fn insert(&mut self, values: &[Value]) -> Result<(), DbError> {
+        let values: Vec<Option<Value>> = values.iter().cloned().map(Some).collect();
+        self.insert_values(&values)
+    }
+
+    /// Inserts a row with the value of each column or `None` for NULL, which the primary key
+    /// columns cannot be.
+    pub fn insert_values(&mut self, values: &[Option<Value>]) -> Result<(), DbError> {
+        let columns = &self.info.columns;
+        if values.len() != columns.len() {
+            return Err(DbError::Invalid(format!(
+                "expected {} values, found {}",
+                columns.len(),
+                values.len()
+            )));
+        }
+        let mut record = vec![];
+        for (i, (value, column)) in values.iter().zip(columns).enumerate() {
+            match value {
+                Some(value) => record.push(encode(value, column)?),
+                None if i < self.info.table.num_key_elems => {
+                    let name = &column.name;
+                    return Err(DbError::Invalid(format!(
+                        "primary key column {name} must be given a value"
+                    )));
+                }
+                None => record.push(vec![]),
+            }
+        }
+        let record: Vec<&[u8]> = record.iter().map(Vec::as_slice).collect();
+        self.info.table.insert(self.bufmgr, &record)
+    }
+
+    /// Inserts a row from the fields of `value` named after the columns, see [`sql::to_row`].
+    /// Columns without a field are NULL.
+    pub fn insert_struct<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), DbError> {
+        let values = sql::to_row(value, &self.info.columns)?;
+        self.insert_values(&values)
+    }
+
+    /// Returns the row with the primary key `key`, if there is one.
+    pub fn get(&mut self, key: &[Value]) -> Result<Option<Vec<Option<Value>>>, DbError> {
+        let Some(record) = self.get_record(key)? else {
+            return Ok(None);
+        };
+        let values = record
+            .iter()
+            .zip(&self.info.columns)
+            .map(|(bytes, column)| Value::decode(bytes, column.data_type))
+            .collect();
+        Ok(Some(values))
+    }
+
+    /// Deletes the row with the primary key `key`, returning whether there was one.
+    pub fn delete(&mut self, key: &[Value]) -> Result<bool, DbError> {
+        let Some(record) = self.get_record(key)? else {
+            return Ok(false);
+        };
+        self.info.table.delete(self.bufmgr, &record)?;
+        Ok(true)
+    }
+
+    /// Returns every row in primary key order.
+    pub fn scan(&mut self) -> Result<Rows, DbError> {
+        let btree = BTree::new(self.info.table.meta_page_id);
+        let mut rows = vec![];
+        for pair in btree.cursor(self.bufmgr, SearchMode::Start)? {
+            let (key, value) = pair?;
+            let mut record = vec![];
+            tuple::decode(&key, &mut record);
+            tuple::decode(&value, &mut record);
+            rows.push(record);
+        }
+        Ok(Rows {
+            result_set: ResultSet {
+                columns: self.info.columns.clone(),
+                rows,
+            },
+        })
+    }
+
+    fn get_record(&mut self, key: &[Value]) -> Result<Option<Vec<Vec<u8>>>, DbError> {
+        let key = self.encode(key, self.info.table.num_key_elems)?;
+        let mut encoded_key = vec![];
+        tuple::encode(key.iter(), &mut encoded_key);
+        let btree = BTree::new(self.info.table.meta_page_id);
+        let mut iter = btree.search(self.bufmgr, SearchMode::Key(encoded_key.clone()))?;
+        match iter.next(self.bufmgr)? {
+            Some((found, value)) if found == encoded_key => {
+                let mut record = key;
+                tuple::decode(&value, &mut record);
+                Ok(Some(record))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Encodes the values of the first `len` columns, checking their types.
+    fn encode(&self, values: &[Value], len: usize) -> Result<Vec<Vec<u8>>, DbError> {
+        if values.len() != len {
+            return Err(DbError::Invalid(format!(
+                "expected {len} values, found {}",
+                values.len()
+            )));
+        }
+        values
+            .iter()
+            .zip(&self.info.columns)
+            .map(|(value, column)| encode(value, column))
+            .collect()
+    }
+}
+
+/// Encodes a value of `column`, checking its type.
+fn encode(value: &Value, column: &Column) -> Result<Vec<u8>, DbError> {
+    if value.data_type() != column.data_type {
+        return Err(DbError::Invalid(format!(
+            "column {} is of type {}, but the value is of type {}",
+            column.name,
+            column.data_type,
+            value.data_type()
+        )));
+    }
+    Ok(value.encode())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::catalog::TableStats;
+    use tempfile::NamedTempFile;
+
+    #[test]
+    fn test_database() {
+        let file = NamedTempFile::new().unwrap();
+        {
+            let mut db = Database::open(file.path(), Options::default()).unwrap();
+            assert_eq!(
+                2,
+                db.execute(
+                    "CREATE TABLE users (id INT PRIMARY KEY, name TEXT, admin BOOLEAN);
+                     INSERT INTO users VALUES (1, 'alice', true), (2, 'bob', false);",
+                )
+                .unwrap()
+            );
+            let mut users = db.table("users").unwrap();
+            users
+                .insert(&[
+                    Value::Int(3),
+                    Value::Text("carol".to_string()),
+                    Value::Bool(false),
+                ])
+                .unwrap();
+            assert!(users
+                .insert(&[Value::Int(4), Value::Text("dave".to_string())])
+                .is_err());
+            assert!(users.delete(&[Value::Int(2)]).unwrap());
+            assert!(!users.delete(&[Value::Int(2)]).unwrap());
+            db.close().unwrap();
+        }
+
+        // Dropping the database flushes it too.
+        {
+            let mut db = Database::open(file.path(), Options::default()).unwrap();
+            db.execute("UPDATE users SET admin = true WHERE id = 3")
+                .unwrap();
+        }
+
+        // Opening the database reads no table unless asked to.
+        {
+            let db = Database::open(file.path(), Options::default()).unwrap();
+            let users = db.catalog().table("users").unwrap();
+            assert_eq!(TableStats::default().num_rows, users.stats.num_rows);
+            db.close().unwrap();
+            let options = Options {
+                analyze: true,
+                ..Options::default()
+            };
+            let db = Database::open(file.path(), options).unwrap();
+            assert_eq!(2, db.catalog().table("users").unwrap().stats.num_rows);
+        }
+
+        let mut db = Database::open(file.path(), Options::default()).unwrap();
+        let rows = db
+            .query("SELECT id, name FROM users WHERE admin ORDER BY id")
+            .unwrap();
+        assert_eq!(2, rows.len());
+        let row = rows.get(1).unwrap();
+        assert_eq!(Some(Value::Int(3)), row.get(0));
+        assert_eq!(
+            Some(Value::Text("carol".to_string())),
+            row.get_by_name("name")
+        );
+        assert!(db.query("DELETE FROM users").is_err());
+        assert_eq!(2, db.query("SELECT * FROM users").unwrap().len());
+
+        let mut users = db.table("users").unwrap();
+        assert_eq!(
+            Some(vec![
+                Some(Value::Int(1)),
+                Some(Value::Text("alice".to_string())),
+                Some(Value::Bool(true)),
+            ]),
+            users.get(&[Value::Int(1)]).unwrap()
+        );
+        assert_eq!(None, users.get(&[Value::Int(2)]).unwrap());
+        assert_eq!(2, users.scan().unwrap().len());
+        assert!(db.table("nothing").is_err());
+    }
+
+    #[test]
+    fn test_prepare() {
+        let disk = DiskManager::new(tempfile::tempfile().unwrap()).unwrap();
+        let mut db = Database::with_disk(disk, Options::default()).unwrap();
+        db.execute("CREATE TABLE users (id INT PRIMARY KEY, name TEXT)")
+            .unwrap();
+
+        let insert = db.prepare("INSERT INTO users VALUES (?, ?)").unwrap();
+        assert_eq!([DataType::Int, DataType::Text], insert.param_types());
+        for (id, name) in [(1, "alice"), (2, "bob"), (3, "carol")] {
+            let params = [Value::Int(id), Value::Text(name.to_string())];
+            assert_eq!(1, insert.execute(&mut db, &params).unwrap());
+        }
+        assert!(insert.execute(&mut db, &[Value::Int(4)]).is_err());
+        assert!(insert
+            .execute(&mut db, &[Value::Text("4".to_string()), Value::Int(4)])
+            .is_err());
+
+        let select = db
+            .prepare("SELECT name FROM users WHERE id = :id OR name = :name")
+            .unwrap();
+        let name = |rows: Rows| rows.get(0).and_then(|row| row.get(0));
+        let rows = select
+            .query_named(
+                &mut db,
+                &[
+                    ("name", Value::Text("nobody".to_string())),
+                    (":id", Value::Int(2)),
+                ],
+            )
+            .unwrap();
+        assert_eq!(Some(Value::Text("bob".to_string())), name(rows));
+        assert!(select
+            .query_named(&mut db, &[("id", Value::Int(2))])
+            .is_err());
+        assert!(select.execute(&mut db, &[]).is_err());
+
+        // The plan made once looks up the key given each time.
+        let by_id = db.prepare("SELECT name FROM users WHERE id = ?").unwrap();
+        let explain = db
+            .prepare("EXPLAIN SELECT name FROM users WHERE id = ?")
+            .unwrap();
+        let plan = explain
+            .query(&mut db, &[Value::Int(3)])
+            .unwrap()
+            .to_string();
+        assert!(plan.contains("range=(#0 = 0x8000000000000003)"), "{plan}");
+        let rows = by_id.query(&mut db, &[Value::Int(3)]).unwrap();
+        assert_eq!(Some(Value::Text("carol".to_string())), name(rows));
+        assert!(by_id.query(&mut db, &[Value::Int(9)]).unwrap().is_empty());
+
+        let delete = db.prepare("DELETE FROM users WHERE id < $1").unwrap();
+        assert!(delete.query(&mut db, &[Value::Int(3)]).is_err());
+        assert_eq!(2, delete.execute(&mut db, &[Value::Int(3)]).unwrap());
+        assert!(db.prepare("SELECT ? FROM users").is_err());
+    }
+
+    #[test]
+    fn test_query_as() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct User {
+            id: i64,
+            name: String,
+            email: Option<String>,
+        }
+
+        let disk = DiskManager::new(tempfile::tempfile().unwrap()).unwrap();
+        let mut db = Database::with_disk(disk, Options::default()).unwrap();
+        db.execute(
+            "CREATE TABLE users (id INT PRIMARY KEY, name TEXT, email TEXT, admin BOOLEAN);
+             INSERT INTO users (id, name, admin) VALUES (1, 'alice', true);",
+        )
+        .unwrap();
+        let bob = User {
+            id: 2,
+            name: "bob".to_string(),
+            email: Some("bob@example.com".to_string()),
+        };
+        let mut users = db.table("users").unwrap();
+        users.insert_struct(&bob).unwrap();
+        assert!(users.insert_struct(&(3, "carol")).is_err());
+
+        let all: Vec<User> = db.query_as("SELECT * FROM users ORDER BY id").unwrap();
+        assert_eq!(2, all.len());
+        assert_eq!(None, all[0].email);
+        assert_eq!(bob, all[1]);
+
+        let select = db
+            .prepare("SELECT name, admin FROM users WHERE id = ?")
+            .unwrap();
+        let rows: Vec<(String, Option<bool>)> = select.query_as(&mut db, &[Value::Int(2)]).unwrap();
+        assert_eq!(vec![("bob".to_string(), None)], rows);
+        let rows = select.query(&mut db, &[Value::Int(1)]).unwrap();
+        let (name, admin): (&str, bool) = rows.get(0).unwrap().deserialize().unwrap();
+        assert_eq!(("alice", true), (name, admin));
+        assert!(db
+            .query_as::<(i64, i64)>("SELECT id, name FROM users")
+            .is_err());
+    }
+}
@@ -3,11 +3,14 @@ use std::io;
 use std::io::{Read, Seek, SeekFrom, Write};
 use std::path::Path;
 
+use serde::{Deserialize, Serialize};
 use zerocopy::{AsBytes, FromBytes, FromZeroes};
 
 pub const PAGE_SIZE: usize = 4096;
 
-#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, FromZeroes, FromBytes, AsBytes)]
+#[derive(
+    Debug, Clone, Copy, Eq, PartialEq, Hash, FromZeroes, FromBytes, AsBytes, Serialize, Deserialize,
+)]
 #[repr(C)]
 pub struct PageId(pub u64);
 
@@ -74,6 +77,11 @@ impl DiskManager {
         Self::new(heap_file)
     }
 
+    /// The number of pages in the file, including those allocated but not written yet.
+    pub fn num_pages(&self) -> u64 {
+        self.next_page_id
+    }
+
     pub fn allocate_page(&mut self) -> PageId {
         let page_id = self.next_page_id;
         self.next_page_id += 1;
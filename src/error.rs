@@ -0,0 +1,111 @@
+use crate::btree::BTreeError;
+use crate::buffer::BufferError;
+use crate::hash::HashError;
+use crate::heap::HeapError;
+use crate::query::expr::EvalError;
+use crate::sql::{MappingError, ParseError};
+use std::io;
+use thiserror::Error;
+
+/// The errors returned by tables, queries and the [`Database`](crate::Database) handle.
+#[derive(Debug, Error)]
+pub enum DbError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("no free buffer available in buffer pool")]
+    BufferExhausted,
+    #[error("duplicate key")]
+    DuplicateKey,
+    #[error("duplicate key violates unique index {index}")]
+    UniqueViolation { index: String },
+    /// A table, column, key or other object that is looked up by name, like `table users`.
+    #[error("{0} does not exist")]
+    NotFound(String),
+    /// A file opened as a database that does not start with a catalog.
+    #[error("file is not a database")]
+    NotADatabase,
+    /// Data read from the file that cannot be decoded.
+    #[error("database is corrupted: {0}")]
+    Corrupted(String),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+    #[error(transparent)]
+    Eval(#[from] EvalError),
+    #[error(transparent)]
+    Mapping(#[from] MappingError),
+    /// Other failures of the B+ tree, like a key too large to fit in a page.
+    #[error(transparent)]
+    BTree(BTreeError),
+    /// Other failures of the hash index, like a directory that cannot grow any more.
+    #[error(transparent)]
+    Hash(HashError),
+    /// Other failures of the heap file, like a record too large to fit in a page.
+    #[error(transparent)]
+    Heap(HeapError),
+    /// Other failures of the buffer pool.
+    #[error(transparent)]
+    Buffer(BufferError),
+    /// A query joining more tables than the optimizer can plan.
+    #[error("cannot join more than {limit} tables in one query")]
+    TooManyJoins { limit: usize },
+    /// A table or other object that is created under a name already in use, like `table users`.
+    #[error("{0} already exists")]
+    AlreadyExists(String),
+    /// A table definition that cannot be stored, like one without a primary key.
+    #[error("invalid table definition: {0}")]
+    InvalidSchema(String),
+    /// A feature that is not implemented, like `hash indexes with included columns`.
+    #[error("{0} are not supported")]
+    Unsupported(String),
+    /// An argument of a call out of its range, like a fill factor above 1.
+    #[error("{0}")]
+    InvalidArgument(String),
+    /// A statement, or the values bound to it, that does not fit the schema, like a type
+    /// mismatch or a parameter without a value.
+    #[error("{0}")]
+    Invalid(String),
+}
+
+impl From<BTreeError> for DbError {
+    fn from(err: BTreeError) -> Self {
+        match err {
+            BTreeError::DuplicateKey => DbError::DuplicateKey,
+            BTreeError::KeyNotFound => DbError::NotFound("key".to_string()),
+            BTreeError::Corrupted(message) => DbError::Corrupted(message),
+            BTreeError::InvalidArgument(message) => DbError::InvalidArgument(message),
+            BTreeError::Buffer(err) => err.into(),
+            err => DbError::BTree(err),
+        }
+    }
+}
+
+impl From<HashError> for DbError {
+    fn from(err: HashError) -> Self {
+        match err {
+            HashError::DuplicateKey => DbError::DuplicateKey,
+            HashError::KeyNotFound => DbError::NotFound("key".to_string()),
+            HashError::Buffer(err) => err.into(),
+            err => DbError::Hash(err),
+        }
+    }
+}
+
+impl From<HeapError> for DbError {
+    fn from(err: HeapError) -> Self {
+        match err {
+            HeapError::RecordNotFound => DbError::NotFound("record".to_string()),
+            HeapError::Buffer(err) => err.into(),
+            err => DbError::Heap(err),
+        }
+    }
+}
+
+impl From<BufferError> for DbError {
+    fn from(err: BufferError) -> Self {
+        match err {
+            BufferError::Io(err) => DbError::Io(err),
+            BufferError::NoFreeBuffer => DbError::BufferExhausted,
+            err => DbError::Buffer(err),
+        }
+    }
+}
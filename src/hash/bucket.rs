@@ -0,0 +1,101 @@
+use crate::slotted::{Pointer, Slotted};
+use bincode::Options;
+use std::mem::size_of;
+use zerocopy::{AsBytes, ByteSlice, ByteSliceMut, FromBytes, FromZeroes, Ref};
+
+#[derive(Debug, FromZeroes, FromBytes, AsBytes)]
+#[repr(C)]
+pub struct Header {
+    local_depth: u64,
+}
+
+/// A page holding the entries whose hashes share their lowest `local_depth` bits, in no
+/// particular order.
+pub struct Bucket<B> {
+    header: Ref<B, Header>,
+    body: Slotted<B>,
+}
+
+impl<B: ByteSlice> Bucket<B> {
+    pub fn new(bytes: B) -> Self {
+        let (header, body) = Ref::new_from_prefix(bytes).expect("bucket header must be aligned");
+        let body = Slotted::new(body);
+        Self { header, body }
+    }
+
+    pub fn local_depth(&self) -> u32 {
+        self.header.local_depth as u32
+    }
+
+    pub fn num_entries(&self) -> usize {
+        self.body.num_slots()
+    }
+
+    pub fn entry_at(&self, slot_id: usize) -> (&[u8], &[u8]) {
+        bincode::options().deserialize(&self.body[slot_id]).unwrap()
+    }
+
+    pub fn find(&self, key: &[u8]) -> Option<usize> {
+        (0..self.num_entries()).find(|&slot_id| self.entry_at(slot_id).0 == key)
+    }
+
+    /// The largest encoded entry a bucket accepts, so that a split always leaves room.
+    pub fn max_entry_size(&self) -> usize {
+        self.body.capacity() / 2 - size_of::<Pointer>()
+    }
+}
+
+impl<B: ByteSliceMut> Bucket<B> {
+    pub fn initialize(&mut self, local_depth: u32) {
+        self.header.local_depth = local_depth as u64;
+        self.body.initialize();
+    }
+
+    pub fn set_local_depth(&mut self, local_depth: u32) {
+        self.header.local_depth = local_depth as u64;
+    }
+
+    #[must_use = "insertion may fail"]
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) -> Option<()> {
+        let entry_bytes = encode_entry(key, value);
+        assert!(entry_bytes.len() <= self.max_entry_size());
+        let slot_id = self.num_entries();
+        self.body.insert(slot_id, entry_bytes.len())?;
+        self.body[slot_id].copy_from_slice(&entry_bytes);
+        Some(())
+    }
+
+    pub fn remove(&mut self, slot_id: usize) {
+        self.body.remove(slot_id);
+    }
+}
+
+pub fn encode_entry(key: &[u8], value: &[u8]) -> Vec<u8> {
+    bincode::options().serialize(&(key, value)).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bucket_insert_and_remove() {
+        let mut page_data = vec![0; 80];
+        let mut bucket = Bucket::new(page_data.as_mut_slice());
+        bucket.initialize(3);
+        assert_eq!(3, bucket.local_depth());
+
+        bucket.insert(b"deadbeef", b"world").unwrap();
+        bucket.insert(b"facebook", b"!").unwrap();
+        bucket.insert(b"beefdead", b"hello").unwrap();
+        assert!(bucket.insert(b"cafebabe", b"full").is_none());
+
+        assert_eq!(Some(1), bucket.find(b"facebook"));
+        assert_eq!((&b"beefdead"[..], &b"hello"[..]), bucket.entry_at(2));
+        bucket.remove(1);
+        assert_eq!(None, bucket.find(b"facebook"));
+        assert_eq!(Some(1), bucket.find(b"beefdead"));
+        bucket.insert(b"cafebabe", b"fits").unwrap();
+        assert_eq!(3, bucket.num_entries());
+    }
+}
@@ -0,0 +1,16 @@
+use crate::buffer;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum HashError {
+    #[error("duplicate key")]
+    DuplicateKey,
+    #[error("key not found")]
+    KeyNotFound,
+    #[error("key is too large")]
+    KeyTooLarge,
+    #[error("hash directory is full")]
+    DirectoryFull,
+    #[error(transparent)]
+    Buffer(#[from] buffer::BufferError),
+}
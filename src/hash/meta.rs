@@ -0,0 +1,54 @@
+use crate::disk::PageId;
+use zerocopy::{AsBytes, ByteSlice, ByteSliceMut, FromBytes, FromZeroes, Ref};
+
+#[derive(Debug, FromZeroes, FromBytes, AsBytes)]
+#[repr(C)]
+pub struct Header {
+    global_depth: u64,
+}
+
+/// The first page of a hash index, holding the global depth and the pages of the directory.
+pub struct Meta<B> {
+    header: Ref<B, Header>,
+    directory_page_ids: Ref<B, [PageId]>,
+}
+
+impl<B: ByteSlice> Meta<B> {
+    pub fn new(bytes: B) -> Self {
+        let (header, body) = Ref::new_from_prefix(bytes).expect("meta page must be aligned");
+        let directory_page_ids = Ref::new_slice(body).expect("meta body must hold page ids");
+        Self {
+            header,
+            directory_page_ids,
+        }
+    }
+
+    /// The number of low hash bits indexing the directory.
+    pub fn global_depth(&self) -> u32 {
+        self.header.global_depth as u32
+    }
+
+    pub fn directory_page_id(&self, index: usize) -> PageId {
+        self.directory_page_ids[index]
+    }
+
+    /// The number of directory pages the meta page can hold.
+    pub fn capacity(&self) -> usize {
+        self.directory_page_ids.len()
+    }
+}
+
+impl<B: ByteSliceMut> Meta<B> {
+    pub fn initialize(&mut self, directory_page_id: PageId) {
+        self.header.global_depth = 0;
+        self.directory_page_ids[0] = directory_page_id;
+    }
+
+    pub fn set_global_depth(&mut self, global_depth: u32) {
+        self.header.global_depth = global_depth as u64;
+    }
+
+    pub fn set_directory_page_id(&mut self, index: usize, page_id: PageId) {
+        self.directory_page_ids[index] = page_id;
+    }
+}
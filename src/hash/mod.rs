@@ -0,0 +1,325 @@
+//! An extendible hash index.
+//!
+//! The meta page holds the global depth and the pages of the directory, which maps the lowest
+//! global depth bits of a key hash to the bucket holding the key. A full bucket is split in two
+//! by one more hash bit, doubling the directory first if the bucket already uses all of its
+//! bits. Buckets are not merged when they become empty.
+//!
+//! Lookups read the meta page, a directory page and a bucket, however large the index is, but
+//! only exact keys can be looked up.
+
+use crate::buffer::{Buffer, BufferPoolManager};
+use crate::disk::{PageId, PAGE_SIZE};
+use std::mem::size_of;
+use std::rc::Rc;
+use zerocopy::Ref;
+
+mod bucket;
+mod error;
+mod meta;
+
+use bucket::Bucket;
+use meta::Meta;
+
+pub use error::HashError;
+
+/// The number of bucket page ids in a directory page.
+const ENTRIES_PER_PAGE: usize = PAGE_SIZE / size_of::<PageId>();
+
+pub struct HashIndex {
+    pub meta_page_id: PageId,
+}
+
+impl HashIndex {
+    pub fn create(bufmgr: &mut BufferPoolManager) -> Result<Self, HashError> {
+        let meta_buffer = bufmgr.create_page()?;
+        let directory_buffer = bufmgr.create_page()?;
+        let bucket_buffer = bufmgr.create_page()?;
+        Bucket::new(bucket_buffer.page.borrow_mut().as_mut_slice()).initialize(0);
+        directory_mut(directory_buffer.page.borrow_mut().as_mut_slice())[0] = bucket_buffer.page_id;
+        Meta::new(meta_buffer.page.borrow_mut().as_mut_slice())
+            .initialize(directory_buffer.page_id);
+        Ok(Self::new(meta_buffer.page_id))
+    }
+
+    pub fn new(meta_page_id: PageId) -> Self {
+        Self { meta_page_id }
+    }
+
+    /// Returns the value stored with `key`, if any.
+    pub fn get(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        key: &[u8],
+    ) -> Result<Option<Vec<u8>>, HashError> {
+        let bucket_buffer = self.fetch_bucket(bufmgr, hash(key))?;
+        let bucket_page = bucket_buffer.page.borrow();
+        let bucket = Bucket::new(bucket_page.as_slice());
+        let value = bucket
+            .find(key)
+            .map(|slot_id| bucket.entry_at(slot_id).1.to_vec());
+        Ok(value)
+    }
+
+    pub fn insert(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        key: &[u8],
+        value: &[u8],
+    ) -> Result<(), HashError> {
+        let hash = hash(key);
+        loop {
+            let bucket_buffer = self.fetch_bucket(bufmgr, hash)?;
+            {
+                let mut bucket_page = bucket_buffer.page.borrow_mut();
+                let mut bucket = Bucket::new(bucket_page.as_mut_slice());
+                if bucket.find(key).is_some() {
+                    return Err(HashError::DuplicateKey);
+                }
+                if bucket::encode_entry(key, value).len() > bucket.max_entry_size() {
+                    return Err(HashError::KeyTooLarge);
+                }
+                if bucket.insert(key, value).is_some() {
+                    bucket_buffer.is_dirty.set(true);
+                    return Ok(());
+                }
+            }
+            self.split(bufmgr, &bucket_buffer, hash)?;
+        }
+    }
+
+    pub fn delete(&self, bufmgr: &mut BufferPoolManager, key: &[u8]) -> Result<(), HashError> {
+        let bucket_buffer = self.fetch_bucket(bufmgr, hash(key))?;
+        let mut bucket_page = bucket_buffer.page.borrow_mut();
+        let mut bucket = Bucket::new(bucket_page.as_mut_slice());
+        let slot_id = bucket.find(key).ok_or(HashError::KeyNotFound)?;
+        bucket.remove(slot_id);
+        bucket_buffer.is_dirty.set(true);
+        Ok(())
+    }
+
+    /// The number of low hash bits indexing the directory.
+    pub fn global_depth(&self, bufmgr: &mut BufferPoolManager) -> Result<u32, HashError> {
+        let meta_buffer = bufmgr.fetch_page(self.meta_page_id)?;
+        let meta_page = meta_buffer.page.borrow();
+        Ok(Meta::new(meta_page.as_slice()).global_depth())
+    }
+
+    fn fetch_bucket(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        hash: u64,
+    ) -> Result<Rc<Buffer>, HashError> {
+        let (directory_page_id, index) = {
+            let meta_buffer = bufmgr.fetch_page(self.meta_page_id)?;
+            let meta_page = meta_buffer.page.borrow();
+            let meta = Meta::new(meta_page.as_slice());
+            let index = (hash & mask(meta.global_depth())) as usize;
+            (meta.directory_page_id(index / ENTRIES_PER_PAGE), index)
+        };
+        let bucket_page_id = {
+            let directory_buffer = bufmgr.fetch_page(directory_page_id)?;
+            let directory_page = directory_buffer.page.borrow();
+            directory(directory_page.as_slice())[index % ENTRIES_PER_PAGE]
+        };
+        Ok(bufmgr.fetch_page(bucket_page_id)?)
+    }
+
+    /// Splits the full bucket that `hash` maps to, moving the entries with the next hash bit
+    /// set to a new bucket.
+    fn split(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        bucket_buffer: &Rc<Buffer>,
+        hash: u64,
+    ) -> Result<(), HashError> {
+        let mut bucket_page = bucket_buffer.page.borrow_mut();
+        let mut bucket = Bucket::new(bucket_page.as_mut_slice());
+        let local_depth = bucket.local_depth();
+        let global_depth = self.global_depth(bufmgr)?;
+        if local_depth == global_depth {
+            self.grow_directory(bufmgr)?;
+        }
+
+        let new_buffer = bufmgr.create_page()?;
+        let mut new_page = new_buffer.page.borrow_mut();
+        let mut new_bucket = Bucket::new(new_page.as_mut_slice());
+        new_bucket.initialize(local_depth + 1);
+        bucket.set_local_depth(local_depth + 1);
+        for slot_id in (0..bucket.num_entries()).rev() {
+            let (key, value) = bucket.entry_at(slot_id);
+            if (self::hash(key) >> local_depth) & 1 == 1 {
+                new_bucket
+                    .insert(key, value)
+                    .expect("new bucket must have space");
+                bucket.remove(slot_id);
+            }
+        }
+        bucket_buffer.is_dirty.set(true);
+        new_buffer.is_dirty.set(true);
+
+        // Every directory entry agreeing with `hash` in the old bits and having the new bit set
+        // now points to the new bucket.
+        let global_depth = self.global_depth(bufmgr)?;
+        let first = (hash & mask(local_depth)) | 1 << local_depth;
+        let step = 1 << (local_depth + 1);
+        for index in (first..1 << global_depth).step_by(step) {
+            self.set_directory_entry(bufmgr, index as usize, new_buffer.page_id)?;
+        }
+        Ok(())
+    }
+
+    /// Doubles the directory, the new half pointing to the same buckets as the old half.
+    fn grow_directory(&self, bufmgr: &mut BufferPoolManager) -> Result<(), HashError> {
+        let meta_buffer = bufmgr.fetch_page(self.meta_page_id)?;
+        let mut meta_page = meta_buffer.page.borrow_mut();
+        let mut meta = Meta::new(meta_page.as_mut_slice());
+        let global_depth = meta.global_depth();
+        let len = 1 << global_depth;
+        if len * 2 > meta.capacity() * ENTRIES_PER_PAGE {
+            return Err(HashError::DirectoryFull);
+        }
+        if len < ENTRIES_PER_PAGE {
+            let directory_buffer = bufmgr.fetch_page(meta.directory_page_id(0))?;
+            let mut directory_page = directory_buffer.page.borrow_mut();
+            directory_mut(directory_page.as_mut_slice()).copy_within(0..len, len);
+            directory_buffer.is_dirty.set(true);
+        } else {
+            let num_pages = len / ENTRIES_PER_PAGE;
+            for index in 0..num_pages {
+                let directory_buffer = bufmgr.fetch_page(meta.directory_page_id(index))?;
+                let new_buffer = bufmgr.create_page()?;
+                new_buffer
+                    .page
+                    .borrow_mut()
+                    .copy_from_slice(directory_buffer.page.borrow().as_slice());
+                meta.set_directory_page_id(num_pages + index, new_buffer.page_id);
+            }
+        }
+        meta.set_global_depth(global_depth + 1);
+        meta_buffer.is_dirty.set(true);
+        Ok(())
+    }
+
+    fn set_directory_entry(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        index: usize,
+        bucket_page_id: PageId,
+    ) -> Result<(), HashError> {
+        let directory_page_id = {
+            let meta_buffer = bufmgr.fetch_page(self.meta_page_id)?;
+            let meta_page = meta_buffer.page.borrow();
+            Meta::new(meta_page.as_slice()).directory_page_id(index / ENTRIES_PER_PAGE)
+        };
+        let directory_buffer = bufmgr.fetch_page(directory_page_id)?;
+        let mut directory_page = directory_buffer.page.borrow_mut();
+        directory_mut(directory_page.as_mut_slice())[index % ENTRIES_PER_PAGE] = bucket_page_id;
+        directory_buffer.is_dirty.set(true);
+        Ok(())
+    }
+}
+
+/// Views a directory page as its bucket page ids.
+fn directory(page: &[u8]) -> &[PageId] {
+    Ref::new_slice(page)
+        .expect("directory page must be aligned")
+        .into_slice()
+}
+
+fn directory_mut(page: &mut [u8]) -> &mut [PageId] {
+    Ref::new_slice(page)
+        .expect("directory page must be aligned")
+        .into_mut_slice()
+}
+
+fn mask(depth: u32) -> u64 {
+    (1 << depth) - 1
+}
+
+/// Hashes a key with 64-bit FNV-1a, which is stable across builds as the file format needs.
+fn hash(key: &[u8]) -> u64 {
+    key.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::BufferPool;
+    use crate::disk::DiskManager;
+    use tempfile::tempfile;
+
+    #[test]
+    fn test_insert_get_delete() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let index = HashIndex::create(&mut bufmgr).unwrap();
+
+        for i in 0u64..10000 {
+            let key = format!("id-{i:x}");
+            index
+                .insert(&mut bufmgr, key.as_bytes(), &i.to_be_bytes())
+                .unwrap();
+        }
+        // About 200 entries fit in a bucket.
+        assert!(index.global_depth(&mut bufmgr).unwrap() >= 6);
+        assert!(matches!(
+            index.insert(&mut bufmgr, b"id-0", b"again"),
+            Err(HashError::DuplicateKey)
+        ));
+
+        for i in (0u64..10000).step_by(2) {
+            let key = format!("id-{i:x}");
+            index.delete(&mut bufmgr, key.as_bytes()).unwrap();
+        }
+        for i in 0u64..10000 {
+            let key = format!("id-{i:x}");
+            let value = index.get(&mut bufmgr, key.as_bytes()).unwrap();
+            let expected = (i % 2 == 1).then(|| i.to_be_bytes().to_vec());
+            assert_eq!(expected, value);
+        }
+        assert!(matches!(
+            index.delete(&mut bufmgr, b"id-0"),
+            Err(HashError::KeyNotFound)
+        ));
+        assert_eq!(None, index.get(&mut bufmgr, b"missing").unwrap());
+    }
+
+    #[test]
+    fn test_key_too_large() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let index = HashIndex::create(&mut bufmgr).unwrap();
+        assert!(matches!(
+            index.insert(&mut bufmgr, &[0; PAGE_SIZE / 2], b""),
+            Err(HashError::KeyTooLarge)
+        ));
+    }
+
+    #[test]
+    fn test_large_directory() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let index = HashIndex::create(&mut bufmgr).unwrap();
+
+        // Keys of 1000 bytes fit 3 to a bucket, so the directory spans several pages.
+        let key = |i: u64| {
+            let mut key = vec![0; 1000];
+            key[..8].copy_from_slice(&i.to_be_bytes());
+            key
+        };
+        for i in 0..3000 {
+            index.insert(&mut bufmgr, &key(i), b"").unwrap();
+        }
+        assert!(1 << index.global_depth(&mut bufmgr).unwrap() > ENTRIES_PER_PAGE);
+        bufmgr.flush().unwrap();
+        for i in 0..3000 {
+            assert_eq!(Some(vec![]), index.get(&mut bufmgr, &key(i)).unwrap());
+        }
+    }
+}
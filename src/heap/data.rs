@@ -0,0 +1,108 @@
+use crate::disk::{PageId, PAGE_SIZE};
+use crate::slotted::{self, Pointer, Slotted};
+use std::mem::size_of;
+use zerocopy::{AsBytes, ByteSlice, ByteSliceMut, FromBytes, FromZeroes, Ref};
+
+/// The largest record an empty data page accepts.
+pub const MAX_RECORD_SIZE: usize =
+    PAGE_SIZE - size_of::<Header>() - size_of::<slotted::Header>() - size_of::<Pointer>();
+
+#[derive(Debug, FromZeroes, FromBytes, AsBytes)]
+#[repr(C)]
+pub struct Header {
+    fsm_page_id: PageId,
+    fsm_index: u64,
+}
+
+/// A page of records addressed by slot. A deleted record leaves a tombstone behind, so that the
+/// slots of the other records keep their numbers.
+pub struct DataPage<B> {
+    header: Ref<B, Header>,
+    body: Slotted<B>,
+}
+
+impl<B: ByteSlice> DataPage<B> {
+    pub fn new(bytes: B) -> Self {
+        let (header, body) = Ref::new_from_prefix(bytes).expect("data page header must be aligned");
+        let body = Slotted::new(body);
+        Self { header, body }
+    }
+
+    /// The free space map page and index of the entry describing this page.
+    pub fn fsm_entry(&self) -> (PageId, usize) {
+        (self.header.fsm_page_id, self.header.fsm_index as usize)
+    }
+
+    pub fn num_slots(&self) -> usize {
+        self.body.num_slots()
+    }
+
+    pub fn free_space(&self) -> usize {
+        self.body.free_space()
+    }
+
+    /// Returns the record in `slot_id`, or `None` if the slot does not exist or was deleted.
+    pub fn get(&self, slot_id: usize) -> Option<&[u8]> {
+        if slot_id >= self.num_slots() || self.body.is_deleted(slot_id) {
+            return None;
+        }
+        Some(&self.body[slot_id])
+    }
+}
+
+impl<B: ByteSliceMut> DataPage<B> {
+    pub fn initialize(&mut self, fsm_page_id: PageId, fsm_index: usize) {
+        self.header.fsm_page_id = fsm_page_id;
+        self.header.fsm_index = fsm_index as u64;
+        self.body.initialize();
+    }
+
+    /// Stores `record` in a deleted or new slot and returns the slot number.
+    #[must_use = "insertion may fail"]
+    pub fn insert(&mut self, record: &[u8]) -> Option<usize> {
+        let slot_id = self.body.push(record.len())?;
+        self.body[slot_id].copy_from_slice(record);
+        Some(slot_id)
+    }
+
+    /// Replaces the record in `slot_id` with `record` if the page has room for it.
+    #[must_use = "update may fail"]
+    pub fn update(&mut self, slot_id: usize, record: &[u8]) -> Option<()> {
+        self.body.resize(slot_id, record.len())?;
+        self.body[slot_id].copy_from_slice(record);
+        Some(())
+    }
+
+    /// Leaves a tombstone in `slot_id`, freeing the space of its record but not the slot
+    /// itself, so the ids of the other records do not change. A later insert may reuse it.
+    pub fn remove(&mut self, slot_id: usize) {
+        self.body.delete(slot_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_data_page_keeps_slots() {
+        let mut page_data = vec![0; 64];
+        let mut page = DataPage::new(page_data.as_mut_slice());
+        page.initialize(PageId(1), 0);
+        assert_eq!(Some(0), page.insert(b"hello"));
+        assert_eq!(Some(1), page.insert(b"world"));
+        assert_eq!(Some(2), page.insert(b"!"));
+
+        page.remove(1);
+        assert_eq!(None, page.get(1));
+        assert_eq!(Some(&b"!"[..]), page.get(2));
+        page.update(0, b"goodbye").unwrap();
+        assert_eq!(Some(&b"goodbye"[..]), page.get(0));
+        assert_eq!(Some(&b"!"[..]), page.get(2));
+        assert_eq!(None, page.get(3));
+
+        // Empty records are stored, and deleted slots are reused.
+        assert_eq!(Some(1), page.insert(b""));
+        assert_eq!(Some(&b""[..]), page.get(1));
+    }
+}
@@ -0,0 +1,12 @@
+use crate::buffer;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum HeapError {
+    #[error("record not found")]
+    RecordNotFound,
+    #[error("record is too large")]
+    RecordTooLarge,
+    #[error(transparent)]
+    Buffer(#[from] buffer::BufferError),
+}
@@ -0,0 +1,83 @@
+use crate::disk::PageId;
+use zerocopy::{AsBytes, ByteSlice, ByteSliceMut, FromBytes, FromZeroes, Ref};
+
+#[derive(Debug, FromZeroes, FromBytes, AsBytes)]
+#[repr(C)]
+pub struct Header {
+    next_page_id: PageId,
+    num_entries: u64,
+}
+
+#[derive(Debug, FromZeroes, FromBytes, AsBytes, Clone, Copy)]
+#[repr(C)]
+pub struct Entry {
+    pub page_id: PageId,
+    /// The free bytes of the data page, as reported by [`Slotted::free_space`].
+    ///
+    /// [`Slotted::free_space`]: crate::slotted::Slotted::free_space
+    pub free_space: u64,
+}
+
+/// A page of the free space map, listing data pages of the heap file in the order they were
+/// allocated with how many bytes each has left. The pages are chained by `next_page_id`.
+pub struct FreeSpaceMap<B> {
+    header: Ref<B, Header>,
+    entries: Ref<B, [Entry]>,
+}
+
+impl<B: ByteSlice> FreeSpaceMap<B> {
+    pub fn new(bytes: B) -> Self {
+        let (header, body) = Ref::new_from_prefix(bytes).expect("fsm header must be aligned");
+        let entries = Ref::new_slice(body).expect("fsm body must hold entries");
+        Self { header, entries }
+    }
+
+    pub fn next_page_id(&self) -> Option<PageId> {
+        self.header.next_page_id.valid()
+    }
+
+    pub fn num_entries(&self) -> usize {
+        self.header.num_entries as usize
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.num_entries() == self.entries.len()
+    }
+
+    pub fn entry(&self, index: usize) -> Entry {
+        self.entries[..self.num_entries()][index]
+    }
+
+    /// Returns the index of the first data page with at least `len` free bytes.
+    pub fn find(&self, len: usize) -> Option<usize> {
+        self.entries[..self.num_entries()]
+            .iter()
+            .position(|entry| entry.free_space as usize >= len)
+    }
+}
+
+impl<B: ByteSliceMut> FreeSpaceMap<B> {
+    pub fn initialize(&mut self) {
+        self.header.next_page_id = PageId::INVALID_PAGE_ID;
+        self.header.num_entries = 0;
+    }
+
+    pub fn set_next_page_id(&mut self, page_id: PageId) {
+        self.header.next_page_id = page_id;
+    }
+
+    /// Appends a data page and returns its index in this page.
+    pub fn push(&mut self, page_id: PageId, free_space: usize) -> usize {
+        let index = self.num_entries();
+        self.entries[index] = Entry {
+            page_id,
+            free_space: free_space as u64,
+        };
+        self.header.num_entries += 1;
+        index
+    }
+
+    pub fn set_free_space(&mut self, index: usize, free_space: usize) {
+        self.entries[index].free_space = free_space as u64;
+    }
+}
@@ -0,0 +1,41 @@
+use crate::disk::PageId;
+use zerocopy::{AsBytes, ByteSlice, ByteSliceMut, FromBytes, FromZeroes, Ref};
+
+#[derive(Debug, FromZeroes, FromBytes, AsBytes)]
+#[repr(C)]
+pub struct Header {
+    first_fsm_page_id: PageId,
+    last_fsm_page_id: PageId,
+}
+
+/// The first page of a heap file, holding the ends of its chain of free space map pages.
+pub struct Meta<B> {
+    header: Ref<B, Header>,
+}
+
+impl<B: ByteSlice> Meta<B> {
+    pub fn new(bytes: B) -> Self {
+        let (header, _) = Ref::new_from_prefix(bytes).expect("meta page must be aligned");
+        Self { header }
+    }
+
+    pub fn first_fsm_page_id(&self) -> PageId {
+        self.header.first_fsm_page_id
+    }
+
+    /// The free space map page that new data pages are appended to.
+    pub fn last_fsm_page_id(&self) -> PageId {
+        self.header.last_fsm_page_id
+    }
+}
+
+impl<B: ByteSliceMut> Meta<B> {
+    pub fn initialize(&mut self, fsm_page_id: PageId) {
+        self.header.first_fsm_page_id = fsm_page_id;
+        self.header.last_fsm_page_id = fsm_page_id;
+    }
+
+    pub fn set_last_fsm_page_id(&mut self, page_id: PageId) {
+        self.header.last_fsm_page_id = page_id;
+    }
+}
@@ -0,0 +1,388 @@
+//! A heap file: records in no particular order, addressed by the page and slot they live in.
+//!
+//! The meta page points to a chain of free space map pages, which list every data page with the
+//! number of bytes it has left. Records are appended to the newest data page while they fit, and
+//! otherwise to the first page with room, so space freed by deletes is reused. Each data page
+//! records where its free space map entry is, so that deletes can update it directly.
+//!
+//! A deleted record leaves a tombstone in its slot, so the IDs of the other records stay valid.
+//! The slot may be handed out again to a record inserted later.
+//!
+//! The free space map doubles as the list of data pages for scans, which return records page by
+//! page in the order the pages were allocated.
+
+use crate::buffer::{Buffer, BufferPoolManager};
+use crate::disk::PageId;
+use crate::slotted::Pointer;
+use std::mem::size_of;
+use std::rc::Rc;
+
+mod data;
+mod error;
+mod fsm;
+mod meta;
+
+use data::DataPage;
+use fsm::FreeSpaceMap;
+use meta::Meta;
+
+pub use error::HeapError;
+
+/// The address of a record in a heap file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RecordId {
+    pub page_id: PageId,
+    pub slot_id: u16,
+}
+
+impl RecordId {
+    /// The size of [`to_bytes`](Self::to_bytes).
+    pub const SIZE: usize = size_of::<u64>() + size_of::<u16>();
+
+    /// Encodes the record ID so that the bytes compare in the same order as the IDs.
+    pub fn to_bytes(self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        bytes[..8].copy_from_slice(&self.page_id.0.to_be_bytes());
+        bytes[8..].copy_from_slice(&self.slot_id.to_be_bytes());
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let bytes: [u8; Self::SIZE] = bytes.try_into().ok()?;
+        Some(Self {
+            page_id: PageId(u64::from_be_bytes(bytes[..8].try_into().unwrap())),
+            slot_id: u16::from_be_bytes(bytes[8..].try_into().unwrap()),
+        })
+    }
+}
+
+pub struct HeapFile {
+    pub meta_page_id: PageId,
+}
+
+impl HeapFile {
+    pub fn create(bufmgr: &mut BufferPoolManager) -> Result<Self, HeapError> {
+        let meta_buffer = bufmgr.create_page()?;
+        let fsm_buffer = bufmgr.create_page()?;
+        FreeSpaceMap::new(fsm_buffer.page.borrow_mut().as_mut_slice()).initialize();
+        Meta::new(meta_buffer.page.borrow_mut().as_mut_slice()).initialize(fsm_buffer.page_id);
+        Ok(Self::new(meta_buffer.page_id))
+    }
+
+    pub fn new(meta_page_id: PageId) -> Self {
+        Self { meta_page_id }
+    }
+
+    /// Returns the record stored at `rid`, or `None` if it was deleted.
+    pub fn get(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        rid: RecordId,
+    ) -> Result<Option<Vec<u8>>, HeapError> {
+        let data_buffer = bufmgr.fetch_page(rid.page_id)?;
+        let data_page = data_buffer.page.borrow();
+        let data_page = DataPage::new(data_page.as_slice());
+        Ok(data_page.get(rid.slot_id as usize).map(<[u8]>::to_vec))
+    }
+
+    pub fn insert(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        record: &[u8],
+    ) -> Result<RecordId, HeapError> {
+        if record.len() > data::MAX_RECORD_SIZE {
+            return Err(HeapError::RecordTooLarge);
+        }
+        let data_buffer = self.find_data_page(bufmgr, record.len() + size_of::<Pointer>())?;
+        let mut data_page = data_buffer.page.borrow_mut();
+        let mut data_page = DataPage::new(data_page.as_mut_slice());
+        let slot_id = data_page
+            .insert(record)
+            .expect("free space map must only return pages with room");
+        data_buffer.is_dirty.set(true);
+        update_free_space(bufmgr, &data_page)?;
+        Ok(RecordId {
+            page_id: data_buffer.page_id,
+            slot_id: slot_id as u16,
+        })
+    }
+
+    /// Replaces the record at `rid` and returns its new ID, which differs from `rid` if the
+    /// record no longer fits in its page and had to be moved.
+    pub fn update(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        rid: RecordId,
+        record: &[u8],
+    ) -> Result<RecordId, HeapError> {
+        {
+            let data_buffer = bufmgr.fetch_page(rid.page_id)?;
+            let mut data_page = data_buffer.page.borrow_mut();
+            let mut data_page = DataPage::new(data_page.as_mut_slice());
+            let slot_id = rid.slot_id as usize;
+            if data_page.get(slot_id).is_none() {
+                return Err(HeapError::RecordNotFound);
+            }
+            if data_page.update(slot_id, record).is_some() {
+                data_buffer.is_dirty.set(true);
+                update_free_space(bufmgr, &data_page)?;
+                return Ok(rid);
+            }
+        }
+        let new_rid = self.insert(bufmgr, record)?;
+        self.delete(bufmgr, rid)?;
+        Ok(new_rid)
+    }
+
+    pub fn delete(&self, bufmgr: &mut BufferPoolManager, rid: RecordId) -> Result<(), HeapError> {
+        let data_buffer = bufmgr.fetch_page(rid.page_id)?;
+        let mut data_page = data_buffer.page.borrow_mut();
+        let mut data_page = DataPage::new(data_page.as_mut_slice());
+        let slot_id = rid.slot_id as usize;
+        if data_page.get(slot_id).is_none() {
+            return Err(HeapError::RecordNotFound);
+        }
+        data_page.remove(slot_id);
+        data_buffer.is_dirty.set(true);
+        update_free_space(bufmgr, &data_page)
+    }
+
+    /// Returns an iterator over every record and its ID.
+    pub fn scan(&self, bufmgr: &mut BufferPoolManager) -> Result<Iter, HeapError> {
+        let meta_buffer = bufmgr.fetch_page(self.meta_page_id)?;
+        let meta_page = meta_buffer.page.borrow();
+        Ok(Iter {
+            fsm_page_id: Some(Meta::new(meta_page.as_slice()).first_fsm_page_id()),
+            fsm_index: 0,
+            data_buffer: None,
+            slot_id: 0,
+        })
+    }
+
+    /// Returns a data page with at least `len` free bytes, allocating one if none has.
+    fn find_data_page(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        len: usize,
+    ) -> Result<Rc<Buffer>, HeapError> {
+        let (first_fsm_page_id, last_fsm_page_id) = {
+            let meta_buffer = bufmgr.fetch_page(self.meta_page_id)?;
+            let meta_page = meta_buffer.page.borrow();
+            let meta = Meta::new(meta_page.as_slice());
+            (meta.first_fsm_page_id(), meta.last_fsm_page_id())
+        };
+        // Appends usually fit in the newest page, so check it before the whole map.
+        let last_fsm_buffer = bufmgr.fetch_page(last_fsm_page_id)?;
+        let newest = {
+            let fsm_page = last_fsm_buffer.page.borrow();
+            let fsm = FreeSpaceMap::new(fsm_page.as_slice());
+            fsm.num_entries()
+                .checked_sub(1)
+                .map(|index| fsm.entry(index))
+                .filter(|entry| entry.free_space as usize >= len)
+        };
+        if let Some(entry) = newest {
+            return Ok(bufmgr.fetch_page(entry.page_id)?);
+        }
+        let mut fsm_page_id = Some(first_fsm_page_id);
+        while let Some(page_id) = fsm_page_id {
+            let fsm_buffer = bufmgr.fetch_page(page_id)?;
+            let fsm_page = fsm_buffer.page.borrow();
+            let fsm = FreeSpaceMap::new(fsm_page.as_slice());
+            if let Some(index) = fsm.find(len) {
+                return Ok(bufmgr.fetch_page(fsm.entry(index).page_id)?);
+            }
+            fsm_page_id = fsm.next_page_id();
+        }
+        self.allocate_data_page(bufmgr, last_fsm_buffer)
+    }
+
+    /// Creates an empty data page and appends it to the free space map, chaining a new map page
+    /// if the last one is full.
+    fn allocate_data_page(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        mut fsm_buffer: Rc<Buffer>,
+    ) -> Result<Rc<Buffer>, HeapError> {
+        if FreeSpaceMap::new(fsm_buffer.page.borrow().as_slice()).is_full() {
+            let new_fsm_buffer = bufmgr.create_page()?;
+            FreeSpaceMap::new(new_fsm_buffer.page.borrow_mut().as_mut_slice()).initialize();
+            FreeSpaceMap::new(fsm_buffer.page.borrow_mut().as_mut_slice())
+                .set_next_page_id(new_fsm_buffer.page_id);
+            fsm_buffer.is_dirty.set(true);
+            let meta_buffer = bufmgr.fetch_page(self.meta_page_id)?;
+            Meta::new(meta_buffer.page.borrow_mut().as_mut_slice())
+                .set_last_fsm_page_id(new_fsm_buffer.page_id);
+            meta_buffer.is_dirty.set(true);
+            fsm_buffer = new_fsm_buffer;
+        }
+        let data_buffer = bufmgr.create_page()?;
+        {
+            let mut fsm_page = fsm_buffer.page.borrow_mut();
+            let mut fsm = FreeSpaceMap::new(fsm_page.as_mut_slice());
+            let mut data_page = data_buffer.page.borrow_mut();
+            let mut data_page = DataPage::new(data_page.as_mut_slice());
+            data_page.initialize(fsm_buffer.page_id, fsm.num_entries());
+            fsm.push(data_buffer.page_id, data_page.free_space());
+            fsm_buffer.is_dirty.set(true);
+        }
+        Ok(data_buffer)
+    }
+}
+
+/// Records the free space of `data_page` in its free space map entry.
+fn update_free_space(
+    bufmgr: &mut BufferPoolManager,
+    data_page: &DataPage<&mut [u8]>,
+) -> Result<(), HeapError> {
+    let (fsm_page_id, fsm_index) = data_page.fsm_entry();
+    let fsm_buffer = bufmgr.fetch_page(fsm_page_id)?;
+    FreeSpaceMap::new(fsm_buffer.page.borrow_mut().as_mut_slice())
+        .set_free_space(fsm_index, data_page.free_space());
+    fsm_buffer.is_dirty.set(true);
+    Ok(())
+}
+
+/// A scan over a heap file, holding the data page it is reading.
+pub struct Iter {
+    /// The free space map page listing the data page being read, or `None` after the last one.
+    fsm_page_id: Option<PageId>,
+    fsm_index: usize,
+    data_buffer: Option<Rc<Buffer>>,
+    slot_id: usize,
+}
+
+impl Iter {
+    #[allow(clippy::should_implement_trait)]
+    pub fn next(
+        &mut self,
+        bufmgr: &mut BufferPoolManager,
+    ) -> Result<Option<(RecordId, Vec<u8>)>, HeapError> {
+        loop {
+            if let Some(data_buffer) = &self.data_buffer {
+                let data_page = data_buffer.page.borrow();
+                let data_page = DataPage::new(data_page.as_slice());
+                while self.slot_id < data_page.num_slots() {
+                    let slot_id = self.slot_id;
+                    self.slot_id += 1;
+                    if let Some(record) = data_page.get(slot_id) {
+                        let rid = RecordId {
+                            page_id: data_buffer.page_id,
+                            slot_id: slot_id as u16,
+                        };
+                        return Ok(Some((rid, record.to_vec())));
+                    }
+                }
+            }
+            if self.data_buffer.take().is_some() {
+                self.fsm_index += 1;
+            }
+            let Some(fsm_page_id) = self.fsm_page_id else {
+                return Ok(None);
+            };
+            let fsm_buffer = bufmgr.fetch_page(fsm_page_id)?;
+            let fsm_page = fsm_buffer.page.borrow();
+            let fsm = FreeSpaceMap::new(fsm_page.as_slice());
+            if self.fsm_index < fsm.num_entries() {
+                self.data_buffer = Some(bufmgr.fetch_page(fsm.entry(self.fsm_index).page_id)?);
+                self.slot_id = 0;
+            } else {
+                self.fsm_page_id = fsm.next_page_id();
+                self.fsm_index = 0;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::BufferPool;
+    use crate::disk::DiskManager;
+    use tempfile::tempfile;
+
+    fn new_bufmgr() -> BufferPoolManager {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        BufferPoolManager::new(disk, BufferPool::new(10))
+    }
+
+    fn scan_all(heap: &HeapFile, bufmgr: &mut BufferPoolManager) -> Vec<(RecordId, Vec<u8>)> {
+        let mut iter = heap.scan(bufmgr).unwrap();
+        let mut records = vec![];
+        while let Some(entry) = iter.next(bufmgr).unwrap() {
+            records.push(entry);
+        }
+        records
+    }
+
+    #[test]
+    fn test_insert_get_delete() {
+        let mut bufmgr = new_bufmgr();
+        let heap = HeapFile::create(&mut bufmgr).unwrap();
+        let hello = heap.insert(&mut bufmgr, b"hello").unwrap();
+        let world = heap.insert(&mut bufmgr, b"world").unwrap();
+        let bang = heap.insert(&mut bufmgr, b"!").unwrap();
+        assert_eq!(hello.page_id, world.page_id);
+
+        heap.delete(&mut bufmgr, world).unwrap();
+        assert_eq!(None, heap.get(&mut bufmgr, world).unwrap());
+        assert_eq!(Some(b"!".to_vec()), heap.get(&mut bufmgr, bang).unwrap());
+        assert!(matches!(
+            heap.delete(&mut bufmgr, world),
+            Err(HeapError::RecordNotFound)
+        ));
+
+        let hello2 = heap.update(&mut bufmgr, hello, b"hello, world").unwrap();
+        assert_eq!(hello, hello2);
+        assert_eq!(
+            vec![(hello, b"hello, world".to_vec()), (bang, b"!".to_vec())],
+            scan_all(&heap, &mut bufmgr)
+        );
+
+        // The slot of a deleted record is reused.
+        assert_eq!(world, heap.insert(&mut bufmgr, b"").unwrap());
+        assert_eq!(Some(vec![]), heap.get(&mut bufmgr, world).unwrap());
+    }
+
+    #[test]
+    fn test_record_id_bytes() {
+        let rid = RecordId {
+            page_id: PageId(3),
+            slot_id: 258,
+        };
+        assert_eq!(Some(rid), RecordId::from_bytes(&rid.to_bytes()));
+        let next = RecordId {
+            page_id: PageId(4),
+            slot_id: 0,
+        };
+        assert!(rid.to_bytes() < next.to_bytes());
+        assert_eq!(None, RecordId::from_bytes(b"short"));
+    }
+
+    #[test]
+    fn test_many_pages() {
+        let mut bufmgr = new_bufmgr();
+        let heap = HeapFile::create(&mut bufmgr).unwrap();
+        let record = [0xab; 100];
+        // 39 records fill a page, and 300 pages need a second free space map page.
+        let rids: Vec<RecordId> = (0..300 * 39)
+            .map(|_| heap.insert(&mut bufmgr, &record).unwrap())
+            .collect();
+        assert!(rids
+            .windows(2)
+            .all(|pair| pair[0].to_bytes() < pair[1].to_bytes()));
+        let records = scan_all(&heap, &mut bufmgr);
+        assert_eq!(rids.len(), records.len());
+        assert!(records.iter().map(|(rid, _)| *rid).eq(rids.iter().copied()));
+
+        // Space freed in an early page is reused before allocating a new one.
+        heap.delete(&mut bufmgr, rids[5]).unwrap();
+        let rid = heap.insert(&mut bufmgr, &record).unwrap();
+        assert_eq!(rids[5].page_id, rid.page_id);
+
+        assert!(matches!(
+            heap.insert(&mut bufmgr, &[0; 4096]),
+            Err(HeapError::RecordTooLarge)
+        ));
+    }
+}
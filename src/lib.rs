@@ -1,7 +1,17 @@
 pub mod btree;
 pub mod buffer;
+pub mod catalog;
+pub mod database;
 pub mod disk;
+pub mod error;
+pub mod hash;
+pub mod heap;
+pub mod pgwire;
 pub mod query;
 mod slotted;
+pub mod sql;
 pub mod table;
 pub mod tuple;
+
+pub use database::{Database, Options};
+pub use error::DbError;
@@ -0,0 +1,1074 @@
+//! A server speaking the PostgreSQL frontend/backend protocol, version 3.0, over TCP.
+//!
+//! Both the simple query flow and the extended query flow (Parse, Bind, Describe, Execute) are
+//! supported, without authentication or TLS. Connections are served one at a time, since the
+//! buffer pool is not shared between threads.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::vec;
+
+use crate::catalog::{Column, DataType};
+use crate::database::Database;
+use crate::error::DbError;
+use crate::query::expr::EvalError;
+use crate::query::Tuple;
+use crate::sql::ast::Statement;
+use crate::sql::{self, Output, ParseError, Value};
+use thiserror::Error;
+
+const PROTOCOL_VERSION: i32 = 3 << 16;
+const CANCEL_REQUEST_CODE: i32 = 80877102;
+const SSL_REQUEST_CODE: i32 = 80877103;
+const GSSENC_REQUEST_CODE: i32 = 80877104;
+
+/// The largest message accepted from a client, which is plenty for the statements and
+/// parameters this server handles.
+const MAX_MESSAGE_LEN: usize = 1 << 24;
+
+const BOOL_OID: i32 = 16;
+const INT8_OID: i32 = 20;
+const INT2_OID: i32 = 21;
+const INT4_OID: i32 = 23;
+const TEXT_OID: i32 = 25;
+const VARCHAR_OID: i32 = 1043;
+
+const TEXT_FORMAT: i16 = 0;
+const BINARY_FORMAT: i16 = 1;
+
+/// Serves a database to PostgreSQL clients.
+pub struct Server {
+    database: Database,
+}
+
+/// The state of a client session.
+struct Connection<'a> {
+    server: &'a mut Server,
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+    statements: HashMap<String, Prepared>,
+    portals: HashMap<String, Portal>,
+    /// Whether statements have changed the tables since they were last written to disk.
+    dirty: bool,
+}
+
+/// A statement prepared by a Parse message. An empty query has no statement.
+struct Prepared {
+    statement: Option<Statement>,
+    param_types: Vec<DataType>,
+}
+
+/// A prepared statement given its parameters by a Bind message.
+struct Portal {
+    statement: Option<Statement>,
+    result_formats: Vec<i16>,
+    /// The rows not yet sent of a query suspended by the row limit of an Execute message.
+    pending: Option<PendingRows>,
+}
+
+struct PendingRows {
+    columns: Vec<Column>,
+    rows: vec::IntoIter<Tuple>,
+    num_sent: usize,
+}
+
+/// A message to a client, built before it is sent.
+struct Message {
+    tag: u8,
+    body: Vec<u8>,
+}
+
+/// Reads the fields of a message from a client.
+struct Fields<'a>(&'a [u8]);
+
+/// An error handling a message from a client.
+#[derive(Debug, Error)]
+enum ConnectionError {
+    /// A failure of the connection itself, which ends the session.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// A message that does not follow the protocol.
+    #[error("{0}")]
+    Protocol(String),
+    /// A statement that failed, reported to the client with a code for its kind.
+    #[error(transparent)]
+    Db(#[from] DbError),
+}
+
+impl From<ParseError> for ConnectionError {
+    fn from(err: ParseError) -> Self {
+        ConnectionError::Db(err.into())
+    }
+}
+
+impl Server {
+    pub fn new(database: Database) -> Self {
+        Self { database }
+    }
+
+    /// Serves the connections accepted by `listener` one after another.
+    pub fn serve(&mut self, listener: &TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            if let Err(err) = self.handle(stream?) {
+                eprintln!("connection closed: {err}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Serves a client until it ends the session or closes the connection.
+    pub fn handle(&mut self, stream: TcpStream) -> io::Result<()> {
+        let mut conn = Connection {
+            server: self,
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+            statements: HashMap::new(),
+            portals: HashMap::new(),
+            dirty: false,
+        };
+        if conn.startup()? {
+            conn.run()?;
+        }
+        conn.flush_tables()
+    }
+}
+
+impl<'a> Connection<'a> {
+    /// Reads the startup message and greets the client. Returns `false` if the client asked
+    /// for something else than a session.
+    fn startup(&mut self) -> io::Result<bool> {
+        loop {
+            let mut len = [0; 4];
+            self.reader.read_exact(&mut len)?;
+            let body = self.read_body(i32::from_be_bytes(len))?;
+            let code = Fields(&body).i32().map_err(invalid_data)?;
+            match code {
+                SSL_REQUEST_CODE | GSSENC_REQUEST_CODE => {
+                    // Encryption is not supported, so the client goes on in plain text.
+                    self.writer.write_all(b"N")?;
+                    self.writer.flush()?;
+                }
+                CANCEL_REQUEST_CODE => return Ok(false),
+                PROTOCOL_VERSION => break,
+                _ => {
+                    let message = format!("unsupported protocol version {code:#x}");
+                    self.send(error_response("FATAL", "0A000", &message))?;
+                    self.writer.flush()?;
+                    return Ok(false);
+                }
+            }
+        }
+        self.send(Message::new(b'R').i32(0))?;
+        for (name, value) in [
+            ("server_version", "16.0"),
+            ("server_encoding", "UTF8"),
+            ("client_encoding", "UTF8"),
+            ("DateStyle", "ISO, MDY"),
+            ("integer_datetimes", "on"),
+            ("standard_conforming_strings", "on"),
+        ] {
+            self.send(Message::new(b'S').cstr(name).cstr(value))?;
+        }
+        let process_id = std::process::id() as i32;
+        self.send(Message::new(b'K').i32(process_id).i32(0))?;
+        self.ready_for_query()?;
+        Ok(true)
+    }
+
+    fn run(&mut self) -> io::Result<()> {
+        // After an error in the extended query flow, messages are skipped until a Sync.
+        let mut skipping = false;
+        while let Some((tag, body)) = self.read_message()? {
+            if skipping && tag != b'S' && tag != b'X' {
+                continue;
+            }
+            let mut fields = Fields(&body);
+            let result = match tag {
+                b'Q' => {
+                    let result = fields.cstr().and_then(|sql| self.simple_query(sql));
+                    if let Err(err) = result {
+                        self.send_error(err)?;
+                    }
+                    self.flush_tables()?;
+                    self.ready_for_query()?;
+                    continue;
+                }
+                b'P' => self.parse(&mut fields),
+                b'B' => self.bind(&mut fields),
+                b'D' => self.describe(&mut fields),
+                b'E' => self.execute(&mut fields),
+                b'C' => self.close(&mut fields),
+                b'H' => {
+                    self.writer.flush()?;
+                    continue;
+                }
+                b'S' => {
+                    skipping = false;
+                    self.flush_tables()?;
+                    self.ready_for_query()?;
+                    continue;
+                }
+                b'X' => return Ok(()),
+                _ => Err(ConnectionError::Protocol(format!(
+                    "unsupported message type {:?}",
+                    tag as char
+                ))),
+            };
+            if let Err(err) = result {
+                self.send_error(err)?;
+                skipping = true;
+            }
+        }
+        Ok(())
+    }
+
+    fn simple_query(&mut self, sql: &str) -> Result<(), ConnectionError> {
+        let statements = sql::parse_statements(sql)?;
+        if statements.is_empty() {
+            self.send(Message::new(b'I'))?;
+        }
+        for statement in statements {
+            let output = self.run_statement(&statement)?;
+            let num_rows = match &output {
+                Output::Rows(result_set) => {
+                    self.send(row_description(&result_set.columns, &[]))?;
+                    for row in &result_set.rows {
+                        self.send(data_row(row, &result_set.columns, &[]))?;
+                    }
+                    result_set.rows.len()
+                }
+                _ => 0,
+            };
+            self.send(command_complete(&statement, &output, num_rows))?;
+        }
+        Ok(())
+    }
+
+    fn run_statement(&mut self, statement: &Statement) -> Result<Output, ConnectionError> {
+        self.dirty |= !matches!(statement, Statement::Select(_) | Statement::Explain { .. });
+        Ok(self.server.database.run(statement)?)
+    }
+
+    fn parse(&mut self, fields: &mut Fields) -> Result<(), ConnectionError> {
+        let name = fields.cstr()?.to_string();
+        let sql = fields.cstr()?;
+        let num_params = fields.i16()?;
+        let mut declared = vec![];
+        for _ in 0..num_params {
+            declared.push(fields.i32()?);
+        }
+        let mut statements = sql::parse_statements(sql)?;
+        if statements.len() > 1 {
+            return Err(DbError::Unsupported(
+                "multiple commands in a prepared statement".to_string(),
+            )
+            .into());
+        }
+        let statement = statements.pop();
+        let mut inferred = match &statement {
+            Some(statement) => sql::param_types(self.server.database.catalog(), statement)?,
+            None => vec![],
+        };
+        inferred.resize(inferred.len().max(declared.len()), None);
+        let param_types = inferred
+            .into_iter()
+            .enumerate()
+            .map(|(i, inferred)| match declared.get(i) {
+                Some(&oid) if oid != 0 => type_of_oid(oid),
+                // Parameters of unknown type are taken as text, as PostgreSQL does.
+                _ => Ok(inferred.unwrap_or(DataType::Text)),
+            })
+            .collect::<Result<_, _>>()?;
+        if !name.is_empty() && self.statements.contains_key(&name) {
+            return Err(DbError::AlreadyExists(format!("prepared statement {name}")).into());
+        }
+        let prepared = Prepared {
+            statement,
+            param_types,
+        };
+        self.statements.insert(name, prepared);
+        self.send(Message::new(b'1'))?;
+        Ok(())
+    }
+
+    fn bind(&mut self, fields: &mut Fields) -> Result<(), ConnectionError> {
+        let portal_name = fields.cstr()?.to_string();
+        let statement_name = fields.cstr()?;
+        let prepared = self
+            .statements
+            .get(statement_name)
+            .ok_or_else(|| DbError::NotFound(format!("prepared statement {statement_name:?}")))?;
+        let param_formats = fields.formats()?;
+        let num_params = fields.i16()? as usize;
+        if num_params != prepared.param_types.len() {
+            return Err(ConnectionError::Protocol(format!(
+                "expected {} parameters, found {num_params}",
+                prepared.param_types.len()
+            )));
+        }
+        let mut params = vec![];
+        for (i, &data_type) in prepared.param_types.iter().enumerate() {
+            let len = fields.i32()?;
+            if len < 0 {
+                return Err(
+                    DbError::Unsupported(format!("NULL values of parameter ${}", i + 1)).into(),
+                );
+            }
+            let bytes = fields.bytes(len as usize)?;
+            let value =
+                decode_param(bytes, data_type, format_of(&param_formats, i)).ok_or_else(|| {
+                    DbError::InvalidArgument(format!(
+                        "parameter ${} is not a valid {data_type}",
+                        i + 1
+                    ))
+                })?;
+            params.push(value);
+        }
+        let result_formats = fields.formats()?;
+        let mut statement = prepared.statement.clone();
+        if let Some(statement) = &mut statement {
+            statement.bind_params(&params)?;
+        }
+        let portal = Portal {
+            statement,
+            result_formats,
+            pending: None,
+        };
+        self.portals.insert(portal_name, portal);
+        self.send(Message::new(b'2'))?;
+        Ok(())
+    }
+
+    fn describe(&mut self, fields: &mut Fields) -> Result<(), ConnectionError> {
+        let kind = fields.u8()?;
+        let name = fields.cstr()?;
+        let catalog = self.server.database.catalog();
+        let (param_description, columns, result_formats) = match kind {
+            b'S' => {
+                let prepared = self
+                    .statements
+                    .get(name)
+                    .ok_or_else(|| DbError::NotFound(format!("prepared statement {name:?}")))?;
+                let mut description = Message::new(b't').i16(prepared.param_types.len() as i16);
+                for &data_type in &prepared.param_types {
+                    description = description.i32(type_oid(data_type));
+                }
+                // The result columns do not depend on the values of the parameters.
+                let columns = match &prepared.statement {
+                    Some(statement) => {
+                        let mut statement = statement.clone();
+                        let params: Vec<Value> = prepared
+                            .param_types
+                            .iter()
+                            .map(|&data_type| placeholder(data_type))
+                            .collect();
+                        statement.bind_params(&params)?;
+                        sql::describe(catalog, &statement)?
+                    }
+                    None => None,
+                };
+                (Some(description), columns, vec![])
+            }
+            b'P' => {
+                let portal = self
+                    .portals
+                    .get(name)
+                    .ok_or_else(|| DbError::NotFound(format!("portal {name:?}")))?;
+                let columns = match &portal.statement {
+                    Some(statement) => sql::describe(catalog, statement)?,
+                    None => None,
+                };
+                (None, columns, portal.result_formats.clone())
+            }
+            _ => {
+                return Err(ConnectionError::Protocol(format!(
+                    "invalid Describe kind {:?}",
+                    kind as char
+                )))
+            }
+        };
+        if let Some(param_description) = param_description {
+            self.send(param_description)?;
+        }
+        match columns {
+            Some(columns) => self.send(row_description(&columns, &result_formats))?,
+            None => self.send(Message::new(b'n'))?,
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, fields: &mut Fields) -> Result<(), ConnectionError> {
+        let name = fields.cstr()?;
+        let max_rows = fields.i32()?;
+        let mut portal = self
+            .portals
+            .remove(name)
+            .ok_or_else(|| DbError::NotFound(format!("portal {name:?}")))?;
+        let result = self.execute_portal(&mut portal, max_rows);
+        self.portals.insert(name.to_string(), portal);
+        result
+    }
+
+    /// Sends the rows of a portal, at most `max_rows` of them unless it is 0.
+    fn execute_portal(
+        &mut self,
+        portal: &mut Portal,
+        max_rows: i32,
+    ) -> Result<(), ConnectionError> {
+        let Some(statement) = &portal.statement else {
+            self.send(Message::new(b'I'))?;
+            return Ok(());
+        };
+        if portal.pending.is_none() {
+            match self.run_statement(statement)? {
+                Output::Rows(result_set) => {
+                    portal.pending = Some(PendingRows {
+                        columns: result_set.columns,
+                        rows: result_set.rows.into_iter(),
+                        num_sent: 0,
+                    })
+                }
+                output => {
+                    self.send(command_complete(statement, &output, 0))?;
+                    return Ok(());
+                }
+            }
+        }
+        let pending = portal.pending.as_mut().unwrap();
+        let limit = if max_rows > 0 {
+            max_rows as usize
+        } else {
+            usize::MAX
+        };
+        for row in pending.rows.by_ref().take(limit) {
+            let message = data_row(&row, &pending.columns, &portal.result_formats);
+            self.send(message)?;
+            pending.num_sent += 1;
+        }
+        if pending.rows.len() > 0 {
+            self.send(Message::new(b's'))?;
+        } else {
+            let output = Output::Rows(Default::default());
+            self.send(command_complete(statement, &output, pending.num_sent))?;
+        }
+        Ok(())
+    }
+
+    fn close(&mut self, fields: &mut Fields) -> Result<(), ConnectionError> {
+        let kind = fields.u8()?;
+        let name = fields.cstr()?;
+        match kind {
+            b'S' => self.statements.remove(name).map(|_| ()),
+            b'P' => self.portals.remove(name).map(|_| ()),
+            _ => {
+                return Err(ConnectionError::Protocol(format!(
+                    "invalid Close kind {:?}",
+                    kind as char
+                )))
+            }
+        };
+        self.send(Message::new(b'3'))?;
+        Ok(())
+    }
+
+    fn read_message(&mut self) -> io::Result<Option<(u8, Vec<u8>)>> {
+        let mut header = [0; 5];
+        match self.reader.read_exact(&mut header[..1]) {
+            Ok(()) => {}
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err),
+        }
+        self.reader.read_exact(&mut header[1..])?;
+        let body = self.read_body(i32::from_be_bytes(header[1..].try_into().unwrap()))?;
+        Ok(Some((header[0], body)))
+    }
+
+    /// Reads the body of a message whose length, including the length itself, is `len`.
+    ///
+    /// The body grows as its bytes arrive, so a client cannot make the server allocate more
+    /// than it actually sends.
+    fn read_body(&mut self, len: i32) -> io::Result<Vec<u8>> {
+        let len = usize::try_from(len)
+            .ok()
+            .and_then(|len| len.checked_sub(4))
+            .filter(|&len| len <= MAX_MESSAGE_LEN)
+            .ok_or_else(|| invalid_data(format!("invalid message length {len}")))?;
+        let mut body = vec![];
+        (&mut self.reader).take(len as u64).read_to_end(&mut body)?;
+        if body.len() < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(body)
+    }
+
+    fn send(&mut self, message: Message) -> io::Result<()> {
+        self.writer.write_all(&[message.tag])?;
+        self.writer
+            .write_all(&(message.body.len() as i32 + 4).to_be_bytes())?;
+        self.writer.write_all(&message.body)
+    }
+
+    /// Sends an error, or returns it if it is a failure of the connection itself.
+    fn send_error(&mut self, err: ConnectionError) -> io::Result<()> {
+        let (code, message) = match err {
+            ConnectionError::Io(err) => return Err(err),
+            ConnectionError::Protocol(message) => ("08P01", message),
+            ConnectionError::Db(err) => (sqlstate(&err), err.to_string()),
+        };
+        self.send(error_response("ERROR", code, &message))
+    }
+
+    fn ready_for_query(&mut self) -> io::Result<()> {
+        self.send(Message::new(b'Z').u8(b'I'))?;
+        self.writer.flush()
+    }
+
+    /// Writes the changes made by the statements run so far to disk.
+    fn flush_tables(&mut self) -> io::Result<()> {
+        if std::mem::take(&mut self.dirty) {
+            let result = self.server.database.flush();
+            result.map_err(|err| io::Error::other(err.to_string()))?;
+        }
+        Ok(())
+    }
+}
+
+impl Message {
+    fn new(tag: u8) -> Self {
+        Self { tag, body: vec![] }
+    }
+
+    fn u8(mut self, n: u8) -> Self {
+        self.body.push(n);
+        self
+    }
+
+    fn i16(mut self, n: i16) -> Self {
+        self.body.extend_from_slice(&n.to_be_bytes());
+        self
+    }
+
+    fn i32(mut self, n: i32) -> Self {
+        self.body.extend_from_slice(&n.to_be_bytes());
+        self
+    }
+
+    fn cstr(mut self, s: &str) -> Self {
+        self.body.extend_from_slice(s.as_bytes());
+        self.body.push(0);
+        self
+    }
+
+    /// Appends a length-prefixed value, or -1 for NULL.
+    fn value(mut self, bytes: Option<&[u8]>) -> Self {
+        match bytes {
+            Some(bytes) => {
+                self = self.i32(bytes.len() as i32);
+                self.body.extend_from_slice(bytes);
+                self
+            }
+            None => self.i32(-1),
+        }
+    }
+}
+
+impl<'a> Fields<'a> {
+    fn bytes(&mut self, len: usize) -> Result<&'a [u8], ConnectionError> {
+        if self.0.len() < len {
+            return Err(ConnectionError::Protocol(
+                "message is too short".to_string(),
+            ));
+        }
+        let (bytes, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, ConnectionError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn i16(&mut self) -> Result<i16, ConnectionError> {
+        Ok(i16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, ConnectionError> {
+        Ok(i32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn cstr(&mut self) -> Result<&'a str, ConnectionError> {
+        let len = self
+            .0
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or_else(|| ConnectionError::Protocol("string is not terminated".to_string()))?;
+        let s = std::str::from_utf8(self.bytes(len)?)
+            .map_err(|_| ConnectionError::Protocol("string is not valid UTF-8".to_string()))?;
+        self.bytes(1)?;
+        Ok(s)
+    }
+
+    /// Reads a list of format codes.
+    fn formats(&mut self) -> Result<Vec<i16>, ConnectionError> {
+        let len = self.i16()?;
+        (0..len).map(|_| self.i16()).collect()
+    }
+}
+
+fn invalid_data(message: impl Display) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Returns the format of the `i`th value: the only format if there is one, and text if there
+/// are none.
+fn format_of(formats: &[i16], i: usize) -> i16 {
+    match formats {
+        [] => TEXT_FORMAT,
+        [format] => *format,
+        formats => formats.get(i).copied().unwrap_or(TEXT_FORMAT),
+    }
+}
+
+fn type_oid(data_type: DataType) -> i32 {
+    match data_type {
+        DataType::Int => INT8_OID,
+        DataType::Text => TEXT_OID,
+        DataType::Bool => BOOL_OID,
+    }
+}
+
+fn type_of_oid(oid: i32) -> Result<DataType, DbError> {
+    match oid {
+        INT2_OID | INT4_OID | INT8_OID => Ok(DataType::Int),
+        TEXT_OID | VARCHAR_OID => Ok(DataType::Text),
+        BOOL_OID => Ok(DataType::Bool),
+        _ => Err(DbError::Unsupported(format!("parameters of type {oid}"))),
+    }
+}
+
+/// A value of `data_type`, standing for a parameter when only the types matter.
+fn placeholder(data_type: DataType) -> Value {
+    match data_type {
+        DataType::Int => Value::Int(0),
+        DataType::Text => Value::Text(String::new()),
+        DataType::Bool => Value::Bool(false),
+    }
+}
+
+fn decode_param(bytes: &[u8], data_type: DataType, format: i16) -> Option<Value> {
+    if format == BINARY_FORMAT {
+        return match data_type {
+            DataType::Int => match bytes.len() {
+                2 => Some(i16::from_be_bytes(bytes.try_into().ok()?).into()),
+                4 => Some(i32::from_be_bytes(bytes.try_into().ok()?).into()),
+                _ => Some(i64::from_be_bytes(bytes.try_into().ok()?)),
+            }
+            .map(Value::Int),
+            DataType::Text => Some(Value::Text(String::from_utf8(bytes.to_vec()).ok()?)),
+            DataType::Bool => match bytes {
+                [byte] => Some(Value::Bool(*byte != 0)),
+                _ => None,
+            },
+        };
+    }
+    let text = std::str::from_utf8(bytes).ok()?;
+    match data_type {
+        DataType::Int => text.trim().parse().ok().map(Value::Int),
+        DataType::Text => Some(Value::Text(text.to_string())),
+        DataType::Bool => match text.trim().to_ascii_lowercase().as_str() {
+            "t" | "true" | "y" | "yes" | "on" | "1" => Some(Value::Bool(true)),
+            "f" | "false" | "n" | "no" | "off" | "0" => Some(Value::Bool(false)),
+            _ => None,
+        },
+    }
+}
+
+fn row_description(columns: &[Column], formats: &[i16]) -> Message {
+    let mut message = Message::new(b'T').i16(columns.len() as i16);
+    for (i, column) in columns.iter().enumerate() {
+        let type_len = match column.data_type {
+            DataType::Int => 8,
+            DataType::Text => -1,
+            DataType::Bool => 1,
+        };
+        message = message
+            .cstr(&column.name)
+            .i32(0)
+            .i16(0)
+            .i32(type_oid(column.data_type))
+            .i16(type_len)
+            .i32(-1)
+            .i16(format_of(formats, i));
+    }
+    message
+}
+
+/// Encodes a row, sending as NULL the columns that do not hold a value of their type, like
+/// those padding a left outer join.
+fn data_row(row: &Tuple, columns: &[Column], formats: &[i16]) -> Message {
+    let mut message = Message::new(b'D').i16(row.len() as i16);
+    for (i, (bytes, column)) in row.iter().zip(columns).enumerate() {
+        let value = Value::decode(bytes, column.data_type);
+        let encoded = match (value, format_of(formats, i)) {
+            (None, _) => None,
+            (Some(Value::Int(n)), BINARY_FORMAT) => Some(n.to_be_bytes().to_vec()),
+            (Some(Value::Bool(b)), BINARY_FORMAT) => Some(vec![b as u8]),
+            (Some(Value::Bool(b)), _) => Some(if b { b"t" } else { b"f" }.to_vec()),
+            (Some(value), _) => Some(value.to_string().into_bytes()),
+        };
+        message = message.value(encoded.as_deref());
+    }
+    message
+}
+
+fn command_complete(statement: &Statement, output: &Output, num_rows: usize) -> Message {
+    let tag = match (statement, output) {
+        (Statement::CreateTable { .. }, _) => "CREATE TABLE".to_string(),
+        (Statement::CreateIndex { .. }, _) => "CREATE INDEX".to_string(),
+        (Statement::Insert { .. }, Output::Affected(n)) => format!("INSERT 0 {n}"),
+        (Statement::Update { .. }, Output::Affected(n)) => format!("UPDATE {n}"),
+        (Statement::Delete { .. }, Output::Affected(n)) => format!("DELETE {n}"),
+        (Statement::Explain { .. }, _) => "EXPLAIN".to_string(),
+        (Statement::Analyze, _) => "ANALYZE".to_string(),
+        _ => format!("SELECT {num_rows}"),
+    };
+    Message::new(b'C').cstr(&tag)
+}
+
+/// Returns the SQLSTATE code that PostgreSQL reports for errors of the kind of `err`.
+fn sqlstate(err: &DbError) -> &'static str {
+    match err {
+        DbError::Io(_) => "58030",
+        DbError::BufferExhausted => "53000",
+        DbError::DuplicateKey | DbError::UniqueViolation { .. } => "23505",
+        DbError::NotFound(_) => "42704",
+        DbError::NotADatabase | DbError::Corrupted(_) => "XX001",
+        DbError::Parse(_) => "42601",
+        DbError::Eval(EvalError::DivisionByZero) => "22012",
+        DbError::Eval(EvalError::Overflow) => "22003",
+        DbError::Eval(_) => "22000",
+        DbError::TooManyJoins { .. } => "54000",
+        DbError::AlreadyExists(_) => "42710",
+        DbError::InvalidSchema(_) => "42P16",
+        DbError::Unsupported(_) => "0A000",
+        DbError::InvalidArgument(_) => "22023",
+        DbError::Invalid(_) => "42000",
+        _ => "XX000",
+    }
+}
+
+fn error_response(severity: &str, code: &str, message: &str) -> Message {
+    Message::new(b'E')
+        .u8(b'S')
+        .cstr(severity)
+        .u8(b'V')
+        .cstr(severity)
+        .u8(b'C')
+        .cstr(code)
+        .u8(b'M')
+        .cstr(message)
+        .u8(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database::Options;
+    use crate::disk::DiskManager;
+    use std::net::SocketAddr;
+    use std::thread;
+    use tempfile::tempfile;
+
+    /// Starts a server on a new database in a background thread.
+    fn start_server() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+            let database = Database::with_disk(disk, Options::default()).unwrap();
+            Server::new(database).serve(&listener).unwrap();
+        });
+        addr
+    }
+
+    struct Client(TcpStream);
+
+    impl Client {
+        fn connect(addr: SocketAddr) -> Self {
+            let mut client = Client(TcpStream::connect(addr).unwrap());
+            let body = Message::new(0)
+                .i32(PROTOCOL_VERSION)
+                .cstr("user")
+                .cstr("test")
+                .u8(0)
+                .body;
+            client
+                .0
+                .write_all(&(body.len() as i32 + 4).to_be_bytes())
+                .unwrap();
+            client.0.write_all(&body).unwrap();
+            let tags: Vec<u8> = client
+                .recv_until_ready()
+                .iter()
+                .map(|(tag, _)| *tag)
+                .collect();
+            assert_eq!(b'R', tags[0]);
+            assert_eq!(Some(&b'Z'), tags.last());
+            client
+        }
+
+        fn send(&mut self, message: Message) {
+            self.0.write_all(&[message.tag]).unwrap();
+            self.0
+                .write_all(&(message.body.len() as i32 + 4).to_be_bytes())
+                .unwrap();
+            self.0.write_all(&message.body).unwrap();
+        }
+
+        fn recv(&mut self) -> (u8, Vec<u8>) {
+            let mut header = [0; 5];
+            self.0.read_exact(&mut header).unwrap();
+            let len = i32::from_be_bytes(header[1..].try_into().unwrap()) as usize;
+            let mut body = vec![0; len - 4];
+            self.0.read_exact(&mut body).unwrap();
+            (header[0], body)
+        }
+
+        fn recv_until_ready(&mut self) -> Vec<(u8, Vec<u8>)> {
+            let mut messages = vec![];
+            loop {
+                let message = self.recv();
+                let ready = message.0 == b'Z';
+                messages.push(message);
+                if ready {
+                    return messages;
+                }
+            }
+        }
+
+        fn query(&mut self, sql: &str) -> Vec<(u8, Vec<u8>)> {
+            self.send(Message::new(b'Q').cstr(sql));
+            self.recv_until_ready()
+        }
+    }
+
+    fn tags(messages: &[(u8, Vec<u8>)]) -> String {
+        messages.iter().map(|(tag, _)| *tag as char).collect()
+    }
+
+    fn command_tag(body: &[u8]) -> String {
+        Fields(body).cstr().unwrap().to_string()
+    }
+
+    fn row_values(body: &[u8]) -> Vec<Option<Vec<u8>>> {
+        let mut fields = Fields(body);
+        (0..fields.i16().unwrap())
+            .map(|_| match fields.i32().unwrap() {
+                -1 => None,
+                len => Some(fields.bytes(len as usize).unwrap().to_vec()),
+            })
+            .collect()
+    }
+
+    /// Returns the field tagged `tag` of an ErrorResponse, like `b'M'` for its message.
+    fn error_field(body: &[u8], tag: u8) -> String {
+        let mut fields = Fields(body);
+        loop {
+            let field = fields.u8().unwrap();
+            let value = fields.cstr().unwrap();
+            if field == tag {
+                return value.to_string();
+            }
+        }
+    }
+
+    #[test]
+    fn test_simple_query() {
+        let mut client = Client::connect(start_server());
+
+        let messages = client.query(
+            "CREATE TABLE users (id INT PRIMARY KEY, name TEXT, admin BOOLEAN); \
+             INSERT INTO users (id, name) VALUES (1, 'alice'), (2, 'bob');",
+        );
+        assert_eq!("CCZ", tags(&messages));
+        assert_eq!("CREATE TABLE", command_tag(&messages[0].1));
+        assert_eq!("INSERT 0 2", command_tag(&messages[1].1));
+
+        let messages = client.query("SELECT id, name, admin FROM users ORDER BY id DESC");
+        assert_eq!("TDDCZ", tags(&messages));
+        let mut fields = Fields(&messages[0].1);
+        assert_eq!(3, fields.i16().unwrap());
+        assert_eq!("id", fields.cstr().unwrap());
+        assert_eq!(
+            vec![Some(b"2".to_vec()), Some(b"bob".to_vec()), None],
+            row_values(&messages[1].1)
+        );
+        assert_eq!("SELECT 2", command_tag(&messages[3].1));
+
+        let messages = client.query(
+            "CREATE TABLE posts (id INT PRIMARY KEY, author INT, title TEXT); \
+             INSERT INTO posts VALUES (1, 1, 'hello'); \
+             SELECT u.name, p.title FROM users u LEFT JOIN posts p ON u.id = p.author \
+             ORDER BY u.id",
+        );
+        assert_eq!("CCTDDCZ", tags(&messages));
+        assert_eq!(
+            vec![Some(b"alice".to_vec()), Some(b"hello".to_vec())],
+            row_values(&messages[3].1)
+        );
+        assert_eq!(
+            vec![Some(b"bob".to_vec()), None],
+            row_values(&messages[4].1)
+        );
+
+        let messages = client.query("SELECT nothing FROM users; SELECT 1 FROM users");
+        assert_eq!("EZ", tags(&messages));
+        assert_eq!(
+            "column nothing does not exist",
+            error_field(&messages[0].1, b'M')
+        );
+        assert_eq!("42704", error_field(&messages[0].1, b'C'));
+        assert_eq!("IZ", tags(&client.query("")));
+
+        // Each kind of error has its own code.
+        for (sql, code) in [
+            ("SELECT FROM", "42601"),
+            ("INSERT INTO users (id) VALUES (1)", "23505"),
+            ("CREATE TABLE users (id INT PRIMARY KEY)", "42710"),
+            ("SELECT id / 0 FROM users", "22012"),
+            ("SELECT id FROM users WHERE name", "42000"),
+        ] {
+            let messages = client.query(sql);
+            assert_eq!("EZ", tags(&messages), "{sql}");
+            assert_eq!(code, error_field(&messages[0].1, b'C'), "{sql}");
+        }
+    }
+
+    #[test]
+    fn test_message_too_long() {
+        let addr = start_server();
+        let mut client = Client::connect(addr);
+        client.0.write_all(b"Q").unwrap();
+        client.0.write_all(&(1i32 << 28).to_be_bytes()).unwrap();
+        client.0.write_all(b"SELECT").unwrap();
+        // The server closes the connection instead of waiting for the rest of the message.
+        let mut buf = vec![];
+        assert!(matches!(client.0.read_to_end(&mut buf), Ok(0) | Err(_)));
+
+        let mut client = Client::connect(addr);
+        assert_eq!("IZ", tags(&client.query("")));
+    }
+
+    #[test]
+    fn test_extended_query() {
+        let mut client = Client::connect(start_server());
+        client.query("CREATE TABLE users (id INT PRIMARY KEY, name TEXT)");
+
+        client.send(
+            Message::new(b'P')
+                .cstr("insert")
+                .cstr("INSERT INTO users VALUES ($1, $2)")
+                .i16(0),
+        );
+        client.send(Message::new(b'D').u8(b'S').cstr("insert"));
+        for (id, name) in [("1", "alice"), ("2", "bob"), ("3", "carol")] {
+            client.send(
+                Message::new(b'B')
+                    .cstr("")
+                    .cstr("insert")
+                    .i16(0)
+                    .i16(2)
+                    .value(Some(id.as_bytes()))
+                    .value(Some(name.as_bytes()))
+                    .i16(0),
+            );
+            client.send(Message::new(b'E').cstr("").i32(0));
+        }
+        client.send(Message::new(b'S'));
+        let messages = client.recv_until_ready();
+        assert_eq!("1tn2C2C2CZ", tags(&messages));
+        let mut fields = Fields(&messages[1].1);
+        assert_eq!(2, fields.i16().unwrap());
+        assert_eq!(INT8_OID, fields.i32().unwrap());
+        assert_eq!(TEXT_OID, fields.i32().unwrap());
+        assert_eq!("INSERT 0 1", command_tag(&messages[4].1));
+
+        // A binary parameter, binary results and a row limit.
+        client.send(
+            Message::new(b'P')
+                .cstr("")
+                .cstr("SELECT id, name FROM users WHERE id > $1 ORDER BY id")
+                .i16(1)
+                .i32(INT4_OID),
+        );
+        client.send(
+            Message::new(b'B')
+                .cstr("")
+                .cstr("")
+                .i16(1)
+                .i16(BINARY_FORMAT)
+                .i16(1)
+                .value(Some(&1i32.to_be_bytes()))
+                .i16(2)
+                .i16(BINARY_FORMAT)
+                .i16(TEXT_FORMAT),
+        );
+        client.send(Message::new(b'D').u8(b'P').cstr(""));
+        client.send(Message::new(b'E').cstr("").i32(1));
+        client.send(Message::new(b'E').cstr("").i32(0));
+        client.send(Message::new(b'S'));
+        let messages = client.recv_until_ready();
+        assert_eq!("12TDsDCZ", tags(&messages));
+        assert_eq!(
+            vec![Some(2i64.to_be_bytes().to_vec()), Some(b"bob".to_vec())],
+            row_values(&messages[3].1)
+        );
+        assert_eq!(
+            vec![Some(3i64.to_be_bytes().to_vec()), Some(b"carol".to_vec())],
+            row_values(&messages[5].1)
+        );
+        assert_eq!("SELECT 2", command_tag(&messages[6].1));
+
+        // Messages after an error are skipped until Sync.
+        client.send(Message::new(b'P').cstr("").cstr("SELECT FROM").i16(0));
+        client.send(Message::new(b'B').cstr("").cstr("").i16(0).i16(0).i16(0));
+        client.send(Message::new(b'S'));
+        let messages = client.recv_until_ready();
+        assert_eq!("EZ", tags(&messages));
+        assert_eq!("42601", error_field(&messages[0].1, b'C'));
+
+        // Malformed messages are protocol violations, unlike unknown names.
+        client.send(
+            Message::new(b'B')
+                .cstr("")
+                .cstr("nothing")
+                .i16(0)
+                .i16(0)
+                .i16(0),
+        );
+        client.send(Message::new(b'S'));
+        let messages = client.recv_until_ready();
+        assert_eq!("42704", error_field(&messages[0].1, b'C'));
+        client.send(Message::new(b'D').u8(b'X').cstr(""));
+        client.send(Message::new(b'S'));
+        let messages = client.recv_until_ready();
+        assert_eq!("08P01", error_field(&messages[0].1, b'C'));
+        client.send(
+            Message::new(b'P')
+                .cstr("")
+                .cstr("SELECT id FROM users WHERE id = $1")
+                .i16(1)
+                .i32(700),
+        );
+        client.send(Message::new(b'S'));
+        let messages = client.recv_until_ready();
+        assert_eq!("0A000", error_field(&messages[0].1, b'C'));
+
+        client.send(Message::new(b'X'));
+        let mut rest = vec![];
+        client.0.read_to_end(&mut rest).unwrap();
+        assert!(rest.is_empty());
+    }
+}
@@ -0,0 +1,182 @@
+use std::fmt::{self, Display, Formatter};
+
+use super::expr::{EvalError, Expr};
+use super::{Tuple, TupleSlice};
+
+use crate::error::DbError;
+use crate::tuple;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggFunc {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Avg,
+}
+
+/// An aggregate function over an argument. `COUNT(*)` has no argument.
+///
+/// `SUM` and `AVG` work on integers encoded with [`tuple::encode_int`], and `AVG` truncates
+/// toward zero. Except for `COUNT`, the result over no tuples is an empty value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AggExpr {
+    pub func: AggFunc,
+    pub arg: Option<Expr>,
+}
+
+impl AggExpr {
+    pub fn count_star() -> Self {
+        Self {
+            func: AggFunc::Count,
+            arg: None,
+        }
+    }
+
+    pub fn new(func: AggFunc, arg: Expr) -> Self {
+        Self {
+            func,
+            arg: Some(arg),
+        }
+    }
+
+    pub fn columns(&self, columns: &mut Vec<usize>) {
+        if let Some(arg) = &self.arg {
+            arg.columns(columns);
+        }
+    }
+}
+
+impl Display for AggExpr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = match self.func {
+            AggFunc::Count => "COUNT",
+            AggFunc::Sum => "SUM",
+            AggFunc::Min => "MIN",
+            AggFunc::Max => "MAX",
+            AggFunc::Avg => "AVG",
+        };
+        match &self.arg {
+            Some(arg) => write!(f, "{name}({arg})"),
+            None => write!(f, "{name}(*)"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Accumulator {
+    Count(i64),
+    Sum(Option<i64>),
+    Min(Option<Vec<u8>>),
+    Max(Option<Vec<u8>>),
+    Avg { sum: i64, count: i64 },
+}
+
+impl Accumulator {
+    pub fn new(func: AggFunc) -> Self {
+        match func {
+            AggFunc::Count => Accumulator::Count(0),
+            AggFunc::Sum => Accumulator::Sum(None),
+            AggFunc::Min => Accumulator::Min(None),
+            AggFunc::Max => Accumulator::Max(None),
+            AggFunc::Avg => Accumulator::Avg { sum: 0, count: 0 },
+        }
+    }
+
+    pub fn update(&mut self, agg: &AggExpr, tuple: TupleSlice) -> Result<(), EvalError> {
+        let value = agg.arg.as_ref().map(|arg| arg.eval(tuple)).transpose()?;
+        let int = |value: &Option<Vec<u8>>| {
+            let value = value.as_deref().unwrap_or_default();
+            tuple::decode_int(value).ok_or(EvalError::NotInteger)
+        };
+        match self {
+            Accumulator::Count(count) => *count += 1,
+            Accumulator::Sum(sum) => {
+                let n = int(&value)?;
+                let sum = sum.get_or_insert(0);
+                *sum = sum.checked_add(n).ok_or(EvalError::Overflow)?;
+            }
+            Accumulator::Min(min) => {
+                let value = value.unwrap_or_default();
+                if min.as_ref().is_none_or(|min| value < *min) {
+                    *min = Some(value);
+                }
+            }
+            Accumulator::Max(max) => {
+                let value = value.unwrap_or_default();
+                if max.as_ref().is_none_or(|max| value > *max) {
+                    *max = Some(value);
+                }
+            }
+            Accumulator::Avg { sum, count } => {
+                *sum = sum.checked_add(int(&value)?).ok_or(EvalError::Overflow)?;
+                *count += 1;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(&self) -> Vec<u8> {
+        match self {
+            Accumulator::Count(count) => tuple::encode_int(*count).to_vec(),
+            Accumulator::Sum(sum) => sum
+                .map(|sum| tuple::encode_int(sum).to_vec())
+                .unwrap_or_default(),
+            Accumulator::Min(value) | Accumulator::Max(value) => value.clone().unwrap_or_default(),
+            Accumulator::Avg { count: 0, .. } => vec![],
+            Accumulator::Avg { sum, count } => tuple::encode_int(sum / count).to_vec(),
+        }
+    }
+}
+
+/// The accumulators of one group.
+pub struct Group<'a> {
+    aggregates: &'a [AggExpr],
+    accumulators: Vec<Accumulator>,
+}
+
+impl<'a> Group<'a> {
+    pub fn new(aggregates: &'a [AggExpr]) -> Self {
+        let accumulators = aggregates
+            .iter()
+            .map(|agg| Accumulator::new(agg.func))
+            .collect();
+        Self {
+            aggregates,
+            accumulators,
+        }
+    }
+
+    pub fn update(&mut self, tuple: TupleSlice) -> Result<(), EvalError> {
+        for (acc, agg) in self.accumulators.iter_mut().zip(self.aggregates) {
+            acc.update(agg, tuple)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the output tuple: the group key followed by the aggregate results.
+    pub fn finish(&self, key: Tuple) -> Tuple {
+        let mut tuple = key;
+        tuple.extend(self.accumulators.iter().map(Accumulator::finish));
+        tuple
+    }
+}
+
+pub fn eval_group_key(group_by: &[Expr], tuple: TupleSlice) -> Result<Tuple, DbError> {
+    Ok(group_by
+        .iter()
+        .map(|expr| expr.eval(tuple))
+        .collect::<Result<_, _>>()?)
+}
+
+/// Returns the columns read by the group keys and aggregates.
+pub fn columns(group_by: &[Expr], aggregates: &[AggExpr]) -> Vec<usize> {
+    let mut columns = vec![];
+    for expr in group_by {
+        expr.columns(&mut columns);
+    }
+    for agg in aggregates {
+        agg.columns(&mut columns);
+    }
+    columns
+}
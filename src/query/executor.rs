@@ -1,32 +1,76 @@
-use std::error::Error;
+use std::cell::Cell;
+use std::collections::HashMap;
 
-use super::{BoxExecutor, Condition, Tuple};
+use super::aggregate::{eval_group_key, AggExpr, Group};
+use super::explain::ActualStats;
+use super::expr::Expr;
+use super::join::{encode_join_key, eval_join_key, join_tuples, JoinType};
+use super::planner::PlanNode;
+use super::sort::{ExternalSorter, SortKey, Sorted, TopN};
+use super::{BoxExecutor, Condition, Predicate, Tuple};
 
 use crate::btree::{BTree, Iter, SearchMode};
 use crate::buffer::BufferPoolManager;
+use crate::error::DbError;
+use crate::heap;
+use crate::table::Table;
 use crate::tuple;
 use crate::tuple::decode;
 
 pub trait Executor {
-    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, Box<dyn Error>>;
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError>;
+}
+
+/// An executor with the buffer pool it reads pages through, yielding its tuples as an
+/// [`Iterator`]. It ends after the first error.
+pub struct TupleIter<'a, 'b> {
+    executor: BoxExecutor<'a>,
+    bufmgr: &'b mut BufferPoolManager,
+    done: bool,
+}
+
+impl<'a, 'b> TupleIter<'a, 'b> {
+    pub fn new(executor: BoxExecutor<'a>, bufmgr: &'b mut BufferPoolManager) -> Self {
+        Self {
+            executor,
+            bufmgr,
+            done: false,
+        }
+    }
+}
+
+impl<'a, 'b> Iterator for TupleIter<'a, 'b> {
+    type Item = Result<Tuple, DbError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.executor.next(self.bufmgr).transpose();
+        self.done = !matches!(result, Some(Ok(_)));
+        result
+    }
 }
 
 pub struct ExecSeqScan<'a> {
     table_iter: Iter,
     while_cond: Condition<'a>,
+    /// The columns to decode from the value, or all if `None`.
+    columns: Option<Vec<usize>>,
 }
 
 impl<'a> ExecSeqScan<'a> {
-    pub fn new(table_iter: Iter, while_cond: Condition<'a>) -> Self {
+    pub fn new(table_iter: Iter, while_cond: Condition<'a>, columns: Option<Vec<usize>>) -> Self {
         Self {
             table_iter,
             while_cond,
+            columns,
         }
     }
 }
 
 impl<'a> Executor for ExecSeqScan<'a> {
-    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, Box<dyn Error>> {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
         let (pk_bytes, tuple_bytes) = match self.table_iter.next(bufmgr)? {
             Some(pair) => pair,
             None => return Ok(None),
@@ -39,29 +83,53 @@ impl<'a> Executor for ExecSeqScan<'a> {
         }
 
         let mut tuple = pk;
-        tuple::decode(&tuple_bytes, &mut tuple);
+        decode_value(&tuple_bytes, &mut tuple, self.columns.as_deref());
+        Ok(Some(tuple))
+    }
+}
+
+/// Reads every row of a [`HeapTable`](crate::table::HeapTable) in storage order.
+pub struct ExecHeapScan {
+    heap_iter: heap::Iter,
+    /// The columns to decode, or all if `None`.
+    columns: Option<Vec<usize>>,
+}
+
+impl ExecHeapScan {
+    pub fn new(heap_iter: heap::Iter, columns: Option<Vec<usize>>) -> Self {
+        Self { heap_iter, columns }
+    }
+}
+
+impl Executor for ExecHeapScan {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
+        let Some((_, bytes)) = self.heap_iter.next(bufmgr)? else {
+            return Ok(None);
+        };
+        let mut tuple = vec![];
+        decode_value(&bytes, &mut tuple, self.columns.as_deref());
         Ok(Some(tuple))
     }
 }
 
 pub struct ExecFilter<'a> {
     inner_executor: BoxExecutor<'a>,
-    while_cond: Condition<'a>,
+    cond: Predicate<'a>,
 }
 
 impl<'a> ExecFilter<'a> {
-    pub fn new(inner_executor: BoxExecutor<'a>, while_cond: Condition<'a>) -> Self {
+    pub fn new(inner_executor: BoxExecutor<'a>, cond: Predicate<'a>) -> Self {
         Self {
             inner_executor,
-            while_cond,
+            cond,
         }
     }
 }
 
 impl<'a> Executor for ExecFilter<'a> {
-    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, Box<dyn Error>> {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
         while let Some(tuple) = self.inner_executor.next(bufmgr)? {
-            if (self.while_cond)(&tuple) {
+            if (self.cond)(&tuple)? {
                 return Ok(Some(tuple));
             }
         }
@@ -71,23 +139,35 @@ impl<'a> Executor for ExecFilter<'a> {
 
 pub struct ExecIndexScan<'a> {
     table_btree: BTree,
+    /// The number of primary key columns of the table, which start the index values.
+    num_key_elems: usize,
     index_iter: Iter,
     while_cond: Condition<'a>,
+    /// The columns to decode from the table value, or all if `None`.
+    columns: Option<Vec<usize>>,
 }
 
 impl<'a> ExecIndexScan<'a> {
-    pub fn new(table_btree: BTree, index_iter: Iter, while_cond: Condition<'a>) -> Self {
+    pub fn new(
+        table_btree: BTree,
+        num_key_elems: usize,
+        index_iter: Iter,
+        while_cond: Condition<'a>,
+        columns: Option<Vec<usize>>,
+    ) -> Self {
         Self {
             table_btree,
+            num_key_elems,
             index_iter,
             while_cond,
+            columns,
         }
     }
 }
 
 impl<'a> Executor for ExecIndexScan<'a> {
-    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, Box<dyn Error>> {
-        let (skey_bytes, pkey_bytes) = match self.index_iter.next(bufmgr)? {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
+        let (skey_bytes, value_bytes) = match self.index_iter.next(bufmgr)? {
             Some(x) => x,
             None => return Ok(None),
         };
@@ -96,11 +176,889 @@ impl<'a> Executor for ExecIndexScan<'a> {
         if !(self.while_cond)(&skey) {
             return Ok(None);
         }
-        let table_iter = self.table_btree.search(bufmgr, SearchMode::Key(pkey_bytes));
-        let (pkey_bytes, tuple_bytes) = table_iter?.next(bufmgr)?.unwrap();
-        let mut record = vec![];
-        decode(pkey_bytes.as_slice(), &mut record);
-        decode(tuple_bytes.as_slice(), &mut record);
+        let pkey_bytes = index_pkey(&value_bytes, self.num_key_elems)?;
+        let record = fetch_row(
+            &self.table_btree,
+            bufmgr,
+            pkey_bytes,
+            self.columns.as_deref(),
+        )?;
+        Ok(Some(record))
+    }
+}
+
+/// Returns the row found by a lookup in a hash index, whose value is given when the executor is
+/// created.
+pub struct ExecHashIndexScan {
+    table_btree: BTree,
+    num_key_elems: usize,
+    index_value: Option<Vec<u8>>,
+    columns: Option<Vec<usize>>,
+}
+
+impl ExecHashIndexScan {
+    pub fn new(
+        table_btree: BTree,
+        num_key_elems: usize,
+        index_value: Option<Vec<u8>>,
+        columns: Option<Vec<usize>>,
+    ) -> Self {
+        Self {
+            table_btree,
+            num_key_elems,
+            index_value,
+            columns,
+        }
+    }
+}
+
+impl Executor for ExecHashIndexScan {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
+        let Some(value_bytes) = self.index_value.take() else {
+            return Ok(None);
+        };
+        let pkey_bytes = index_pkey(&value_bytes, self.num_key_elems)?;
+        let record = fetch_row(
+            &self.table_btree,
+            bufmgr,
+            pkey_bytes,
+            self.columns.as_deref(),
+        )?;
+        Ok(Some(record))
+    }
+}
+
+/// Reads the rows straight from an index holding every column the consumer needs, without
+/// looking them up in the table. The rows have the columns of the table, with those not in the
+/// index left empty.
+pub struct ExecIndexOnlyScan<'a> {
+    index_iter: Iter,
+    num_key_elems: usize,
+    skey: &'a [usize],
+    include: &'a [usize],
+    while_cond: Condition<'a>,
+    num_columns: usize,
+}
+
+impl<'a> ExecIndexOnlyScan<'a> {
+    pub fn new(
+        index_iter: Iter,
+        num_key_elems: usize,
+        skey: &'a [usize],
+        include: &'a [usize],
+        while_cond: Condition<'a>,
+    ) -> Self {
+        let num_columns = skey
+            .iter()
+            .chain(include)
+            .map(|&column| column + 1)
+            .fold(num_key_elems, usize::max);
+        Self {
+            index_iter,
+            num_key_elems,
+            skey,
+            include,
+            while_cond,
+            num_columns,
+        }
+    }
+}
+
+impl<'a> Executor for ExecIndexOnlyScan<'a> {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
+        let (skey_bytes, value_bytes) = match self.index_iter.next(bufmgr)? {
+            Some(x) => x,
+            None => return Ok(None),
+        };
+        let mut skey = vec![];
+        decode(&skey_bytes, &mut skey);
+        if !(self.while_cond)(&skey) {
+            return Ok(None);
+        }
+        let mut value = vec![];
+        decode(&value_bytes, &mut value);
+        let mut record = vec![vec![]; self.num_columns];
+        let mut value = value.into_iter();
+        for column in record.iter_mut().take(self.num_key_elems) {
+            *column = value.next().ok_or_else(short_index_entry)?;
+        }
+        for (&column, elem) in self.include.iter().zip(value) {
+            record[column] = elem;
+        }
+        for (&column, elem) in self.skey.iter().zip(skey) {
+            record[column] = elem;
+        }
         Ok(Some(record))
     }
 }
+
+pub struct ExecProject<'a> {
+    inner_executor: BoxExecutor<'a>,
+    exprs: &'a [Expr],
+}
+
+impl<'a> ExecProject<'a> {
+    pub fn new(inner_executor: BoxExecutor<'a>, exprs: &'a [Expr]) -> Self {
+        Self {
+            inner_executor,
+            exprs,
+        }
+    }
+}
+
+impl<'a> Executor for ExecProject<'a> {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
+        let Some(tuple) = self.inner_executor.next(bufmgr)? else {
+            return Ok(None);
+        };
+        let projected = self
+            .exprs
+            .iter()
+            .map(|expr| expr.eval(&tuple))
+            .collect::<Result<_, _>>()?;
+        Ok(Some(projected))
+    }
+}
+
+pub struct ExecSort<'a> {
+    inner_executor: BoxExecutor<'a>,
+    keys: &'a [SortKey],
+    memory_budget: usize,
+    sorted: Option<Sorted<'a>>,
+}
+
+impl<'a> ExecSort<'a> {
+    pub fn new(inner_executor: BoxExecutor<'a>, keys: &'a [SortKey], memory_budget: usize) -> Self {
+        Self {
+            inner_executor,
+            keys,
+            memory_budget,
+            sorted: None,
+        }
+    }
+}
+
+impl<'a> Executor for ExecSort<'a> {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
+        let sorted = match &mut self.sorted {
+            Some(sorted) => sorted,
+            None => {
+                let mut sorter = ExternalSorter::new(self.keys, self.memory_budget);
+                while let Some(tuple) = self.inner_executor.next(bufmgr)? {
+                    sorter.push(tuple)?;
+                }
+                self.sorted.insert(sorter.finish()?)
+            }
+        };
+        sorted.next().transpose()
+    }
+}
+
+pub struct ExecLimit<'a> {
+    inner_executor: BoxExecutor<'a>,
+    offset: usize,
+    count: usize,
+}
+
+impl<'a> ExecLimit<'a> {
+    pub fn new(inner_executor: BoxExecutor<'a>, offset: usize, count: usize) -> Self {
+        Self {
+            inner_executor,
+            offset,
+            count,
+        }
+    }
+}
+
+impl<'a> Executor for ExecLimit<'a> {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
+        while self.offset > 0 {
+            if self.inner_executor.next(bufmgr)?.is_none() {
+                self.count = 0;
+                return Ok(None);
+            }
+            self.offset -= 1;
+        }
+        if self.count == 0 {
+            return Ok(None);
+        }
+        let tuple = self.inner_executor.next(bufmgr)?;
+        self.count = if tuple.is_some() { self.count - 1 } else { 0 };
+        Ok(tuple)
+    }
+}
+
+pub struct ExecTopN<'a> {
+    inner_executor: BoxExecutor<'a>,
+    keys: &'a [SortKey],
+    offset: usize,
+    count: usize,
+    tuples: Option<std::vec::IntoIter<Tuple>>,
+}
+
+impl<'a> ExecTopN<'a> {
+    pub fn new(
+        inner_executor: BoxExecutor<'a>,
+        keys: &'a [SortKey],
+        offset: usize,
+        count: usize,
+    ) -> Self {
+        Self {
+            inner_executor,
+            keys,
+            offset,
+            count,
+            tuples: None,
+        }
+    }
+}
+
+impl<'a> Executor for ExecTopN<'a> {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
+        let tuples = match &mut self.tuples {
+            Some(tuples) => tuples,
+            None => {
+                let mut top_n = TopN::new(self.keys, self.offset.saturating_add(self.count));
+                while let Some(tuple) = self.inner_executor.next(bufmgr)? {
+                    top_n.push(tuple)?;
+                }
+                let mut tuples = top_n.finish().into_iter();
+                if self.offset > 0 {
+                    tuples.nth(self.offset - 1);
+                }
+                self.tuples.insert(tuples)
+            }
+        };
+        Ok(tuples.next())
+    }
+}
+
+pub struct ExecHashAggregate<'a> {
+    inner_executor: BoxExecutor<'a>,
+    group_by: &'a [Expr],
+    aggregates: &'a [AggExpr],
+    output: Option<std::vec::IntoIter<Tuple>>,
+}
+
+impl<'a> ExecHashAggregate<'a> {
+    pub fn new(
+        inner_executor: BoxExecutor<'a>,
+        group_by: &'a [Expr],
+        aggregates: &'a [AggExpr],
+    ) -> Self {
+        Self {
+            inner_executor,
+            group_by,
+            aggregates,
+            output: None,
+        }
+    }
+
+    fn aggregate(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Vec<Tuple>, DbError> {
+        let mut group_ids = HashMap::new();
+        let mut groups = vec![];
+        if self.group_by.is_empty() {
+            group_ids.insert(vec![], 0);
+            groups.push((vec![], Group::new(self.aggregates)));
+        }
+        while let Some(tuple) = self.inner_executor.next(bufmgr)? {
+            let key = eval_group_key(self.group_by, &tuple)?;
+            let group_id = *group_ids.entry(key).or_insert_with_key(|key| {
+                groups.push((key.clone(), Group::new(self.aggregates)));
+                groups.len() - 1
+            });
+            groups[group_id].1.update(&tuple)?;
+        }
+        Ok(groups
+            .into_iter()
+            .map(|(key, group)| group.finish(key))
+            .collect())
+    }
+}
+
+impl<'a> Executor for ExecHashAggregate<'a> {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
+        if self.output.is_none() {
+            let output = self.aggregate(bufmgr)?;
+            self.output = Some(output.into_iter());
+        }
+        Ok(self.output.as_mut().and_then(Iterator::next))
+    }
+}
+
+pub struct ExecStreamAggregate<'a> {
+    inner_executor: BoxExecutor<'a>,
+    group_by: &'a [Expr],
+    aggregates: &'a [AggExpr],
+    current: Option<(Tuple, Group<'a>)>,
+    done: bool,
+}
+
+impl<'a> ExecStreamAggregate<'a> {
+    pub fn new(
+        inner_executor: BoxExecutor<'a>,
+        group_by: &'a [Expr],
+        aggregates: &'a [AggExpr],
+    ) -> Self {
+        Self {
+            inner_executor,
+            group_by,
+            aggregates,
+            current: None,
+            done: false,
+        }
+    }
+}
+
+impl<'a> Executor for ExecStreamAggregate<'a> {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
+        if self.done {
+            return Ok(None);
+        }
+        while let Some(tuple) = self.inner_executor.next(bufmgr)? {
+            let key = eval_group_key(self.group_by, &tuple)?;
+            match &mut self.current {
+                Some((current_key, group)) if *current_key == key => group.update(&tuple)?,
+                _ => {
+                    let mut group = Group::new(self.aggregates);
+                    group.update(&tuple)?;
+                    if let Some((key, group)) = self.current.replace((key, group)) {
+                        return Ok(Some(group.finish(key)));
+                    }
+                }
+            }
+        }
+        self.done = true;
+        match self.current.take() {
+            Some((key, group)) => Ok(Some(group.finish(key))),
+            None if self.group_by.is_empty() => {
+                Ok(Some(Group::new(self.aggregates).finish(vec![])))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+pub struct ExecNestedLoopJoin<'a> {
+    left_executor: BoxExecutor<'a>,
+    right_plan: &'a dyn PlanNode,
+    cond: &'a Expr,
+    join_type: JoinType,
+    /// The current left tuple, the scan of the right plan for it and whether it matched.
+    current: Option<(Tuple, BoxExecutor<'a>, bool)>,
+}
+
+impl<'a> ExecNestedLoopJoin<'a> {
+    pub fn new(
+        left_executor: BoxExecutor<'a>,
+        right_plan: &'a dyn PlanNode,
+        cond: &'a Expr,
+        join_type: JoinType,
+    ) -> Self {
+        Self {
+            left_executor,
+            right_plan,
+            cond,
+            join_type,
+            current: None,
+        }
+    }
+}
+
+impl<'a> Executor for ExecNestedLoopJoin<'a> {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
+        loop {
+            let (left, right_executor, matched) = match &mut self.current {
+                Some(current) => current,
+                None => {
+                    let Some(left) = self.left_executor.next(bufmgr)? else {
+                        return Ok(None);
+                    };
+                    let right_executor = self.right_plan.start(bufmgr)?;
+                    self.current.insert((left, right_executor, false))
+                }
+            };
+            while let Some(right) = right_executor.next(bufmgr)? {
+                let tuple = join_tuples(left, &right);
+                if self.cond.eval_bool(&tuple)? {
+                    *matched = true;
+                    return Ok(Some(tuple));
+                }
+            }
+            let (left, _, matched) = self.current.take().unwrap();
+            if !matched {
+                if let Some(tuple) = self.join_type.unmatched(left) {
+                    return Ok(Some(tuple));
+                }
+            }
+        }
+    }
+}
+
+pub struct ExecHashJoin<'a> {
+    left_executor: BoxExecutor<'a>,
+    right_executor: BoxExecutor<'a>,
+    left_keys: &'a [Expr],
+    right_keys: &'a [Expr],
+    join_type: JoinType,
+    hash_table: Option<HashMap<Tuple, Vec<Tuple>>>,
+    /// The joined tuples of the current left tuple not returned yet.
+    pending: std::vec::IntoIter<Tuple>,
+}
+
+impl<'a> ExecHashJoin<'a> {
+    pub fn new(
+        left_executor: BoxExecutor<'a>,
+        right_executor: BoxExecutor<'a>,
+        left_keys: &'a [Expr],
+        right_keys: &'a [Expr],
+        join_type: JoinType,
+    ) -> Self {
+        Self {
+            left_executor,
+            right_executor,
+            left_keys,
+            right_keys,
+            join_type,
+            hash_table: None,
+            pending: vec![].into_iter(),
+        }
+    }
+
+    fn build(
+        &mut self,
+        bufmgr: &mut BufferPoolManager,
+    ) -> Result<HashMap<Tuple, Vec<Tuple>>, DbError> {
+        let mut hash_table: HashMap<_, Vec<_>> = HashMap::new();
+        while let Some(right) = self.right_executor.next(bufmgr)? {
+            let key = eval_join_key(self.right_keys, &right)?;
+            hash_table.entry(key).or_default().push(right);
+        }
+        Ok(hash_table)
+    }
+}
+
+impl<'a> Executor for ExecHashJoin<'a> {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
+        if self.hash_table.is_none() {
+            let hash_table = self.build(bufmgr)?;
+            self.hash_table = Some(hash_table);
+        }
+        let hash_table = self.hash_table.as_ref().unwrap();
+        loop {
+            if let Some(tuple) = self.pending.next() {
+                return Ok(Some(tuple));
+            }
+            let Some(left) = self.left_executor.next(bufmgr)? else {
+                return Ok(None);
+            };
+            let key = eval_join_key(self.left_keys, &left)?;
+            match hash_table.get(&key) {
+                Some(rights) => {
+                    let joined: Vec<_> = rights
+                        .iter()
+                        .map(|right| join_tuples(&left, right))
+                        .collect();
+                    self.pending = joined.into_iter();
+                }
+                None => {
+                    if let Some(tuple) = self.join_type.unmatched(left) {
+                        return Ok(Some(tuple));
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub struct ExecMergeJoin<'a> {
+    left_executor: BoxExecutor<'a>,
+    right_executor: BoxExecutor<'a>,
+    left_keys: &'a [Expr],
+    right_keys: &'a [Expr],
+    join_type: JoinType,
+    /// The next right tuple not in `right_group` with its key.
+    right_lookahead: Option<(Tuple, Tuple)>,
+    right_started: bool,
+    /// The key of the last group of right tuples read and the tuples of the group.
+    right_group: Option<(Tuple, Vec<Tuple>)>,
+    /// The current left tuple and the index of the next right tuple of the group to join.
+    current: Option<(Tuple, usize)>,
+}
+
+impl<'a> ExecMergeJoin<'a> {
+    pub fn new(
+        left_executor: BoxExecutor<'a>,
+        right_executor: BoxExecutor<'a>,
+        left_keys: &'a [Expr],
+        right_keys: &'a [Expr],
+        join_type: JoinType,
+    ) -> Self {
+        Self {
+            left_executor,
+            right_executor,
+            left_keys,
+            right_keys,
+            join_type,
+            right_lookahead: None,
+            right_started: false,
+            right_group: None,
+            current: None,
+        }
+    }
+
+    fn next_right(
+        &mut self,
+        bufmgr: &mut BufferPoolManager,
+    ) -> Result<Option<(Tuple, Tuple)>, DbError> {
+        if !self.right_started {
+            self.right_started = true;
+        } else if self.right_lookahead.is_none() {
+            return Ok(None);
+        }
+        let next = match self.right_executor.next(bufmgr)? {
+            Some(right) => Some((eval_join_key(self.right_keys, &right)?, right)),
+            None => None,
+        };
+        Ok(std::mem::replace(&mut self.right_lookahead, next))
+    }
+
+    /// Reads the right input up to the group of tuples whose key is `key`, and returns whether
+    /// the group is not empty.
+    fn seek_right(&mut self, bufmgr: &mut BufferPoolManager, key: &Tuple) -> Result<bool, DbError> {
+        if let Some((group_key, group)) = &self.right_group {
+            if group_key == key {
+                return Ok(!group.is_empty());
+            }
+        }
+        if !self.right_started {
+            self.next_right(bufmgr)?;
+        }
+        while matches!(&self.right_lookahead, Some((right_key, _)) if right_key < key) {
+            self.next_right(bufmgr)?;
+        }
+        let mut group = vec![];
+        while matches!(&self.right_lookahead, Some((right_key, _)) if right_key == key) {
+            group.push(self.next_right(bufmgr)?.unwrap().1);
+        }
+        let found = !group.is_empty();
+        self.right_group = Some((key.clone(), group));
+        Ok(found)
+    }
+}
+
+impl<'a> Executor for ExecMergeJoin<'a> {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
+        loop {
+            if let Some((left, right_idx)) = &mut self.current {
+                let (_, group) = self.right_group.as_ref().unwrap();
+                if let Some(right) = group.get(*right_idx) {
+                    *right_idx += 1;
+                    return Ok(Some(join_tuples(left, right)));
+                }
+                self.current = None;
+            }
+            let Some(left) = self.left_executor.next(bufmgr)? else {
+                return Ok(None);
+            };
+            let key = eval_join_key(self.left_keys, &left)?;
+            if self.seek_right(bufmgr, &key)? {
+                self.current = Some((left, 0));
+            } else if let Some(tuple) = self.join_type.unmatched(left) {
+                return Ok(Some(tuple));
+            }
+        }
+    }
+}
+
+pub struct ExecIndexNestedLoopJoin<'a> {
+    left_executor: BoxExecutor<'a>,
+    table_btree: BTree,
+    num_key_elems: usize,
+    index_btree: Option<BTree>,
+    left_keys: &'a [Expr],
+    join_type: JoinType,
+}
+
+impl<'a> ExecIndexNestedLoopJoin<'a> {
+    pub fn new(
+        left_executor: BoxExecutor<'a>,
+        table_btree: BTree,
+        num_key_elems: usize,
+        index_btree: Option<BTree>,
+        left_keys: &'a [Expr],
+        join_type: JoinType,
+    ) -> Self {
+        Self {
+            left_executor,
+            table_btree,
+            num_key_elems,
+            index_btree,
+            left_keys,
+            join_type,
+        }
+    }
+
+    /// Returns the row of the table whose primary key, or secondary key if probing an index,
+    /// is `key`.
+    fn probe(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        key: Vec<u8>,
+    ) -> Result<Option<Tuple>, DbError> {
+        let pkey = match &self.index_btree {
+            Some(index_btree) => {
+                let mut index_iter = index_btree.search(bufmgr, SearchMode::Key(key.clone()))?;
+                match index_iter.next(bufmgr)? {
+                    Some((skey, value)) if skey == key => index_pkey(&value, self.num_key_elems)?,
+                    _ => return Ok(None),
+                }
+            }
+            None => key,
+        };
+        let mut table_iter = self
+            .table_btree
+            .search(bufmgr, SearchMode::Key(pkey.clone()))?;
+        match table_iter.next(bufmgr)? {
+            Some((pkey_bytes, tuple_bytes)) if pkey_bytes == pkey => {
+                let mut record = vec![];
+                decode(&pkey_bytes, &mut record);
+                decode(&tuple_bytes, &mut record);
+                Ok(Some(record))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+impl<'a> Executor for ExecIndexNestedLoopJoin<'a> {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
+        while let Some(left) = self.left_executor.next(bufmgr)? {
+            let key = encode_join_key(self.left_keys, &left)?;
+            match self.probe(bufmgr, key)? {
+                Some(right) => return Ok(Some(join_tuples(&left, &right))),
+                None => {
+                    if let Some(tuple) = self.join_type.unmatched(left) {
+                        return Ok(Some(tuple));
+                    }
+                }
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Returns literal rows, like those of an `INSERT ... VALUES` statement.
+pub struct ExecValues<'a> {
+    rows: std::slice::Iter<'a, Tuple>,
+}
+
+impl<'a> ExecValues<'a> {
+    pub fn new(rows: &'a [Tuple]) -> Self {
+        Self { rows: rows.iter() }
+    }
+}
+
+impl<'a> Executor for ExecValues<'a> {
+    fn next(&mut self, _: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
+        Ok(self.rows.next().cloned())
+    }
+}
+
+/// Inserts the tuples of the inner executor into a table, then returns a single tuple with the
+/// number of rows inserted.
+///
+/// Like the other data changing executors, it reads all of its input before changing the table,
+/// so that the changes are not seen by the scans producing the input.
+pub struct ExecInsert<'a> {
+    table: &'a Table,
+    inner_executor: BoxExecutor<'a>,
+    done: bool,
+}
+
+impl<'a> ExecInsert<'a> {
+    pub fn new(table: &'a Table, inner_executor: BoxExecutor<'a>) -> Self {
+        Self {
+            table,
+            inner_executor,
+            done: false,
+        }
+    }
+}
+
+impl<'a> Executor for ExecInsert<'a> {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
+        if std::mem::replace(&mut self.done, true) {
+            return Ok(None);
+        }
+        let rows = read_all(&mut self.inner_executor, bufmgr)?;
+        for row in &rows {
+            let record: Vec<&[u8]> = row.iter().map(Vec::as_slice).collect();
+            self.table.insert(bufmgr, &record)?;
+        }
+        Ok(Some(row_count(rows.len())))
+    }
+}
+
+/// Replaces each row of a table returned by the inner executor with the values of `exprs`
+/// over it, then returns a single tuple with the number of rows updated.
+pub struct ExecUpdate<'a> {
+    table: &'a Table,
+    inner_executor: BoxExecutor<'a>,
+    exprs: &'a [Expr],
+    done: bool,
+}
+
+impl<'a> ExecUpdate<'a> {
+    pub fn new(table: &'a Table, inner_executor: BoxExecutor<'a>, exprs: &'a [Expr]) -> Self {
+        Self {
+            table,
+            inner_executor,
+            exprs,
+            done: false,
+        }
+    }
+}
+
+impl<'a> Executor for ExecUpdate<'a> {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
+        if std::mem::replace(&mut self.done, true) {
+            return Ok(None);
+        }
+        let rows = read_all(&mut self.inner_executor, bufmgr)?;
+        for row in &rows {
+            let new_row = self
+                .exprs
+                .iter()
+                .map(|expr| expr.eval(row))
+                .collect::<Result<Tuple, _>>()?;
+            let new_record: Vec<&[u8]> = new_row.iter().map(Vec::as_slice).collect();
+            self.table.update(bufmgr, row, &new_record)?;
+        }
+        Ok(Some(row_count(rows.len())))
+    }
+}
+
+/// Deletes each row of a table returned by the inner executor, then returns a single tuple with
+/// the number of rows deleted.
+pub struct ExecDelete<'a> {
+    table: &'a Table,
+    inner_executor: BoxExecutor<'a>,
+    done: bool,
+}
+
+impl<'a> ExecDelete<'a> {
+    pub fn new(table: &'a Table, inner_executor: BoxExecutor<'a>) -> Self {
+        Self {
+            table,
+            inner_executor,
+            done: false,
+        }
+    }
+}
+
+impl<'a> Executor for ExecDelete<'a> {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
+        if std::mem::replace(&mut self.done, true) {
+            return Ok(None);
+        }
+        let rows = read_all(&mut self.inner_executor, bufmgr)?;
+        for row in &rows {
+            self.table.delete(bufmgr, row)?;
+        }
+        Ok(Some(row_count(rows.len())))
+    }
+}
+
+fn read_all(
+    executor: &mut BoxExecutor,
+    bufmgr: &mut BufferPoolManager,
+) -> Result<Vec<Tuple>, DbError> {
+    let mut tuples = vec![];
+    while let Some(tuple) = executor.next(bufmgr)? {
+        tuples.push(tuple);
+    }
+    Ok(tuples)
+}
+
+/// Returns the tuple reporting the number of rows changed, as an integer like `COUNT(*)`.
+fn row_count(count: usize) -> Tuple {
+    vec![tuple::encode_int(count as i64).to_vec()]
+}
+
+/// Passes the tuples of the inner executor through, counting them and the pages fetched.
+pub struct ExecAnalyze<'a> {
+    inner_executor: BoxExecutor<'a>,
+    actual: &'a Cell<ActualStats>,
+}
+
+impl<'a> ExecAnalyze<'a> {
+    pub fn new(inner_executor: BoxExecutor<'a>, actual: &'a Cell<ActualStats>) -> Self {
+        Self {
+            inner_executor,
+            actual,
+        }
+    }
+}
+
+impl<'a> Executor for ExecAnalyze<'a> {
+    fn next(&mut self, bufmgr: &mut BufferPoolManager) -> Result<Option<Tuple>, DbError> {
+        let num_fetches = bufmgr.num_fetches();
+        let tuple = self.inner_executor.next(bufmgr)?;
+        let mut stats = self.actual.get();
+        stats.pages += bufmgr.num_fetches() - num_fetches;
+        stats.rows += tuple.is_some() as u64;
+        self.actual.set(stats);
+        Ok(tuple)
+    }
+}
+
+/// Returns the encoded primary key at the start of an index value.
+fn index_pkey(value: &[u8], num_key_elems: usize) -> Result<Vec<u8>, DbError> {
+    let mut elems = vec![];
+    decode(value, &mut elems);
+    if elems.len() == num_key_elems {
+        return Ok(value.to_vec());
+    }
+    let Some(pkey_elems) = elems.get(..num_key_elems) else {
+        return Err(short_index_entry());
+    };
+    let mut pkey = vec![];
+    tuple::encode(pkey_elems.iter(), &mut pkey);
+    Ok(pkey)
+}
+
+fn short_index_entry() -> DbError {
+    DbError::Corrupted("index entry holds fewer elements than the primary key".to_string())
+}
+
+/// Reads the row with the primary key an index entry points to, failing if it is missing.
+fn fetch_row(
+    table_btree: &BTree,
+    bufmgr: &mut BufferPoolManager,
+    pkey_bytes: Vec<u8>,
+    columns: Option<&[usize]>,
+) -> Result<Tuple, DbError> {
+    let mut table_iter = table_btree.search(bufmgr, SearchMode::Key(pkey_bytes.clone()))?;
+    // The search stops at the next larger key if the row is gone.
+    let tuple_bytes = match table_iter.next(bufmgr)? {
+        Some((found, tuple_bytes)) if found == pkey_bytes => tuple_bytes,
+        _ => {
+            return Err(DbError::Corrupted(
+                "index entry points to a missing row".to_string(),
+            ))
+        }
+    };
+    let mut record = vec![];
+    decode(pkey_bytes.as_slice(), &mut record);
+    decode_value(&tuple_bytes, &mut record, columns);
+    Ok(record)
+}
+
+/// Decodes the value part of a record after its key columns in `record`.
+fn decode_value(bytes: &[u8], record: &mut Tuple, columns: Option<&[usize]>) {
+    match columns {
+        Some(columns) => tuple::decode_columns(bytes, record, columns),
+        None => decode(bytes, record),
+    }
+}
@@ -0,0 +1,143 @@
+use std::cell::Cell;
+use std::fmt::{Display, Write};
+
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+
+use super::executor::ExecAnalyze;
+use super::expr::Literal;
+use super::planner::PlanNode;
+use super::BoxExecutor;
+
+use crate::buffer::BufferPoolManager;
+use crate::error::DbError;
+
+/// A node of a plan tree rendered by [`PlanNode::explain`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Explain {
+    pub node: String,
+    /// The table or index targeted by the node, its bounds, predicates and other arguments.
+    #[serde(serialize_with = "serialize_properties")]
+    pub properties: Vec<(String, String)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimate: Option<Estimate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual: Option<ActualStats>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<Explain>,
+}
+
+/// The cost and number of output tuples estimated by the optimizer.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Estimate {
+    pub cost: f64,
+    pub rows: f64,
+}
+
+/// What happened when the node was run, counted for `EXPLAIN ANALYZE`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ActualStats {
+    /// The number of times the node was started, such as once per left tuple for the right
+    /// input of a nested loop join.
+    pub loops: u64,
+    pub rows: u64,
+    /// The pages fetched from the buffer pool by the node and its inputs.
+    pub pages: u64,
+}
+
+impl Explain {
+    pub fn new(node: &str) -> Self {
+        Self {
+            node: node.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn property(mut self, name: &str, value: impl Display) -> Self {
+        self.properties.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn child(mut self, child: Explain) -> Self {
+        self.children.push(child);
+        self
+    }
+
+    /// Renders the tree with a line per node, indenting the inputs of each node under it.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        self.write_text(&mut text, 0);
+        text
+    }
+
+    fn write_text(&self, text: &mut String, depth: usize) {
+        let _ = write!(text, "{:indent$}{}", "", self.node, indent = depth * 2);
+        for (name, value) in &self.properties {
+            let _ = write!(text, " {name}={value}");
+        }
+        if let Some(Estimate { cost, rows }) = self.estimate {
+            let _ = write!(text, " (cost={cost:.2} rows={rows:.0})");
+        }
+        if let Some(ActualStats { loops, rows, pages }) = self.actual {
+            let _ = write!(text, " (actual loops={loops} rows={rows} pages={pages})");
+        }
+        text.push('\n');
+        for child in &self.children {
+            child.write_text(text, depth + 1);
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+}
+
+fn serialize_properties<S: Serializer>(
+    properties: &[(String, String)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(properties.len()))?;
+    for (name, value) in properties {
+        map.serialize_entry(name, value)?;
+    }
+    map.end()
+}
+
+/// Formats values as a parenthesized list of literals.
+pub fn fmt_values(values: &[impl AsRef<[u8]>]) -> String {
+    fmt_list(values.iter().map(|value| Literal(value.as_ref())))
+}
+
+/// Formats items as a parenthesized, comma-separated list.
+pub fn fmt_list(items: impl IntoIterator<Item = impl Display>) -> String {
+    let items: Vec<String> = items.into_iter().map(|item| item.to_string()).collect();
+    format!("({})", items.join(", "))
+}
+
+/// Starts a node with `start` and wraps its executor to count its statistics in `actual`.
+pub(crate) fn start_analyzed<'a>(
+    actual: &'a Cell<ActualStats>,
+    bufmgr: &mut BufferPoolManager,
+    start: impl FnOnce(&mut BufferPoolManager) -> Result<BoxExecutor<'a>, DbError>,
+) -> Result<BoxExecutor<'a>, DbError> {
+    let num_fetches = bufmgr.num_fetches();
+    let inner_executor = start(bufmgr)?;
+    let mut stats = actual.get();
+    stats.loops += 1;
+    stats.pages += bufmgr.num_fetches() - num_fetches;
+    actual.set(stats);
+    Ok(Box::new(ExecAnalyze::new(inner_executor, actual)))
+}
+
+/// Runs `plan` to the end, discarding its output, and explains it with the statistics counted
+/// by the [`Analyze`](super::planner::Analyze) nodes and analyzed
+/// [`PhysicalPlan`](super::physical::PhysicalPlan)s in it.
+pub fn explain_analyze(
+    plan: &dyn PlanNode,
+    bufmgr: &mut BufferPoolManager,
+) -> Result<Explain, DbError> {
+    let mut exec = plan.start(bufmgr)?;
+    while exec.next(bufmgr)?.is_some() {}
+    drop(exec);
+    Ok(plan.explain())
+}
@@ -0,0 +1,289 @@
+use super::TupleSlice;
+use crate::tuple;
+use std::fmt::{self, Display, Formatter};
+use thiserror::Error;
+
+pub const TRUE: &[u8] = &[1];
+pub const FALSE: &[u8] = &[0];
+
+#[derive(Debug, Error)]
+pub enum EvalError {
+    #[error("column {0} is out of range")]
+    ColumnOutOfRange(usize),
+    #[error("operand is not an integer")]
+    NotInteger,
+    #[error("division by zero")]
+    DivisionByZero,
+    #[error("integer overflow")]
+    Overflow,
+    #[error("parameter ${} has no value", .0 + 1)]
+    UnboundParam(usize),
+}
+
+/// An expression computing a column from the columns of a tuple.
+///
+/// Values are plain bytes. Comparisons compare the bytes, boolean results are [`TRUE`] and
+/// [`FALSE`], and arithmetic works on integers encoded with [`tuple::encode_int`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Column(usize),
+    Literal(Vec<u8>),
+    /// A parameter of a prepared statement, numbered from 0, replaced with its value by
+    /// [`bind_params`](Self::bind_params) before the expression is evaluated.
+    Param(usize),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+    Concat,
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+impl Expr {
+    pub fn int(n: i64) -> Self {
+        Expr::Literal(tuple::encode_int(n).to_vec())
+    }
+
+    pub fn binary(op: BinaryOp, lhs: Expr, rhs: Expr) -> Self {
+        Expr::Binary(op, Box::new(lhs), Box::new(rhs))
+    }
+
+    pub fn eval(&self, tuple: TupleSlice) -> Result<Vec<u8>, EvalError> {
+        match self {
+            Expr::Column(index) => tuple
+                .get(*index)
+                .cloned()
+                .ok_or(EvalError::ColumnOutOfRange(*index)),
+            Expr::Literal(bytes) => Ok(bytes.clone()),
+            Expr::Param(index) => Err(EvalError::UnboundParam(*index)),
+            Expr::Not(expr) => Ok(bool_bytes(!expr.eval_bool(tuple)?)),
+            Expr::Binary(BinaryOp::And, lhs, rhs) => {
+                Ok(bool_bytes(lhs.eval_bool(tuple)? && rhs.eval_bool(tuple)?))
+            }
+            Expr::Binary(BinaryOp::Or, lhs, rhs) => {
+                Ok(bool_bytes(lhs.eval_bool(tuple)? || rhs.eval_bool(tuple)?))
+            }
+            Expr::Binary(op, lhs, rhs) => op.apply(lhs.eval(tuple)?, rhs.eval(tuple)?),
+        }
+    }
+
+    pub fn eval_bool(&self, tuple: TupleSlice) -> Result<bool, EvalError> {
+        Ok(self.eval(tuple)? == TRUE)
+    }
+
+    /// Appends the indexes of the columns the expression reads to `columns`.
+    pub fn columns(&self, columns: &mut Vec<usize>) {
+        match self {
+            Expr::Column(index) => columns.push(*index),
+            Expr::Literal(_) | Expr::Param(_) => {}
+            Expr::Not(expr) => expr.columns(columns),
+            Expr::Binary(_, lhs, rhs) => {
+                lhs.columns(columns);
+                rhs.columns(columns);
+            }
+        }
+    }
+
+    /// Returns the expression with each column index replaced by `f(index)`.
+    pub fn map_columns(&self, f: &impl Fn(usize) -> usize) -> Expr {
+        match self {
+            Expr::Column(index) => Expr::Column(f(*index)),
+            Expr::Literal(_) | Expr::Param(_) => self.clone(),
+            Expr::Not(expr) => Expr::Not(Box::new(expr.map_columns(f))),
+            Expr::Binary(op, lhs, rhs) => Expr::binary(*op, lhs.map_columns(f), rhs.map_columns(f)),
+        }
+    }
+
+    /// Returns the expression with each parameter replaced with its value in `params`.
+    pub fn bind_params(&self, params: &[Vec<u8>]) -> Result<Expr, EvalError> {
+        match self {
+            Expr::Column(_) | Expr::Literal(_) => Ok(self.clone()),
+            Expr::Param(index) => match params.get(*index) {
+                Some(value) => Ok(Expr::Literal(value.clone())),
+                None => Err(EvalError::UnboundParam(*index)),
+            },
+            Expr::Not(expr) => Ok(Expr::Not(Box::new(expr.bind_params(params)?))),
+            Expr::Binary(op, lhs, rhs) => Ok(Expr::binary(
+                *op,
+                lhs.bind_params(params)?,
+                rhs.bind_params(params)?,
+            )),
+        }
+    }
+
+    /// Splits the expression into the operands of its top-level `AND`s.
+    pub fn conjuncts(&self) -> Vec<Expr> {
+        match self {
+            Expr::Binary(BinaryOp::And, lhs, rhs) => {
+                let mut conjuncts = lhs.conjuncts();
+                conjuncts.extend(rhs.conjuncts());
+                conjuncts
+            }
+            expr => vec![expr.clone()],
+        }
+    }
+
+    /// Combines `conjuncts` with `AND`, or returns `None` if there are none.
+    pub fn and_all(conjuncts: impl IntoIterator<Item = Expr>) -> Option<Expr> {
+        conjuncts
+            .into_iter()
+            .reduce(|lhs, rhs| Expr::binary(BinaryOp::And, lhs, rhs))
+    }
+}
+
+impl BinaryOp {
+    fn apply(self, lhs: Vec<u8>, rhs: Vec<u8>) -> Result<Vec<u8>, EvalError> {
+        let int = |bytes: &[u8]| tuple::decode_int(bytes).ok_or(EvalError::NotInteger);
+        let arith = |f: fn(i64, i64) -> Option<i64>| {
+            let n = f(int(&lhs)?, int(&rhs)?).ok_or(EvalError::Overflow)?;
+            Ok(tuple::encode_int(n).to_vec())
+        };
+        match self {
+            BinaryOp::Eq => Ok(bool_bytes(lhs == rhs)),
+            BinaryOp::Ne => Ok(bool_bytes(lhs != rhs)),
+            BinaryOp::Lt => Ok(bool_bytes(lhs < rhs)),
+            BinaryOp::Le => Ok(bool_bytes(lhs <= rhs)),
+            BinaryOp::Gt => Ok(bool_bytes(lhs > rhs)),
+            BinaryOp::Ge => Ok(bool_bytes(lhs >= rhs)),
+            BinaryOp::And => Ok(bool_bytes(lhs == TRUE && rhs == TRUE)),
+            BinaryOp::Or => Ok(bool_bytes(lhs == TRUE || rhs == TRUE)),
+            BinaryOp::Concat => Ok([lhs, rhs].concat()),
+            BinaryOp::Add => arith(i64::checked_add),
+            BinaryOp::Sub => arith(i64::checked_sub),
+            BinaryOp::Mul => arith(i64::checked_mul),
+            BinaryOp::Div => {
+                if int(&rhs)? == 0 {
+                    return Err(EvalError::DivisionByZero);
+                }
+                arith(i64::checked_div)
+            }
+        }
+    }
+}
+
+impl Display for Expr {
+    /// Formats columns as `#index`, and literals as quoted text if they are UTF-8 and as hex
+    /// otherwise.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Column(index) => write!(f, "#{index}"),
+            Expr::Literal(bytes) => write!(f, "{}", Literal(bytes)),
+            Expr::Param(index) => write!(f, "${}", index + 1),
+            Expr::Not(expr) => write!(f, "NOT {expr}"),
+            Expr::Binary(op, lhs, rhs) => write!(f, "({lhs} {op} {rhs})"),
+        }
+    }
+}
+
+impl Display for BinaryOp {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            BinaryOp::Eq => "=",
+            BinaryOp::Ne => "<>",
+            BinaryOp::Lt => "<",
+            BinaryOp::Le => "<=",
+            BinaryOp::Gt => ">",
+            BinaryOp::Ge => ">=",
+            BinaryOp::And => "AND",
+            BinaryOp::Or => "OR",
+            BinaryOp::Concat => "||",
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+        };
+        f.write_str(symbol)
+    }
+}
+
+/// Formats a value the way [`Expr`] formats literals.
+pub struct Literal<'a>(pub &'a [u8]);
+
+impl<'a> Display for Literal<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match std::str::from_utf8(self.0) {
+            Ok(s) if !s.chars().any(char::is_control) => write!(f, "'{}'", s.replace('\'', "''")),
+            _ => {
+                f.write_str("0x")?;
+                self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+            }
+        }
+    }
+}
+
+fn bool_bytes(b: bool) -> Vec<u8> {
+    if b { TRUE } else { FALSE }.to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_eval() {
+        let tuple = vec![
+            b"alice".to_vec(),
+            tuple::encode_int(30).to_vec(),
+            b"smith".to_vec(),
+        ];
+        let concat = Expr::binary(
+            BinaryOp::Concat,
+            Expr::Column(0),
+            Expr::binary(
+                BinaryOp::Concat,
+                Expr::Literal(b" ".to_vec()),
+                Expr::Column(2),
+            ),
+        );
+        assert_eq!(b"alice smith".to_vec(), concat.eval(&tuple).unwrap());
+
+        let age = Expr::binary(BinaryOp::Mul, Expr::Column(1), Expr::int(-2));
+        assert_eq!(tuple::encode_int(-60).to_vec(), age.eval(&tuple).unwrap());
+
+        let cond = Expr::binary(
+            BinaryOp::And,
+            Expr::binary(BinaryOp::Ge, Expr::Column(1), Expr::int(18)),
+            Expr::Not(Box::new(Expr::binary(
+                BinaryOp::Eq,
+                Expr::Column(2),
+                Expr::Literal(b"lee".to_vec()),
+            ))),
+        );
+        assert!(cond.eval_bool(&tuple).unwrap());
+
+        let div = Expr::binary(BinaryOp::Div, Expr::Column(1), Expr::int(0));
+        assert!(matches!(div.eval(&tuple), Err(EvalError::DivisionByZero)));
+        assert!(matches!(
+            Expr::Column(3).eval(&tuple),
+            Err(EvalError::ColumnOutOfRange(3))
+        ));
+
+        assert_eq!(
+            "((#1 >= 0x8000000000000012) AND NOT (#2 = 'lee'))",
+            cond.to_string()
+        );
+
+        let param = Expr::binary(BinaryOp::Eq, Expr::Column(2), Expr::Param(0));
+        assert_eq!("(#2 = $1)", param.to_string());
+        assert!(matches!(
+            param.eval(&tuple),
+            Err(EvalError::UnboundParam(0))
+        ));
+        let bound = param.bind_params(&[b"smith".to_vec()]).unwrap();
+        assert!(bound.eval_bool(&tuple).unwrap());
+        assert!(param.bind_params(&[]).is_err());
+    }
+}
@@ -0,0 +1,56 @@
+use std::fmt::{self, Display, Formatter};
+
+use super::expr::{EvalError, Expr};
+use super::{Tuple, TupleSlice};
+
+use crate::tuple;
+
+/// Whether a join drops or keeps the left tuples that match no right tuple.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinType {
+    Inner,
+    /// Unmatched left tuples are returned with `right_num_columns` empty values appended.
+    LeftOuter {
+        right_num_columns: usize,
+    },
+}
+
+impl Display for JoinType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            JoinType::Inner => f.write_str("inner"),
+            JoinType::LeftOuter { .. } => f.write_str("left_outer"),
+        }
+    }
+}
+
+impl JoinType {
+    /// Returns the output tuple for a left tuple without a match, if any.
+    pub fn unmatched(self, left: Tuple) -> Option<Tuple> {
+        match self {
+            JoinType::Inner => None,
+            JoinType::LeftOuter { right_num_columns } => {
+                let mut tuple = left;
+                tuple.resize(tuple.len() + right_num_columns, vec![]);
+                Some(tuple)
+            }
+        }
+    }
+}
+
+/// Returns the columns of `left` followed by the columns of `right`.
+pub fn join_tuples(left: TupleSlice, right: TupleSlice) -> Tuple {
+    left.iter().chain(right).cloned().collect()
+}
+
+/// Evaluates the join keys for `tuple`.
+pub fn eval_join_key(keys: &[Expr], tuple: TupleSlice) -> Result<Tuple, EvalError> {
+    keys.iter().map(|key| key.eval(tuple)).collect()
+}
+
+/// Evaluates the join keys for `tuple` and encodes them like a primary or secondary key.
+pub fn encode_join_key(keys: &[Expr], tuple: TupleSlice) -> Result<Vec<u8>, EvalError> {
+    let mut bytes = vec![];
+    tuple::encode(eval_join_key(keys, tuple)?.iter(), &mut bytes);
+    Ok(bytes)
+}
@@ -0,0 +1,131 @@
+use super::aggregate::AggExpr;
+use super::expr::Expr;
+use super::sort::SortKey;
+
+use crate::catalog::Catalog;
+use crate::error::DbError;
+
+/// A query as a tree of relational operators over tables named in the [`Catalog`], turned into
+/// an executable plan by the [`Optimizer`](super::optimizer::Optimizer).
+///
+/// Columns are referred to by index, as in [`Expr`]. The columns of a join are those of the left
+/// input followed by those of the right input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogicalPlan {
+    Scan {
+        table: String,
+    },
+    Filter {
+        input: Box<LogicalPlan>,
+        cond: Expr,
+    },
+    Project {
+        input: Box<LogicalPlan>,
+        exprs: Vec<Expr>,
+    },
+    Join {
+        left: Box<LogicalPlan>,
+        right: Box<LogicalPlan>,
+        cond: Expr,
+        left_outer: bool,
+    },
+    Aggregate {
+        input: Box<LogicalPlan>,
+        group_by: Vec<Expr>,
+        aggregates: Vec<AggExpr>,
+    },
+    Sort {
+        input: Box<LogicalPlan>,
+        keys: Vec<SortKey>,
+    },
+    Limit {
+        input: Box<LogicalPlan>,
+        offset: usize,
+        count: usize,
+    },
+}
+
+impl LogicalPlan {
+    pub fn scan(table: &str) -> Self {
+        LogicalPlan::Scan {
+            table: table.to_string(),
+        }
+    }
+
+    pub fn filter(self, cond: Expr) -> Self {
+        LogicalPlan::Filter {
+            input: Box::new(self),
+            cond,
+        }
+    }
+
+    pub fn project(self, exprs: Vec<Expr>) -> Self {
+        LogicalPlan::Project {
+            input: Box::new(self),
+            exprs,
+        }
+    }
+
+    pub fn join(self, right: LogicalPlan, cond: Expr) -> Self {
+        LogicalPlan::Join {
+            left: Box::new(self),
+            right: Box::new(right),
+            cond,
+            left_outer: false,
+        }
+    }
+
+    pub fn left_outer_join(self, right: LogicalPlan, cond: Expr) -> Self {
+        LogicalPlan::Join {
+            left: Box::new(self),
+            right: Box::new(right),
+            cond,
+            left_outer: true,
+        }
+    }
+
+    pub fn aggregate(self, group_by: Vec<Expr>, aggregates: Vec<AggExpr>) -> Self {
+        LogicalPlan::Aggregate {
+            input: Box::new(self),
+            group_by,
+            aggregates,
+        }
+    }
+
+    pub fn sort(self, keys: Vec<SortKey>) -> Self {
+        LogicalPlan::Sort {
+            input: Box::new(self),
+            keys,
+        }
+    }
+
+    pub fn limit(self, offset: usize, count: usize) -> Self {
+        LogicalPlan::Limit {
+            input: Box::new(self),
+            offset,
+            count,
+        }
+    }
+
+    /// Returns the number of columns of the output tuples.
+    pub fn num_columns(&self, catalog: &Catalog) -> Result<usize, DbError> {
+        match self {
+            LogicalPlan::Scan { table } => match catalog.table(table) {
+                Some(info) => Ok(info.columns.len()),
+                None => Err(DbError::NotFound(format!("table {table}"))),
+            },
+            LogicalPlan::Project { exprs, .. } => Ok(exprs.len()),
+            LogicalPlan::Join { left, right, .. } => {
+                Ok(left.num_columns(catalog)? + right.num_columns(catalog)?)
+            }
+            LogicalPlan::Aggregate {
+                group_by,
+                aggregates,
+                ..
+            } => Ok(group_by.len() + aggregates.len()),
+            LogicalPlan::Filter { input, .. }
+            | LogicalPlan::Sort { input, .. }
+            | LogicalPlan::Limit { input, .. } => input.num_columns(catalog),
+        }
+    }
+}
@@ -1,9 +1,18 @@
+pub mod aggregate;
 pub mod executor;
+pub mod explain;
+pub mod expr;
+pub mod join;
+pub mod logical;
+pub mod optimizer;
+pub mod physical;
 pub mod planner;
+pub mod sort;
 
 use crate::btree::SearchMode;
 use crate::tuple;
 use executor::Executor;
+use expr::EvalError;
 
 pub type Tuple = Vec<Vec<u8>>;
 
@@ -13,6 +22,9 @@ pub type BoxExecutor<'a> = Box<dyn Executor + 'a>;
 
 pub type Condition<'a> = &'a dyn Fn(TupleSlice) -> bool;
 
+/// A condition whose evaluation may fail, like the condition of a filter.
+pub type Predicate<'a> = &'a dyn Fn(TupleSlice) -> Result<bool, EvalError>;
+
 pub enum TupleSearchMode<'a> {
     Start,
     Key(&'a [&'a [u8]]),
@@ -0,0 +1,1383 @@
+use super::aggregate;
+use super::expr::{BinaryOp, EvalError, Expr, TRUE};
+use super::join::JoinType;
+use super::logical::LogicalPlan;
+use super::physical::{compile, KeyRange, PhysicalNode, PhysicalPlan};
+
+use crate::catalog::{Catalog, TableInfo};
+use crate::disk::PageId;
+use crate::error::DbError;
+use crate::table::IndexKind;
+
+/// The cost of processing a tuple in memory, relative to reading a page.
+const CPU_TUPLE_COST: f64 = 0.01;
+/// The selectivities assumed for conditions the statistics say nothing about.
+const EQ_SELECTIVITY: f64 = 0.1;
+const RANGE_SELECTIVITY: f64 = 0.3;
+const DEFAULT_SELECTIVITY: f64 = 0.5;
+/// The pages read to find a key in a hash index: the meta page, a directory page and a bucket.
+const HASH_LOOKUP_COST: f64 = 3.0;
+/// Inner joins of more tables than this are executed in the order written instead of searching
+/// every order.
+const MAX_REORDERED_JOINS: usize = 10;
+/// Sets of joined relations are bit masks, so a query cannot join more tables than this.
+const MAX_JOINED_RELATIONS: usize = usize::BITS as usize;
+
+/// Turns a [`LogicalPlan`] into the [`PhysicalPlan`] with the lowest estimated cost.
+///
+/// Conditions are pushed down to the scans, where those on leading key columns become the range
+/// of a primary key scan or a unique index scan. Trees of inner joins are reordered, and each
+/// join is done by index lookups, a hash table or a nested loop.
+pub struct Optimizer<'a> {
+    catalog: &'a Catalog,
+    /// The memory budget of each sort in bytes.
+    pub sort_memory_budget: usize,
+}
+
+/// An input of a join: a table or a subquery.
+struct Relation<'a> {
+    source: &'a LogicalPlan,
+    /// The table if the relation is a plain scan, so that it can be probed by index lookups.
+    table: Option<&'a TableInfo>,
+    /// The conditions on the relation alone, over its own columns.
+    conjuncts: Vec<Expr>,
+    /// The position of the first column of the relation in the joined tuples as written.
+    offset: usize,
+    num_columns: usize,
+    /// The plan of the relation alone, taken when it is joined.
+    plan: Option<PhysicalPlan>,
+    cost: f64,
+    rows: f64,
+}
+
+impl<'a> Relation<'a> {
+    fn contains(&self, column: usize) -> bool {
+        (self.offset..self.offset + self.num_columns).contains(&column)
+    }
+}
+
+/// How a relation is joined to the tuples built so far. Expressions are over the columns of the
+/// joined tuples as written.
+struct JoinStep {
+    method: JoinMethod,
+    /// The conditions checked by a nested loop join, or after the join by the other methods.
+    residual: Vec<Expr>,
+    cost: f64,
+    rows: f64,
+}
+
+enum JoinMethod {
+    NestedLoop,
+    Hash {
+        left_keys: Vec<Expr>,
+        right_keys: Vec<Expr>,
+    },
+    IndexNestedLoop {
+        index_meta_page_id: Option<PageId>,
+        left_keys: Vec<Expr>,
+    },
+}
+
+/// The best join order found for a set of relations.
+#[derive(Clone)]
+struct JoinOrder {
+    order: Vec<usize>,
+    cost: f64,
+    rows: f64,
+}
+
+impl<'a> Optimizer<'a> {
+    pub fn new(catalog: &'a Catalog) -> Self {
+        Self {
+            catalog,
+            sort_memory_budget: 64 << 20,
+        }
+    }
+
+    pub fn optimize(&self, plan: &LogicalPlan) -> Result<PhysicalPlan, DbError> {
+        self.plan_filtered(plan, vec![], None)
+    }
+
+    fn table(&self, name: &str) -> Result<&'a TableInfo, DbError> {
+        self.catalog
+            .table(name)
+            .ok_or_else(|| DbError::NotFound(format!("table {name}")))
+    }
+
+    /// Plans `plan` followed by a filter on `conjuncts`, for a consumer that reads only
+    /// `columns` of the output, or all of them if `None`.
+    fn plan_filtered(
+        &self,
+        plan: &LogicalPlan,
+        mut conjuncts: Vec<Expr>,
+        columns: Option<&[usize]>,
+    ) -> Result<PhysicalPlan, DbError> {
+        match plan {
+            LogicalPlan::Filter { input, cond } => {
+                conjuncts.extend(cond.conjuncts());
+                self.plan_filtered(input, conjuncts, columns)
+            }
+            LogicalPlan::Scan { table } => {
+                Ok(self.plan_scan(self.table(table)?, conjuncts, columns))
+            }
+            LogicalPlan::Join {
+                left_outer: false, ..
+            } => self.plan_inner_joins(plan, conjuncts),
+            _ => {
+                let input = self.plan_node(plan)?;
+                let rows = conjuncts
+                    .iter()
+                    .fold(input.rows, |rows, conj| rows * selectivity(None, conj));
+                Ok(filter(input, conjuncts, rows))
+            }
+        }
+    }
+
+    fn plan_node(&self, plan: &LogicalPlan) -> Result<PhysicalPlan, DbError> {
+        let plan = match plan {
+            LogicalPlan::Scan { .. }
+            | LogicalPlan::Filter { .. }
+            | LogicalPlan::Join {
+                left_outer: false, ..
+            } => self.plan_filtered(plan, vec![], None)?,
+            LogicalPlan::Project { input, exprs } => {
+                let mut columns = vec![];
+                for expr in exprs {
+                    expr.columns(&mut columns);
+                }
+                let input = self.plan_filtered(input, vec![], Some(&columns))?;
+                PhysicalPlan {
+                    cost: input.cost + input.rows * CPU_TUPLE_COST,
+                    rows: input.rows,
+                    node: PhysicalNode::Project {
+                        input: Box::new(input),
+                        exprs: exprs.clone(),
+                    },
+                    actual: None,
+                }
+            }
+            LogicalPlan::Join {
+                left,
+                right,
+                cond,
+                left_outer: true,
+            } => self.plan_left_outer_join(left, right, cond)?,
+            LogicalPlan::Aggregate {
+                input,
+                group_by,
+                aggregates,
+            } => {
+                let columns = aggregate::columns(group_by, aggregates);
+                let input = self.plan_filtered(input, vec![], Some(&columns))?;
+                let rows = match group_by.is_empty() {
+                    true => 1.0,
+                    false => (input.rows * EQ_SELECTIVITY).max(1.0),
+                };
+                PhysicalPlan {
+                    cost: input.cost + input.rows * CPU_TUPLE_COST,
+                    rows,
+                    node: PhysicalNode::HashAggregate {
+                        input: Box::new(input),
+                        group_by: group_by.clone(),
+                        aggregates: aggregates.clone(),
+                    },
+                    actual: None,
+                }
+            }
+            LogicalPlan::Sort { input, keys } => {
+                let input = self.optimize(input)?;
+                PhysicalPlan {
+                    cost: input.cost + sort_cost(input.rows),
+                    rows: input.rows,
+                    node: PhysicalNode::Sort {
+                        input: Box::new(input),
+                        keys: keys.clone(),
+                        memory_budget: self.sort_memory_budget,
+                    },
+                    actual: None,
+                }
+            }
+            LogicalPlan::Limit {
+                input,
+                offset,
+                count,
+            } => {
+                let limit_rows = |rows: f64| (rows - *offset as f64).clamp(0.0, *count as f64);
+                match input.as_ref() {
+                    LogicalPlan::Sort { input, keys } => {
+                        let input = self.optimize(input)?;
+                        PhysicalPlan {
+                            cost: input.cost + input.rows * CPU_TUPLE_COST,
+                            rows: limit_rows(input.rows),
+                            node: PhysicalNode::TopN {
+                                input: Box::new(input),
+                                keys: keys.clone(),
+                                offset: *offset,
+                                count: *count,
+                            },
+                            actual: None,
+                        }
+                    }
+                    _ => {
+                        let input = self.optimize(input)?;
+                        PhysicalPlan {
+                            cost: input.cost,
+                            rows: limit_rows(input.rows),
+                            node: PhysicalNode::Limit {
+                                input: Box::new(input),
+                                offset: *offset,
+                                count: *count,
+                            },
+                            actual: None,
+                        }
+                    }
+                }
+            }
+        };
+        Ok(plan)
+    }
+
+    /// Chooses between a primary key scan and the unique index scans of the table. An index
+    /// holding all of `columns` and the columns of `conjuncts` is scanned without reading the
+    /// table.
+    fn plan_scan(
+        &self,
+        info: &TableInfo,
+        conjuncts: Vec<Expr>,
+        columns: Option<&[usize]>,
+    ) -> PhysicalPlan {
+        let stats = info.stats;
+        let height = stats.height as f64;
+        let rows = conjuncts.iter().fold(stats.num_rows as f64, |rows, conj| {
+            rows * selectivity(Some(info), conj)
+        });
+        let range_selectivity = |used: &[usize]| {
+            used.iter()
+                .map(|&idx| selectivity(Some(info), &conjuncts[idx]))
+                .product::<f64>()
+        };
+        let read_columns = columns.map(|columns| {
+            let mut columns = columns.to_vec();
+            for conj in &conjuncts {
+                conj.columns(&mut columns);
+            }
+            columns
+        });
+        let num_key_elems = info.table.num_key_elems;
+
+        let primary_key: Vec<usize> = (0..num_key_elems).collect();
+        let (range, used, enforced) = key_range(&primary_key, &conjuncts);
+        let mut best_cost = height + stats.num_leaf_pages as f64 * range_selectivity(&used);
+        let mut best = (None, range, enforced);
+        for unique_index in &info.table.unique_index {
+            let (range, used, enforced) = key_range(&unique_index.skey, &conjuncts);
+            if range.is_full() {
+                continue;
+            }
+            if unique_index.kind == IndexKind::Hash {
+                // A hash index only finds rows by all of its columns. Its cost does not grow
+                // with the table, so it wins a tie with a B+ tree.
+                if range.prefix.len() < unique_index.skey.len() {
+                    continue;
+                }
+                let cost = HASH_LOOKUP_COST + height;
+                if cost <= best_cost {
+                    best_cost = cost;
+                    best = (Some((unique_index, false)), range, enforced);
+                }
+                continue;
+            }
+            let covering = read_columns
+                .as_ref()
+                .is_some_and(|columns| unique_index.covers(num_key_elems, columns));
+            // An index-only scan reads about as many leaves as the same range of the table,
+            // while an index scan also looks up each row it finds in the table.
+            let cost = match covering {
+                true => height + stats.num_leaf_pages as f64 * range_selectivity(&used),
+                false => height + stats.num_rows as f64 * range_selectivity(&used) * height,
+            };
+            if cost < best_cost {
+                best_cost = cost;
+                best = (Some((unique_index, covering)), range, enforced);
+            }
+        }
+
+        let (index, range, enforced) = best;
+        let while_cond = range.while_cond();
+        let node = match index {
+            None => PhysicalNode::SeqScan {
+                table: info.name.clone(),
+                table_meta_page_id: info.table.meta_page_id,
+                range,
+                while_cond,
+            },
+            Some((unique_index, _)) if unique_index.kind == IndexKind::Hash => {
+                PhysicalNode::HashIndexScan {
+                    table: info.name.clone(),
+                    table_meta_page_id: info.table.meta_page_id,
+                    num_key_elems,
+                    index_meta_page_id: unique_index.meta_page_id,
+                    key: range.prefix,
+                }
+            }
+            Some((unique_index, false)) => PhysicalNode::IndexScan {
+                table: info.name.clone(),
+                table_meta_page_id: info.table.meta_page_id,
+                num_key_elems,
+                index_meta_page_id: unique_index.meta_page_id,
+                range,
+                while_cond,
+            },
+            Some((unique_index, true)) => PhysicalNode::IndexOnlyScan {
+                table: info.name.clone(),
+                num_key_elems,
+                index_meta_page_id: unique_index.meta_page_id,
+                skey: unique_index.skey.clone(),
+                include: unique_index.include.clone(),
+                range,
+                while_cond,
+            },
+        };
+        let scan = PhysicalPlan {
+            node,
+            cost: best_cost,
+            rows: rows.max(1.0),
+            actual: None,
+        };
+        let residual = conjuncts
+            .into_iter()
+            .enumerate()
+            .filter(|(idx, _)| !enforced.contains(idx))
+            .map(|(_, conj)| conj)
+            .collect();
+        filter(scan, residual, rows.max(1.0))
+    }
+
+    fn plan_left_outer_join(
+        &self,
+        left: &LogicalPlan,
+        right: &LogicalPlan,
+        cond: &Expr,
+    ) -> Result<PhysicalPlan, DbError> {
+        let left_num_columns = left.num_columns(self.catalog)?;
+        let right_num_columns = right.num_columns(self.catalog)?;
+        let left = self.optimize(left)?;
+        let mut right = self.relation(right, left_num_columns, right_num_columns)?;
+        // Conditions on the right input alone can filter it before the join, unlike those on
+        // the left input.
+        let mut preds = vec![];
+        for conj in cond.conjuncts() {
+            let mut columns = vec![];
+            conj.columns(&mut columns);
+            if !columns.is_empty() && columns.iter().all(|&column| right.contains(column)) {
+                right
+                    .conjuncts
+                    .push(conj.map_columns(&|column| column - left_num_columns));
+            } else {
+                preds.push(conj);
+            }
+        }
+        self.plan_relation(&mut right)?;
+        let in_left = |column| column < left_num_columns;
+        let step = self.join_step(left.cost, left.rows, &in_left, &right, &preds, true);
+        let join_type = JoinType::LeftOuter { right_num_columns };
+        Ok(build_join(
+            left,
+            left_num_columns,
+            &|column| column,
+            &mut right,
+            step,
+            join_type,
+        ))
+    }
+
+    fn plan_inner_joins(
+        &self,
+        plan: &LogicalPlan,
+        mut conds: Vec<Expr>,
+    ) -> Result<PhysicalPlan, DbError> {
+        let mut relations = vec![];
+        self.flatten_joins(plan, 0, &mut relations, &mut conds)?;
+        if relations.len() > MAX_JOINED_RELATIONS {
+            return Err(DbError::TooManyJoins {
+                limit: MAX_JOINED_RELATIONS,
+            });
+        }
+        let column_relations: Vec<usize> = relations
+            .iter()
+            .enumerate()
+            .flat_map(|(idx, relation)| std::iter::repeat_n(idx, relation.num_columns))
+            .collect();
+
+        // Conditions on a single relation are pushed down to it, and the others are checked
+        // when the last relation they refer to is joined.
+        let mut preds = vec![];
+        for cond in conds {
+            let mut columns = vec![];
+            cond.columns(&mut columns);
+            let mut mask = 0usize;
+            for column in columns {
+                match column_relations.get(column) {
+                    Some(idx) => mask |= 1 << idx,
+                    None => return Err(EvalError::ColumnOutOfRange(column).into()),
+                }
+            }
+            match mask.count_ones() {
+                0 => relations[0].conjuncts.push(cond),
+                1 => {
+                    let relation = &mut relations[mask.trailing_zeros() as usize];
+                    let offset = relation.offset;
+                    relation
+                        .conjuncts
+                        .push(cond.map_columns(&|column| column - offset));
+                }
+                _ => preds.push((mask, cond)),
+            }
+        }
+        for relation in &mut relations {
+            self.plan_relation(relation)?;
+        }
+
+        let order = self.join_order(&relations, &column_relations, &preds);
+        let mut positions = vec![0; column_relations.len()];
+        let mut joined = 0;
+        let mut width = 0;
+        let mut plan: Option<PhysicalPlan> = None;
+        for idx in order {
+            plan = Some(match plan {
+                None => relations[idx].plan.take().unwrap(),
+                Some(left) => {
+                    let step_preds = join_preds(&preds, joined, idx);
+                    let in_left = |column: usize| joined & (1 << column_relations[column]) != 0;
+                    let step = self.join_step(
+                        left.cost,
+                        left.rows,
+                        &in_left,
+                        &relations[idx],
+                        &step_preds,
+                        false,
+                    );
+                    let left_position = |column: usize| positions[column];
+                    build_join(
+                        left,
+                        width,
+                        &left_position,
+                        &mut relations[idx],
+                        step,
+                        JoinType::Inner,
+                    )
+                }
+            });
+            let relation = &relations[idx];
+            for (position, column) in (relation.offset..).take(relation.num_columns).enumerate() {
+                positions[column] = width + position;
+            }
+            width += relation.num_columns;
+            joined |= 1 << idx;
+        }
+
+        // Put the columns back in the order written.
+        let plan = plan.unwrap();
+        if positions
+            .iter()
+            .enumerate()
+            .all(|(column, &position)| column == position)
+        {
+            return Ok(plan);
+        }
+        Ok(PhysicalPlan {
+            cost: plan.cost + plan.rows * CPU_TUPLE_COST,
+            rows: plan.rows,
+            node: PhysicalNode::Project {
+                input: Box::new(plan),
+                exprs: positions.into_iter().map(Expr::Column).collect(),
+            },
+            actual: None,
+        })
+    }
+
+    /// Collects the inputs of a tree of inner joins and the conditions on them, over the
+    /// columns of the joined tuples as written.
+    fn flatten_joins<'p>(
+        &self,
+        plan: &'p LogicalPlan,
+        offset: usize,
+        relations: &mut Vec<Relation<'p>>,
+        conds: &mut Vec<Expr>,
+    ) -> Result<(), DbError>
+    where
+        'a: 'p,
+    {
+        let shift = |column| column + offset;
+        match plan {
+            LogicalPlan::Join {
+                left,
+                right,
+                cond,
+                left_outer: false,
+            } => {
+                let left_num_columns = left.num_columns(self.catalog)?;
+                self.flatten_joins(left, offset, relations, conds)?;
+                self.flatten_joins(right, offset + left_num_columns, relations, conds)?;
+                conds.extend(cond.conjuncts().iter().map(|conj| conj.map_columns(&shift)));
+            }
+            LogicalPlan::Filter { input, cond } => {
+                self.flatten_joins(input, offset, relations, conds)?;
+                conds.extend(cond.conjuncts().iter().map(|conj| conj.map_columns(&shift)));
+            }
+            _ => {
+                let num_columns = plan.num_columns(self.catalog)?;
+                relations.push(self.relation(plan, offset, num_columns)?);
+            }
+        }
+        Ok(())
+    }
+
+    fn relation<'p>(
+        &self,
+        source: &'p LogicalPlan,
+        offset: usize,
+        num_columns: usize,
+    ) -> Result<Relation<'p>, DbError>
+    where
+        'a: 'p,
+    {
+        let table = match source {
+            LogicalPlan::Scan { table } => Some(self.table(table)?),
+            _ => None,
+        };
+        Ok(Relation {
+            source,
+            table,
+            conjuncts: vec![],
+            offset,
+            num_columns,
+            plan: None,
+            cost: 0.0,
+            rows: 0.0,
+        })
+    }
+
+    fn plan_relation(&self, relation: &mut Relation) -> Result<(), DbError> {
+        let plan = self.plan_filtered(relation.source, relation.conjuncts.clone(), None)?;
+        relation.cost = plan.cost;
+        relation.rows = plan.rows;
+        relation.plan = Some(plan);
+        Ok(())
+    }
+
+    /// Returns the cheapest order to join `relations` in, considering every left-deep order
+    /// unless there are too many relations.
+    fn join_order(
+        &self,
+        relations: &[Relation],
+        column_relations: &[usize],
+        preds: &[(usize, Expr)],
+    ) -> Vec<usize> {
+        let n = relations.len();
+        if n > MAX_REORDERED_JOINS {
+            return (0..n).collect();
+        }
+        // The best order of each set of relations, indexed by the bit mask of the set.
+        let mut best: Vec<Option<JoinOrder>> = vec![None; 1 << n];
+        for (idx, relation) in relations.iter().enumerate() {
+            best[1 << idx] = Some(JoinOrder {
+                order: vec![idx],
+                cost: relation.cost,
+                rows: relation.rows,
+            });
+        }
+        for set in 1..best.len() {
+            if set.count_ones() < 2 {
+                continue;
+            }
+            for (idx, relation) in relations.iter().enumerate() {
+                let rest = set & !(1 << idx);
+                if rest == set {
+                    continue;
+                }
+                let Some(left) = best[rest].clone() else {
+                    continue;
+                };
+                let step_preds = join_preds(preds, rest, idx);
+                let in_left = |column: usize| rest & (1 << column_relations[column]) != 0;
+                let step =
+                    self.join_step(left.cost, left.rows, &in_left, relation, &step_preds, false);
+                if best[set].as_ref().is_none_or(|best| step.cost < best.cost) {
+                    let mut order = left.order;
+                    order.push(idx);
+                    best[set] = Some(JoinOrder {
+                        order,
+                        cost: step.cost,
+                        rows: step.rows,
+                    });
+                }
+            }
+        }
+        best.pop().flatten().unwrap().order
+    }
+
+    /// Chooses how to join `relation` to left tuples on `preds`.
+    fn join_step(
+        &self,
+        left_cost: f64,
+        left_rows: f64,
+        in_left: &dyn Fn(usize) -> bool,
+        relation: &Relation,
+        preds: &[Expr],
+        outer: bool,
+    ) -> JoinStep {
+        let mut equi = vec![];
+        let mut others = vec![];
+        for pred in preds {
+            match equi_keys(pred, in_left, relation) {
+                Some(keys) => equi.push(keys),
+                None => others.push(pred.clone()),
+            }
+        }
+
+        let mut rows = left_rows * relation.rows;
+        for _ in &equi {
+            rows /= left_rows.max(relation.rows).max(1.0);
+        }
+        for _ in &others {
+            rows *= DEFAULT_SELECTIVITY;
+        }
+        if outer {
+            rows = rows.max(left_rows);
+        }
+
+        let mut best = JoinStep {
+            method: JoinMethod::NestedLoop,
+            residual: preds.to_vec(),
+            cost: left_cost
+                + left_rows.max(1.0) * relation.cost
+                + left_rows * relation.rows * CPU_TUPLE_COST,
+            rows,
+        };
+        if !equi.is_empty() && (!outer || others.is_empty()) {
+            let cost = left_cost + relation.cost + (left_rows + relation.rows) * CPU_TUPLE_COST;
+            if cost < best.cost {
+                best = JoinStep {
+                    method: JoinMethod::Hash {
+                        left_keys: equi.iter().map(|(left, _)| left.clone()).collect(),
+                        right_keys: equi.iter().map(|(_, right)| right.clone()).collect(),
+                    },
+                    residual: others.clone(),
+                    cost,
+                    rows,
+                };
+            }
+        }
+        if let Some((index_meta_page_id, left_keys, unused)) = index_lookup(relation, &equi) {
+            let offset = relation.offset;
+            let mut residual = others;
+            residual.extend(unused);
+            residual.extend(
+                relation
+                    .conjuncts
+                    .iter()
+                    .map(|conj| conj.map_columns(&|column| column + offset)),
+            );
+            let height = relation.table.unwrap().stats.height as f64;
+            let probe_cost = match index_meta_page_id {
+                None => height,
+                Some(_) => 2.0 * height,
+            };
+            let cost = left_cost + left_rows * probe_cost;
+            if cost < best.cost && (!outer || residual.is_empty()) {
+                best = JoinStep {
+                    method: JoinMethod::IndexNestedLoop {
+                        index_meta_page_id,
+                        left_keys,
+                    },
+                    residual,
+                    cost,
+                    rows,
+                };
+            }
+        }
+        best
+    }
+}
+
+/// Returns the join conditions to check when joining relation `idx` to the relations in
+/// `joined`.
+fn join_preds(preds: &[(usize, Expr)], joined: usize, idx: usize) -> Vec<Expr> {
+    let set = joined | 1 << idx;
+    preds
+        .iter()
+        .filter(|(mask, _)| mask & (1 << idx) != 0 && mask & !set == 0)
+        .map(|(_, pred)| pred.clone())
+        .collect()
+}
+
+/// Splits `left = right` into the expressions over the left tuples and over `relation`.
+fn equi_keys(
+    pred: &Expr,
+    in_left: &dyn Fn(usize) -> bool,
+    relation: &Relation,
+) -> Option<(Expr, Expr)> {
+    let Expr::Binary(BinaryOp::Eq, lhs, rhs) = pred else {
+        return None;
+    };
+    let side = |expr: &Expr| {
+        let mut columns = vec![];
+        expr.columns(&mut columns);
+        if columns.is_empty() {
+            None
+        } else if columns.iter().all(|&column| in_left(column)) {
+            Some(true)
+        } else if columns.iter().all(|&column| relation.contains(column)) {
+            Some(false)
+        } else {
+            None
+        }
+    };
+    match (side(lhs)?, side(rhs)?) {
+        (true, false) => Some((lhs.as_ref().clone(), rhs.as_ref().clone())),
+        (false, true) => Some((rhs.as_ref().clone(), lhs.as_ref().clone())),
+        _ => None,
+    }
+}
+
+/// Finds the primary key or a unique index of the table of `relation` whose columns are all
+/// equal to left keys in `equi`. Returns the index, the left keys in the order of the key columns
+/// and the conditions in `equi` not used by the lookup.
+fn index_lookup(
+    relation: &Relation,
+    equi: &[(Expr, Expr)],
+) -> Option<(Option<PageId>, Vec<Expr>, Vec<Expr>)> {
+    let info = relation.table?;
+    let lookup = |key_columns: &[usize]| {
+        let mut left_keys = vec![];
+        let mut used = vec![];
+        for &key_column in key_columns {
+            let idx = equi.iter().position(
+                |(_, right)| matches!(right, Expr::Column(column) if *column == relation.offset + key_column),
+            )?;
+            left_keys.push(equi[idx].0.clone());
+            used.push(idx);
+        }
+        let unused = (0..equi.len())
+            .filter(|idx| !used.contains(idx))
+            .map(|idx| Expr::binary(BinaryOp::Eq, equi[idx].0.clone(), equi[idx].1.clone()))
+            .collect();
+        Some((left_keys, unused))
+    };
+    let primary_key: Vec<usize> = (0..info.table.num_key_elems).collect();
+    if let Some((left_keys, unused)) = lookup(&primary_key) {
+        return Some((None, left_keys, unused));
+    }
+    // The join probes B+ tree indexes only.
+    info.table.unique_index.iter().find_map(|unique_index| {
+        if unique_index.kind != IndexKind::BTree {
+            return None;
+        }
+        let (left_keys, unused) = lookup(&unique_index.skey)?;
+        Some((Some(unique_index.meta_page_id), left_keys, unused))
+    })
+}
+
+/// Builds the plan joining `relation` to `left` as chosen by `step`. `left_position` maps the
+/// columns as written to their positions in the left tuples.
+fn build_join(
+    left: PhysicalPlan,
+    left_num_columns: usize,
+    left_position: &dyn Fn(usize) -> usize,
+    relation: &mut Relation,
+    step: JoinStep,
+    join_type: JoinType,
+) -> PhysicalPlan {
+    let offset = relation.offset;
+    let num_columns = relation.num_columns;
+    let right_position = |column: usize| column - offset;
+    let joined_position = |column: usize| match column.checked_sub(offset) {
+        Some(position) if position < num_columns => left_num_columns + position,
+        _ => left_position(column),
+    };
+    let JoinStep {
+        method,
+        residual,
+        cost,
+        rows,
+    } = step;
+    let residual: Vec<Expr> = residual
+        .iter()
+        .map(|conj| conj.map_columns(&joined_position))
+        .collect();
+    let left = Box::new(left);
+    let node = match method {
+        JoinMethod::NestedLoop => {
+            let cond = Expr::and_all(residual).unwrap_or(Expr::Literal(TRUE.to_vec()));
+            return PhysicalPlan {
+                node: PhysicalNode::NestedLoopJoin {
+                    left,
+                    right: Box::new(relation.plan.take().unwrap()),
+                    cond,
+                    join_type,
+                },
+                cost,
+                rows,
+                actual: None,
+            };
+        }
+        JoinMethod::Hash {
+            left_keys,
+            right_keys,
+        } => PhysicalNode::HashJoin {
+            left,
+            right: Box::new(relation.plan.take().unwrap()),
+            left_keys: left_keys
+                .iter()
+                .map(|key| key.map_columns(&left_position))
+                .collect(),
+            right_keys: right_keys
+                .iter()
+                .map(|key| key.map_columns(&right_position))
+                .collect(),
+            join_type,
+        },
+        JoinMethod::IndexNestedLoop {
+            index_meta_page_id,
+            left_keys,
+        } => {
+            let info = relation.table.unwrap();
+            PhysicalNode::IndexNestedLoopJoin {
+                left,
+                table: info.name.clone(),
+                table_meta_page_id: info.table.meta_page_id,
+                num_key_elems: info.table.num_key_elems,
+                index_meta_page_id,
+                left_keys: left_keys
+                    .iter()
+                    .map(|key| key.map_columns(&left_position))
+                    .collect(),
+                join_type,
+            }
+        }
+    };
+    let plan = PhysicalPlan {
+        node,
+        cost,
+        rows,
+        actual: None,
+    };
+    filter(plan, residual, rows)
+}
+
+/// Wraps `input` in a filter on `conjuncts`, if any, returning about `rows` tuples.
+fn filter(input: PhysicalPlan, conjuncts: Vec<Expr>, rows: f64) -> PhysicalPlan {
+    let Some(cond) = Expr::and_all(conjuncts) else {
+        return input;
+    };
+    PhysicalPlan {
+        cost: input.cost + input.rows * CPU_TUPLE_COST,
+        rows,
+        node: PhysicalNode::Filter {
+            compiled: compile(&cond),
+            cond,
+            input: Box::new(input),
+        },
+        actual: None,
+    }
+}
+
+fn sort_cost(rows: f64) -> f64 {
+    rows * rows.max(2.0).log2() * CPU_TUPLE_COST
+}
+
+/// Returns `(column, op, value)` if `conj` compares a column with a literal or a parameter, with
+/// the column on the left.
+fn as_bound(conj: &Expr) -> Option<(usize, BinaryOp, &Expr)> {
+    let Expr::Binary(op, lhs, rhs) = conj else {
+        return None;
+    };
+    let flipped = match op {
+        BinaryOp::Eq | BinaryOp::Ne => *op,
+        BinaryOp::Lt => BinaryOp::Gt,
+        BinaryOp::Le => BinaryOp::Ge,
+        BinaryOp::Gt => BinaryOp::Lt,
+        BinaryOp::Ge => BinaryOp::Le,
+        _ => return None,
+    };
+    match (lhs.as_ref(), rhs.as_ref()) {
+        (Expr::Column(column), value @ (Expr::Literal(_) | Expr::Param(_))) => {
+            Some((*column, *op, value))
+        }
+        (value @ (Expr::Literal(_) | Expr::Param(_)), Expr::Column(column)) => {
+            Some((*column, flipped, value))
+        }
+        _ => None,
+    }
+}
+
+/// Builds the range of a scan over the keys made of `key_columns` from `conjuncts`.
+///
+/// Returns the range, the indexes of the conjuncts used for it, and the indexes of those the
+/// scan fully checks. An excluded lower bound is used but not fully checked.
+fn key_range(key_columns: &[usize], conjuncts: &[Expr]) -> (KeyRange, Vec<usize>, Vec<usize>) {
+    let bounds: Vec<_> = conjuncts
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, conj)| Some((idx, as_bound(conj)?)))
+        .collect();
+    let find = |key_column: usize, ops: &[BinaryOp]| {
+        bounds
+            .iter()
+            .find(|(_, (column, op, _))| *column == key_column && ops.contains(op))
+            .map(|&(idx, (_, op, value))| (idx, op, value.clone()))
+    };
+
+    let mut range = KeyRange::default();
+    let mut used = vec![];
+    let mut enforced = vec![];
+    for &key_column in key_columns {
+        if let Some((idx, _, value)) = find(key_column, &[BinaryOp::Eq]) {
+            range.prefix.push(value);
+            used.push(idx);
+            enforced.push(idx);
+            continue;
+        }
+        if let Some((idx, op, value)) = find(key_column, &[BinaryOp::Ge, BinaryOp::Gt]) {
+            range.lower = Some((value, op == BinaryOp::Ge));
+            used.push(idx);
+            if op == BinaryOp::Ge {
+                enforced.push(idx);
+            }
+        }
+        if let Some((idx, op, value)) = find(key_column, &[BinaryOp::Le, BinaryOp::Lt]) {
+            range.upper = Some((value, op == BinaryOp::Le));
+            used.push(idx);
+            enforced.push(idx);
+        }
+        break;
+    }
+    (range, used, enforced)
+}
+
+/// Estimates the fraction of tuples for which `conj` holds.
+fn selectivity(info: Option<&TableInfo>, conj: &Expr) -> f64 {
+    match (as_bound(conj), info) {
+        (Some((column, BinaryOp::Eq, _)), Some(info)) if is_unique(info, column) => {
+            1.0 / info.stats.num_rows.max(1) as f64
+        }
+        (Some((_, BinaryOp::Eq, _)), _) => EQ_SELECTIVITY,
+        (Some((_, BinaryOp::Ne, _)), _) => 1.0 - EQ_SELECTIVITY,
+        (Some(_), _) => RANGE_SELECTIVITY,
+        (None, _) => DEFAULT_SELECTIVITY,
+    }
+}
+
+/// Returns whether `column` alone is the primary key or a unique key of the table.
+fn is_unique(info: &TableInfo, column: usize) -> bool {
+    (info.table.num_key_elems == 1 && column == 0)
+        || info
+            .table
+            .unique_index
+            .iter()
+            .any(|unique_index| unique_index.skey == [column])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::{BufferPool, BufferPoolManager};
+    use crate::catalog::{Column, DataType};
+    use crate::disk::DiskManager;
+    use crate::query::explain::explain_analyze;
+    use crate::query::planner::PlanNode;
+    use crate::query::Tuple;
+    use crate::table::Table;
+    use crate::tuple;
+    use tempfile::tempfile;
+
+    fn create_catalog(bufmgr: &mut BufferPoolManager) -> Catalog {
+        let mut customers = Table {
+            meta_page_id: PageId::INVALID_PAGE_ID,
+            num_key_elems: 1,
+            unique_index: vec![],
+        };
+        customers.create(bufmgr).unwrap();
+        let address = [b'x'; 200];
+        for i in 0..100 {
+            let id = format!("c{i:03}");
+            let name = format!("N{i:03}");
+            customers
+                .insert(bufmgr, &[id.as_bytes(), &address, name.as_bytes()])
+                .unwrap();
+        }
+        customers
+            .add_unique_index(bufmgr, "customers_name_key", vec![2])
+            .unwrap();
+
+        let mut orders = Table {
+            meta_page_id: PageId::INVALID_PAGE_ID,
+            num_key_elems: 1,
+            unique_index: vec![],
+        };
+        orders.create(bufmgr).unwrap();
+        for i in 0..1000 {
+            let id = format!("o{i:04}");
+            let customer_id = format!("c{:03}", i * 7 % 100);
+            let amount = tuple::encode_int(i);
+            orders
+                .insert(bufmgr, &[id.as_bytes(), customer_id.as_bytes(), &amount])
+                .unwrap();
+        }
+
+        let columns = |names: &[&str]| {
+            names
+                .iter()
+                .map(|name| Column::new(name, DataType::Text))
+                .collect()
+        };
+        let mut catalog = Catalog::default();
+        catalog
+            .add_table("customers", columns(&["id", "address", "name"]), customers)
+            .unwrap();
+        catalog
+            .add_table("orders", columns(&["id", "customer_id", "amount"]), orders)
+            .unwrap();
+        catalog.analyze(bufmgr).unwrap();
+        catalog
+    }
+
+    fn execute(plan: &PhysicalPlan, bufmgr: &mut BufferPoolManager) -> Vec<Tuple> {
+        plan.iter(bufmgr).unwrap().map(Result::unwrap).collect()
+    }
+
+    fn lit(value: &str) -> Expr {
+        Expr::Literal(value.as_bytes().to_vec())
+    }
+
+    fn eq(column: usize, value: &str) -> Expr {
+        Expr::binary(BinaryOp::Eq, Expr::Column(column), lit(value))
+    }
+
+    #[test]
+    fn test_scan_method() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let catalog = create_catalog(&mut bufmgr);
+        let optimizer = Optimizer::new(&catalog);
+        assert_eq!(100, catalog.table("customers").unwrap().stats.num_rows);
+
+        // Equality on the primary key becomes the range of the scan.
+        let plan = optimizer
+            .optimize(&LogicalPlan::scan("customers").filter(eq(0, "c042")))
+            .unwrap();
+        assert!(
+            matches!(&plan.node, PhysicalNode::SeqScan { range, .. } if range.prefix == [lit("c042")])
+        );
+        assert_eq!(1, execute(&plan, &mut bufmgr).len());
+
+        // So does equality with a parameter, which is given its value before the plan runs.
+        let cond = Expr::binary(BinaryOp::Eq, Expr::Column(0), Expr::Param(0));
+        let plan = optimizer
+            .optimize(&LogicalPlan::scan("customers").filter(cond))
+            .unwrap();
+        assert!(
+            matches!(&plan.node, PhysicalNode::SeqScan { range, .. } if range.prefix == [Expr::Param(0)])
+        );
+        assert!(plan.start(&mut bufmgr).is_err());
+        let bound = plan.bind_params(&[b"c042".to_vec()]).unwrap();
+        assert_eq!(1, execute(&bound, &mut bufmgr).len());
+
+        // Equality on a unique key uses the index.
+        let plan = optimizer
+            .optimize(&LogicalPlan::scan("customers").filter(eq(2, "N042")))
+            .unwrap();
+        assert!(matches!(&plan.node, PhysicalNode::IndexScan { .. }));
+        let tuples = execute(&plan, &mut bufmgr);
+        assert_eq!(
+            vec![b"c042".to_vec()],
+            tuples.iter().map(|t| t[0].clone()).collect::<Vec<_>>()
+        );
+
+        // The index alone has the columns of the primary key and the unique key.
+        let covered = LogicalPlan::scan("customers")
+            .filter(eq(2, "N042"))
+            .project(vec![Expr::Column(2), Expr::Column(0)]);
+        let plan = optimizer.optimize(&covered).unwrap();
+        let PhysicalNode::Project { input, .. } = &plan.node else {
+            panic!("expected a projection");
+        };
+        assert!(matches!(&input.node, PhysicalNode::IndexOnlyScan { .. }));
+        assert_eq!(
+            vec![vec![b"N042".to_vec(), b"c042".to_vec()]],
+            execute(&plan, &mut bufmgr)
+        );
+
+        // Other conditions are checked by a filter over a full scan.
+        let plan = optimizer
+            .optimize(&LogicalPlan::scan("customers").filter(eq(1, "Seoul")))
+            .unwrap();
+        let PhysicalNode::Filter { input, .. } = &plan.node else {
+            panic!("expected a filter");
+        };
+        assert!(matches!(&input.node, PhysicalNode::SeqScan { range, .. } if range.is_full()));
+        assert!(execute(&plan, &mut bufmgr).is_empty());
+
+        // Bounds on the primary key, with the excluded lower bound checked again by a filter.
+        let bound = |op, value: &str| Expr::binary(op, Expr::Column(0), lit(value));
+        let cond = Expr::binary(
+            BinaryOp::And,
+            bound(BinaryOp::Gt, "c010"),
+            bound(BinaryOp::Le, "c020"),
+        );
+        let plan = optimizer
+            .optimize(&LogicalPlan::scan("customers").filter(cond))
+            .unwrap();
+        let PhysicalNode::Filter { input, .. } = &plan.node else {
+            panic!("expected a filter");
+        };
+        let PhysicalNode::SeqScan { range, .. } = &input.node else {
+            panic!("expected a scan");
+        };
+        assert_eq!(Some((lit("c010"), false)), range.lower);
+        assert_eq!(Some((lit("c020"), true)), range.upper);
+        let ids: Vec<Vec<u8>> = execute(&plan, &mut bufmgr)
+            .into_iter()
+            .map(|tuple| tuple[0].clone())
+            .collect();
+        let expected: Vec<Vec<u8>> = (11..=20).map(|i| format!("c{i:03}").into_bytes()).collect();
+        assert_eq!(expected, ids);
+    }
+
+    #[test]
+    fn test_hash_index_scan() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let mut catalog = create_catalog(&mut bufmgr);
+        let customers = &mut catalog.table_mut("customers").unwrap().table;
+        customers.unique_index.clear();
+        customers
+            .add_hash_index(&mut bufmgr, "customers_name_key", vec![2])
+            .unwrap();
+        let optimizer = Optimizer::new(&catalog);
+
+        // Equality on the key of a hash index looks the row up.
+        let cond = Expr::binary(BinaryOp::Eq, Expr::Column(2), Expr::Param(0));
+        let plan = optimizer
+            .optimize(&LogicalPlan::scan("customers").filter(cond))
+            .unwrap();
+        assert!(
+            matches!(&plan.node, PhysicalNode::HashIndexScan { key, .. } if key == &[Expr::Param(0)])
+        );
+        assert!(plan.start(&mut bufmgr).is_err());
+        let bound = plan.bind_params(&[b"N042".to_vec()]).unwrap();
+        let tuples = execute(&bound, &mut bufmgr);
+        assert_eq!(
+            vec![b"c042".to_vec()],
+            tuples.iter().map(|t| t[0].clone()).collect::<Vec<_>>()
+        );
+        let bound = plan.bind_params(&[b"N100".to_vec()]).unwrap();
+        assert!(execute(&bound, &mut bufmgr).is_empty());
+
+        // A bound on the key cannot use it.
+        let cond = Expr::binary(BinaryOp::Ge, Expr::Column(2), lit("N042"));
+        let plan = optimizer
+            .optimize(&LogicalPlan::scan("customers").filter(cond))
+            .unwrap();
+        let PhysicalNode::Filter { input, .. } = &plan.node else {
+            panic!("expected a filter");
+        };
+        assert!(matches!(&input.node, PhysicalNode::SeqScan { .. }));
+        assert_eq!(58, execute(&plan, &mut bufmgr).len());
+
+        // An entry whose row is gone is reported instead of returning the next row.
+        let customers = &catalog.table("customers").unwrap().table;
+        let mut pkey = vec![];
+        tuple::encode([b"c042"].iter(), &mut pkey);
+        crate::btree::BTree::new(customers.meta_page_id)
+            .delete(&mut bufmgr, &pkey)
+            .unwrap();
+        let cond = Expr::binary(BinaryOp::Eq, Expr::Column(2), lit("N042"));
+        let plan = optimizer
+            .optimize(&LogicalPlan::scan("customers").filter(cond))
+            .unwrap();
+        assert!(matches!(
+            plan.iter(&mut bufmgr).unwrap().next(),
+            Some(Err(DbError::Corrupted(_)))
+        ));
+    }
+
+    #[test]
+    fn test_join_order() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let catalog = create_catalog(&mut bufmgr);
+        let optimizer = Optimizer::new(&catalog);
+
+        let join = LogicalPlan::scan("orders").join(
+            LogicalPlan::scan("customers"),
+            Expr::binary(BinaryOp::Eq, Expr::Column(1), Expr::Column(3)),
+        );
+
+        // A single order looks up its customer by primary key.
+        let plan = optimizer
+            .optimize(&join.clone().filter(eq(0, "o0042")))
+            .unwrap();
+        assert!(matches!(
+            &plan.node,
+            PhysicalNode::IndexNestedLoopJoin {
+                index_meta_page_id: None,
+                ..
+            }
+        ));
+        let tuples = execute(&plan, &mut bufmgr);
+        assert_eq!(1, tuples.len());
+        assert_eq!(b"c094", tuples[0][3].as_slice());
+
+        // All orders are joined with a hash table.
+        let plan = optimizer.optimize(&join).unwrap();
+        let node = match &plan.node {
+            PhysicalNode::Project { input, .. } => &input.node,
+            node => node,
+        };
+        assert!(matches!(node, PhysicalNode::HashJoin { .. }));
+        let tuples = execute(&plan, &mut bufmgr);
+        assert_eq!(1000, tuples.len());
+        assert!(tuples.iter().all(|tuple| tuple[1] == tuple[3]));
+
+        // With a single customer selected, the customer is read first and the columns are put
+        // back in the order written.
+        let plan = optimizer.optimize(&join.filter(eq(5, "N007"))).unwrap();
+        let PhysicalNode::Project { input, .. } = &plan.node else {
+            panic!("expected the columns to be reordered");
+        };
+        let (PhysicalNode::HashJoin { left, .. } | PhysicalNode::NestedLoopJoin { left, .. }) =
+            &input.node
+        else {
+            panic!("expected orders to be joined to the customer");
+        };
+        assert!(matches!(&left.node, PhysicalNode::IndexScan { .. }));
+        let tuples = execute(&plan, &mut bufmgr);
+        assert_eq!(10, tuples.len());
+        for tuple in tuples {
+            assert_eq!(b"c007", tuple[1].as_slice());
+            assert_eq!(b"N007", tuple[5].as_slice());
+        }
+    }
+
+    #[test]
+    fn test_filter_error() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let catalog = create_catalog(&mut bufmgr);
+        let optimizer = Optimizer::new(&catalog);
+
+        // A condition that fails to evaluate fails the query instead of dropping the rows.
+        let ratio = Expr::binary(BinaryOp::Div, Expr::Column(2), Expr::int(0));
+        let plan = optimizer
+            .optimize(&LogicalPlan::scan("orders").filter(Expr::binary(
+                BinaryOp::Eq,
+                ratio,
+                Expr::int(1),
+            )))
+            .unwrap();
+        let mut iter = plan.iter(&mut bufmgr).unwrap();
+        assert!(matches!(
+            iter.next(),
+            Some(Err(DbError::Eval(EvalError::DivisionByZero)))
+        ));
+    }
+
+    #[test]
+    fn test_too_many_joins() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let catalog = create_catalog(&mut bufmgr);
+        let optimizer = Optimizer::new(&catalog);
+
+        let join = |num_relations: usize| {
+            (1..num_relations).fold(LogicalPlan::scan("customers"), |plan, _| {
+                plan.join(LogicalPlan::scan("customers"), lit("1"))
+            })
+        };
+        assert!(optimizer.optimize(&join(MAX_JOINED_RELATIONS)).is_ok());
+        assert!(matches!(
+            optimizer.optimize(&join(MAX_JOINED_RELATIONS + 1)),
+            Err(DbError::TooManyJoins { .. })
+        ));
+    }
+
+    #[test]
+    fn test_left_outer_join() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let catalog = create_catalog(&mut bufmgr);
+        let optimizer = Optimizer::new(&catalog);
+
+        // Customers with their orders over 950, or with empty order columns if there are none.
+        let cond = Expr::binary(
+            BinaryOp::And,
+            Expr::binary(BinaryOp::Eq, Expr::Column(0), Expr::Column(4)),
+            Expr::binary(BinaryOp::Gt, Expr::Column(5), Expr::int(950)),
+        );
+        let plan = LogicalPlan::scan("customers")
+            .filter(Expr::binary(
+                BinaryOp::Lt,
+                Expr::Column(0),
+                Expr::Literal(b"c005".to_vec()),
+            ))
+            .left_outer_join(LogicalPlan::scan("orders"), cond);
+        let plan = optimizer.optimize(&plan).unwrap();
+        let tuples = execute(&plan, &mut bufmgr);
+        let orders: Vec<(&[u8], &[u8])> = tuples
+            .iter()
+            .map(|tuple| (tuple[0].as_slice(), tuple[3].as_slice()))
+            .collect();
+        assert_eq!(
+            vec![
+                (b"c000".as_slice(), b"".as_slice()),
+                (b"c001", b""),
+                (b"c002", b"o0986"),
+                (b"c003", b""),
+                (b"c004", b"o0972"),
+            ],
+            orders
+        );
+    }
+
+    #[test]
+    fn test_explain_analyze() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let catalog = create_catalog(&mut bufmgr);
+        let optimizer = Optimizer::new(&catalog);
+
+        let join = LogicalPlan::scan("orders")
+            .join(
+                LogicalPlan::scan("customers"),
+                Expr::binary(BinaryOp::Eq, Expr::Column(1), Expr::Column(3)),
+            )
+            .filter(eq(0, "o0042"));
+        let mut plan = optimizer.optimize(&join).unwrap();
+        assert_eq!(
+            "IndexNestedLoopJoin type=inner table=customers left_keys=(#1) (cost=4.02 rows=1)\n  \
+             SeqScan table=orders range=(#0 = 'o0042') (cost=2.02 rows=1)\n",
+            plan.explain().to_text()
+        );
+
+        // Each node counts the pages fetched by it and its inputs: the join looks up the customer
+        // after the scan has found the order.
+        plan.analyze();
+        let explain = explain_analyze(&plan, &mut bufmgr).unwrap();
+        assert_eq!(
+            "IndexNestedLoopJoin type=inner table=customers left_keys=(#1) (cost=4.02 rows=1) \
+             (actual loops=1 rows=1 pages=6)\n  \
+             SeqScan table=orders range=(#0 = 'o0042') (cost=2.02 rows=1) \
+             (actual loops=1 rows=1 pages=3)\n",
+            explain.to_text()
+        );
+    }
+}
@@ -0,0 +1,795 @@
+use std::cell::Cell;
+use std::fmt::{self, Display, Formatter};
+
+use super::aggregate::{self, AggExpr};
+use super::executor::{
+    ExecFilter, ExecHashAggregate, ExecHashIndexScan, ExecHashJoin, ExecIndexNestedLoopJoin,
+    ExecIndexOnlyScan, ExecIndexScan, ExecLimit, ExecNestedLoopJoin, ExecProject, ExecSeqScan,
+    ExecSort, ExecTopN,
+};
+use super::explain::{self, fmt_list, ActualStats, Estimate, Explain};
+use super::expr::{EvalError, Expr};
+use super::join::JoinType;
+use super::planner::PlanNode;
+use super::sort::SortKey;
+use super::{BoxExecutor, Tuple, TupleSlice};
+
+use crate::btree::{BTree, SearchMode};
+use crate::buffer::BufferPoolManager;
+use crate::disk::PageId;
+use crate::error::DbError;
+use crate::hash::HashIndex;
+use crate::tuple;
+
+pub type BoxCondition = Box<dyn Fn(TupleSlice) -> bool>;
+
+pub type BoxPredicate = Box<dyn Fn(TupleSlice) -> Result<bool, EvalError>>;
+
+/// An executable plan chosen by the [`Optimizer`](super::optimizer::Optimizer), with its
+/// estimated cost and number of output tuples.
+///
+/// Unlike the plan nodes in [`planner`](super::planner), it owns its children and conditions.
+pub struct PhysicalPlan {
+    pub node: PhysicalNode,
+    /// The estimated number of page reads.
+    pub cost: f64,
+    pub rows: f64,
+    /// The statistics counted when the plan runs, if [`analyze`](Self::analyze) was called.
+    pub actual: Option<Cell<ActualStats>>,
+}
+
+pub enum PhysicalNode {
+    SeqScan {
+        table: String,
+        table_meta_page_id: PageId,
+        range: KeyRange,
+        while_cond: BoxCondition,
+    },
+    IndexScan {
+        table: String,
+        table_meta_page_id: PageId,
+        num_key_elems: usize,
+        index_meta_page_id: PageId,
+        range: KeyRange,
+        while_cond: BoxCondition,
+    },
+    /// Looks up the row whose columns of a hash index equal `key`.
+    HashIndexScan {
+        table: String,
+        table_meta_page_id: PageId,
+        num_key_elems: usize,
+        index_meta_page_id: PageId,
+        key: Vec<Expr>,
+    },
+    IndexOnlyScan {
+        table: String,
+        num_key_elems: usize,
+        index_meta_page_id: PageId,
+        skey: Vec<usize>,
+        include: Vec<usize>,
+        range: KeyRange,
+        while_cond: BoxCondition,
+    },
+    Filter {
+        input: Box<PhysicalPlan>,
+        cond: Expr,
+        compiled: BoxPredicate,
+    },
+    Project {
+        input: Box<PhysicalPlan>,
+        exprs: Vec<Expr>,
+    },
+    NestedLoopJoin {
+        left: Box<PhysicalPlan>,
+        right: Box<PhysicalPlan>,
+        cond: Expr,
+        join_type: JoinType,
+    },
+    HashJoin {
+        left: Box<PhysicalPlan>,
+        right: Box<PhysicalPlan>,
+        left_keys: Vec<Expr>,
+        right_keys: Vec<Expr>,
+        join_type: JoinType,
+    },
+    IndexNestedLoopJoin {
+        left: Box<PhysicalPlan>,
+        table: String,
+        table_meta_page_id: PageId,
+        num_key_elems: usize,
+        index_meta_page_id: Option<PageId>,
+        left_keys: Vec<Expr>,
+        join_type: JoinType,
+    },
+    HashAggregate {
+        input: Box<PhysicalPlan>,
+        group_by: Vec<Expr>,
+        aggregates: Vec<AggExpr>,
+    },
+    Sort {
+        input: Box<PhysicalPlan>,
+        keys: Vec<SortKey>,
+        memory_budget: usize,
+    },
+    Limit {
+        input: Box<PhysicalPlan>,
+        offset: usize,
+        count: usize,
+    },
+    TopN {
+        input: Box<PhysicalPlan>,
+        keys: Vec<SortKey>,
+        offset: usize,
+        count: usize,
+    },
+}
+
+/// The keys read by a scan: those starting with `prefix` whose next column is within `lower`
+/// and `upper`. Each bound is a value and whether the bound itself is included.
+///
+/// The values are literals, or parameters until the plan is bound with
+/// [`PhysicalPlan::bind_params`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KeyRange {
+    pub prefix: Vec<Expr>,
+    pub lower: Option<(Expr, bool)>,
+    pub upper: Option<(Expr, bool)>,
+}
+
+impl KeyRange {
+    pub fn is_full(&self) -> bool {
+        self.prefix.is_empty() && self.lower.is_none() && self.upper.is_none()
+    }
+
+    fn search_mode(&self) -> Result<SearchMode, EvalError> {
+        if self.prefix.is_empty() && self.lower.is_none() {
+            return Ok(SearchMode::Start);
+        }
+        let lower = self.lower.iter().map(|(value, _)| value);
+        let values = self
+            .prefix
+            .iter()
+            .chain(lower)
+            .map(|value| value.eval(&[]))
+            .collect::<Result<Tuple, _>>()?;
+        let mut key = vec![];
+        tuple::encode(values.iter(), &mut key);
+        Ok(SearchMode::Key(key))
+    }
+
+    /// Returns the condition on the key columns that holds until the scan passes the range.
+    ///
+    /// An excluded lower bound is not checked, so the keys equal to it are still returned. A
+    /// range with unbound parameters holds for no key.
+    pub fn while_cond(&self) -> BoxCondition {
+        let prefix: Result<Tuple, _> = self.prefix.iter().map(|value| value.eval(&[])).collect();
+        let upper = self
+            .upper
+            .as_ref()
+            .map(|(value, inclusive)| Ok::<_, EvalError>((value.eval(&[])?, *inclusive)))
+            .transpose();
+        let (Ok(prefix), Ok(upper)) = (prefix, upper) else {
+            return Box::new(|_| false);
+        };
+        Box::new(move |key| {
+            if key.get(..prefix.len()) != Some(&prefix) {
+                return false;
+            }
+            match (&upper, key.get(prefix.len())) {
+                (Some((bound, true)), Some(value)) => value <= bound,
+                (Some((bound, false)), Some(value)) => value < bound,
+                _ => true,
+            }
+        })
+    }
+
+    fn bind_params(&self, params: &[Vec<u8>]) -> Result<Self, EvalError> {
+        let bind_bound = |bound: &Option<(Expr, bool)>| {
+            bound
+                .as_ref()
+                .map(|(value, inclusive)| Ok((value.bind_params(params)?, *inclusive)))
+                .transpose()
+        };
+        Ok(Self {
+            prefix: bind_all(&self.prefix, params)?,
+            lower: bind_bound(&self.lower)?,
+            upper: bind_bound(&self.upper)?,
+        })
+    }
+}
+
+impl Display for KeyRange {
+    /// Formats the range as conditions on the key columns, numbered from the first column of the
+    /// key.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.is_full() {
+            return f.write_str("full");
+        }
+        let mut conds: Vec<String> = self
+            .prefix
+            .iter()
+            .enumerate()
+            .map(|(i, value)| format!("#{i} = {value}"))
+            .collect();
+        let column = self.prefix.len();
+        if let Some((value, inclusive)) = &self.lower {
+            let op = if *inclusive { ">=" } else { ">" };
+            conds.push(format!("#{column} {op} {value}"));
+        }
+        if let Some((value, inclusive)) = &self.upper {
+            let op = if *inclusive { "<=" } else { "<" };
+            conds.push(format!("#{column} {op} {value}"));
+        }
+        write!(f, "({})", conds.join(" AND "))
+    }
+}
+
+fn bind_all(exprs: &[Expr], params: &[Vec<u8>]) -> Result<Vec<Expr>, EvalError> {
+    exprs.iter().map(|expr| expr.bind_params(params)).collect()
+}
+
+/// Compiles a condition for [`ExecFilter`], which fails if evaluating it fails.
+pub fn compile(cond: &Expr) -> BoxPredicate {
+    let cond = cond.clone();
+    Box::new(move |tuple| cond.eval_bool(tuple))
+}
+
+impl PhysicalPlan {
+    /// Makes every node of the plan count what happens when it runs, for `EXPLAIN ANALYZE`.
+    pub fn analyze(&mut self) {
+        self.actual = Some(Cell::default());
+        for child in self.children_mut() {
+            child.analyze();
+        }
+    }
+
+    /// Returns a copy of the plan with each parameter replaced with its value in `params`, so
+    /// that a plan made once for a prepared statement runs with different values.
+    pub fn bind_params(&self, params: &[Vec<u8>]) -> Result<PhysicalPlan, EvalError> {
+        let bind_input = |input: &PhysicalPlan| input.bind_params(params).map(Box::new);
+        let bind_keys = |keys: &[SortKey]| {
+            keys.iter()
+                .map(|key| {
+                    Ok(SortKey {
+                        expr: key.expr.bind_params(params)?,
+                        descending: key.descending,
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let node = match &self.node {
+            PhysicalNode::SeqScan {
+                table,
+                table_meta_page_id,
+                range,
+                ..
+            } => {
+                let range = range.bind_params(params)?;
+                PhysicalNode::SeqScan {
+                    table: table.clone(),
+                    table_meta_page_id: *table_meta_page_id,
+                    while_cond: range.while_cond(),
+                    range,
+                }
+            }
+            PhysicalNode::IndexScan {
+                table,
+                table_meta_page_id,
+                num_key_elems,
+                index_meta_page_id,
+                range,
+                ..
+            } => {
+                let range = range.bind_params(params)?;
+                PhysicalNode::IndexScan {
+                    table: table.clone(),
+                    table_meta_page_id: *table_meta_page_id,
+                    num_key_elems: *num_key_elems,
+                    index_meta_page_id: *index_meta_page_id,
+                    while_cond: range.while_cond(),
+                    range,
+                }
+            }
+            PhysicalNode::HashIndexScan {
+                table,
+                table_meta_page_id,
+                num_key_elems,
+                index_meta_page_id,
+                key,
+            } => PhysicalNode::HashIndexScan {
+                table: table.clone(),
+                table_meta_page_id: *table_meta_page_id,
+                num_key_elems: *num_key_elems,
+                index_meta_page_id: *index_meta_page_id,
+                key: bind_all(key, params)?,
+            },
+            PhysicalNode::IndexOnlyScan {
+                table,
+                num_key_elems,
+                index_meta_page_id,
+                skey,
+                include,
+                range,
+                ..
+            } => {
+                let range = range.bind_params(params)?;
+                PhysicalNode::IndexOnlyScan {
+                    table: table.clone(),
+                    num_key_elems: *num_key_elems,
+                    index_meta_page_id: *index_meta_page_id,
+                    skey: skey.clone(),
+                    include: include.clone(),
+                    while_cond: range.while_cond(),
+                    range,
+                }
+            }
+            PhysicalNode::Filter { input, cond, .. } => {
+                let cond = cond.bind_params(params)?;
+                PhysicalNode::Filter {
+                    input: bind_input(input)?,
+                    compiled: compile(&cond),
+                    cond,
+                }
+            }
+            PhysicalNode::Project { input, exprs } => PhysicalNode::Project {
+                input: bind_input(input)?,
+                exprs: bind_all(exprs, params)?,
+            },
+            PhysicalNode::NestedLoopJoin {
+                left,
+                right,
+                cond,
+                join_type,
+            } => PhysicalNode::NestedLoopJoin {
+                left: bind_input(left)?,
+                right: bind_input(right)?,
+                cond: cond.bind_params(params)?,
+                join_type: *join_type,
+            },
+            PhysicalNode::HashJoin {
+                left,
+                right,
+                left_keys,
+                right_keys,
+                join_type,
+            } => PhysicalNode::HashJoin {
+                left: bind_input(left)?,
+                right: bind_input(right)?,
+                left_keys: bind_all(left_keys, params)?,
+                right_keys: bind_all(right_keys, params)?,
+                join_type: *join_type,
+            },
+            PhysicalNode::IndexNestedLoopJoin {
+                left,
+                table,
+                table_meta_page_id,
+                num_key_elems,
+                index_meta_page_id,
+                left_keys,
+                join_type,
+            } => PhysicalNode::IndexNestedLoopJoin {
+                left: bind_input(left)?,
+                table: table.clone(),
+                table_meta_page_id: *table_meta_page_id,
+                num_key_elems: *num_key_elems,
+                index_meta_page_id: *index_meta_page_id,
+                left_keys: bind_all(left_keys, params)?,
+                join_type: *join_type,
+            },
+            PhysicalNode::HashAggregate {
+                input,
+                group_by,
+                aggregates,
+            } => PhysicalNode::HashAggregate {
+                input: bind_input(input)?,
+                group_by: bind_all(group_by, params)?,
+                aggregates: aggregates
+                    .iter()
+                    .map(|aggregate| {
+                        Ok(AggExpr {
+                            func: aggregate.func,
+                            arg: aggregate
+                                .arg
+                                .as_ref()
+                                .map(|arg| arg.bind_params(params))
+                                .transpose()?,
+                        })
+                    })
+                    .collect::<Result<_, EvalError>>()?,
+            },
+            PhysicalNode::Sort {
+                input,
+                keys,
+                memory_budget,
+            } => PhysicalNode::Sort {
+                input: bind_input(input)?,
+                keys: bind_keys(keys)?,
+                memory_budget: *memory_budget,
+            },
+            PhysicalNode::Limit {
+                input,
+                offset,
+                count,
+            } => PhysicalNode::Limit {
+                input: bind_input(input)?,
+                offset: *offset,
+                count: *count,
+            },
+            PhysicalNode::TopN {
+                input,
+                keys,
+                offset,
+                count,
+            } => PhysicalNode::TopN {
+                input: bind_input(input)?,
+                keys: bind_keys(keys)?,
+                offset: *offset,
+                count: *count,
+            },
+        };
+        Ok(PhysicalPlan {
+            node,
+            cost: self.cost,
+            rows: self.rows,
+            actual: None,
+        })
+    }
+
+    fn children(&self) -> Vec<&PhysicalPlan> {
+        match &self.node {
+            PhysicalNode::SeqScan { .. }
+            | PhysicalNode::IndexScan { .. }
+            | PhysicalNode::HashIndexScan { .. }
+            | PhysicalNode::IndexOnlyScan { .. } => vec![],
+            PhysicalNode::NestedLoopJoin { left, right, .. }
+            | PhysicalNode::HashJoin { left, right, .. } => vec![left, right],
+            PhysicalNode::Filter { input, .. }
+            | PhysicalNode::Project { input, .. }
+            | PhysicalNode::HashAggregate { input, .. }
+            | PhysicalNode::Sort { input, .. }
+            | PhysicalNode::Limit { input, .. }
+            | PhysicalNode::TopN { input, .. }
+            | PhysicalNode::IndexNestedLoopJoin { left: input, .. } => vec![input],
+        }
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut PhysicalPlan> {
+        match &mut self.node {
+            PhysicalNode::SeqScan { .. }
+            | PhysicalNode::IndexScan { .. }
+            | PhysicalNode::HashIndexScan { .. }
+            | PhysicalNode::IndexOnlyScan { .. } => vec![],
+            PhysicalNode::NestedLoopJoin { left, right, .. }
+            | PhysicalNode::HashJoin { left, right, .. } => vec![left, right],
+            PhysicalNode::Filter { input, .. }
+            | PhysicalNode::Project { input, .. }
+            | PhysicalNode::HashAggregate { input, .. }
+            | PhysicalNode::Sort { input, .. }
+            | PhysicalNode::Limit { input, .. }
+            | PhysicalNode::TopN { input, .. }
+            | PhysicalNode::IndexNestedLoopJoin { left: input, .. } => vec![input],
+        }
+    }
+
+    fn start_columns(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        columns: Option<Vec<usize>>,
+    ) -> Result<BoxExecutor, DbError> {
+        match &self.actual {
+            Some(actual) => {
+                explain::start_analyzed(actual, bufmgr, |bufmgr| self.start_node(bufmgr, columns))
+            }
+            None => self.start_node(bufmgr, columns),
+        }
+    }
+
+    fn start_node(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        columns: Option<Vec<usize>>,
+    ) -> Result<BoxExecutor, DbError> {
+        let executor: BoxExecutor = match &self.node {
+            PhysicalNode::SeqScan {
+                table_meta_page_id,
+                range,
+                while_cond,
+                ..
+            } => {
+                let btree = BTree::new(*table_meta_page_id);
+                let table_iter = btree.search(bufmgr, range.search_mode()?)?;
+                Box::new(ExecSeqScan::new(table_iter, while_cond, columns))
+            }
+            PhysicalNode::IndexScan {
+                table_meta_page_id,
+                num_key_elems,
+                index_meta_page_id,
+                range,
+                while_cond,
+                ..
+            } => {
+                let table_btree = BTree::new(*table_meta_page_id);
+                let index_btree = BTree::new(*index_meta_page_id);
+                let index_iter = index_btree.search(bufmgr, range.search_mode()?)?;
+                Box::new(ExecIndexScan::new(
+                    table_btree,
+                    *num_key_elems,
+                    index_iter,
+                    while_cond,
+                    columns,
+                ))
+            }
+            PhysicalNode::HashIndexScan {
+                table_meta_page_id,
+                num_key_elems,
+                index_meta_page_id,
+                key,
+                ..
+            } => {
+                let values = key
+                    .iter()
+                    .map(|value| value.eval(&[]))
+                    .collect::<Result<Tuple, _>>()?;
+                let mut skey = vec![];
+                tuple::encode(values.iter(), &mut skey);
+                let index_value = HashIndex::new(*index_meta_page_id).get(bufmgr, &skey)?;
+                Box::new(ExecHashIndexScan::new(
+                    BTree::new(*table_meta_page_id),
+                    *num_key_elems,
+                    index_value,
+                    columns,
+                ))
+            }
+            PhysicalNode::IndexOnlyScan {
+                num_key_elems,
+                index_meta_page_id,
+                skey,
+                include,
+                range,
+                while_cond,
+                ..
+            } => {
+                let index_btree = BTree::new(*index_meta_page_id);
+                let index_iter = index_btree.search(bufmgr, range.search_mode()?)?;
+                Box::new(ExecIndexOnlyScan::new(
+                    index_iter,
+                    *num_key_elems,
+                    skey,
+                    include,
+                    while_cond,
+                ))
+            }
+            PhysicalNode::Filter {
+                input,
+                cond,
+                compiled,
+            } => {
+                let inner_executor = match columns {
+                    Some(mut columns) => {
+                        cond.columns(&mut columns);
+                        input.start_projected(bufmgr, &columns)?
+                    }
+                    None => input.start(bufmgr)?,
+                };
+                Box::new(ExecFilter::new(inner_executor, compiled))
+            }
+            PhysicalNode::Project { input, exprs } => {
+                let mut columns = vec![];
+                for expr in exprs {
+                    expr.columns(&mut columns);
+                }
+                let inner_executor = input.start_projected(bufmgr, &columns)?;
+                Box::new(ExecProject::new(inner_executor, exprs))
+            }
+            PhysicalNode::NestedLoopJoin {
+                left,
+                right,
+                cond,
+                join_type,
+            } => {
+                let left_executor = left.start(bufmgr)?;
+                Box::new(ExecNestedLoopJoin::new(
+                    left_executor,
+                    right.as_ref(),
+                    cond,
+                    *join_type,
+                ))
+            }
+            PhysicalNode::HashJoin {
+                left,
+                right,
+                left_keys,
+                right_keys,
+                join_type,
+            } => {
+                let left_executor = left.start(bufmgr)?;
+                let right_executor = right.start(bufmgr)?;
+                Box::new(ExecHashJoin::new(
+                    left_executor,
+                    right_executor,
+                    left_keys,
+                    right_keys,
+                    *join_type,
+                ))
+            }
+            PhysicalNode::IndexNestedLoopJoin {
+                left,
+                table_meta_page_id,
+                num_key_elems,
+                index_meta_page_id,
+                left_keys,
+                join_type,
+                ..
+            } => {
+                let left_executor = left.start(bufmgr)?;
+                Box::new(ExecIndexNestedLoopJoin::new(
+                    left_executor,
+                    BTree::new(*table_meta_page_id),
+                    *num_key_elems,
+                    index_meta_page_id.map(BTree::new),
+                    left_keys,
+                    *join_type,
+                ))
+            }
+            PhysicalNode::HashAggregate {
+                input,
+                group_by,
+                aggregates,
+            } => {
+                let columns = aggregate::columns(group_by, aggregates);
+                let inner_executor = input.start_projected(bufmgr, &columns)?;
+                Box::new(ExecHashAggregate::new(inner_executor, group_by, aggregates))
+            }
+            PhysicalNode::Sort {
+                input,
+                keys,
+                memory_budget,
+            } => {
+                let inner_executor = input.start(bufmgr)?;
+                Box::new(ExecSort::new(inner_executor, keys, *memory_budget))
+            }
+            PhysicalNode::Limit {
+                input,
+                offset,
+                count,
+            } => {
+                let inner_executor = match columns {
+                    Some(columns) => input.start_projected(bufmgr, &columns)?,
+                    None => input.start(bufmgr)?,
+                };
+                Box::new(ExecLimit::new(inner_executor, *offset, *count))
+            }
+            PhysicalNode::TopN {
+                input,
+                keys,
+                offset,
+                count,
+            } => {
+                let inner_executor = input.start(bufmgr)?;
+                Box::new(ExecTopN::new(inner_executor, keys, *offset, *count))
+            }
+        };
+        Ok(executor)
+    }
+}
+
+impl PlanNode for PhysicalPlan {
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        self.start_columns(bufmgr, None)
+    }
+
+    fn start_projected(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        columns: &[usize],
+    ) -> Result<BoxExecutor, DbError> {
+        self.start_columns(bufmgr, Some(columns.to_vec()))
+    }
+
+    fn explain(&self) -> Explain {
+        let explain = match &self.node {
+            PhysicalNode::SeqScan { table, range, .. } => Explain::new("SeqScan")
+                .property("table", table)
+                .property("range", range),
+            PhysicalNode::IndexScan {
+                table,
+                index_meta_page_id,
+                range,
+                ..
+            } => Explain::new("IndexScan")
+                .property("table", table)
+                .property("index", index_meta_page_id.0)
+                .property("range", range),
+            PhysicalNode::HashIndexScan {
+                table,
+                index_meta_page_id,
+                key,
+                ..
+            } => Explain::new("HashIndexScan")
+                .property("table", table)
+                .property("index", index_meta_page_id.0)
+                .property("key", fmt_list(key)),
+            PhysicalNode::IndexOnlyScan {
+                table,
+                index_meta_page_id,
+                range,
+                ..
+            } => Explain::new("IndexOnlyScan")
+                .property("table", table)
+                .property("index", index_meta_page_id.0)
+                .property("range", range),
+            PhysicalNode::Filter { cond, .. } => Explain::new("Filter").property("cond", cond),
+            PhysicalNode::Project { exprs, .. } => {
+                Explain::new("Project").property("exprs", fmt_list(exprs))
+            }
+            PhysicalNode::NestedLoopJoin {
+                cond, join_type, ..
+            } => Explain::new("NestedLoopJoin")
+                .property("type", join_type)
+                .property("cond", cond),
+            PhysicalNode::HashJoin {
+                left_keys,
+                right_keys,
+                join_type,
+                ..
+            } => Explain::new("HashJoin")
+                .property("type", join_type)
+                .property("left_keys", fmt_list(left_keys))
+                .property("right_keys", fmt_list(right_keys)),
+            PhysicalNode::IndexNestedLoopJoin {
+                table,
+                index_meta_page_id,
+                left_keys,
+                join_type,
+                ..
+            } => {
+                let explain = Explain::new("IndexNestedLoopJoin")
+                    .property("type", join_type)
+                    .property("table", table);
+                let explain = match index_meta_page_id {
+                    Some(index_meta_page_id) => explain.property("index", index_meta_page_id.0),
+                    None => explain,
+                };
+                explain.property("left_keys", fmt_list(left_keys))
+            }
+            PhysicalNode::HashAggregate {
+                group_by,
+                aggregates,
+                ..
+            } => Explain::new("HashAggregate")
+                .property("group_by", fmt_list(group_by))
+                .property("aggregates", fmt_list(aggregates)),
+            PhysicalNode::Sort {
+                keys,
+                memory_budget,
+                ..
+            } => Explain::new("Sort")
+                .property("keys", fmt_list(keys))
+                .property("memory_budget", memory_budget),
+            PhysicalNode::Limit { offset, count, .. } => Explain::new("Limit")
+                .property("offset", offset)
+                .property("count", count),
+            PhysicalNode::TopN {
+                keys,
+                offset,
+                count,
+                ..
+            } => Explain::new("TopN")
+                .property("keys", fmt_list(keys))
+                .property("offset", offset)
+                .property("count", count),
+        };
+        let explain = self
+            .children()
+            .into_iter()
+            .fold(explain, |explain, child| explain.child(child.explain()));
+        Explain {
+            estimate: Some(Estimate {
+                cost: self.cost,
+                rows: self.rows,
+            }),
+            actual: self.actual.as_ref().map(Cell::get),
+            ..explain
+        }
+    }
+}
@@ -1,37 +1,153 @@
-use std::error::Error;
+use std::cell::Cell;
 
-use super::executor::{ExecFilter, ExecIndexScan, ExecSeqScan};
-use super::{BoxExecutor, Condition, TupleSearchMode};
+use super::aggregate::{self, AggExpr};
+use super::executor::{
+    ExecDelete, ExecFilter, ExecHashAggregate, ExecHashJoin, ExecHeapScan, ExecIndexNestedLoopJoin,
+    ExecIndexOnlyScan, ExecIndexScan, ExecInsert, ExecLimit, ExecMergeJoin, ExecNestedLoopJoin,
+    ExecProject, ExecSeqScan, ExecSort, ExecStreamAggregate, ExecTopN, ExecUpdate, ExecValues,
+    TupleIter,
+};
+use super::explain::{self, fmt_list, fmt_values, ActualStats, Explain};
+use super::expr::Expr;
+use super::join::JoinType;
+use super::sort::SortKey;
+use super::{BoxExecutor, Condition, Predicate, Tuple, TupleSearchMode};
 
 use crate::btree::BTree;
 use crate::buffer::BufferPoolManager;
 use crate::disk::PageId;
+use crate::error::DbError;
+use crate::heap::HeapFile;
+use crate::table::Table;
 
 pub trait PlanNode {
-    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, Box<dyn Error>>;
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError>;
+
+    /// Describes the plan tree, for `EXPLAIN`.
+    fn explain(&self) -> Explain;
+
+    /// Starts the plan for a consumer that reads only `columns` of each tuple. The other columns
+    /// may be left empty or missing from the end of the tuple.
+    fn start_projected(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        columns: &[usize],
+    ) -> Result<BoxExecutor, DbError> {
+        let _ = columns;
+        self.start(bufmgr)
+    }
+
+    /// Starts the plan, returning its tuples as an [`Iterator`] that holds `bufmgr`.
+    fn iter<'a, 'b>(
+        &'a self,
+        bufmgr: &'b mut BufferPoolManager,
+    ) -> Result<TupleIter<'a, 'b>, DbError> {
+        let executor = self.start(bufmgr)?;
+        Ok(TupleIter::new(executor, bufmgr))
+    }
+}
+
+fn fmt_search_mode(search_mode: &TupleSearchMode) -> String {
+    match search_mode {
+        TupleSearchMode::Start => "start".to_string(),
+        TupleSearchMode::Key(key) => fmt_values(key),
+    }
 }
 
 pub struct SeqScan<'a> {
     pub table_meta_page_id: PageId,
     pub search_mode: TupleSearchMode<'a>,
     pub while_cond: Condition<'a>,
+    /// `while_cond` as shown by `EXPLAIN`, like `(#0 < 'd')`.
+    pub condition: &'a str,
 }
 
-impl<'a> PlanNode for SeqScan<'a> {
-    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, Box<dyn Error>> {
+impl<'a> SeqScan<'a> {
+    fn start_columns(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        columns: Option<Vec<usize>>,
+    ) -> Result<BoxExecutor, DbError> {
         let btree = BTree::new(self.table_meta_page_id);
         let table_iter = btree.search(bufmgr, self.search_mode.encode())?;
-        Ok(Box::new(ExecSeqScan::new(table_iter, self.while_cond)))
+        Ok(Box::new(ExecSeqScan::new(
+            table_iter,
+            self.while_cond,
+            columns,
+        )))
+    }
+}
+
+impl<'a> PlanNode for SeqScan<'a> {
+    fn explain(&self) -> Explain {
+        Explain::new("SeqScan")
+            .property("table", self.table_meta_page_id.0)
+            .property("search", fmt_search_mode(&self.search_mode))
+            .property("condition", self.condition)
+    }
+
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        self.start_columns(bufmgr, None)
+    }
+
+    fn start_projected(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        columns: &[usize],
+    ) -> Result<BoxExecutor, DbError> {
+        self.start_columns(bufmgr, Some(columns.to_vec()))
+    }
+}
+
+/// Scans every row of a [`HeapTable`](crate::table::HeapTable), which has no key to search by.
+pub struct HeapScan {
+    pub heap_meta_page_id: PageId,
+}
+
+impl HeapScan {
+    fn start_columns(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        columns: Option<Vec<usize>>,
+    ) -> Result<BoxExecutor, DbError> {
+        let heap_iter = HeapFile::new(self.heap_meta_page_id).scan(bufmgr)?;
+        Ok(Box::new(ExecHeapScan::new(heap_iter, columns)))
+    }
+}
+
+impl PlanNode for HeapScan {
+    fn explain(&self) -> Explain {
+        Explain::new("HeapScan").property("heap", self.heap_meta_page_id.0)
+    }
+
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        self.start_columns(bufmgr, None)
+    }
+
+    fn start_projected(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        columns: &[usize],
+    ) -> Result<BoxExecutor, DbError> {
+        self.start_columns(bufmgr, Some(columns.to_vec()))
     }
 }
 
 pub struct Filter<'a> {
     pub inner_plan: &'a dyn PlanNode,
-    pub cond: Condition<'a>,
+    pub cond: Predicate<'a>,
+    /// `cond` as shown by `EXPLAIN`.
+    pub condition: &'a str,
 }
 
 impl<'a> PlanNode for Filter<'a> {
-    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, Box<dyn Error>> {
+    fn explain(&self) -> Explain {
+        Explain::new("Filter")
+            .property("condition", self.condition)
+            .child(self.inner_plan.explain())
+    }
+
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
         let inner_executor = self.inner_plan.start(bufmgr)?;
         Ok(Box::new(ExecFilter::new(inner_executor, &self.cond)))
     }
@@ -39,20 +155,1224 @@ impl<'a> PlanNode for Filter<'a> {
 
 pub struct IndexScan<'a> {
     pub table_meta_page_id: PageId,
+    /// The number of primary key columns of the table.
+    pub num_key_elems: usize,
     pub index_meta_page_id: PageId,
     pub search_mode: TupleSearchMode<'a>,
     pub while_cond: Condition<'a>,
+    /// `while_cond` as shown by `EXPLAIN`.
+    pub condition: &'a str,
 }
 
-impl<'a> PlanNode for IndexScan<'a> {
-    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, Box<dyn Error>> {
+impl<'a> IndexScan<'a> {
+    fn start_columns(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        columns: Option<Vec<usize>>,
+    ) -> Result<BoxExecutor, DbError> {
         let table_btree = BTree::new(self.table_meta_page_id);
         let index_btree = BTree::new(self.index_meta_page_id);
         let index_iter = index_btree.search(bufmgr, self.search_mode.encode())?;
         Ok(Box::new(ExecIndexScan::new(
             table_btree,
+            self.num_key_elems,
             index_iter,
             self.while_cond,
+            columns,
+        )))
+    }
+}
+
+impl<'a> PlanNode for IndexScan<'a> {
+    fn explain(&self) -> Explain {
+        Explain::new("IndexScan")
+            .property("table", self.table_meta_page_id.0)
+            .property("index", self.index_meta_page_id.0)
+            .property("search", fmt_search_mode(&self.search_mode))
+            .property("condition", self.condition)
+    }
+
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        self.start_columns(bufmgr, None)
+    }
+
+    fn start_projected(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        columns: &[usize],
+    ) -> Result<BoxExecutor, DbError> {
+        self.start_columns(bufmgr, Some(columns.to_vec()))
+    }
+}
+
+/// Scans a unique index holding every column the consumer reads, the `skey` and `include`
+/// columns of the index and the primary key, without touching the table.
+pub struct IndexOnlyScan<'a> {
+    pub index_meta_page_id: PageId,
+    /// The number of primary key columns of the table.
+    pub num_key_elems: usize,
+    pub skey: &'a [usize],
+    pub include: &'a [usize],
+    pub search_mode: TupleSearchMode<'a>,
+    pub while_cond: Condition<'a>,
+    /// `while_cond` as shown by `EXPLAIN`.
+    pub condition: &'a str,
+}
+
+impl<'a> PlanNode for IndexOnlyScan<'a> {
+    fn explain(&self) -> Explain {
+        Explain::new("IndexOnlyScan")
+            .property("index", self.index_meta_page_id.0)
+            .property("search", fmt_search_mode(&self.search_mode))
+            .property("condition", self.condition)
+    }
+
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        let index_btree = BTree::new(self.index_meta_page_id);
+        let index_iter = index_btree.search(bufmgr, self.search_mode.encode())?;
+        Ok(Box::new(ExecIndexOnlyScan::new(
+            index_iter,
+            self.num_key_elems,
+            self.skey,
+            self.include,
+            self.while_cond,
+        )))
+    }
+}
+
+/// Computes each output column from an expression over the tuples of the inner plan.
+pub struct Project<'a> {
+    pub inner_plan: &'a dyn PlanNode,
+    pub exprs: &'a [Expr],
+}
+
+impl<'a> PlanNode for Project<'a> {
+    fn explain(&self) -> Explain {
+        Explain::new("Project")
+            .property("exprs", fmt_list(self.exprs))
+            .child(self.inner_plan.explain())
+    }
+
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        let mut columns = vec![];
+        for expr in self.exprs {
+            expr.columns(&mut columns);
+        }
+        let inner_executor = self.inner_plan.start_projected(bufmgr, &columns)?;
+        Ok(Box::new(ExecProject::new(inner_executor, self.exprs)))
+    }
+}
+
+/// Sorts the tuples of the inner plan by `keys`. Tuples beyond `memory_budget` bytes are
+/// sorted in runs spilled to temporary files and merged.
+pub struct Sort<'a> {
+    pub inner_plan: &'a dyn PlanNode,
+    pub keys: &'a [SortKey],
+    pub memory_budget: usize,
+}
+
+impl<'a> PlanNode for Sort<'a> {
+    fn explain(&self) -> Explain {
+        Explain::new("Sort")
+            .property("keys", fmt_list(self.keys))
+            .property("memory_budget", self.memory_budget)
+            .child(self.inner_plan.explain())
+    }
+
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        let inner_executor = self.inner_plan.start(bufmgr)?;
+        Ok(Box::new(ExecSort::new(
+            inner_executor,
+            self.keys,
+            self.memory_budget,
+        )))
+    }
+}
+
+/// Skips the first `offset` tuples of the inner plan and returns at most `count` of the rest.
+/// The inner plan is not pulled any further once `count` tuples are returned.
+pub struct Limit<'a> {
+    pub inner_plan: &'a dyn PlanNode,
+    pub offset: usize,
+    pub count: usize,
+}
+
+impl<'a> PlanNode for Limit<'a> {
+    fn explain(&self) -> Explain {
+        Explain::new("Limit")
+            .property("offset", self.offset)
+            .property("count", self.count)
+            .child(self.inner_plan.explain())
+    }
+
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        let inner_executor = self.inner_plan.start(bufmgr)?;
+        Ok(Box::new(ExecLimit::new(
+            inner_executor,
+            self.offset,
+            self.count,
+        )))
+    }
+
+    fn start_projected(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        columns: &[usize],
+    ) -> Result<BoxExecutor, DbError> {
+        let inner_executor = self.inner_plan.start_projected(bufmgr, columns)?;
+        Ok(Box::new(ExecLimit::new(
+            inner_executor,
+            self.offset,
+            self.count,
+        )))
+    }
+}
+
+/// Equivalent to a [`Limit`] over a [`Sort`], but keeps only `offset + count` tuples in a heap
+/// instead of sorting all of them.
+pub struct TopN<'a> {
+    pub inner_plan: &'a dyn PlanNode,
+    pub keys: &'a [SortKey],
+    pub offset: usize,
+    pub count: usize,
+}
+
+impl<'a> PlanNode for TopN<'a> {
+    fn explain(&self) -> Explain {
+        Explain::new("TopN")
+            .property("keys", fmt_list(self.keys))
+            .property("offset", self.offset)
+            .property("count", self.count)
+            .child(self.inner_plan.explain())
+    }
+
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        let inner_executor = self.inner_plan.start(bufmgr)?;
+        Ok(Box::new(ExecTopN::new(
+            inner_executor,
+            self.keys,
+            self.offset,
+            self.count,
+        )))
+    }
+}
+
+/// Groups the tuples of the inner plan by `group_by` in a hash table and computes `aggregates`
+/// for each group. Output tuples are the group key followed by the aggregate results.
+pub struct HashAggregate<'a> {
+    pub inner_plan: &'a dyn PlanNode,
+    pub group_by: &'a [Expr],
+    pub aggregates: &'a [AggExpr],
+}
+
+impl<'a> PlanNode for HashAggregate<'a> {
+    fn explain(&self) -> Explain {
+        Explain::new("HashAggregate")
+            .property("group_by", fmt_list(self.group_by))
+            .property("aggregates", fmt_list(self.aggregates))
+            .child(self.inner_plan.explain())
+    }
+
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        let columns = aggregate::columns(self.group_by, self.aggregates);
+        let inner_executor = self.inner_plan.start_projected(bufmgr, &columns)?;
+        Ok(Box::new(ExecHashAggregate::new(
+            inner_executor,
+            self.group_by,
+            self.aggregates,
+        )))
+    }
+}
+
+/// Like [`HashAggregate`], but for an inner plan already sorted by `group_by`, such as a
+/// [`SeqScan`] grouped by a primary key prefix or a [`Sort`]. Groups are computed one at a time
+/// without hashing.
+pub struct StreamAggregate<'a> {
+    pub inner_plan: &'a dyn PlanNode,
+    pub group_by: &'a [Expr],
+    pub aggregates: &'a [AggExpr],
+}
+
+impl<'a> PlanNode for StreamAggregate<'a> {
+    fn explain(&self) -> Explain {
+        Explain::new("StreamAggregate")
+            .property("group_by", fmt_list(self.group_by))
+            .property("aggregates", fmt_list(self.aggregates))
+            .child(self.inner_plan.explain())
+    }
+
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        let columns = aggregate::columns(self.group_by, self.aggregates);
+        let inner_executor = self.inner_plan.start_projected(bufmgr, &columns)?;
+        Ok(Box::new(ExecStreamAggregate::new(
+            inner_executor,
+            self.group_by,
+            self.aggregates,
+        )))
+    }
+}
+
+/// Joins each tuple of the left plan with every tuple of the right plan for which `cond` holds.
+/// `cond` is evaluated over the left columns followed by the right columns, and the right plan
+/// is started again for each left tuple.
+pub struct NestedLoopJoin<'a> {
+    pub left_plan: &'a dyn PlanNode,
+    pub right_plan: &'a dyn PlanNode,
+    pub cond: &'a Expr,
+    pub join_type: JoinType,
+}
+
+impl<'a> PlanNode for NestedLoopJoin<'a> {
+    fn explain(&self) -> Explain {
+        Explain::new("NestedLoopJoin")
+            .property("type", self.join_type)
+            .property("cond", self.cond)
+            .child(self.left_plan.explain())
+            .child(self.right_plan.explain())
+    }
+
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        let left_executor = self.left_plan.start(bufmgr)?;
+        Ok(Box::new(ExecNestedLoopJoin::new(
+            left_executor,
+            self.right_plan,
+            self.cond,
+            self.join_type,
+        )))
+    }
+}
+
+/// Joins the tuples of the left and right plans whose `left_keys` and `right_keys` are equal.
+/// The right plan is read into a hash table before the first tuple is returned, and the output
+/// is in the order of the left plan.
+pub struct HashJoin<'a> {
+    pub left_plan: &'a dyn PlanNode,
+    pub right_plan: &'a dyn PlanNode,
+    pub left_keys: &'a [Expr],
+    pub right_keys: &'a [Expr],
+    pub join_type: JoinType,
+}
+
+impl<'a> PlanNode for HashJoin<'a> {
+    fn explain(&self) -> Explain {
+        Explain::new("HashJoin")
+            .property("type", self.join_type)
+            .property("left_keys", fmt_list(self.left_keys))
+            .property("right_keys", fmt_list(self.right_keys))
+            .child(self.left_plan.explain())
+            .child(self.right_plan.explain())
+    }
+
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        let left_executor = self.left_plan.start(bufmgr)?;
+        let right_executor = self.right_plan.start(bufmgr)?;
+        Ok(Box::new(ExecHashJoin::new(
+            left_executor,
+            right_executor,
+            self.left_keys,
+            self.right_keys,
+            self.join_type,
+        )))
+    }
+}
+
+/// Like [`HashJoin`], but for left and right plans both sorted in ascending order of their keys,
+/// such as scans of tables clustered by them or [`Sort`]s. Only one group of right tuples with
+/// equal keys is kept in memory at a time.
+pub struct MergeJoin<'a> {
+    pub left_plan: &'a dyn PlanNode,
+    pub right_plan: &'a dyn PlanNode,
+    pub left_keys: &'a [Expr],
+    pub right_keys: &'a [Expr],
+    pub join_type: JoinType,
+}
+
+impl<'a> PlanNode for MergeJoin<'a> {
+    fn explain(&self) -> Explain {
+        Explain::new("MergeJoin")
+            .property("type", self.join_type)
+            .property("left_keys", fmt_list(self.left_keys))
+            .property("right_keys", fmt_list(self.right_keys))
+            .child(self.left_plan.explain())
+            .child(self.right_plan.explain())
+    }
+
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        let left_executor = self.left_plan.start(bufmgr)?;
+        let right_executor = self.right_plan.start(bufmgr)?;
+        Ok(Box::new(ExecMergeJoin::new(
+            left_executor,
+            right_executor,
+            self.left_keys,
+            self.right_keys,
+            self.join_type,
+        )))
+    }
+}
+
+/// Joins each tuple of the left plan with the row of a table found by looking up `left_keys`,
+/// either in a unique index of the table or, if `index_meta_page_id` is `None`, in the table
+/// itself by primary key.
+pub struct IndexNestedLoopJoin<'a> {
+    pub left_plan: &'a dyn PlanNode,
+    pub table_meta_page_id: PageId,
+    /// The number of primary key columns of the table.
+    pub num_key_elems: usize,
+    pub index_meta_page_id: Option<PageId>,
+    pub left_keys: &'a [Expr],
+    pub join_type: JoinType,
+}
+
+impl<'a> PlanNode for IndexNestedLoopJoin<'a> {
+    fn explain(&self) -> Explain {
+        let explain = Explain::new("IndexNestedLoopJoin")
+            .property("type", self.join_type)
+            .property("table", self.table_meta_page_id.0);
+        let explain = match self.index_meta_page_id {
+            Some(index_meta_page_id) => explain.property("index", index_meta_page_id.0),
+            None => explain,
+        };
+        explain
+            .property("left_keys", fmt_list(self.left_keys))
+            .child(self.left_plan.explain())
+    }
+
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        let left_executor = self.left_plan.start(bufmgr)?;
+        Ok(Box::new(ExecIndexNestedLoopJoin::new(
+            left_executor,
+            BTree::new(self.table_meta_page_id),
+            self.num_key_elems,
+            self.index_meta_page_id.map(BTree::new),
+            self.left_keys,
+            self.join_type,
         )))
     }
 }
+
+/// Returns the given tuples, such as the rows of an `INSERT ... VALUES`.
+pub struct Values<'a> {
+    pub rows: &'a [Tuple],
+}
+
+impl<'a> PlanNode for Values<'a> {
+    fn explain(&self) -> Explain {
+        Explain::new("Values").property("rows", self.rows.len())
+    }
+
+    fn start(&self, _: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        Ok(Box::new(ExecValues::new(self.rows)))
+    }
+}
+
+/// Inserts the tuples of the inner plan into `table` and returns the number of rows inserted.
+pub struct Insert<'a> {
+    pub table: &'a Table,
+    pub inner_plan: &'a dyn PlanNode,
+}
+
+impl<'a> PlanNode for Insert<'a> {
+    fn explain(&self) -> Explain {
+        Explain::new("Insert")
+            .property("table", self.table.meta_page_id.0)
+            .child(self.inner_plan.explain())
+    }
+
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        let inner_executor = self.inner_plan.start(bufmgr)?;
+        Ok(Box::new(ExecInsert::new(self.table, inner_executor)))
+    }
+}
+
+/// Replaces the rows of `table` returned by the inner plan with the values of `exprs` over them,
+/// and returns the number of rows updated.
+pub struct Update<'a> {
+    pub table: &'a Table,
+    pub inner_plan: &'a dyn PlanNode,
+    pub exprs: &'a [Expr],
+}
+
+impl<'a> PlanNode for Update<'a> {
+    fn explain(&self) -> Explain {
+        Explain::new("Update")
+            .property("table", self.table.meta_page_id.0)
+            .property("exprs", fmt_list(self.exprs))
+            .child(self.inner_plan.explain())
+    }
+
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        let inner_executor = self.inner_plan.start(bufmgr)?;
+        Ok(Box::new(ExecUpdate::new(
+            self.table,
+            inner_executor,
+            self.exprs,
+        )))
+    }
+}
+
+/// Deletes the rows of `table` returned by the inner plan and returns the number of rows
+/// deleted.
+pub struct Delete<'a> {
+    pub table: &'a Table,
+    pub inner_plan: &'a dyn PlanNode,
+}
+
+impl<'a> PlanNode for Delete<'a> {
+    fn explain(&self) -> Explain {
+        Explain::new("Delete")
+            .property("table", self.table.meta_page_id.0)
+            .child(self.inner_plan.explain())
+    }
+
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        let inner_executor = self.inner_plan.start(bufmgr)?;
+        Ok(Box::new(ExecDelete::new(self.table, inner_executor)))
+    }
+}
+
+/// Runs the inner plan unchanged while counting the times it is started, the tuples it returns
+/// and the pages it fetches, for `EXPLAIN ANALYZE`.
+pub struct Analyze<'a> {
+    pub inner_plan: &'a dyn PlanNode,
+    pub actual: Cell<ActualStats>,
+}
+
+impl<'a> Analyze<'a> {
+    pub fn new(inner_plan: &'a dyn PlanNode) -> Self {
+        Self {
+            inner_plan,
+            actual: Cell::default(),
+        }
+    }
+}
+
+impl<'a> PlanNode for Analyze<'a> {
+    fn start(&self, bufmgr: &mut BufferPoolManager) -> Result<BoxExecutor, DbError> {
+        explain::start_analyzed(&self.actual, bufmgr, |bufmgr| self.inner_plan.start(bufmgr))
+    }
+
+    fn start_projected(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        columns: &[usize],
+    ) -> Result<BoxExecutor, DbError> {
+        explain::start_analyzed(&self.actual, bufmgr, |bufmgr| {
+            self.inner_plan.start_projected(bufmgr, columns)
+        })
+    }
+
+    fn explain(&self) -> Explain {
+        Explain {
+            actual: Some(self.actual.get()),
+            ..self.inner_plan.explain()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::BufferPool;
+    use crate::disk::DiskManager;
+    use crate::query::aggregate::AggFunc;
+    use crate::query::expr::BinaryOp;
+    use crate::query::Tuple;
+    use crate::table::{HeapTable, Table};
+    use crate::tuple;
+    use tempfile::tempfile;
+
+    fn create_table(bufmgr: &mut BufferPoolManager) -> Table {
+        let mut table = Table {
+            meta_page_id: PageId::INVALID_PAGE_ID,
+            num_key_elems: 1,
+            unique_index: vec![],
+        };
+        table.create(bufmgr).unwrap();
+        table
+            .insert(bufmgr, &[b"a", b"Charlie", b"MUNGER"])
+            .unwrap();
+        table.insert(bufmgr, &[b"b", b"Brian", b"LEE"]).unwrap();
+        table.insert(bufmgr, &[b"c", b"Alice", b"SMITH"]).unwrap();
+        table.insert(bufmgr, &[b"d", b"John", b"BAKERY"]).unwrap();
+        table
+    }
+
+    #[test]
+    fn test_project() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let table = create_table(&mut bufmgr);
+
+        let scan = SeqScan {
+            table_meta_page_id: table.meta_page_id,
+            search_mode: TupleSearchMode::Key(&[b"b"]),
+            while_cond: &|pk| pk[0].as_slice() < b"d",
+            condition: "(#0 < 'd')",
+        };
+        let exprs = [
+            Expr::Column(2),
+            Expr::binary(
+                BinaryOp::Concat,
+                Expr::Column(1),
+                Expr::Literal(b"!".to_vec()),
+            ),
+        ];
+        let project = Project {
+            inner_plan: &scan,
+            exprs: &exprs,
+        };
+        let mut exec = project.start(&mut bufmgr).unwrap();
+        assert_eq!(
+            Some(vec![b"LEE".to_vec(), b"Brian!".to_vec()]),
+            exec.next(&mut bufmgr).unwrap()
+        );
+        assert_eq!(
+            Some(vec![b"SMITH".to_vec(), b"Alice!".to_vec()]),
+            exec.next(&mut bufmgr).unwrap()
+        );
+        assert_eq!(None, exec.next(&mut bufmgr).unwrap());
+
+        // Columns after the last one needed are not decoded at all.
+        let mut exec = scan.start_projected(&mut bufmgr, &[0]).unwrap();
+        assert_eq!(Some(vec![b"b".to_vec()]), exec.next(&mut bufmgr).unwrap());
+
+        let last_names: Vec<Vec<u8>> = project
+            .iter(&mut bufmgr)
+            .unwrap()
+            .map(|tuple| tuple.unwrap().remove(0))
+            .collect();
+        assert_eq!(vec![b"LEE".to_vec(), b"SMITH".to_vec()], last_names);
+
+        // As an iterator, a plan ends after the first error.
+        let exprs = [Expr::Param(0)];
+        let unbound = Project {
+            inner_plan: &scan,
+            exprs: &exprs,
+        };
+        let results: Vec<_> = unbound.iter(&mut bufmgr).unwrap().collect();
+        assert_eq!(1, results.len());
+        assert!(results[0].is_err());
+    }
+
+    #[test]
+    fn test_heap_scan() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let mut table = HeapTable {
+            meta_page_id: PageId::INVALID_PAGE_ID,
+            unique_index: vec![],
+        };
+        table.create(&mut bufmgr).unwrap();
+        table.insert(&mut bufmgr, &[b"GET", b"/"]).unwrap();
+        let rid = table.insert(&mut bufmgr, &[b"POST", b"/login"]).unwrap();
+        table.insert(&mut bufmgr, &[b"GET", b"/home"]).unwrap();
+        table.delete(&mut bufmgr, rid).unwrap();
+
+        let scan = HeapScan {
+            heap_meta_page_id: table.meta_page_id,
+        };
+        let filter = Filter {
+            inner_plan: &scan,
+            cond: &|record| Ok(record[0].as_slice() == b"GET"),
+            condition: "(#0 = 'GET')",
+        };
+        let paths: Vec<Vec<u8>> = filter
+            .iter(&mut bufmgr)
+            .unwrap()
+            .map(|tuple| tuple.unwrap().remove(1))
+            .collect();
+        assert_eq!(vec![b"/".to_vec(), b"/home".to_vec()], paths);
+        assert!(scan
+            .explain()
+            .to_text()
+            .starts_with(&format!("HeapScan heap={}", table.meta_page_id.0)));
+    }
+
+    #[test]
+    fn test_index_only_scan() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let mut table = create_table(&mut bufmgr);
+        table
+            .add_unique_index_including(&mut bufmgr, "users_last_name_key", vec![2], vec![1])
+            .unwrap();
+        let index = &table.unique_index[0];
+
+        let index_scan = IndexScan {
+            table_meta_page_id: table.meta_page_id,
+            num_key_elems: table.num_key_elems,
+            index_meta_page_id: index.meta_page_id,
+            search_mode: TupleSearchMode::Key(&[b"LEE"]),
+            while_cond: &|skey| skey[0].as_slice() < b"N",
+            condition: "(#0 < 'N')",
+        };
+        let index_only_scan = IndexOnlyScan {
+            index_meta_page_id: index.meta_page_id,
+            num_key_elems: table.num_key_elems,
+            skey: &index.skey,
+            include: &index.include,
+            search_mode: TupleSearchMode::Key(&[b"LEE"]),
+            while_cond: &|skey| skey[0].as_slice() < b"N",
+            condition: "(#0 < 'N')",
+        };
+        let mut num_fetches = vec![];
+        for plan in [&index_scan as &dyn PlanNode, &index_only_scan] {
+            let start = bufmgr.num_fetches();
+            let mut exec = plan.start(&mut bufmgr).unwrap();
+            assert_eq!(
+                Some(vec![b"b".to_vec(), b"Brian".to_vec(), b"LEE".to_vec()]),
+                exec.next(&mut bufmgr).unwrap()
+            );
+            assert_eq!(
+                Some(vec![b"a".to_vec(), b"Charlie".to_vec(), b"MUNGER".to_vec()]),
+                exec.next(&mut bufmgr).unwrap()
+            );
+            assert_eq!(None, exec.next(&mut bufmgr).unwrap());
+            num_fetches.push(bufmgr.num_fetches() - start);
+        }
+        // The index-only scan never reads the table.
+        assert!(num_fetches[1] < num_fetches[0]);
+
+        // An index entry whose row is gone is reported instead of returning the next row.
+        let mut pkey = vec![];
+        tuple::encode([b"b"].iter(), &mut pkey);
+        crate::btree::BTree::new(table.meta_page_id)
+            .delete(&mut bufmgr, &pkey)
+            .unwrap();
+        assert!(matches!(
+            index_scan.start(&mut bufmgr).unwrap().next(&mut bufmgr),
+            Err(DbError::Corrupted(_))
+        ));
+
+        // So is an entry too short to hold the primary key.
+        let mut skey = vec![];
+        tuple::encode([b"AAA"].iter(), &mut skey);
+        crate::btree::BTree::new(index.meta_page_id)
+            .insert(&mut bufmgr, &skey, &[])
+            .unwrap();
+        let index_only_scan = IndexOnlyScan {
+            search_mode: TupleSearchMode::Start,
+            ..index_only_scan
+        };
+        assert!(matches!(
+            index_only_scan
+                .start(&mut bufmgr)
+                .unwrap()
+                .next(&mut bufmgr),
+            Err(DbError::Corrupted(_))
+        ));
+    }
+
+    #[test]
+    fn test_insert_update_delete() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let mut table = create_table(&mut bufmgr);
+        table
+            .add_unique_index(&mut bufmgr, "users_last_name_key", vec![2])
+            .unwrap();
+        let row_count = |n| Some(vec![tuple::encode_int(n).to_vec()]);
+
+        let rows = [vec![b"e".to_vec(), b"Eve".to_vec(), b"ADAMS".to_vec()]];
+        let values = Values { rows: &rows };
+        let insert = Insert {
+            table: &table,
+            inner_plan: &values,
+        };
+        let mut exec = insert.start(&mut bufmgr).unwrap();
+        assert_eq!(row_count(1), exec.next(&mut bufmgr).unwrap());
+        assert_eq!(None, exec.next(&mut bufmgr).unwrap());
+
+        let scan = SeqScan {
+            table_meta_page_id: table.meta_page_id,
+            search_mode: TupleSearchMode::Start,
+            while_cond: &|_| true,
+            condition: "true",
+        };
+        let brian = Filter {
+            inner_plan: &scan,
+            cond: &|record| Ok(record[1].as_slice() == b"Brian"),
+            condition: "(#1 = 'Brian')",
+        };
+        let exprs = [
+            Expr::Column(0),
+            Expr::Literal(b"Bryan".to_vec()),
+            Expr::Column(2),
+        ];
+        let update = Update {
+            table: &table,
+            inner_plan: &brian,
+            exprs: &exprs,
+        };
+        let mut exec = update.start(&mut bufmgr).unwrap();
+        assert_eq!(row_count(1), exec.next(&mut bufmgr).unwrap());
+
+        // The filter reads the rows while they are deleted from the same tree.
+        let before_c = Filter {
+            inner_plan: &scan,
+            cond: &|record| Ok(record[0].as_slice() < b"c"),
+            condition: "(#0 < 'c')",
+        };
+        let delete = Delete {
+            table: &table,
+            inner_plan: &before_c,
+        };
+        let mut exec = delete.start(&mut bufmgr).unwrap();
+        assert_eq!(row_count(2), exec.next(&mut bufmgr).unwrap());
+
+        let mut exec = scan.start(&mut bufmgr).unwrap();
+        for (id, first_name) in [("c", "Alice"), ("d", "John"), ("e", "Eve")] {
+            let record = exec.next(&mut bufmgr).unwrap().unwrap();
+            assert_eq!(id.as_bytes(), record[0].as_slice());
+            assert_eq!(first_name.as_bytes(), record[1].as_slice());
+        }
+        assert_eq!(None, exec.next(&mut bufmgr).unwrap());
+
+        // The index entries of the deleted rows are gone too.
+        let index = &table.unique_index[0];
+        let munger: [&[u8]; 3] = [b"a", b"Charlie", b"MUNGER"];
+        assert!(!index.contains(&mut bufmgr, &munger).unwrap());
+
+        // INSERT ... SELECT into another table.
+        let mut copy = Table {
+            meta_page_id: PageId::INVALID_PAGE_ID,
+            num_key_elems: 1,
+            unique_index: vec![],
+        };
+        copy.create(&mut bufmgr).unwrap();
+        let insert = Insert {
+            table: &copy,
+            inner_plan: &scan,
+        };
+        let mut exec = insert.start(&mut bufmgr).unwrap();
+        assert_eq!(row_count(3), exec.next(&mut bufmgr).unwrap());
+    }
+
+    #[test]
+    fn test_sort() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let table = create_table(&mut bufmgr);
+
+        let scan = SeqScan {
+            table_meta_page_id: table.meta_page_id,
+            search_mode: TupleSearchMode::Start,
+            while_cond: &|_| true,
+            condition: "true",
+        };
+        let keys = [SortKey::asc(Expr::Column(1))];
+        let sort = Sort {
+            inner_plan: &scan,
+            keys: &keys,
+            memory_budget: 0,
+        };
+        let mut exec = sort.start(&mut bufmgr).unwrap();
+        for first_name in ["Alice", "Brian", "Charlie", "John"] {
+            let record = exec.next(&mut bufmgr).unwrap().unwrap();
+            assert_eq!(first_name.as_bytes(), record[1].as_slice());
+        }
+        assert_eq!(None, exec.next(&mut bufmgr).unwrap());
+    }
+
+    #[test]
+    fn test_limit_and_top_n() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let table = create_table(&mut bufmgr);
+
+        let scan = SeqScan {
+            table_meta_page_id: table.meta_page_id,
+            search_mode: TupleSearchMode::Start,
+            while_cond: &|_| true,
+            condition: "true",
+        };
+        let limit = Limit {
+            inner_plan: &scan,
+            offset: 1,
+            count: 2,
+        };
+        let mut exec = limit.start(&mut bufmgr).unwrap();
+        assert_eq!(b"b", exec.next(&mut bufmgr).unwrap().unwrap()[0].as_slice());
+        assert_eq!(b"c", exec.next(&mut bufmgr).unwrap().unwrap()[0].as_slice());
+        assert_eq!(None, exec.next(&mut bufmgr).unwrap());
+
+        let keys = [SortKey::desc(Expr::Column(2))];
+        let top_n = TopN {
+            inner_plan: &scan,
+            keys: &keys,
+            offset: 1,
+            count: 2,
+        };
+        let mut exec = top_n.start(&mut bufmgr).unwrap();
+        assert_eq!(
+            b"MUNGER",
+            exec.next(&mut bufmgr).unwrap().unwrap()[2].as_slice()
+        );
+        assert_eq!(
+            b"LEE",
+            exec.next(&mut bufmgr).unwrap().unwrap()[2].as_slice()
+        );
+        assert_eq!(None, exec.next(&mut bufmgr).unwrap());
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+
+        let mut table = Table {
+            meta_page_id: PageId::INVALID_PAGE_ID,
+            num_key_elems: 2,
+            unique_index: vec![],
+        };
+        table.create(&mut bufmgr).unwrap();
+        for (customer, order, amount) in [("a", 1, 10), ("a", 2, 25), ("b", 3, 7), ("c", 4, 1)] {
+            let order = tuple::encode_int(order);
+            let amount = tuple::encode_int(amount);
+            table
+                .insert(&mut bufmgr, &[customer.as_bytes(), &order, &amount])
+                .unwrap();
+        }
+
+        let scan = SeqScan {
+            table_meta_page_id: table.meta_page_id,
+            search_mode: TupleSearchMode::Start,
+            while_cond: &|_| true,
+            condition: "true",
+        };
+        let group_by = [Expr::Column(0)];
+        let aggregates = [
+            AggExpr::count_star(),
+            AggExpr::new(AggFunc::Sum, Expr::Column(2)),
+            AggExpr::new(AggFunc::Max, Expr::Column(2)),
+            AggExpr::new(AggFunc::Avg, Expr::Column(2)),
+        ];
+        let expected = |customer: &str, count, sum, max, avg| {
+            let mut tuple = vec![customer.as_bytes().to_vec()];
+            for n in [count, sum, max, avg] {
+                tuple.push(tuple::encode_int(n).to_vec());
+            }
+            tuple
+        };
+
+        let hash_aggregate = HashAggregate {
+            inner_plan: &scan,
+            group_by: &group_by,
+            aggregates: &aggregates,
+        };
+        let stream_aggregate = StreamAggregate {
+            inner_plan: &scan,
+            group_by: &group_by,
+            aggregates: &aggregates,
+        };
+        for plan in [&hash_aggregate as &dyn PlanNode, &stream_aggregate] {
+            let mut exec = plan.start(&mut bufmgr).unwrap();
+            assert_eq!(
+                Some(expected("a", 2, 35, 25, 17)),
+                exec.next(&mut bufmgr).unwrap()
+            );
+            assert_eq!(
+                Some(expected("b", 1, 7, 7, 7)),
+                exec.next(&mut bufmgr).unwrap()
+            );
+            assert_eq!(
+                Some(expected("c", 1, 1, 1, 1)),
+                exec.next(&mut bufmgr).unwrap()
+            );
+            assert_eq!(None, exec.next(&mut bufmgr).unwrap());
+        }
+
+        // Without grouping, an empty input still produces one tuple.
+        let empty = SeqScan {
+            table_meta_page_id: table.meta_page_id,
+            search_mode: TupleSearchMode::Start,
+            while_cond: &|_| false,
+            condition: "false",
+        };
+        let aggregates = [AggExpr::count_star()];
+        let count = StreamAggregate {
+            inner_plan: &empty,
+            group_by: &[],
+            aggregates: &aggregates,
+        };
+        let mut exec = count.start(&mut bufmgr).unwrap();
+        assert_eq!(
+            Some(vec![tuple::encode_int(0).to_vec()]),
+            exec.next(&mut bufmgr).unwrap()
+        );
+        assert_eq!(None, exec.next(&mut bufmgr).unwrap());
+    }
+
+    #[test]
+    fn test_join() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let mut customers = create_table(&mut bufmgr);
+        customers
+            .add_unique_index(&mut bufmgr, "customers_name_key", vec![1])
+            .unwrap();
+
+        let mut orders = Table {
+            meta_page_id: PageId::INVALID_PAGE_ID,
+            num_key_elems: 1,
+            unique_index: vec![],
+        };
+        orders.create(&mut bufmgr).unwrap();
+        for (order, customer, name) in [
+            ("1", "c", "Alice"),
+            ("2", "a", "Charlie"),
+            ("3", "z", "Zoe"),
+            ("4", "c", "Alice"),
+        ] {
+            orders
+                .insert(
+                    &mut bufmgr,
+                    &[order.as_bytes(), customer.as_bytes(), name.as_bytes()],
+                )
+                .unwrap();
+        }
+
+        let scan_orders = SeqScan {
+            table_meta_page_id: orders.meta_page_id,
+            search_mode: TupleSearchMode::Start,
+            while_cond: &|_| true,
+            condition: "true",
+        };
+        let scan_customers = SeqScan {
+            table_meta_page_id: customers.meta_page_id,
+            search_mode: TupleSearchMode::Start,
+            while_cond: &|_| true,
+            condition: "true",
+        };
+        let order_keys = [Expr::Column(1)];
+        let name_keys = [Expr::Column(2)];
+        let customer_keys = [Expr::Column(0)];
+        let sort_keys = [SortKey::asc(Expr::Column(1))];
+        let sorted_orders = Sort {
+            inner_plan: &scan_orders,
+            keys: &sort_keys,
+            memory_budget: usize::MAX,
+        };
+        let cond = Expr::binary(BinaryOp::Eq, Expr::Column(1), Expr::Column(3));
+
+        let tuple = |columns: &[&str]| -> Tuple {
+            columns
+                .iter()
+                .map(|column| column.as_bytes().to_vec())
+                .collect()
+        };
+        let order_1 = tuple(&["1", "c", "Alice", "c", "Alice", "SMITH"]);
+        let order_2 = tuple(&["2", "a", "Charlie", "a", "Charlie", "MUNGER"]);
+        let order_3 = tuple(&["3", "z", "Zoe", "", "", ""]);
+        let order_4 = tuple(&["4", "c", "Alice", "c", "Alice", "SMITH"]);
+
+        for join_type in [
+            JoinType::Inner,
+            JoinType::LeftOuter {
+                right_num_columns: 3,
+            },
+        ] {
+            let nested_loop = NestedLoopJoin {
+                left_plan: &scan_orders,
+                right_plan: &scan_customers,
+                cond: &cond,
+                join_type,
+            };
+            let hash = HashJoin {
+                left_plan: &scan_orders,
+                right_plan: &scan_customers,
+                left_keys: &order_keys,
+                right_keys: &customer_keys,
+                join_type,
+            };
+            let merge = MergeJoin {
+                left_plan: &sorted_orders,
+                right_plan: &scan_customers,
+                left_keys: &order_keys,
+                right_keys: &customer_keys,
+                join_type,
+            };
+            let primary_key_lookup = IndexNestedLoopJoin {
+                left_plan: &scan_orders,
+                table_meta_page_id: customers.meta_page_id,
+                num_key_elems: customers.num_key_elems,
+                index_meta_page_id: None,
+                left_keys: &order_keys,
+                join_type,
+            };
+            let unique_index_lookup = IndexNestedLoopJoin {
+                left_plan: &scan_orders,
+                table_meta_page_id: customers.meta_page_id,
+                num_key_elems: customers.num_key_elems,
+                index_meta_page_id: Some(customers.unique_index[0].meta_page_id),
+                left_keys: &name_keys,
+                join_type,
+            };
+
+            let outer = matches!(join_type, JoinType::LeftOuter { .. });
+            let in_order_of_orders = [&order_1, &order_2, &order_3, &order_4];
+            let in_order_of_customers = [&order_2, &order_1, &order_4, &order_3];
+            let plans: [(&dyn PlanNode, _); 5] = [
+                (&nested_loop, in_order_of_orders),
+                (&hash, in_order_of_orders),
+                (&merge, in_order_of_customers),
+                (&primary_key_lookup, in_order_of_orders),
+                (&unique_index_lookup, in_order_of_orders),
+            ];
+            for (plan, expected) in plans {
+                let expected: Vec<Tuple> = expected
+                    .into_iter()
+                    .filter(|tuple| outer || *tuple != &order_3)
+                    .cloned()
+                    .collect();
+                let mut exec = plan.start(&mut bufmgr).unwrap();
+                let mut actual = vec![];
+                while let Some(tuple) = exec.next(&mut bufmgr).unwrap() {
+                    actual.push(tuple);
+                }
+                assert_eq!(expected, actual);
+            }
+        }
+    }
+
+    #[test]
+    fn test_merge_join_unmatched_duplicates() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let customers = create_table(&mut bufmgr);
+
+        let mut orders = Table {
+            meta_page_id: PageId::INVALID_PAGE_ID,
+            num_key_elems: 1,
+            unique_index: vec![],
+        };
+        orders.create(&mut bufmgr).unwrap();
+        for (order, customer) in [("1", "z"), ("2", "z"), ("3", "a"), ("4", "z")] {
+            orders
+                .insert(&mut bufmgr, &[order.as_bytes(), customer.as_bytes()])
+                .unwrap();
+        }
+
+        let scan_orders = SeqScan {
+            table_meta_page_id: orders.meta_page_id,
+            search_mode: TupleSearchMode::Start,
+            while_cond: &|_| true,
+            condition: "true",
+        };
+        let scan_customers = SeqScan {
+            table_meta_page_id: customers.meta_page_id,
+            search_mode: TupleSearchMode::Start,
+            while_cond: &|_| true,
+            condition: "true",
+        };
+        let sort_keys = [SortKey::asc(Expr::Column(1))];
+        let sorted_orders = Sort {
+            inner_plan: &scan_orders,
+            keys: &sort_keys,
+            memory_budget: usize::MAX,
+        };
+        let order_keys = [Expr::Column(1)];
+        let customer_keys = [Expr::Column(0)];
+        let merge = MergeJoin {
+            left_plan: &sorted_orders,
+            right_plan: &scan_customers,
+            left_keys: &order_keys,
+            right_keys: &customer_keys,
+            join_type: JoinType::LeftOuter {
+                right_num_columns: 3,
+            },
+        };
+
+        let mut exec = merge.start(&mut bufmgr).unwrap();
+        let mut orders = vec![];
+        while let Some(tuple) = exec.next(&mut bufmgr).unwrap() {
+            assert_eq!(5, tuple.len());
+            orders.push(tuple[0].clone());
+        }
+        assert_eq!(
+            vec![b"3".to_vec(), b"1".to_vec(), b"2".to_vec(), b"4".to_vec()],
+            orders
+        );
+    }
+
+    #[test]
+    fn test_explain() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, pool);
+        let table = create_table(&mut bufmgr);
+
+        let scan = SeqScan {
+            table_meta_page_id: table.meta_page_id,
+            search_mode: TupleSearchMode::Key(&[b"b"]),
+            while_cond: &|_| true,
+            condition: "true",
+        };
+        let filter = Filter {
+            inner_plan: &scan,
+            cond: &|record| Ok(record[1].as_slice() == b"Brian"),
+            condition: "(#1 = 'Brian')",
+        };
+        let keys = [SortKey::desc(Expr::Column(2))];
+        let sort = Sort {
+            inner_plan: &filter,
+            keys: &keys,
+            memory_budget: 0,
+        };
+        let table_id = table.meta_page_id.0;
+        assert_eq!(
+            format!(
+                "Sort keys=(#2 DESC) memory_budget=0\n  \
+                 Filter condition=(#1 = 'Brian')\n    \
+                 SeqScan table={table_id} search=('b') condition=true\n"
+            ),
+            sort.explain().to_text()
+        );
+        let json: serde_json::Value = serde_json::from_str(&sort.explain().to_json()).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "node": "Sort",
+                "properties": {"keys": "(#2 DESC)", "memory_budget": "0"},
+                "children": [{
+                    "node": "Filter",
+                    "properties": {"condition": "(#1 = 'Brian')"},
+                    "children": [{
+                        "node": "SeqScan",
+                        "properties": {
+                            "table": table_id.to_string(),
+                            "search": "('b')",
+                            "condition": "true",
+                        },
+                    }],
+                }],
+            }),
+            json
+        );
+
+        let analyzed_scan = Analyze::new(&scan);
+        let sort = Sort {
+            inner_plan: &analyzed_scan,
+            keys: &keys,
+            memory_budget: 0,
+        };
+        let analyzed_sort = Analyze::new(&sort);
+        let explain = explain::explain_analyze(&analyzed_sort, &mut bufmgr).unwrap();
+        // The sort fetches no pages itself; its count includes those of its input.
+        assert_eq!(
+            format!(
+                "Sort keys=(#2 DESC) memory_budget=0 (actual loops=1 rows=3 pages=2)\n  \
+                 SeqScan table={table_id} search=('b') condition=true (actual loops=1 rows=3 pages=2)\n"
+            ),
+            explain.to_text()
+        );
+    }
+}
@@ -0,0 +1,446 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::fmt::{self, Display, Formatter};
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, Write};
+use std::mem::size_of;
+
+use super::expr::Expr;
+use super::{Tuple, TupleSlice};
+
+use crate::error::DbError;
+use crate::tuple;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    pub expr: Expr,
+    pub descending: bool,
+}
+
+impl Display for SortKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.descending {
+            false => write!(f, "{}", self.expr),
+            true => write!(f, "{} DESC", self.expr),
+        }
+    }
+}
+
+impl SortKey {
+    pub fn asc(expr: Expr) -> Self {
+        Self {
+            expr,
+            descending: false,
+        }
+    }
+
+    pub fn desc(expr: Expr) -> Self {
+        Self {
+            expr,
+            descending: true,
+        }
+    }
+}
+
+/// Evaluates the sort keys for `tuple`.
+pub fn eval_keys(keys: &[SortKey], tuple: TupleSlice) -> Result<Tuple, DbError> {
+    Ok(keys
+        .iter()
+        .map(|key| key.expr.eval(tuple))
+        .collect::<Result<_, _>>()?)
+}
+
+/// Compares two sets of values evaluated by [`eval_keys`].
+pub fn compare_keys(keys: &[SortKey], a: TupleSlice, b: TupleSlice) -> Ordering {
+    keys.iter()
+        .zip(a.iter().zip(b))
+        .map(|(key, (a, b))| match key.descending {
+            false => a.cmp(b),
+            true => b.cmp(a),
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+struct Entry {
+    key: Tuple,
+    tuple: Tuple,
+}
+
+impl Entry {
+    fn size(&self) -> usize {
+        let elems = self.key.iter().chain(&self.tuple);
+        size_of::<Self>()
+            + elems
+                .map(|elem| elem.len() + size_of::<Vec<u8>>())
+                .sum::<usize>()
+    }
+}
+
+/// The most runs merged at once, which bounds the temporary files open at a time.
+const MAX_FAN_IN: usize = 64;
+
+/// Sorts tuples within a memory budget, spilling sorted runs to temporary files when the
+/// budget is exceeded.
+///
+/// Runs are merged [`MAX_FAN_IN`] at a time as they pile up, into runs of the next level, so
+/// that a large input needs few passes and few open files.
+pub struct ExternalSorter<'a> {
+    keys: &'a [SortKey],
+    memory_budget: usize,
+    entries: Vec<Entry>,
+    used: usize,
+    /// The runs in input order with their levels, which never increase along the vector.
+    runs: Vec<(File, usize)>,
+    num_spilled: usize,
+}
+
+impl<'a> ExternalSorter<'a> {
+    pub fn new(keys: &'a [SortKey], memory_budget: usize) -> Self {
+        Self {
+            keys,
+            memory_budget,
+            entries: vec![],
+            used: 0,
+            runs: vec![],
+            num_spilled: 0,
+        }
+    }
+
+    pub fn push(&mut self, tuple: Tuple) -> Result<(), DbError> {
+        let entry = Entry {
+            key: eval_keys(self.keys, &tuple)?,
+            tuple,
+        };
+        self.used += entry.size();
+        self.entries.push(entry);
+        if self.used > self.memory_budget {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// The number of runs spilled to temporary files so far.
+    pub fn num_runs(&self) -> usize {
+        self.num_spilled
+    }
+
+    fn sort_entries(&mut self) {
+        let keys = self.keys;
+        self.entries
+            .sort_by(|a, b| compare_keys(keys, &a.key, &b.key));
+    }
+
+    fn spill(&mut self) -> io::Result<()> {
+        self.sort_entries();
+        let file = write_run(self.entries.drain(..).map(Ok))?;
+        self.runs.push((file, 0));
+        self.num_spilled += 1;
+        self.used = 0;
+        // Merge the last runs while there are enough of the same level.
+        while let Some(&(_, level)) = self.runs.last() {
+            let first = self.runs.len().saturating_sub(MAX_FAN_IN);
+            if self.runs.len() < MAX_FAN_IN || self.runs[first].1 != level {
+                break;
+            }
+            self.merge_tail(MAX_FAN_IN, level + 1)?;
+        }
+        Ok(())
+    }
+
+    /// Merges the last `fan_in` runs into one run of `level`, which keeps the order of equal
+    /// keys as the runs are consecutive.
+    fn merge_tail(&mut self, fan_in: usize, level: usize) -> io::Result<()> {
+        let tail = self.runs.split_off(self.runs.len() - fan_in);
+        let mut merge = Merge::new(self.keys, tail.into_iter().map(|(file, _)| file))?;
+        let file = write_run(std::iter::from_fn(|| merge.next_entry().transpose()))?;
+        self.runs.push((file, level));
+        Ok(())
+    }
+
+    /// Finishes the input and returns the tuples in sorted order.
+    pub fn finish(mut self) -> Result<Sorted<'a>, DbError> {
+        if self.runs.is_empty() {
+            self.sort_entries();
+            return Ok(Sorted(SortedInner::Memory(self.entries.into_iter())));
+        }
+        if !self.entries.is_empty() {
+            self.spill()?;
+        }
+        while self.runs.len() > MAX_FAN_IN {
+            self.merge_tail(MAX_FAN_IN, 0)?;
+        }
+        let runs = self.runs.into_iter().map(|(file, _)| file);
+        Ok(Sorted(SortedInner::Merge(Merge::new(self.keys, runs)?)))
+    }
+}
+
+/// Writes sorted entries to a new temporary file and returns it rewound.
+fn write_run(entries: impl Iterator<Item = io::Result<Entry>>) -> io::Result<File> {
+    let mut file = tempfile::tempfile()?;
+    {
+        let mut writer = BufWriter::new(&mut file);
+        let mut bytes = vec![];
+        for entry in entries {
+            let entry = entry?;
+            bytes.clear();
+            tuple::encode(entry.key.iter().chain(&entry.tuple), &mut bytes);
+            writer.write_all(&(bytes.len() as u32).to_le_bytes())?;
+            writer.write_all(&bytes)?;
+        }
+        writer.flush()?;
+    }
+    file.rewind()?;
+    Ok(file)
+}
+
+fn read_entry(reader: &mut impl Read, num_keys: usize) -> io::Result<Option<Entry>> {
+    let mut len = [0; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err),
+    }
+    let mut bytes = vec![0; u32::from_le_bytes(len) as usize];
+    reader.read_exact(&mut bytes)?;
+    let mut key = vec![];
+    tuple::decode(&bytes, &mut key);
+    let tuple = key.split_off(num_keys);
+    Ok(Some(Entry { key, tuple }))
+}
+
+/// A k-way merge of sorted runs.
+struct Merge<'a> {
+    runs: Vec<BufReader<File>>,
+    heap: BinaryHeap<HeapEntry<'a>>,
+}
+
+impl<'a> Merge<'a> {
+    fn new(keys: &'a [SortKey], runs: impl Iterator<Item = File>) -> io::Result<Self> {
+        let mut runs: Vec<_> = runs.map(BufReader::new).collect();
+        let mut heap = BinaryHeap::with_capacity(runs.len());
+        for (run, reader) in runs.iter_mut().enumerate() {
+            if let Some(entry) = read_entry(reader, keys.len())? {
+                heap.push(HeapEntry { keys, entry, run });
+            }
+        }
+        Ok(Self { runs, heap })
+    }
+
+    fn next_entry(&mut self) -> io::Result<Option<Entry>> {
+        let Some(HeapEntry { keys, entry, run }) = self.heap.pop() else {
+            return Ok(None);
+        };
+        if let Some(next) = read_entry(&mut self.runs[run], keys.len())? {
+            self.heap.push(HeapEntry {
+                keys,
+                entry: next,
+                run,
+            });
+        }
+        Ok(Some(entry))
+    }
+}
+
+/// The head of a run in the k-way merge, ordered so that the smallest key is popped first.
+struct HeapEntry<'a> {
+    keys: &'a [SortKey],
+    entry: Entry,
+    run: usize,
+}
+
+impl<'a> Ord for HeapEntry<'a> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_keys(self.keys, &other.entry.key, &self.entry.key)
+            .then_with(|| other.run.cmp(&self.run))
+    }
+}
+
+impl<'a> PartialOrd for HeapEntry<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> PartialEq for HeapEntry<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl<'a> Eq for HeapEntry<'a> {}
+
+/// The sorted tuples returned by [`ExternalSorter::finish`].
+pub struct Sorted<'a>(SortedInner<'a>);
+
+enum SortedInner<'a> {
+    Memory(std::vec::IntoIter<Entry>),
+    Merge(Merge<'a>),
+}
+
+impl<'a> Sorted<'a> {
+    fn next_tuple(&mut self) -> Result<Option<Tuple>, DbError> {
+        match &mut self.0 {
+            SortedInner::Memory(entries) => Ok(entries.next().map(|entry| entry.tuple)),
+            SortedInner::Merge(merge) => Ok(merge.next_entry()?.map(|entry| entry.tuple)),
+        }
+    }
+}
+
+impl<'a> Iterator for Sorted<'a> {
+    type Item = Result<Tuple, DbError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_tuple().transpose()
+    }
+}
+
+/// Keeps the first `limit` tuples in sort order among those pushed.
+pub struct TopN<'a> {
+    keys: &'a [SortKey],
+    limit: usize,
+    heap: BinaryHeap<RankedEntry<'a>>,
+    seq: usize,
+}
+
+impl<'a> TopN<'a> {
+    pub fn new(keys: &'a [SortKey], limit: usize) -> Self {
+        Self {
+            keys,
+            limit,
+            heap: BinaryHeap::with_capacity(limit.saturating_add(1).min(1024)),
+            seq: 0,
+        }
+    }
+
+    pub fn push(&mut self, tuple: Tuple) -> Result<(), DbError> {
+        if self.limit == 0 {
+            return Ok(());
+        }
+        let entry = RankedEntry {
+            keys: self.keys,
+            entry: Entry {
+                key: eval_keys(self.keys, &tuple)?,
+                tuple,
+            },
+            seq: self.seq,
+        };
+        self.seq += 1;
+        if self.heap.len() < self.limit {
+            self.heap.push(entry);
+        } else if let Some(mut worst) = self.heap.peek_mut() {
+            if entry < *worst {
+                *worst = entry;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the kept tuples in sort order.
+    pub fn finish(self) -> Vec<Tuple> {
+        let entries = self.heap.into_sorted_vec();
+        entries
+            .into_iter()
+            .map(|ranked| ranked.entry.tuple)
+            .collect()
+    }
+}
+
+/// An entry of [`TopN`], ordered by its keys and then by arrival so that the worst is on top.
+struct RankedEntry<'a> {
+    keys: &'a [SortKey],
+    entry: Entry,
+    seq: usize,
+}
+
+impl<'a> Ord for RankedEntry<'a> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        compare_keys(self.keys, &self.entry.key, &other.entry.key)
+            .then_with(|| self.seq.cmp(&other.seq))
+    }
+}
+
+impl<'a> PartialOrd for RankedEntry<'a> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> PartialEq for RankedEntry<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl<'a> Eq for RankedEntry<'a> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_external_sort() {
+        let keys = [
+            SortKey::asc(Expr::Column(1)),
+            SortKey::desc(Expr::Column(0)),
+        ];
+        let tuples: Vec<Tuple> = (0i64..1000)
+            .map(|i| {
+                vec![
+                    tuple::encode_int(i).to_vec(),
+                    tuple::encode_int((i * 7919) % 13).to_vec(),
+                ]
+            })
+            .collect();
+        let mut expected = tuples.clone();
+        expected.sort_by(|a, b| a[1].cmp(&b[1]).then(b[0].cmp(&a[0])));
+
+        for memory_budget in [usize::MAX, 4096] {
+            let mut sorter = ExternalSorter::new(&keys, memory_budget);
+            for tuple in tuples.iter().cloned() {
+                sorter.push(tuple).unwrap();
+            }
+            assert_eq!(memory_budget == usize::MAX, sorter.num_runs() == 0);
+            let sorted = sorter.finish().unwrap();
+            let actual: Vec<Tuple> = sorted.map(Result::unwrap).collect();
+            assert_eq!(expected, actual);
+        }
+    }
+
+    #[test]
+    fn test_external_sort_many_runs() {
+        // Equal keys keep their input order across every merge pass.
+        let keys = [SortKey::asc(Expr::Column(1))];
+        let tuples: Vec<Tuple> = (0i64..10_000)
+            .map(|i| {
+                vec![
+                    tuple::encode_int(i).to_vec(),
+                    tuple::encode_int(i % 7).to_vec(),
+                ]
+            })
+            .collect();
+        let mut expected = tuples.clone();
+        expected.sort_by(|a, b| a[1].cmp(&b[1]));
+
+        let mut sorter = ExternalSorter::new(&keys, 0);
+        for tuple in tuples {
+            sorter.push(tuple).unwrap();
+        }
+        assert_eq!(10_000, sorter.num_runs());
+        assert!(sorter.runs.len() <= 2 * MAX_FAN_IN);
+        let actual: Vec<Tuple> = sorter.finish().unwrap().map(Result::unwrap).collect();
+        assert_eq!(expected, actual);
+    }
+
+    #[test]
+    fn test_top_n() {
+        let keys = [SortKey::desc(Expr::Column(1))];
+        let mut top_n = TopN::new(&keys, 3);
+        for (name, score) in [("a", 3), ("b", 9), ("c", 1), ("d", 9), ("e", 5)] {
+            let tuple = vec![name.as_bytes().to_vec(), tuple::encode_int(score).to_vec()];
+            top_n.push(tuple).unwrap();
+        }
+        let names: Vec<Vec<u8>> = top_n.finish().into_iter().map(|t| t[0].clone()).collect();
+        assert_eq!(vec![b"b".to_vec(), b"d".to_vec(), b"e".to_vec()], names);
+    }
+}
@@ -1,3 +1,21 @@
+//! A slotted page: an array of pointers growing from the front of the page, to variable length
+//! records packed at the back.
+//!
+//! Slots are used in one of two ways. Ordered pages like B+ tree nodes [`insert`] and
+//! [`remove`] slots by position, renumbering the slots after them. Pages whose slot numbers are
+//! handed out, like heap pages, [`push`] records and [`delete`] them, which leaves a tombstone
+//! so that the other slots keep their numbers. `push` reuses tombstones before adding slots.
+//!
+//! Removing or shrinking a record does not move any bytes; the space it gave up is a hole that
+//! [`compact`] reclaims. Operations that need contiguous space compact the page first when
+//! the holes add up to enough.
+//!
+//! [`insert`]: Slotted::insert
+//! [`remove`]: Slotted::remove
+//! [`push`]: Slotted::push
+//! [`delete`]: Slotted::delete
+//! [`compact`]: Slotted::compact
+
 use std::mem::size_of;
 use std::ops::{Index, IndexMut, Range};
 use zerocopy::{AsBytes, ByteSlice, ByteSliceMut, FromBytes, FromZeroes, Ref};
@@ -7,7 +25,9 @@ use zerocopy::{AsBytes, ByteSlice, ByteSliceMut, FromBytes, FromZeroes, Ref};
 pub struct Header {
     num_slots: u16,
     free_space_offset: u16,
-    _pad: u32,
+    /// The bytes of the holes left by removed, deleted and shrunk records.
+    fragmented_space: u16,
+    _pad: u16,
 }
 
 #[derive(Debug, FromZeroes, FromBytes, AsBytes, Clone, Copy)]
@@ -17,12 +37,24 @@ pub struct Pointer {
     len: u16,
 }
 
+/// The offset of a deleted slot. Records never start there, as the pointers come first.
+const TOMBSTONE_OFFSET: u16 = 0;
+
 impl Pointer {
+    const TOMBSTONE: Pointer = Pointer {
+        offset: TOMBSTONE_OFFSET,
+        len: 0,
+    };
+
     fn range(&self) -> Range<usize> {
         let start = self.offset as usize;
         let end = start + self.len as usize;
         start..end
     }
+
+    fn is_tombstone(&self) -> bool {
+        self.offset == TOMBSTONE_OFFSET
+    }
 }
 
 pub type Pointers<B> = Ref<B, [Pointer]>;
@@ -46,7 +78,14 @@ impl<B: ByteSlice> Slotted<B> {
         self.header.num_slots as usize
     }
 
+    /// The bytes available for new records and pointers, including holes that have to be
+    /// compacted before they can be used.
     pub fn free_space(&self) -> usize {
+        self.contiguous_free_space() + self.header.fragmented_space as usize
+    }
+
+    /// The bytes between the pointers and the records, usable without compacting.
+    pub fn contiguous_free_space(&self) -> usize {
         self.header.free_space_offset as usize - self.pointers_size()
     }
 
@@ -61,12 +100,32 @@ impl<B: ByteSlice> Slotted<B> {
     pub fn data(&self, pointer: &Pointer) -> &[u8] {
         &self.body[pointer.range()]
     }
+
+    /// Returns whether the slot at `index` was deleted. Indexing a deleted slot gives an empty
+    /// record.
+    pub fn is_deleted(&self, index: usize) -> bool {
+        self.pointers()[index].is_tombstone()
+    }
+
+    /// Like indexing, but returns `None` instead of panicking if the slot does not exist or
+    /// points outside the records, as it may in a corrupted page.
+    pub fn checked_get(&self, index: usize) -> Option<&[u8]> {
+        if index >= self.num_slots() || self.pointers_size() > self.body.len() {
+            return None;
+        }
+        let pointer = self.pointers()[index];
+        if !pointer.is_tombstone() && (pointer.offset as usize) < self.pointers_size() {
+            return None;
+        }
+        self.body.get(pointer.range())
+    }
 }
 
 impl<B: ByteSliceMut> Slotted<B> {
     pub fn initialize(&mut self) {
         self.header.num_slots = 0;
         self.header.free_space_offset = self.body.len() as u16;
+        self.header.fragmented_space = 0;
     }
 
     fn pointers_mut(&mut self) -> Pointers<&mut [u8]> {
@@ -78,61 +137,142 @@ impl<B: ByteSliceMut> Slotted<B> {
         &mut self.body[pointer.range()]
     }
 
+    /// Inserts a slot of `len` bytes at `index`, shifting the slots from `index` on by one.
     pub fn insert(&mut self, index: usize, len: usize) -> Option<()> {
         if self.free_space() < size_of::<Pointer>() + len {
             return None;
         }
+        if self.contiguous_free_space() < size_of::<Pointer>() + len {
+            self.compact();
+        }
         let num_slots_orig = self.num_slots();
         self.header.num_slots += 1;
-        self.header.free_space_offset -= len as u16;
-        let free_space_offset = self.header.free_space_offset;
+        let offset = self.allocate(len);
         let mut pointers = self.pointers_mut();
         pointers.copy_within(index..num_slots_orig, index + 1);
-        let pointer = &mut pointers[index];
-        pointer.offset = free_space_offset;
-        pointer.len = len as u16;
+        pointers[index] = Pointer {
+            offset,
+            len: len as u16,
+        };
         Some(())
     }
 
+    /// Removes the slot at `index`, shifting the slots after it down by one.
     pub fn remove(&mut self, index: usize) {
         let num_slots = self.num_slots();
-        self.resize(index, 0);
+        self.release(index);
         self.pointers_mut().copy_within(index + 1..num_slots, index);
         self.header.num_slots -= 1;
     }
 
-    fn resize(&mut self, index: usize, len_new: usize) -> Option<()> {
-        let pointers = self.pointers();
-        let len_orig = pointers[index].len as usize;
-        let len_incr = len_new as isize - len_orig as isize;
-        if len_incr == 0 {
+    /// Adds a slot of `len` bytes and returns its index, reusing the first deleted slot if any.
+    pub fn push(&mut self, len: usize) -> Option<usize> {
+        let tombstone = self.pointers().iter().position(Pointer::is_tombstone);
+        let Some(index) = tombstone else {
+            let index = self.num_slots();
+            return self.insert(index, len).map(|_| index);
+        };
+        if self.free_space() < len {
+            return None;
+        }
+        if self.contiguous_free_space() < len {
+            self.compact();
+        }
+        let offset = self.allocate(len);
+        self.pointers_mut()[index] = Pointer {
+            offset,
+            len: len as u16,
+        };
+        Some(index)
+    }
+
+    /// Deletes the slot at `index`, leaving a tombstone so that the other slots keep their
+    /// indexes. Tombstones at the end of the pointers are dropped.
+    pub fn delete(&mut self, index: usize) {
+        self.release(index);
+        self.pointers_mut()[index] = Pointer::TOMBSTONE;
+        while self.num_slots() > 0 && self.is_deleted(self.num_slots() - 1) {
+            self.header.num_slots -= 1;
+        }
+    }
+
+    /// Changes the length of the slot at `index`, keeping the first bytes of its record.
+    /// Growing moves the record to contiguous free space, compacting the page if needed.
+    pub fn resize(&mut self, index: usize, len_new: usize) -> Option<()> {
+        let pointer = self.pointers()[index];
+        let len_orig = pointer.len as usize;
+        if len_new <= len_orig {
+            self.header.fragmented_space += (len_orig - len_new) as u16;
+            self.pointers_mut()[index].len = len_new as u16;
             return Some(());
         }
-        if len_incr > self.free_space() as isize {
+        if self.free_space() < len_new - len_orig {
             return None;
         }
-        let free_space_offset = self.header.free_space_offset as usize;
-        let offset_orig = pointers[index].offset as usize;
-        let shift_range = free_space_offset..offset_orig;
-        self.body.copy_within(
-            shift_range,
-            (free_space_offset as isize - len_incr) as usize,
-        );
-        let free_space_offset_new = (free_space_offset as isize - len_incr) as usize;
-        self.header.free_space_offset = free_space_offset_new as u16;
-        let free_space_offset_new = self.header.free_space_offset;
-        let mut pointers = self.pointers_mut();
-        for pointer in pointers.iter_mut() {
-            if pointer.offset <= offset_orig as u16 {
-                pointer.offset = (pointer.offset as isize - len_incr) as u16;
-            }
-        }
-        pointers[index].len = len_new as u16;
-        if len_new == 0 {
-            pointers[index].offset = free_space_offset_new;
+        if self.contiguous_free_space() < len_new {
+            let record = self.data(&pointer).to_vec();
+            self.release(index);
+            self.compact();
+            let offset = self.allocate(len_new);
+            self.body[offset as usize..][..len_orig].copy_from_slice(&record);
+            self.pointers_mut()[index] = Pointer {
+                offset,
+                len: len_new as u16,
+            };
+            return Some(());
         }
+        let offset = self.allocate(len_new);
+        self.body.copy_within(pointer.range(), offset as usize);
+        self.header.fragmented_space += len_orig as u16;
+        self.pointers_mut()[index] = Pointer {
+            offset,
+            len: len_new as u16,
+        };
         Some(())
     }
+
+    /// Packs the records against the back of the page, turning every hole into contiguous free
+    /// space. Slot indexes do not change.
+    pub fn compact(&mut self) {
+        if self.header.fragmented_space == 0 {
+            return;
+        }
+        let mut slots: Vec<(usize, Pointer)> = self
+            .pointers()
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, pointer)| !pointer.is_tombstone())
+            .collect();
+        // Moving the records nearest the back first never overwrites one not yet moved.
+        slots.sort_unstable_by_key(|(_, pointer)| std::cmp::Reverse(pointer.offset));
+        let mut free_space_offset = self.body.len();
+        for (index, pointer) in slots {
+            free_space_offset -= pointer.len as usize;
+            self.body.copy_within(pointer.range(), free_space_offset);
+            self.pointers_mut()[index].offset = free_space_offset as u16;
+        }
+        self.header.free_space_offset = free_space_offset as u16;
+        self.header.fragmented_space = 0;
+    }
+
+    /// Takes `len` bytes from the contiguous free space, which must have room for them.
+    fn allocate(&mut self, len: usize) -> u16 {
+        self.header.free_space_offset -= len as u16;
+        self.header.free_space_offset
+    }
+
+    /// Turns the record at `index` into a hole, leaving the slot empty.
+    fn release(&mut self, index: usize) {
+        let len = self.pointers()[index].len;
+        self.header.fragmented_space += len;
+        let free_space_offset = self.header.free_space_offset;
+        let pointer = &mut self.pointers_mut()[index];
+        if !pointer.is_tombstone() {
+            pointer.offset = free_space_offset;
+        }
+        pointer.len = 0;
+    }
 }
 
 impl<B: ByteSlice> Index<usize> for Slotted<B> {
@@ -177,4 +317,48 @@ mod tests {
         assert_eq!(&slotted[2], b"world");
         assert_eq!(&slotted[3], b".");
     }
+
+    #[test]
+    fn test_delete_and_compact() {
+        let mut page_data = vec![0u8; 48];
+        let mut slotted = Slotted::new(page_data.as_mut_slice());
+        let push = |slotted: &mut Slotted<&mut [u8]>, buf: &[u8]| {
+            let index = slotted.push(buf.len())?;
+            slotted[index].copy_from_slice(buf);
+            Some(index)
+        };
+        slotted.initialize();
+        assert_eq!(Some(0), push(&mut slotted, b"hello"));
+        assert_eq!(Some(1), push(&mut slotted, b"brave"));
+        assert_eq!(Some(2), push(&mut slotted, b"new"));
+        assert_eq!(Some(3), push(&mut slotted, b"world"));
+        assert_eq!(None, push(&mut slotted, b"!!!!!!"));
+
+        // Deleted slots keep the others in place and are reused first.
+        slotted.delete(1);
+        assert!(slotted.is_deleted(1));
+        assert_eq!(&slotted[2], b"new");
+        assert_eq!(11, slotted.free_space());
+        assert_eq!(6, slotted.contiguous_free_space());
+        assert_eq!(Some(1), push(&mut slotted, b"bold"));
+        assert_eq!(&slotted[1], b"bold");
+
+        // Growing a record compacts the page when the holes are needed.
+        slotted.resize(2, 4).unwrap();
+        assert_eq!(&slotted[2][..3], b"new");
+        slotted.delete(0);
+        slotted.resize(3, 9).unwrap();
+        slotted[3][5..].copy_from_slice(b"!!!!");
+        assert_eq!(7, slotted.free_space());
+        assert_eq!(7, slotted.contiguous_free_space());
+        assert_eq!(&slotted[1], b"bold");
+        assert_eq!(&slotted[3], b"world!!!!");
+
+        // Trailing tombstones are dropped.
+        slotted.delete(3);
+        assert_eq!(3, slotted.num_slots());
+        slotted.compact();
+        assert_eq!(slotted.free_space(), slotted.contiguous_free_space());
+        assert_eq!(&slotted[2][..3], b"new");
+    }
 }
@@ -0,0 +1,195 @@
+//! The syntax tree of SQL statements, as written and before names are resolved.
+
+use super::value::Value;
+use crate::catalog::Column;
+use crate::error::DbError;
+use crate::query::expr::BinaryOp;
+use crate::table::IndexKind;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Statement {
+    CreateTable {
+        name: String,
+        columns: Vec<Column>,
+        /// The names of the primary key columns, empty if the table does not declare them.
+        primary_key: Vec<String>,
+    },
+    /// `CREATE UNIQUE INDEX ON table [USING {BTREE | HASH}] (columns) [INCLUDE (include)]`.
+    CreateIndex {
+        table: String,
+        kind: IndexKind,
+        columns: Vec<String>,
+        include: Vec<String>,
+    },
+    Insert {
+        table: String,
+        /// The columns given values, or `None` for all of them in order.
+        columns: Option<Vec<String>>,
+        source: InsertSource,
+    },
+    Update {
+        table: String,
+        assignments: Vec<(String, Expr)>,
+        filter: Option<Expr>,
+    },
+    Delete {
+        table: String,
+        filter: Option<Expr>,
+    },
+    Select(Box<Select>),
+    Explain {
+        analyze: bool,
+        select: Box<Select>,
+    },
+    /// Recomputes the statistics of every table.
+    Analyze,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InsertSource {
+    Values(Vec<Vec<Expr>>),
+    Select(Box<Select>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Select {
+    pub items: Vec<SelectItem>,
+    pub from: TableRef,
+    pub joins: Vec<Join>,
+    pub filter: Option<Expr>,
+    pub group_by: Vec<Expr>,
+    pub order_by: Vec<OrderBy>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectItem {
+    Wildcard,
+    Expr { expr: Expr, alias: Option<String> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableRef {
+    pub name: String,
+    pub alias: Option<String>,
+}
+
+/// A table joined to those before it. A table listed after a comma has no condition.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Join {
+    pub table: TableRef,
+    pub cond: Option<Expr>,
+    pub left_outer: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderBy {
+    pub expr: Expr,
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Expr {
+    Column {
+        table: Option<String>,
+        name: String,
+    },
+    Literal(Value),
+    /// A parameter given a value before the statement runs, numbered from 0 for `$1`.
+    Param(usize),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// A function call. `COUNT(*)` has no arguments and `star` set.
+    Function {
+        name: String,
+        args: Vec<Expr>,
+        star: bool,
+    },
+}
+
+impl Statement {
+    /// Replaces each parameter with its value in `params`.
+    pub fn bind_params(&mut self, params: &[Value]) -> Result<(), DbError> {
+        let mut missing = None;
+        self.walk_exprs_mut(&mut |expr| {
+            if let Expr::Param(index) = *expr {
+                match params.get(index) {
+                    Some(value) => *expr = Expr::Literal(value.clone()),
+                    None => missing = Some(index),
+                }
+            }
+        });
+        match missing {
+            Some(index) => Err(DbError::Invalid(format!(
+                "parameter ${} has no value",
+                index + 1
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    /// Calls `f` on every expression in the statement, each before its operands.
+    pub fn walk_exprs_mut(&mut self, f: &mut impl FnMut(&mut Expr)) {
+        match self {
+            Statement::CreateTable { .. } | Statement::CreateIndex { .. } | Statement::Analyze => {}
+            Statement::Insert { source, .. } => match source {
+                InsertSource::Values(rows) => {
+                    rows.iter_mut().flatten().for_each(|expr| expr.walk_mut(f))
+                }
+                InsertSource::Select(select) => select.walk_exprs_mut(f),
+            },
+            Statement::Update {
+                assignments,
+                filter,
+                ..
+            } => {
+                for (_, expr) in assignments {
+                    expr.walk_mut(f);
+                }
+                filter.iter_mut().for_each(|expr| expr.walk_mut(f));
+            }
+            Statement::Delete { filter, .. } => filter.iter_mut().for_each(|expr| expr.walk_mut(f)),
+            Statement::Select(select) | Statement::Explain { select, .. } => {
+                select.walk_exprs_mut(f)
+            }
+        }
+    }
+}
+
+impl Select {
+    fn walk_exprs_mut(&mut self, f: &mut impl FnMut(&mut Expr)) {
+        for item in &mut self.items {
+            if let SelectItem::Expr { expr, .. } = item {
+                expr.walk_mut(f);
+            }
+        }
+        for join in &mut self.joins {
+            join.cond.iter_mut().for_each(|expr| expr.walk_mut(f));
+        }
+        self.filter.iter_mut().for_each(|expr| expr.walk_mut(f));
+        self.group_by.iter_mut().for_each(|expr| expr.walk_mut(f));
+        for order_by in &mut self.order_by {
+            order_by.expr.walk_mut(f);
+        }
+    }
+}
+
+impl Expr {
+    pub fn binary(op: BinaryOp, lhs: Expr, rhs: Expr) -> Self {
+        Expr::Binary(op, Box::new(lhs), Box::new(rhs))
+    }
+
+    fn walk_mut(&mut self, f: &mut impl FnMut(&mut Expr)) {
+        f(self);
+        match self {
+            Expr::Column { .. } | Expr::Literal(_) | Expr::Param(_) => {}
+            Expr::Not(expr) => expr.walk_mut(f),
+            Expr::Binary(_, lhs, rhs) => {
+                lhs.walk_mut(f);
+                rhs.walk_mut(f);
+            }
+            Expr::Function { args, .. } => args.iter_mut().for_each(|arg| arg.walk_mut(f)),
+        }
+    }
+}
@@ -0,0 +1,705 @@
+//! Resolves the names in the syntax tree against the catalog, checks the types of expressions
+//! and turns queries into logical plans.
+
+use super::ast::{self, SelectItem};
+use crate::catalog::{Catalog, Column, DataType, TableInfo};
+use crate::error::DbError;
+use crate::query::aggregate::{AggExpr, AggFunc};
+use crate::query::expr::{BinaryOp, Expr, TRUE};
+use crate::query::logical::LogicalPlan;
+use crate::query::sort::SortKey;
+
+/// The columns of the tuples an expression is evaluated over, with the names of their tables,
+/// and the types of the parameters of the statement.
+#[derive(Default)]
+pub struct Scope {
+    columns: Vec<(String, Column)>,
+    params: Vec<DataType>,
+}
+
+/// A query turned into a plan, with the names and types of its output columns.
+pub struct BoundSelect {
+    pub plan: LogicalPlan,
+    pub columns: Vec<Column>,
+}
+
+impl Scope {
+    /// The columns of `info`, referred to by `alias` or the table name.
+    pub fn table(info: &TableInfo, alias: Option<&str>) -> Self {
+        let table = alias.unwrap_or(&info.name);
+        Self {
+            columns: info
+                .columns
+                .iter()
+                .map(|column| (table.to_string(), column.clone()))
+                .collect(),
+            params: vec![],
+        }
+    }
+
+    /// Makes parameters bind to [`Expr::Param`]. Without their types, parameters must be
+    /// replaced with values before they are bound.
+    pub fn with_params(self, params: &[DataType]) -> Self {
+        Self {
+            params: params.to_vec(),
+            ..self
+        }
+    }
+
+    /// Appends the columns of `other`, as they are appended by a join.
+    fn join(&mut self, other: Scope) -> Result<(), DbError> {
+        let (table, _) = &other.columns[0];
+        if self.columns.iter().any(|(name, _)| name == table) {
+            return Err(DbError::Invalid(format!(
+                "table name {table} is specified more than once"
+            )));
+        }
+        self.columns.extend(other.columns);
+        Ok(())
+    }
+
+    fn resolve(&self, table: Option<&str>, name: &str) -> Result<(usize, DataType), DbError> {
+        let mut matches =
+            self.columns.iter().enumerate().filter(|(_, (t, column))| {
+                column.name == name && table.is_none_or(|table| table == t)
+            });
+        let qualified = match table {
+            Some(table) => format!("{table}.{name}"),
+            None => name.to_string(),
+        };
+        match (matches.next(), matches.next()) {
+            (Some((index, (_, column))), None) => Ok((index, column.data_type)),
+            (Some(_), Some(_)) => Err(DbError::Invalid(format!(
+                "column reference {qualified} is ambiguous"
+            ))),
+            (None, _) => Err(DbError::NotFound(format!("column {qualified}"))),
+        }
+    }
+}
+
+/// Binds an expression over the columns of `scope`, returning it with the type of its result.
+pub fn bind_expr(scope: &Scope, expr: &ast::Expr) -> Result<(Expr, DataType), DbError> {
+    match expr {
+        ast::Expr::Column { table, name } => {
+            let (index, data_type) = scope.resolve(table.as_deref(), name)?;
+            Ok((Expr::Column(index), data_type))
+        }
+        ast::Expr::Literal(value) => Ok((Expr::Literal(value.encode()), value.data_type())),
+        ast::Expr::Param(index) => match scope.params.get(*index) {
+            Some(data_type) => Ok((Expr::Param(*index), *data_type)),
+            None => Err(DbError::Invalid(format!(
+                "parameter ${} has no value",
+                index + 1
+            ))),
+        },
+        ast::Expr::Not(expr) => bind_not(bind_expr(scope, expr)?),
+        ast::Expr::Binary(op, lhs, rhs) => {
+            bind_binary(*op, bind_expr(scope, lhs)?, bind_expr(scope, rhs)?)
+        }
+        ast::Expr::Function { name, .. } if agg_func(name).is_some() => Err(DbError::Invalid(
+            format!("aggregate function {name} is not allowed here"),
+        )),
+        ast::Expr::Function { name, .. } => Err(DbError::NotFound(format!("function {name}"))),
+    }
+}
+
+/// Binds a condition, which must be a boolean expression.
+pub fn bind_cond(scope: &Scope, expr: &ast::Expr) -> Result<Expr, DbError> {
+    match bind_expr(scope, expr)? {
+        (cond, DataType::Bool) => Ok(cond),
+        (_, data_type) => Err(DbError::Invalid(format!(
+            "condition must be BOOLEAN, not {data_type}"
+        ))),
+    }
+}
+
+fn bind_not((expr, data_type): (Expr, DataType)) -> Result<(Expr, DataType), DbError> {
+    match data_type {
+        DataType::Bool => Ok((Expr::Not(Box::new(expr)), DataType::Bool)),
+        _ => Err(DbError::Invalid(format!(
+            "operator NOT cannot be applied to {data_type}"
+        ))),
+    }
+}
+
+fn bind_binary(
+    op: BinaryOp,
+    (lhs, lhs_type): (Expr, DataType),
+    (rhs, rhs_type): (Expr, DataType),
+) -> Result<(Expr, DataType), DbError> {
+    let data_type = match (op, lhs_type, rhs_type) {
+        (BinaryOp::And | BinaryOp::Or, DataType::Bool, DataType::Bool) => DataType::Bool,
+        (
+            BinaryOp::Eq | BinaryOp::Ne | BinaryOp::Lt | BinaryOp::Le | BinaryOp::Gt | BinaryOp::Ge,
+            lhs_type,
+            rhs_type,
+        ) if lhs_type == rhs_type => DataType::Bool,
+        (BinaryOp::Concat, DataType::Text, DataType::Text) => DataType::Text,
+        (
+            BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div,
+            DataType::Int,
+            DataType::Int,
+        ) => DataType::Int,
+        _ => {
+            return Err(DbError::Invalid(format!(
+                "operator {op} cannot be applied to {lhs_type} and {rhs_type}"
+            )))
+        }
+    };
+    Ok((Expr::binary(op, lhs, rhs), data_type))
+}
+
+fn agg_func(name: &str) -> Option<AggFunc> {
+    match name {
+        "count" => Some(AggFunc::Count),
+        "sum" => Some(AggFunc::Sum),
+        "min" => Some(AggFunc::Min),
+        "max" => Some(AggFunc::Max),
+        "avg" => Some(AggFunc::Avg),
+        _ => None,
+    }
+}
+
+fn contains_aggregate(expr: &ast::Expr) -> bool {
+    match expr {
+        ast::Expr::Column { .. } | ast::Expr::Literal(_) | ast::Expr::Param(_) => false,
+        ast::Expr::Not(expr) => contains_aggregate(expr),
+        ast::Expr::Binary(_, lhs, rhs) => contains_aggregate(lhs) || contains_aggregate(rhs),
+        ast::Expr::Function { name, .. } => agg_func(name).is_some(),
+    }
+}
+
+/// Binds the expressions after the grouping of a query, over the group keys followed by the
+/// aggregates, collecting the aggregates they use.
+struct AggBinder<'a> {
+    scope: &'a Scope,
+    group_by: Vec<(Expr, DataType)>,
+    aggregates: Vec<AggExpr>,
+}
+
+impl<'a> AggBinder<'a> {
+    fn bind(&mut self, expr: &ast::Expr) -> Result<(Expr, DataType), DbError> {
+        if !contains_aggregate(expr) {
+            if let Ok((bound, _)) = bind_expr(self.scope, expr) {
+                if let Some(index) = self.group_by.iter().position(|(key, _)| *key == bound) {
+                    return Ok((Expr::Column(index), self.group_by[index].1));
+                }
+            }
+        }
+        match expr {
+            ast::Expr::Column { name, .. } => {
+                bind_expr(self.scope, expr)?;
+                Err(DbError::Invalid(format!(
+                    "column {name} must appear in GROUP BY or be used in an aggregate function"
+                )))
+            }
+            ast::Expr::Literal(_) | ast::Expr::Param(_) => bind_expr(self.scope, expr),
+            ast::Expr::Not(expr) => bind_not(self.bind(expr)?),
+            ast::Expr::Binary(op, lhs, rhs) => {
+                let lhs = self.bind(lhs)?;
+                bind_binary(*op, lhs, self.bind(rhs)?)
+            }
+            ast::Expr::Function { name, args, star } => {
+                let (aggregate, data_type) = self.bind_aggregate(name, args, *star)?;
+                let index = match self.aggregates.iter().position(|agg| *agg == aggregate) {
+                    Some(index) => index,
+                    None => {
+                        self.aggregates.push(aggregate);
+                        self.aggregates.len() - 1
+                    }
+                };
+                Ok((Expr::Column(self.group_by.len() + index), data_type))
+            }
+        }
+    }
+
+    fn bind_aggregate(
+        &self,
+        name: &str,
+        args: &[ast::Expr],
+        star: bool,
+    ) -> Result<(AggExpr, DataType), DbError> {
+        let func = agg_func(name).ok_or_else(|| DbError::NotFound(format!("function {name}")))?;
+        if star {
+            return match func {
+                AggFunc::Count => Ok((AggExpr::count_star(), DataType::Int)),
+                _ => Err(DbError::Invalid(format!("{name}(*) is not allowed"))),
+            };
+        }
+        let [arg] = args else {
+            return Err(DbError::Invalid(format!(
+                "function {name} takes a single argument"
+            )));
+        };
+        let (arg, arg_type) = bind_expr(self.scope, arg)?;
+        let data_type = match (func, arg_type) {
+            (AggFunc::Count, _) => DataType::Int,
+            (AggFunc::Sum | AggFunc::Avg, DataType::Int) => DataType::Int,
+            (AggFunc::Min | AggFunc::Max, arg_type) => arg_type,
+            (_, arg_type) => {
+                return Err(DbError::Invalid(format!(
+                    "function {name} cannot be applied to {arg_type}"
+                )))
+            }
+        };
+        Ok((AggExpr::new(func, arg), data_type))
+    }
+}
+
+/// Binds a query into a plan of scans and joins, then filter, aggregation, sorting, limit and
+/// the projection of the select list. Its parameters are of the types in `params`.
+pub fn bind_select(
+    catalog: &Catalog,
+    select: &ast::Select,
+    params: &[DataType],
+) -> Result<BoundSelect, DbError> {
+    let table = |table_ref: &ast::TableRef| -> Result<_, DbError> {
+        let info = catalog
+            .table(&table_ref.name)
+            .ok_or_else(|| DbError::NotFound(format!("table {}", table_ref.name)))?;
+        let scope = Scope::table(info, table_ref.alias.as_deref()).with_params(params);
+        Ok((LogicalPlan::scan(&table_ref.name), scope))
+    };
+    let (mut plan, mut scope) = table(&select.from)?;
+    for join in &select.joins {
+        let (right, right_scope) = table(&join.table)?;
+        scope.join(right_scope)?;
+        let cond = match &join.cond {
+            Some(cond) => bind_cond(&scope, cond)?,
+            None => Expr::Literal(TRUE.to_vec()),
+        };
+        plan = match join.left_outer {
+            true => plan.left_outer_join(right, cond),
+            false => plan.join(right, cond),
+        };
+    }
+    if let Some(filter) = &select.filter {
+        plan = plan.filter(bind_cond(&scope, filter)?);
+    }
+
+    let mut items = vec![];
+    for item in &select.items {
+        match item {
+            SelectItem::Wildcard => items.extend(scope.columns.iter().map(|(table, column)| {
+                let expr = ast::Expr::Column {
+                    table: Some(table.clone()),
+                    name: column.name.clone(),
+                };
+                (expr, column.name.clone(), false)
+            })),
+            SelectItem::Expr { expr, alias } => {
+                let name = match (alias, expr) {
+                    (Some(alias), _) => alias.clone(),
+                    (None, ast::Expr::Column { name, .. } | ast::Expr::Function { name, .. }) => {
+                        name.clone()
+                    }
+                    (None, _) => "?column?".to_string(),
+                };
+                items.push((expr.clone(), name, alias.is_some()));
+            }
+        }
+    }
+
+    let is_aggregate = !select.group_by.is_empty()
+        || items.iter().any(|(expr, ..)| contains_aggregate(expr))
+        || select
+            .order_by
+            .iter()
+            .any(|order_by| contains_aggregate(&order_by.expr));
+    let mut agg_binder = match is_aggregate {
+        true => Some(AggBinder {
+            scope: &scope,
+            group_by: select
+                .group_by
+                .iter()
+                .map(|expr| bind_expr(&scope, expr))
+                .collect::<Result<_, _>>()?,
+            aggregates: vec![],
+        }),
+        false => None,
+    };
+    let mut bind = |expr: &ast::Expr| match &mut agg_binder {
+        Some(agg_binder) => agg_binder.bind(expr),
+        None => bind_expr(&scope, expr),
+    };
+
+    let mut exprs = vec![];
+    let mut columns = vec![];
+    for (expr, name, _) in &items {
+        let (expr, data_type) = bind(expr)?;
+        exprs.push(expr);
+        columns.push(Column::new(name, data_type));
+    }
+    let mut keys = vec![];
+    for order_by in &select.order_by {
+        let expr = match &order_by.expr {
+            ast::Expr::Literal(super::Value::Int(position)) => {
+                let position = usize::try_from(*position).unwrap_or(0);
+                exprs
+                    .get(position.wrapping_sub(1))
+                    .cloned()
+                    .ok_or_else(|| {
+                        DbError::Invalid(format!(
+                            "ORDER BY position {position} is not in select list"
+                        ))
+                    })?
+            }
+            ast::Expr::Column { table: None, name } => {
+                match items
+                    .iter()
+                    .position(|(_, alias, is_alias)| *is_alias && alias == name)
+                {
+                    Some(index) => exprs[index].clone(),
+                    None => bind(&order_by.expr)?.0,
+                }
+            }
+            expr => bind(expr)?.0,
+        };
+        keys.push(SortKey {
+            expr,
+            descending: order_by.descending,
+        });
+    }
+
+    if let Some(agg_binder) = agg_binder {
+        let group_by = agg_binder.group_by.into_iter().map(|(expr, _)| expr);
+        plan = plan.aggregate(group_by.collect(), agg_binder.aggregates);
+    }
+    if !keys.is_empty() {
+        plan = plan.sort(keys);
+    }
+    if select.limit.is_some() || select.offset.is_some() {
+        plan = plan.limit(
+            select.offset.unwrap_or(0),
+            select.limit.unwrap_or(usize::MAX),
+        );
+    }
+    Ok(BoundSelect {
+        plan: plan.project(exprs),
+        columns,
+    })
+}
+
+/// Infers the type of each parameter of a statement from where it is used: the column its
+/// value is stored in, the other operand of a comparison or the operands an operator takes.
+/// A parameter used nowhere its type can be inferred from has type `None`.
+pub fn param_types(
+    catalog: &Catalog,
+    statement: &ast::Statement,
+) -> Result<Vec<Option<DataType>>, DbError> {
+    let table_info = |name: &str| {
+        catalog
+            .table(name)
+            .ok_or_else(|| DbError::NotFound(format!("table {name}")))
+    };
+    let mut types = vec![];
+    match statement {
+        ast::Statement::CreateTable { .. }
+        | ast::Statement::CreateIndex { .. }
+        | ast::Statement::Analyze => {}
+        ast::Statement::Insert {
+            table,
+            columns,
+            source,
+        } => match source {
+            ast::InsertSource::Values(rows) => {
+                let info = table_info(table)?;
+                let column_type = |i: usize| {
+                    let index = match columns {
+                        Some(columns) => info.column_index(&columns[i]),
+                        None => Some(i),
+                    };
+                    Some(info.columns.get(index?)?.data_type)
+                };
+                for row in rows {
+                    for (i, expr) in row.iter().enumerate() {
+                        infer(&Scope::default(), expr, column_type(i), &mut types);
+                    }
+                }
+            }
+            ast::InsertSource::Select(select) => infer_select(catalog, select, &mut types)?,
+        },
+        ast::Statement::Update {
+            table,
+            assignments,
+            filter,
+        } => {
+            let info = table_info(table)?;
+            let scope = Scope::table(info, None);
+            for (name, expr) in assignments {
+                let column_type = info.column_index(name).map(|i| info.columns[i].data_type);
+                infer(&scope, expr, column_type, &mut types);
+            }
+            if let Some(filter) = filter {
+                infer(&scope, filter, Some(DataType::Bool), &mut types);
+            }
+        }
+        ast::Statement::Delete { table, filter } => {
+            let scope = Scope::table(table_info(table)?, None);
+            if let Some(filter) = filter {
+                infer(&scope, filter, Some(DataType::Bool), &mut types);
+            }
+        }
+        ast::Statement::Select(select) | ast::Statement::Explain { select, .. } => {
+            infer_select(catalog, select, &mut types)?
+        }
+    }
+    Ok(types)
+}
+
+fn infer_select(
+    catalog: &Catalog,
+    select: &ast::Select,
+    types: &mut Vec<Option<DataType>>,
+) -> Result<(), DbError> {
+    let table = |table_ref: &ast::TableRef| -> Result<_, DbError> {
+        let info = catalog
+            .table(&table_ref.name)
+            .ok_or_else(|| DbError::NotFound(format!("table {}", table_ref.name)))?;
+        Ok(Scope::table(info, table_ref.alias.as_deref()))
+    };
+    let mut scope = table(&select.from)?;
+    for join in &select.joins {
+        scope.join(table(&join.table)?)?;
+        if let Some(cond) = &join.cond {
+            infer(&scope, cond, Some(DataType::Bool), types);
+        }
+    }
+    if let Some(filter) = &select.filter {
+        infer(&scope, filter, Some(DataType::Bool), types);
+    }
+    for item in &select.items {
+        if let SelectItem::Expr { expr, .. } = item {
+            infer(&scope, expr, None, types);
+        }
+    }
+    for expr in &select.group_by {
+        infer(&scope, expr, None, types);
+    }
+    for order_by in &select.order_by {
+        infer(&scope, &order_by.expr, None, types);
+    }
+    Ok(())
+}
+
+/// Records the type of the parameters in `expr`, which is expected to be of `expected`.
+fn infer(
+    scope: &Scope,
+    expr: &ast::Expr,
+    expected: Option<DataType>,
+    types: &mut Vec<Option<DataType>>,
+) {
+    match expr {
+        ast::Expr::Column { .. } | ast::Expr::Literal(_) => {}
+        ast::Expr::Param(index) => {
+            if types.len() <= *index {
+                types.resize(index + 1, None);
+            }
+            types[*index] = types[*index].or(expected);
+        }
+        ast::Expr::Not(expr) => infer(scope, expr, Some(DataType::Bool), types),
+        ast::Expr::Binary(op, lhs, rhs) => {
+            let operand_type = match op {
+                BinaryOp::And | BinaryOp::Or => Some(DataType::Bool),
+                BinaryOp::Concat => Some(DataType::Text),
+                BinaryOp::Add | BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div => {
+                    Some(DataType::Int)
+                }
+                BinaryOp::Eq
+                | BinaryOp::Ne
+                | BinaryOp::Lt
+                | BinaryOp::Le
+                | BinaryOp::Gt
+                | BinaryOp::Ge => bind_expr(scope, lhs)
+                    .or_else(|_| bind_expr(scope, rhs))
+                    .ok()
+                    .map(|(_, data_type)| data_type),
+            };
+            infer(scope, lhs, operand_type, types);
+            infer(scope, rhs, operand_type, types);
+        }
+        ast::Expr::Function { name, args, .. } => {
+            let arg_type = match agg_func(name) {
+                Some(AggFunc::Sum | AggFunc::Avg) => Some(DataType::Int),
+                _ => None,
+            };
+            for arg in args {
+                infer(scope, arg, arg_type, types);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disk::PageId;
+    use crate::sql::ast::Statement;
+    use crate::sql::parse;
+    use crate::table::Table;
+
+    fn catalog() -> Catalog {
+        let mut catalog = Catalog::default();
+        let mut add_table = |name: &str, columns: Vec<Column>| {
+            let table = Table {
+                meta_page_id: PageId::INVALID_PAGE_ID,
+                num_key_elems: 1,
+                unique_index: vec![],
+            };
+            catalog.add_table(name, columns, table).unwrap();
+        };
+        add_table(
+            "users",
+            vec![
+                Column::new("id", DataType::Int),
+                Column::new("name", DataType::Text),
+                Column::new("admin", DataType::Bool),
+            ],
+        );
+        add_table(
+            "posts",
+            vec![
+                Column::new("id", DataType::Int),
+                Column::new("author", DataType::Int),
+            ],
+        );
+        catalog
+    }
+
+    fn bind(catalog: &Catalog, sql: &str) -> Result<BoundSelect, DbError> {
+        let Statement::Select(select) = parse(sql).unwrap() else {
+            panic!("not a select: {sql}");
+        };
+        bind_select(catalog, &select, &[DataType::Int])
+    }
+
+    fn bind_err(catalog: &Catalog, sql: &str) -> String {
+        bind(catalog, sql).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn test_bind_names() {
+        let catalog = catalog();
+        let bound = bind(
+            &catalog,
+            "SELECT p.id, name AS author_name FROM users u JOIN posts p ON u.id = p.author \
+             WHERE u.id = $1 ORDER BY author_name",
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                Column::new("id", DataType::Int),
+                Column::new("author_name", DataType::Text)
+            ],
+            bound.columns
+        );
+        let expected = LogicalPlan::scan("users")
+            .join(
+                LogicalPlan::scan("posts"),
+                Expr::binary(BinaryOp::Eq, Expr::Column(0), Expr::Column(4)),
+            )
+            .filter(Expr::binary(BinaryOp::Eq, Expr::Column(0), Expr::Param(0)))
+            .sort(vec![SortKey {
+                expr: Expr::Column(1),
+                descending: false,
+            }])
+            .project(vec![Expr::Column(3), Expr::Column(1)]);
+        assert_eq!(expected, bound.plan);
+
+        let bound = bind(&catalog, "SELECT * FROM posts").unwrap();
+        assert_eq!(
+            vec![Expr::Column(0), Expr::Column(1)],
+            match bound.plan {
+                LogicalPlan::Project { exprs, .. } => exprs,
+                plan => panic!("not a projection: {plan:?}"),
+            }
+        );
+
+        assert_eq!(
+            "table orders does not exist",
+            bind_err(&catalog, "SELECT 1 FROM orders")
+        );
+        assert_eq!(
+            "column users.author does not exist",
+            bind_err(&catalog, "SELECT users.author FROM users")
+        );
+        assert_eq!(
+            "column u.id does not exist",
+            bind_err(&catalog, "SELECT u.id FROM users")
+        );
+        assert_eq!(
+            "column reference id is ambiguous",
+            bind_err(&catalog, "SELECT id FROM users JOIN posts ON author = 1")
+        );
+        assert_eq!(
+            "table name users is specified more than once",
+            bind_err(&catalog, "SELECT 1 FROM users JOIN users ON true")
+        );
+        assert_eq!(
+            "parameter $2 has no value",
+            bind_err(&catalog, "SELECT $2 FROM users")
+        );
+        assert_eq!(
+            "ORDER BY position 3 is not in select list",
+            bind_err(&catalog, "SELECT id, name FROM users ORDER BY 3")
+        );
+    }
+
+    #[test]
+    fn test_bind_types() {
+        let catalog = catalog();
+        let scope = Scope::table(catalog.table("users").unwrap(), None);
+        let Statement::Select(select) = parse("SELECT NOT admin OR id + 1 > 2 FROM users").unwrap()
+        else {
+            panic!("not a select");
+        };
+        let SelectItem::Expr { expr, .. } = &select.items[0] else {
+            panic!("not an expression");
+        };
+        assert_eq!(DataType::Bool, bind_expr(&scope, expr).unwrap().1);
+
+        let bound = bind(
+            &catalog,
+            "SELECT name, COUNT(*), MAX(admin), SUM(id) FROM users GROUP BY name",
+        )
+        .unwrap();
+        let types: Vec<DataType> = bound.columns.iter().map(|c| c.data_type).collect();
+        assert_eq!(
+            vec![DataType::Text, DataType::Int, DataType::Bool, DataType::Int],
+            types
+        );
+
+        assert!(matches!(
+            bind(&catalog, "SELECT id FROM users WHERE name"),
+            Err(DbError::Invalid(_))
+        ));
+        assert_eq!(
+            "condition must be BOOLEAN, not TEXT",
+            bind_err(&catalog, "SELECT id FROM users WHERE name")
+        );
+        assert_eq!(
+            "operator = cannot be applied to INT and TEXT",
+            bind_err(&catalog, "SELECT id = name FROM users")
+        );
+        assert_eq!(
+            "operator NOT cannot be applied to INT",
+            bind_err(&catalog, "SELECT NOT id FROM users")
+        );
+        assert_eq!(
+            "function sum cannot be applied to TEXT",
+            bind_err(&catalog, "SELECT SUM(name) FROM users")
+        );
+        assert_eq!(
+            "column id must appear in GROUP BY or be used in an aggregate function",
+            bind_err(&catalog, "SELECT id FROM users GROUP BY name")
+        );
+        assert_eq!(
+            "aggregate function count is not allowed here",
+            bind_err(&catalog, "SELECT 1 FROM users WHERE COUNT(*) > 1")
+        );
+        assert_eq!(
+            "function lower does not exist",
+            bind_err(&catalog, "SELECT lower(name) FROM users")
+        );
+    }
+}
@@ -0,0 +1,866 @@
+use std::fmt::{self, Display, Formatter};
+
+use super::ast::{InsertSource, Select, Statement};
+use super::binder::{bind_cond, bind_expr, bind_select, param_types, Scope};
+use super::value::{ColumnDisplay, Value};
+use crate::buffer::BufferPoolManager;
+use crate::catalog::{Catalog, Column, DataType, TableInfo};
+use crate::error::DbError;
+use crate::query::explain::explain_analyze;
+use crate::query::expr::{EvalError, Expr};
+use crate::query::logical::LogicalPlan;
+use crate::query::optimizer::Optimizer;
+use crate::query::physical::PhysicalPlan;
+use crate::query::planner::{Delete, Insert, PlanNode, Update, Values};
+use crate::query::Tuple;
+use crate::table::IndexKind;
+use crate::tuple;
+
+/// What a statement returned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Output {
+    Rows(ResultSet),
+    /// The number of rows inserted, updated or deleted.
+    Affected(usize),
+    Done,
+}
+
+/// The rows returned by a query, with the names and types of their columns.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResultSet {
+    pub columns: Vec<Column>,
+    pub rows: Vec<Tuple>,
+}
+
+/// A statement bound against the catalog and planned, which runs any number of times with
+/// values for its parameters.
+///
+/// Plans stay valid as the catalog changes: tables and indexes are never dropped, and the
+/// tables written to are looked up when the plan runs so that new indexes are kept up to date.
+pub struct Plan {
+    param_types: Vec<DataType>,
+    kind: PlanKind,
+}
+
+enum PlanKind {
+    /// A statement changing the catalog, which is run as it is.
+    Schema(Statement),
+    /// Rows of expressions computing each column of the table, empty for those without a value.
+    InsertValues {
+        table: String,
+        rows: Vec<Vec<Expr>>,
+    },
+    InsertSelect {
+        table: String,
+        plan: PhysicalPlan,
+    },
+    Update {
+        table: String,
+        plan: PhysicalPlan,
+        exprs: Vec<Expr>,
+    },
+    Delete {
+        table: String,
+        plan: PhysicalPlan,
+    },
+    Select {
+        plan: PhysicalPlan,
+        columns: Vec<Column>,
+    },
+    Explain {
+        analyze: bool,
+        plan: PhysicalPlan,
+    },
+}
+
+/// Runs a statement against the tables of `catalog`.
+pub fn execute(
+    catalog: &mut Catalog,
+    bufmgr: &mut BufferPoolManager,
+    statement: &Statement,
+) -> Result<Output, DbError> {
+    prepare(catalog, statement)?.run(catalog, bufmgr, &[])
+}
+
+/// Binds and plans a statement. The type of each of its parameters must be known from where it
+/// is used, see [`param_types`].
+pub fn prepare(catalog: &Catalog, statement: &Statement) -> Result<Plan, DbError> {
+    let param_types = param_types(catalog, statement)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, data_type)| {
+            data_type.ok_or_else(|| {
+                DbError::Invalid(format!(
+                    "could not determine the type of parameter ${}",
+                    i + 1
+                ))
+            })
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let params = param_types.as_slice();
+    let kind = match statement {
+        Statement::CreateTable { .. } | Statement::CreateIndex { .. } | Statement::Analyze => {
+            PlanKind::Schema(statement.clone())
+        }
+        Statement::Insert {
+            table,
+            columns,
+            source,
+        } => prepare_insert(catalog, table, columns.as_deref(), source, params)?,
+        Statement::Update {
+            table,
+            assignments,
+            filter,
+        } => {
+            let info = table_info(catalog, table)?;
+            let scope = Scope::table(info, None).with_params(params);
+            let mut exprs: Vec<Expr> = (0..info.columns.len()).map(Expr::Column).collect();
+            let mut assigned = vec![false; exprs.len()];
+            for (name, expr) in assignments {
+                let index = column_indexes(info, std::slice::from_ref(name))?[0];
+                if std::mem::replace(&mut assigned[index], true) {
+                    return Err(DbError::Invalid(format!(
+                        "column {name} is assigned more than once"
+                    )));
+                }
+                exprs[index] = bind_value(&scope, expr, &info.columns[index])?;
+            }
+            PlanKind::Update {
+                table: table.clone(),
+                plan: optimize_filtered(catalog, info, filter.as_ref(), params)?,
+                exprs,
+            }
+        }
+        Statement::Delete { table, filter } => {
+            let info = table_info(catalog, table)?;
+            PlanKind::Delete {
+                table: table.clone(),
+                plan: optimize_filtered(catalog, info, filter.as_ref(), params)?,
+            }
+        }
+        Statement::Select(select) => {
+            let bound = bind_select(catalog, select, params)?;
+            PlanKind::Select {
+                plan: Optimizer::new(catalog).optimize(&bound.plan)?,
+                columns: bound.columns,
+            }
+        }
+        Statement::Explain { analyze, select } => {
+            let bound = bind_select(catalog, select, params)?;
+            PlanKind::Explain {
+                analyze: *analyze,
+                plan: Optimizer::new(catalog).optimize(&bound.plan)?,
+            }
+        }
+    };
+    Ok(Plan { param_types, kind })
+}
+
+impl Plan {
+    pub fn param_types(&self) -> &[DataType] {
+        &self.param_types
+    }
+
+    /// Returns whether the statement returns rows, like `SELECT` or `EXPLAIN`.
+    pub fn returns_rows(&self) -> bool {
+        matches!(
+            self.kind,
+            PlanKind::Select { .. } | PlanKind::Explain { .. }
+        )
+    }
+
+    /// Runs the statement with `params` as the values of its parameters.
+    pub fn run(
+        &self,
+        catalog: &mut Catalog,
+        bufmgr: &mut BufferPoolManager,
+        params: &[Value],
+    ) -> Result<Output, DbError> {
+        if params.len() < self.param_types.len() {
+            return Err(DbError::Invalid(format!(
+                "parameter ${} has no value",
+                params.len() + 1
+            )));
+        }
+        if params.len() > self.param_types.len() {
+            return Err(DbError::Invalid(format!(
+                "expected {} parameters, found {}",
+                self.param_types.len(),
+                params.len()
+            )));
+        }
+        for (i, (param, &data_type)) in params.iter().zip(&self.param_types).enumerate() {
+            if param.data_type() != data_type {
+                return Err(DbError::Invalid(format!(
+                    "parameter ${} is of type {data_type}, but the value is of type {}",
+                    i + 1,
+                    param.data_type()
+                )));
+            }
+        }
+        let params: Vec<Vec<u8>> = params.iter().map(Value::encode).collect();
+        let mut bound = None;
+        match &self.kind {
+            PlanKind::Schema(statement) => execute_schema(catalog, bufmgr, statement),
+            PlanKind::InsertValues { table, rows } => {
+                let info = table_info(catalog, table)?;
+                let mut records = vec![];
+                for row in rows {
+                    let record = row
+                        .iter()
+                        .map(|expr| expr.bind_params(&params)?.eval(&[]))
+                        .collect::<Result<Tuple, _>>()?;
+                    records.push(record);
+                }
+                let values = Values { rows: &records };
+                let plan = Insert {
+                    table: &info.table,
+                    inner_plan: &values,
+                };
+                Ok(Output::Affected(run_count(&plan, bufmgr)?))
+            }
+            PlanKind::InsertSelect { table, plan } => {
+                let plan = Insert {
+                    table: &table_info(catalog, table)?.table,
+                    inner_plan: bind_plan(plan, &params, &mut bound)?,
+                };
+                Ok(Output::Affected(run_count(&plan, bufmgr)?))
+            }
+            PlanKind::Update { table, plan, exprs } => {
+                let exprs = exprs
+                    .iter()
+                    .map(|expr| expr.bind_params(&params))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let plan = Update {
+                    table: &table_info(catalog, table)?.table,
+                    inner_plan: bind_plan(plan, &params, &mut bound)?,
+                    exprs: &exprs,
+                };
+                Ok(Output::Affected(run_count(&plan, bufmgr)?))
+            }
+            PlanKind::Delete { table, plan } => {
+                let plan = Delete {
+                    table: &table_info(catalog, table)?.table,
+                    inner_plan: bind_plan(plan, &params, &mut bound)?,
+                };
+                Ok(Output::Affected(run_count(&plan, bufmgr)?))
+            }
+            PlanKind::Select { plan, columns } => {
+                let rows = collect(bind_plan(plan, &params, &mut bound)?, bufmgr)?;
+                Ok(Output::Rows(ResultSet {
+                    columns: columns.clone(),
+                    rows,
+                }))
+            }
+            PlanKind::Explain { analyze, plan } => {
+                let explain = match analyze {
+                    true => {
+                        // Analyzing marks the nodes of the plan, so it works on a copy.
+                        let mut plan = plan.bind_params(&params)?;
+                        plan.analyze();
+                        explain_analyze(&plan, bufmgr)?
+                    }
+                    false => bind_plan(plan, &params, &mut bound)?.explain(),
+                };
+                let rows = explain
+                    .to_text()
+                    .lines()
+                    .map(|line| vec![line.as_bytes().to_vec()])
+                    .collect();
+                Ok(Output::Rows(ResultSet {
+                    columns: vec![query_plan_column()],
+                    rows,
+                }))
+            }
+        }
+    }
+}
+
+/// Returns `plan` with the values of its parameters, keeping the copy made in `bound` if it has
+/// any.
+fn bind_plan<'a>(
+    plan: &'a PhysicalPlan,
+    params: &[Vec<u8>],
+    bound: &'a mut Option<PhysicalPlan>,
+) -> Result<&'a PhysicalPlan, EvalError> {
+    if params.is_empty() {
+        return Ok(plan);
+    }
+    Ok(bound.insert(plan.bind_params(params)?))
+}
+
+/// Runs a statement changing the catalog.
+fn execute_schema(
+    catalog: &mut Catalog,
+    bufmgr: &mut BufferPoolManager,
+    statement: &Statement,
+) -> Result<Output, DbError> {
+    match statement {
+        Statement::CreateTable {
+            name,
+            columns,
+            primary_key,
+        } => {
+            for (i, column) in columns.iter().enumerate() {
+                if columns[..i].iter().any(|other| other.name == column.name) {
+                    return Err(DbError::Invalid(format!(
+                        "column {} is specified more than once",
+                        column.name
+                    )));
+                }
+            }
+            // Rows are keyed by their leading columns, so the primary key must come first.
+            let num_key_elems = primary_key.len().max(1);
+            if primary_key
+                .iter()
+                .zip(columns)
+                .any(|(key, column)| *key != column.name)
+                || primary_key.len() > columns.len()
+            {
+                return Err(DbError::InvalidSchema(
+                    "the primary key must be the leading columns of the table".to_string(),
+                ));
+            }
+            catalog.create_table(bufmgr, name, columns.clone(), num_key_elems)?;
+        }
+        Statement::CreateIndex {
+            table,
+            kind,
+            columns,
+            include,
+        } => {
+            let info = table_info(catalog, table)?;
+            let skey = column_indexes(info, columns)?;
+            let include = column_indexes(info, include)?;
+            catalog.create_index(bufmgr, table, *kind, skey, include)?;
+        }
+        Statement::Analyze => catalog.analyze(bufmgr)?,
+        _ => {
+            return Err(DbError::Invalid(
+                "statement does not change the catalog".to_string(),
+            ))
+        }
+    }
+    Ok(Output::Done)
+}
+
+/// Returns the columns of the rows a statement returns, or `None` if it returns no rows.
+pub fn describe(catalog: &Catalog, statement: &Statement) -> Result<Option<Vec<Column>>, DbError> {
+    match statement {
+        Statement::Select(select) => Ok(Some(bind_select(catalog, select, &[])?.columns)),
+        Statement::Explain { .. } => Ok(Some(vec![query_plan_column()])),
+        _ => Ok(None),
+    }
+}
+
+fn query_plan_column() -> Column {
+    Column::new("QUERY PLAN", DataType::Text)
+}
+
+/// Runs a query and collects its rows.
+pub fn query(
+    catalog: &Catalog,
+    bufmgr: &mut BufferPoolManager,
+    select: &Select,
+) -> Result<ResultSet, DbError> {
+    let bound = bind_select(catalog, select, &[])?;
+    let plan = Optimizer::new(catalog).optimize(&bound.plan)?;
+    Ok(ResultSet {
+        columns: bound.columns,
+        rows: collect(&plan, bufmgr)?,
+    })
+}
+
+fn collect(plan: &PhysicalPlan, bufmgr: &mut BufferPoolManager) -> Result<Vec<Tuple>, DbError> {
+    plan.iter(bufmgr)?.collect()
+}
+
+fn prepare_insert(
+    catalog: &Catalog,
+    table: &str,
+    columns: Option<&[String]>,
+    source: &InsertSource,
+    params: &[DataType],
+) -> Result<PlanKind, DbError> {
+    let info = table_info(catalog, table)?;
+    let positions = match columns {
+        Some(columns) => column_indexes(info, columns)?,
+        None => (0..info.columns.len()).collect(),
+    };
+    for (i, position) in positions.iter().enumerate() {
+        if positions[..i].contains(position) {
+            let name = &info.columns[*position].name;
+            return Err(DbError::Invalid(format!(
+                "column {name} is specified more than once"
+            )));
+        }
+    }
+    if let Some(key) = (0..info.table.num_key_elems).find(|key| !positions.contains(key)) {
+        let name = &info.columns[key].name;
+        return Err(DbError::Invalid(format!(
+            "primary key column {name} must be given a value"
+        )));
+    }
+    // Columns without a value are left empty, which is displayed as NULL.
+    match source {
+        InsertSource::Values(rows) => {
+            let scope = Scope::default().with_params(params);
+            let mut records = vec![];
+            for row in rows {
+                if row.len() != positions.len() {
+                    return Err(DbError::Invalid(format!(
+                        "expected {} values, found {}",
+                        positions.len(),
+                        row.len()
+                    )));
+                }
+                let mut record = vec![Expr::Literal(vec![]); info.columns.len()];
+                for (expr, &position) in row.iter().zip(&positions) {
+                    record[position] = bind_value(&scope, expr, &info.columns[position])?;
+                }
+                records.push(record);
+            }
+            Ok(PlanKind::InsertValues {
+                table: table.to_string(),
+                rows: records,
+            })
+        }
+        InsertSource::Select(select) => {
+            let bound = bind_select(catalog, select, params)?;
+            if bound.columns.len() != positions.len() {
+                return Err(DbError::Invalid(format!(
+                    "expected {} columns, found {}",
+                    positions.len(),
+                    bound.columns.len()
+                )));
+            }
+            for (column, &position) in bound.columns.iter().zip(&positions) {
+                check_type(&info.columns[position], column.data_type)?;
+            }
+            let exprs = (0..info.columns.len())
+                .map(
+                    |i| match positions.iter().position(|&position| position == i) {
+                        Some(index) => Expr::Column(index),
+                        None => Expr::Literal(vec![]),
+                    },
+                )
+                .collect();
+            Ok(PlanKind::InsertSelect {
+                table: table.to_string(),
+                plan: Optimizer::new(catalog).optimize(&bound.plan.project(exprs))?,
+            })
+        }
+    }
+}
+
+fn table_info<'a>(catalog: &'a Catalog, name: &str) -> Result<&'a TableInfo, DbError> {
+    catalog
+        .table(name)
+        .ok_or_else(|| DbError::NotFound(format!("table {name}")))
+}
+
+fn column_indexes(info: &TableInfo, names: &[String]) -> Result<Vec<usize>, DbError> {
+    names
+        .iter()
+        .map(|name| {
+            info.column_index(name)
+                .ok_or_else(|| DbError::NotFound(format!("column {name} of table {}", info.name)))
+        })
+        .collect()
+}
+
+/// Binds a value to be stored in `column`, which must be of the column type.
+fn bind_value(scope: &Scope, expr: &super::ast::Expr, column: &Column) -> Result<Expr, DbError> {
+    let (expr, data_type) = bind_expr(scope, expr)?;
+    check_type(column, data_type)?;
+    Ok(expr)
+}
+
+fn check_type(column: &Column, data_type: DataType) -> Result<(), DbError> {
+    if column.data_type != data_type {
+        return Err(DbError::Invalid(format!(
+            "column {} is of type {}, but the value is of type {data_type}",
+            column.name, column.data_type
+        )));
+    }
+    Ok(())
+}
+
+/// Plans a scan of the whole rows of a table matching `filter`.
+fn optimize_filtered(
+    catalog: &Catalog,
+    info: &TableInfo,
+    filter: Option<&super::ast::Expr>,
+    params: &[DataType],
+) -> Result<PhysicalPlan, DbError> {
+    let mut plan = LogicalPlan::scan(&info.name);
+    if let Some(filter) = filter {
+        let scope = Scope::table(info, None).with_params(params);
+        plan = plan.filter(bind_cond(&scope, filter)?);
+    }
+    Optimizer::new(catalog).optimize(&plan)
+}
+
+/// Runs a plan returning the number of rows it changed.
+fn run_count(plan: &dyn PlanNode, bufmgr: &mut BufferPoolManager) -> Result<usize, DbError> {
+    let mut exec = plan.start(bufmgr)?;
+    let tuple = exec
+        .next(bufmgr)?
+        .expect("modifications must return a row count");
+    let count = tuple::decode_int(&tuple[0]).expect("row count must be an integer");
+    Ok(count as usize)
+}
+
+/// Returns the statements that create a table and its indexes.
+pub fn schema(info: &TableInfo) -> String {
+    let mut schema = format!("CREATE TABLE {} (\n", info.name);
+    for column in &info.columns {
+        schema += &format!("  {} {},\n", column.name, column.data_type);
+    }
+    let names = |indexes: &[usize]| {
+        let names: Vec<&str> = indexes
+            .iter()
+            .map(|&i| info.columns[i].name.as_str())
+            .collect();
+        names.join(", ")
+    };
+    let primary_key: Vec<usize> = (0..info.table.num_key_elems).collect();
+    schema += &format!("  PRIMARY KEY ({})\n);", names(&primary_key));
+    for index in &info.table.unique_index {
+        let using = match index.kind {
+            IndexKind::BTree => "",
+            IndexKind::Hash => " USING HASH",
+        };
+        schema += &format!(
+            "\nCREATE UNIQUE INDEX ON {}{using} ({})",
+            info.name,
+            names(&index.skey)
+        );
+        if !index.include.is_empty() {
+            schema += &format!(" INCLUDE ({})", names(&index.include));
+        }
+        schema += ";";
+    }
+    schema
+}
+
+impl Display for ResultSet {
+    /// Formats the rows as a table with a column per line, integers aligned to the right and
+    /// other values to the left, followed by the number of rows.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let cells: Vec<Vec<String>> = self
+            .rows
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .zip(&self.columns)
+                    .map(|(bytes, column)| ColumnDisplay(bytes, column.data_type).to_string())
+                    .collect()
+            })
+            .collect();
+        let widths: Vec<usize> = self
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                cells
+                    .iter()
+                    .map(|row| row[i].chars().count())
+                    .fold(column.name.chars().count(), usize::max)
+            })
+            .collect();
+
+        let mut line = String::new();
+        for (i, column) in self.columns.iter().enumerate() {
+            let sep = if i == 0 { "" } else { "|" };
+            line += &format!("{sep} {:^width$} ", column.name, width = widths[i]);
+        }
+        writeln!(f, "{}", line.trim_end())?;
+        let rule: Vec<String> = widths.iter().map(|width| "-".repeat(width + 2)).collect();
+        writeln!(f, "{}", rule.join("+"))?;
+        for row in &cells {
+            line.clear();
+            for (i, cell) in row.iter().enumerate() {
+                let sep = if i == 0 { "" } else { "|" };
+                line += &match self.columns[i].data_type {
+                    DataType::Int => format!("{sep} {cell:>width$} ", width = widths[i]),
+                    _ => format!("{sep} {cell:<width$} ", width = widths[i]),
+                };
+            }
+            writeln!(f, "{}", line.trim_end())?;
+        }
+        match self.rows.len() {
+            1 => write!(f, "(1 row)"),
+            n => write!(f, "({n} rows)"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::buffer::BufferPool;
+    use crate::disk::DiskManager;
+    use crate::sql::binder::param_types;
+    use crate::sql::parser::parse;
+    use crate::sql::Value;
+    use tempfile::tempfile;
+
+    fn run(catalog: &mut Catalog, bufmgr: &mut BufferPoolManager, sql: &str) -> Output {
+        execute(catalog, bufmgr, &parse(sql).unwrap()).unwrap()
+    }
+
+    fn run_err(catalog: &mut Catalog, bufmgr: &mut BufferPoolManager, sql: &str) -> String {
+        execute(catalog, bufmgr, &parse(sql).unwrap())
+            .unwrap_err()
+            .to_string()
+    }
+
+    fn run_rows(catalog: &mut Catalog, bufmgr: &mut BufferPoolManager, sql: &str) -> usize {
+        match run(catalog, bufmgr, sql) {
+            Output::Rows(result_set) => result_set.rows.len(),
+            output => panic!("no rows returned: {output:?}"),
+        }
+    }
+
+    fn text(output: Output) -> String {
+        match output {
+            Output::Rows(result_set) => result_set.to_string(),
+            output => panic!("no rows returned: {output:?}"),
+        }
+    }
+
+    #[test]
+    fn test_execute() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let mut bufmgr = BufferPoolManager::new(disk, BufferPool::new(10));
+        let mut catalog = Catalog::create(&mut bufmgr).unwrap();
+        let c = &mut catalog;
+        let b = &mut bufmgr;
+
+        run(
+            c,
+            b,
+            "CREATE TABLE customers (id INT PRIMARY KEY, name TEXT, email TEXT)",
+        );
+        run(c, b, "CREATE UNIQUE INDEX ON customers (email)");
+        run(
+            c,
+            b,
+            "CREATE TABLE orders (id INT, customer_id INT, total INT, PRIMARY KEY (id))",
+        );
+        assert_eq!(
+            Output::Affected(3),
+            run(
+                c,
+                b,
+                "INSERT INTO customers VALUES (1, 'alice', 'alice@example.com'), \
+                 (2, 'bob', 'bob@example.com'), (3, 'carol', 'carol@example.com')",
+            )
+        );
+        assert_eq!(
+            Output::Affected(4),
+            run(
+                c,
+                b,
+                "INSERT INTO orders (customer_id, id, total) \
+                 VALUES (1, 10, 250), (1, 11, 100), (2, 12, 75), (1, 13, 10 * -2)",
+            )
+        );
+
+        assert_eq!(
+            " name  | orders | spent
+-------+--------+-------
+ alice |      3 |   330
+ bob   |      1 |    75
+(2 rows)",
+            text(run(
+                c,
+                b,
+                "SELECT c.name, COUNT(*) AS orders, SUM(o.total) AS spent \
+                 FROM customers c JOIN orders o ON c.id = o.customer_id \
+                 GROUP BY c.name ORDER BY spent DESC",
+            ))
+        );
+        assert_eq!(
+            " name  | total
+-------+-------
+ carol |  NULL
+(1 row)",
+            text(run(
+                c,
+                b,
+                "SELECT name, total FROM customers LEFT JOIN orders ON customers.id = customer_id \
+                 WHERE customers.id = 3",
+            ))
+        );
+
+        assert_eq!(
+            Output::Affected(2),
+            run(
+                c,
+                b,
+                "UPDATE orders SET total = total + 1 WHERE customer_id = 1 AND total > 0"
+            )
+        );
+        assert_eq!(
+            Output::Affected(1),
+            run(c, b, "DELETE FROM orders WHERE total < 0")
+        );
+        assert_eq!(
+            " id | total
+----+-------
+ 10 |   251
+ 11 |   101
+(2 rows)",
+            text(run(
+                c,
+                b,
+                "SELECT id, total FROM orders WHERE customer_id = 1 ORDER BY 1 LIMIT 5"
+            ))
+        );
+
+        let plan = text(run(
+            c,
+            b,
+            "EXPLAIN SELECT name FROM customers WHERE email = 'bob@example.com'",
+        ));
+        assert!(plan.contains("IndexScan table=customers"), "{plan}");
+
+        assert_eq!(
+            "table customers already exists",
+            run_err(c, b, "CREATE TABLE customers (id INT)")
+        );
+        assert_eq!(
+            "invalid table definition: the primary key must be the leading columns of the table",
+            run_err(c, b, "CREATE TABLE t (a INT, b INT, PRIMARY KEY (b))")
+        );
+        assert_eq!(
+            "column name must appear in GROUP BY or be used in an aggregate function",
+            run_err(c, b, "SELECT name, COUNT(*) FROM customers")
+        );
+        assert_eq!(
+            "operator + cannot be applied to TEXT and INT",
+            run_err(c, b, "SELECT name + 1 FROM customers")
+        );
+        assert_eq!(
+            "column id is of type INT, but the value is of type TEXT",
+            run_err(c, b, "INSERT INTO orders (id) VALUES ('x')")
+        );
+        assert_eq!(
+            "column reference id is ambiguous",
+            run_err(c, b, "SELECT id FROM customers, orders")
+        );
+
+        assert_eq!(
+            "CREATE TABLE customers (
+  id INT,
+  name TEXT,
+  email TEXT,
+  PRIMARY KEY (id)
+);
+CREATE UNIQUE INDEX ON customers (email);",
+            schema(c.table("customers").unwrap())
+        );
+    }
+
+    #[test]
+    fn test_hash_index() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let mut bufmgr = BufferPoolManager::new(disk, BufferPool::new(10));
+        let mut catalog = Catalog::create(&mut bufmgr).unwrap();
+        let c = &mut catalog;
+        let b = &mut bufmgr;
+
+        run(c, b, "CREATE TABLE users (id INT PRIMARY KEY, token TEXT)");
+        run(c, b, "INSERT INTO users VALUES (1, 'a1'), (2, 'b2')");
+        run(c, b, "CREATE UNIQUE INDEX ON users USING HASH (token)");
+        run(c, b, "INSERT INTO users VALUES (3, 'c3')");
+        assert!(schema(c.table("users").unwrap())
+            .contains("CREATE UNIQUE INDEX ON users USING HASH (token);"));
+
+        let plan = text(run(c, b, "EXPLAIN SELECT id FROM users WHERE token = 'c3'"));
+        assert!(plan.contains("HashIndexScan table=users"), "{plan}");
+        assert_eq!(
+            " id
+----
+  3
+(1 row)",
+            text(run(c, b, "SELECT id FROM users WHERE token = 'c3'"))
+        );
+        assert_eq!(0, run_rows(c, b, "SELECT id FROM users WHERE token = 'd4'"));
+        // A hash index cannot find a range of keys.
+        let plan = text(run(c, b, "EXPLAIN SELECT id FROM users WHERE token > 'b'"));
+        assert!(plan.contains("SeqScan"), "{plan}");
+
+        assert_eq!(
+            "duplicate key violates unique index users_token_key",
+            run_err(c, b, "INSERT INTO users VALUES (4, 'a1')")
+        );
+        run(c, b, "UPDATE users SET token = 'a2' WHERE id = 1");
+        run(c, b, "DELETE FROM users WHERE id = 2");
+        assert_eq!(
+            " id | token
+----+-------
+  1 | a2
+  3 | c3
+(2 rows)",
+            text(run(
+                c,
+                b,
+                "SELECT * FROM users WHERE token = 'a2' OR token = 'c3' OR token = 'b2'"
+            ))
+        );
+        assert_eq!(0, run_rows(c, b, "SELECT id FROM users WHERE token = 'b2'"));
+        assert_eq!(1, run_rows(c, b, "SELECT id FROM users WHERE token = 'a2'"));
+        assert_eq!(
+            "hash indexes with included columns are not supported",
+            run_err(
+                c,
+                b,
+                "CREATE UNIQUE INDEX ON users USING HASH (token) INCLUDE (id)"
+            )
+        );
+    }
+
+    #[test]
+    fn test_params() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let mut bufmgr = BufferPoolManager::new(disk, BufferPool::new(10));
+        let mut catalog = Catalog::default();
+        run(
+            &mut catalog,
+            &mut bufmgr,
+            "CREATE TABLE t (id INT, name TEXT, ok BOOLEAN)",
+        );
+
+        let mut insert = parse("INSERT INTO t (name, id) VALUES ($2, $1 + 1)").unwrap();
+        assert_eq!(
+            vec![Some(DataType::Int), Some(DataType::Text)],
+            param_types(&catalog, &insert).unwrap()
+        );
+        insert
+            .bind_params(&[Value::Int(41), Value::Text("x".to_string())])
+            .unwrap();
+        assert_eq!(
+            Output::Affected(1),
+            execute(&mut catalog, &mut bufmgr, &insert).unwrap()
+        );
+
+        let select = parse("SELECT $3 FROM t WHERE name = $1 AND NOT $2").unwrap();
+        assert_eq!(
+            vec![Some(DataType::Text), Some(DataType::Bool), None],
+            param_types(&catalog, &select).unwrap()
+        );
+        let mut select = parse("SELECT id FROM t WHERE $1 = name").unwrap();
+        assert!(execute(&mut catalog, &mut bufmgr, &select).is_err());
+        select.bind_params(&[Value::Text("x".to_string())]).unwrap();
+        assert_eq!(
+            " id
+----
+ 42
+(1 row)",
+            text(execute(&mut catalog, &mut bufmgr, &select).unwrap())
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use super::parser::ParseError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    /// An identifier or keyword, folded to lower case.
+    Ident(String),
+    Int(i64),
+    Str(String),
+    /// A parameter `$n`, holding `n`.
+    Param(usize),
+    /// A named parameter `:name`, holding the name folded to lower case.
+    NamedParam(String),
+    Punct(&'static str),
+}
+
+/// Punctuation, with the two-character operators first so that they are matched first. `?` is a
+/// positional parameter.
+const PUNCTS: [&str; 18] = [
+    "<>", "!=", "<=", ">=", "||", "(", ")", ",", ";", ".", "*", "=", "<", ">", "+", "-", "/", "?",
+];
+
+/// Splits `sql` into tokens, each with the byte offset at which it starts.
+pub fn tokenize(sql: &str) -> Result<Vec<(usize, Token)>, ParseError> {
+    let mut tokens = vec![];
+    let mut chars = sql.char_indices().peekable();
+    while let Some(&(offset, c)) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if sql[offset..].starts_with("--") {
+            while chars.next_if(|&(_, c)| c != '\n').is_some() {}
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let mut ident = String::new();
+            while let Some((_, c)) = chars.next_if(|&(_, c)| c.is_ascii_alphanumeric() || c == '_')
+            {
+                ident.push(c.to_ascii_lowercase());
+            }
+            tokens.push((offset, Token::Ident(ident)));
+        } else if c.is_ascii_digit() {
+            let mut digits = String::new();
+            while let Some((_, c)) = chars.next_if(|&(_, c)| c.is_ascii_digit()) {
+                digits.push(c);
+            }
+            let n = digits
+                .parse()
+                .map_err(|_| ParseError::new("integer is too large", offset))?;
+            tokens.push((offset, Token::Int(n)));
+        } else if c == '$' {
+            chars.next();
+            let mut digits = String::new();
+            while let Some((_, c)) = chars.next_if(|&(_, c)| c.is_ascii_digit()) {
+                digits.push(c);
+            }
+            match digits.parse() {
+                Ok(n) if n > 0 => tokens.push((offset, Token::Param(n))),
+                _ => return Err(ParseError::new("invalid parameter", offset)),
+            }
+        } else if c == ':' {
+            chars.next();
+            let mut name = String::new();
+            while let Some((_, c)) = chars.next_if(|&(_, c)| c.is_ascii_alphanumeric() || c == '_')
+            {
+                name.push(c.to_ascii_lowercase());
+            }
+            if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                return Err(ParseError::new("invalid parameter", offset));
+            }
+            tokens.push((offset, Token::NamedParam(name)));
+        } else if c == '\'' {
+            chars.next();
+            let mut s = String::new();
+            loop {
+                match chars.next() {
+                    Some((_, '\'')) if chars.next_if(|&(_, c)| c == '\'').is_some() => s.push('\''),
+                    Some((_, '\'')) => break,
+                    Some((_, c)) => s.push(c),
+                    None => return Err(ParseError::new("unterminated string", sql.len())),
+                }
+            }
+            tokens.push((offset, Token::Str(s)));
+        } else {
+            let punct = PUNCTS
+                .iter()
+                .find(|punct| sql[offset..].starts_with(*punct))
+                .ok_or_else(|| ParseError::new(&format!("unexpected character {c:?}"), offset))?;
+            for _ in 0..punct.len() {
+                chars.next();
+            }
+            tokens.push((offset, Token::Punct(punct)));
+        }
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens: Vec<Token> = tokenize("SELECT name, 'it''s' FROM t -- comment\nWHERE id<>42;")
+            .unwrap()
+            .into_iter()
+            .map(|(_, token)| token)
+            .collect();
+        assert_eq!(
+            vec![
+                Token::Ident("select".to_string()),
+                Token::Ident("name".to_string()),
+                Token::Punct(","),
+                Token::Str("it's".to_string()),
+                Token::Ident("from".to_string()),
+                Token::Ident("t".to_string()),
+                Token::Ident("where".to_string()),
+                Token::Ident("id".to_string()),
+                Token::Punct("<>"),
+                Token::Int(42),
+                Token::Punct(";"),
+            ],
+            tokens
+        );
+        assert_eq!(11, tokenize("SELECT 'abc").unwrap_err().offset);
+
+        let tokens: Vec<Token> = tokenize("$2 ? :Name")
+            .unwrap()
+            .into_iter()
+            .map(|(_, token)| token)
+            .collect();
+        assert_eq!(
+            vec![
+                Token::Param(2),
+                Token::Punct("?"),
+                Token::NamedParam("name".to_string()),
+            ],
+            tokens
+        );
+        assert_eq!(5, tokenize("id = :1").unwrap_err().offset);
+    }
+}
@@ -0,0 +1,1081 @@
+//! Maps rows to and from Rust types with serde: a [`RowDeserializer`] reads a row into a struct
+//! by column name, or a tuple by position, and [`to_row`] turns a struct into the values of the
+//! columns of a table.
+//!
+//! An empty column, which is how NULL is stored, is read as `None` into `Option` fields, and
+//! `None` is written as an empty column.
+
+use std::fmt::Display;
+
+use serde::de::value::BorrowedStrDeserializer;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, SeqAccess, Visitor};
+use serde::forward_to_deserialize_any;
+use serde::ser::{self, Impossible, Serialize, SerializeSeq, SerializeStruct, SerializeTuple};
+use thiserror::Error;
+
+use super::value::Value;
+use crate::catalog::{Column, DataType};
+use crate::query::expr::{FALSE, TRUE};
+use crate::query::TupleSlice;
+use crate::tuple;
+
+/// An error mapping a row to or from a Rust type.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{0}")]
+pub struct MappingError(String);
+
+impl de::Error for MappingError {
+    fn custom<T: Display>(msg: T) -> Self {
+        MappingError(msg.to_string())
+    }
+}
+
+impl ser::Error for MappingError {
+    fn custom<T: Display>(msg: T) -> Self {
+        MappingError(msg.to_string())
+    }
+}
+
+/// Reads a row whose columns are `columns` into a `T`.
+pub fn from_row<'de, T: de::Deserialize<'de>>(
+    columns: &'de [Column],
+    row: TupleSlice<'de>,
+) -> Result<T, MappingError> {
+    T::deserialize(RowDeserializer::new(columns, row))
+}
+
+/// Returns the values of `columns` in `value`, a struct with a field for some of the columns or
+/// a tuple with a value for each of them. Columns without a field are `None`.
+pub fn to_row<T: Serialize + ?Sized>(
+    value: &T,
+    columns: &[Column],
+) -> Result<Vec<Option<Value>>, MappingError> {
+    value.serialize(RowSerializer { columns })
+}
+
+/// Deserializes a row into a struct or map by column name, into a tuple or sequence by
+/// position, or a row of a single column into a value.
+pub struct RowDeserializer<'de> {
+    columns: &'de [Column],
+    row: TupleSlice<'de>,
+}
+
+impl<'de> RowDeserializer<'de> {
+    pub fn new(columns: &'de [Column], row: TupleSlice<'de>) -> Self {
+        Self { columns, row }
+    }
+
+    fn single_column(&self) -> Result<ColumnDeserializer<'de>, MappingError> {
+        match (self.columns, self.row) {
+            ([column], [bytes]) => Ok(ColumnDeserializer { column, bytes }),
+            _ => Err(MappingError(format!(
+                "expected a single column, found {}",
+                self.row.len()
+            ))),
+        }
+    }
+
+    fn access(&self) -> RowAccess<'de> {
+        RowAccess {
+            columns: self.columns,
+            row: self.row,
+            index: 0,
+        }
+    }
+}
+
+/// Deserializes a value of a row of a single column.
+macro_rules! single_column {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MappingError> {
+                self.single_column()?.$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for RowDeserializer<'de> {
+    type Error = MappingError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MappingError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_map<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MappingError> {
+        visitor.visit_map(self.access())
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, MappingError> {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MappingError> {
+        visitor.visit_seq(self.access())
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, MappingError> {
+        if len != self.row.len() {
+            return Err(MappingError(format!(
+                "expected {len} columns, found {}",
+                self.row.len()
+            )));
+        }
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, MappingError> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, MappingError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, MappingError> {
+        self.single_column()?
+            .deserialize_enum(name, variants, visitor)
+    }
+
+    single_column! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32 deserialize_i64
+        deserialize_u8 deserialize_u16 deserialize_u32 deserialize_u64 deserialize_f32
+        deserialize_f64 deserialize_char deserialize_str deserialize_string deserialize_bytes
+        deserialize_byte_buf deserialize_option deserialize_unit deserialize_identifier
+        deserialize_ignored_any
+    }
+
+    fn deserialize_unit_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, MappingError> {
+        visitor.visit_unit()
+    }
+}
+
+/// Reads the columns of a row in order, as a map from their names or as a sequence.
+struct RowAccess<'de> {
+    columns: &'de [Column],
+    row: TupleSlice<'de>,
+    index: usize,
+}
+
+impl<'de> RowAccess<'de> {
+    fn next_column(&mut self) -> Option<ColumnDeserializer<'de>> {
+        let column = self.columns.get(self.index)?;
+        let bytes = self.row.get(self.index)?;
+        self.index += 1;
+        Some(ColumnDeserializer { column, bytes })
+    }
+}
+
+impl<'de> MapAccess<'de> for RowAccess<'de> {
+    type Error = MappingError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, MappingError> {
+        match self.columns.get(self.index) {
+            Some(column) if self.index < self.row.len() => {
+                let name = BorrowedStrDeserializer::new(column.name.as_str());
+                seed.deserialize(name).map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> Result<V::Value, MappingError> {
+        let column = self
+            .next_column()
+            .ok_or_else(|| MappingError("no more columns".to_string()))?;
+        column.deserialize_with(seed)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.row.len() - self.index)
+    }
+}
+
+impl<'de> SeqAccess<'de> for RowAccess<'de> {
+    type Error = MappingError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, MappingError> {
+        match self.next_column() {
+            Some(column) => column.deserialize_with(seed).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.row.len() - self.index)
+    }
+}
+
+/// Deserializes the bytes of a column as a value of its type.
+struct ColumnDeserializer<'de> {
+    column: &'de Column,
+    bytes: &'de [u8],
+}
+
+impl<'de> ColumnDeserializer<'de> {
+    /// Deserializes the column with `seed`, naming the column in errors.
+    fn deserialize_with<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, MappingError> {
+        let name = &self.column.name;
+        seed.deserialize(self)
+            .map_err(|err| MappingError(format!("column {name}: {err}")))
+    }
+
+    fn invalid(&self) -> MappingError {
+        let ColumnDeserializer { column, bytes } = self;
+        match bytes.is_empty() {
+            true => MappingError("unexpected NULL".to_string()),
+            false => MappingError(format!("invalid {} value", column.data_type)),
+        }
+    }
+}
+
+impl<'de> Deserializer<'de> for ColumnDeserializer<'de> {
+    type Error = MappingError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MappingError> {
+        match self.column.data_type {
+            DataType::Int => match tuple::decode_int(self.bytes) {
+                Some(n) => visitor.visit_i64(n),
+                None => Err(self.invalid()),
+            },
+            DataType::Text => match std::str::from_utf8(self.bytes) {
+                Ok(s) => visitor.visit_borrowed_str(s),
+                Err(_) => Err(self.invalid()),
+            },
+            DataType::Bool if self.bytes == TRUE => visitor.visit_bool(true),
+            DataType::Bool if self.bytes == FALSE => visitor.visit_bool(false),
+            DataType::Bool => Err(self.invalid()),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, MappingError> {
+        match self.bytes.is_empty() {
+            true => visitor.visit_none(),
+            false => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, MappingError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    /// Skips a column that the type has no field for, without decoding it.
+    fn deserialize_ignored_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> Result<V::Value, MappingError> {
+        visitor.visit_unit()
+    }
+
+    /// Reads a unit variant from its name, as [`to_row`] writes it.
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, MappingError> {
+        match (self.column.data_type, std::str::from_utf8(self.bytes)) {
+            (DataType::Text, Ok(variant)) if !variant.is_empty() => {
+                visitor.visit_enum(BorrowedStrDeserializer::new(variant))
+            }
+            _ => Err(self.invalid()),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf unit
+        unit_struct seq tuple tuple_struct map struct identifier
+    }
+}
+
+/// Serializes a struct or tuple into the values of the columns of a table.
+struct RowSerializer<'a> {
+    columns: &'a [Column],
+}
+
+/// Collects the values of the fields of a struct, or the elements of a tuple.
+struct FieldSerializer<'a> {
+    columns: &'a [Column],
+    values: Vec<Option<Value>>,
+    /// The number of elements of a tuple so far.
+    len: usize,
+}
+
+impl<'a> RowSerializer<'a> {
+    fn fields(self) -> FieldSerializer<'a> {
+        FieldSerializer {
+            columns: self.columns,
+            values: vec![None; self.columns.len()],
+            len: 0,
+        }
+    }
+
+    fn unsupported(&self) -> MappingError {
+        MappingError("a row can only be written from a struct or a tuple".to_string())
+    }
+}
+
+impl<'a> ser::Serializer for RowSerializer<'a> {
+    type Ok = Vec<Option<Value>>;
+    type Error = MappingError;
+    type SerializeSeq = FieldSerializer<'a>;
+    type SerializeTuple = FieldSerializer<'a>;
+    type SerializeTupleStruct = Impossible<Self::Ok, MappingError>;
+    type SerializeTupleVariant = Impossible<Self::Ok, MappingError>;
+    type SerializeMap = Impossible<Self::Ok, MappingError>;
+    type SerializeStruct = FieldSerializer<'a>;
+    type SerializeStructVariant = Impossible<Self::Ok, MappingError>;
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, MappingError> {
+        Ok(self.fields())
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, MappingError> {
+        Ok(self.fields())
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, MappingError> {
+        Ok(self.fields())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, MappingError> {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_char(self, _v: char) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, MappingError> {
+        Err(self.unsupported())
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, MappingError> {
+        Err(self.unsupported())
+    }
+}
+
+impl<'a> FieldSerializer<'a> {
+    fn set(&mut self, index: usize, value: &(impl Serialize + ?Sized)) -> Result<(), MappingError> {
+        let column = &self.columns[index];
+        let value = value
+            .serialize(ValueSerializer)
+            .map_err(|err| MappingError(format!("column {}: {err}", column.name)))?;
+        self.values[index] = value;
+        Ok(())
+    }
+
+    fn element(&mut self, value: &(impl Serialize + ?Sized)) -> Result<(), MappingError> {
+        if self.len == self.columns.len() {
+            return Err(MappingError(format!(
+                "expected {} values, found more",
+                self.columns.len()
+            )));
+        }
+        self.set(self.len, value)?;
+        self.len += 1;
+        Ok(())
+    }
+
+    fn end_elements(self) -> Result<Vec<Option<Value>>, MappingError> {
+        if self.len != self.columns.len() {
+            return Err(MappingError(format!(
+                "expected {} values, found {}",
+                self.columns.len(),
+                self.len
+            )));
+        }
+        Ok(self.values)
+    }
+}
+
+impl<'a> SerializeStruct for FieldSerializer<'a> {
+    type Ok = Vec<Option<Value>>;
+    type Error = MappingError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), MappingError> {
+        let index = self
+            .columns
+            .iter()
+            .position(|column| column.name == key)
+            .ok_or_else(|| MappingError(format!("column {key} does not exist")))?;
+        self.set(index, value)
+    }
+
+    fn end(self) -> Result<Self::Ok, MappingError> {
+        Ok(self.values)
+    }
+}
+
+impl<'a> SerializeTuple for FieldSerializer<'a> {
+    type Ok = Vec<Option<Value>>;
+    type Error = MappingError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), MappingError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, MappingError> {
+        self.end_elements()
+    }
+}
+
+impl<'a> SerializeSeq for FieldSerializer<'a> {
+    type Ok = Vec<Option<Value>>;
+    type Error = MappingError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), MappingError> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, MappingError> {
+        self.end_elements()
+    }
+}
+
+/// Serializes a field into a value, or `None` for NULL.
+struct ValueSerializer;
+
+impl ValueSerializer {
+    fn unsupported(kind: &str) -> MappingError {
+        MappingError(format!("{kind} cannot be stored in a column"))
+    }
+}
+
+impl ser::Serializer for ValueSerializer {
+    type Ok = Option<Value>;
+    type Error = MappingError;
+    type SerializeSeq = Impossible<Self::Ok, MappingError>;
+    type SerializeTuple = Impossible<Self::Ok, MappingError>;
+    type SerializeTupleStruct = Impossible<Self::Ok, MappingError>;
+    type SerializeTupleVariant = Impossible<Self::Ok, MappingError>;
+    type SerializeMap = Impossible<Self::Ok, MappingError>;
+    type SerializeStruct = Impossible<Self::Ok, MappingError>;
+    type SerializeStructVariant = Impossible<Self::Ok, MappingError>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, MappingError> {
+        Ok(Some(Value::Bool(v)))
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, MappingError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, MappingError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, MappingError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, MappingError> {
+        Ok(Some(Value::Int(v)))
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, MappingError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, MappingError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, MappingError> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, MappingError> {
+        let v = i64::try_from(v).map_err(|_| MappingError(format!("{v} is out of range")))?;
+        self.serialize_i64(v)
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok, MappingError> {
+        Err(Self::unsupported("a float"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok, MappingError> {
+        Err(Self::unsupported("a float"))
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, MappingError> {
+        Ok(Some(Value::Text(v.to_string())))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, MappingError> {
+        Ok(Some(Value::Text(v.to_string())))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, MappingError> {
+        Err(Self::unsupported("bytes"))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, MappingError> {
+        Ok(None)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Self::Ok, MappingError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, MappingError> {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, MappingError> {
+        Ok(None)
+    }
+
+    /// Stores a unit variant as its name.
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, MappingError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Self::Ok, MappingError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Self::Ok, MappingError> {
+        Err(Self::unsupported("an enum variant with data"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq, MappingError> {
+        Err(Self::unsupported("a sequence"))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, MappingError> {
+        Err(Self::unsupported("a tuple"))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, MappingError> {
+        Err(Self::unsupported("a tuple struct"))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, MappingError> {
+        Err(Self::unsupported("an enum variant with data"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, MappingError> {
+        Err(Self::unsupported("a map"))
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, MappingError> {
+        Err(Self::unsupported("a struct"))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, MappingError> {
+        Err(Self::unsupported("an enum variant with data"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Role {
+        Admin,
+        Member,
+    }
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct User<'a> {
+        id: i32,
+        name: &'a str,
+        role: Role,
+        email: Option<String>,
+    }
+
+    /// Stores `values` as a row, with an empty column for each `None`.
+    fn encode(values: &[Option<Value>]) -> Vec<Vec<u8>> {
+        values
+            .iter()
+            .map(|value| value.as_ref().map_or(vec![], Value::encode))
+            .collect()
+    }
+
+    #[test]
+    fn test_mapping() {
+        let columns = vec![
+            Column::new("id", DataType::Int),
+            Column::new("name", DataType::Text),
+            Column::new("role", DataType::Text),
+            Column::new("active", DataType::Bool),
+            Column::new("email", DataType::Text),
+        ];
+        let user = User {
+            id: 7,
+            name: "alice",
+            role: Role::Admin,
+            email: None,
+        };
+        let values = to_row(&user, &columns).unwrap();
+        assert_eq!(
+            vec![
+                Some(Value::Int(7)),
+                Some(Value::Text("alice".to_string())),
+                Some(Value::Text("Admin".to_string())),
+                None,
+                None,
+            ],
+            values
+        );
+
+        let row = encode(&values);
+        assert_eq!(user, from_row::<User>(&columns, &row).unwrap());
+
+        let (id, name): (i64, String) = from_row(&columns[..2], &row[..2]).unwrap();
+        assert_eq!((7, "alice".to_string()), (id, name));
+        let id: i64 = from_row(&columns[..1], &row[..1]).unwrap();
+        assert_eq!(7, id);
+
+        // The active column is NULL, which only an Option field can hold.
+        #[derive(Debug, Deserialize)]
+        #[allow(dead_code)]
+        struct Active {
+            active: bool,
+        }
+        let err = from_row::<Active>(&columns, &row).unwrap_err();
+        assert_eq!("column active: unexpected NULL", err.to_string());
+        assert!(from_row::<(i64, String)>(&columns, &row).is_err());
+
+        #[derive(Serialize)]
+        struct Unknown {
+            age: i32,
+        }
+        let err = to_row(&Unknown { age: 3 }, &columns).unwrap_err();
+        assert_eq!("column age does not exist", err.to_string());
+        assert!(to_row(&(1, "bob"), &columns).is_err());
+        assert_eq!(
+            vec![Some(Value::Int(1)), Some(Value::Text("bob".to_string()))],
+            to_row(&(1, "bob"), &columns[..2]).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_mapping_options() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Profile {
+            age: Option<i64>,
+            bio: Option<String>,
+            verified: Option<bool>,
+        }
+        let columns = vec![
+            Column::new("age", DataType::Int),
+            Column::new("bio", DataType::Text),
+            Column::new("verified", DataType::Bool),
+        ];
+        let profile = Profile {
+            age: Some(-30),
+            bio: Some(String::new()),
+            verified: Some(false),
+        };
+        let values = to_row(&profile, &columns).unwrap();
+        assert_eq!(
+            vec![
+                Some(Value::Int(-30)),
+                Some(Value::Text(String::new())),
+                Some(Value::Bool(false)),
+            ],
+            values
+        );
+        // An empty text is stored as an empty column, so it reads back as NULL.
+        let row = encode(&values);
+        let read = from_row::<Profile>(&columns, &row).unwrap();
+        assert_eq!(
+            Profile {
+                bio: None,
+                ..profile
+            },
+            read
+        );
+
+        let row = encode(&[Some(Value::Int(5)), None, Some(Value::Bool(true))]);
+        let read = from_row::<Profile>(&columns, &row).unwrap();
+        assert_eq!(
+            (Some(5), None, Some(true)),
+            (read.age, read.bio, read.verified)
+        );
+    }
+
+    #[test]
+    fn test_mapping_newtypes() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct UserId(i64);
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Account {
+            id: UserId,
+            name: String,
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Wrapped(Account);
+
+        let columns = vec![
+            Column::new("id", DataType::Int),
+            Column::new("name", DataType::Text),
+        ];
+        let account = Wrapped(Account {
+            id: UserId(42),
+            name: "carol".to_string(),
+        });
+        let values = to_row(&account, &columns).unwrap();
+        assert_eq!(
+            vec![Some(Value::Int(42)), Some(Value::Text("carol".to_string()))],
+            values
+        );
+        let row = encode(&values);
+        assert_eq!(account, from_row::<Wrapped>(&columns, &row).unwrap());
+        assert_eq!(UserId(42), from_row(&columns[..1], &row[..1]).unwrap());
+
+        let err = from_row::<UserId>(&columns, &row).unwrap_err();
+        assert_eq!("expected a single column, found 2", err.to_string());
+    }
+
+    #[test]
+    fn test_mapping_enums() {
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        enum Tag {
+            Plain,
+            Named(String),
+            Pair(i64, i64),
+            Labeled { label: String },
+        }
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Tagged {
+            tag: Tag,
+        }
+
+        let text = [Column::new("tag", DataType::Text)];
+        let values = to_row(&Tagged { tag: Tag::Plain }, &text).unwrap();
+        assert_eq!(vec![Some(Value::Text("Plain".to_string()))], values);
+        let row = encode(&values);
+        assert_eq!(Tagged { tag: Tag::Plain }, from_row(&text, &row).unwrap());
+
+        let rejected = [
+            Tag::Named("x".to_string()),
+            Tag::Pair(1, 2),
+            Tag::Labeled {
+                label: "x".to_string(),
+            },
+        ];
+        for tag in rejected {
+            let err = to_row(&Tagged { tag }, &text).unwrap_err();
+            assert_eq!(
+                "column tag: an enum variant with data cannot be stored in a column",
+                err.to_string()
+            );
+        }
+
+        // A variant is read from its name, and only from a text column.
+        let row = encode(&[Some(Value::Text("Other".to_string()))]);
+        let err = from_row::<Tagged>(&text, &row).unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("column tag: unknown variant `Other`"));
+        let row = encode(&[Some(Value::Text("Named".to_string()))]);
+        assert!(from_row::<Tagged>(&text, &row).is_err());
+
+        let int = [Column::new("tag", DataType::Int)];
+        let row = encode(&[Some(Value::Int(0))]);
+        let err = from_row::<Tagged>(&int, &row).unwrap_err();
+        assert_eq!("column tag: invalid INT value", err.to_string());
+        let err = from_row::<Tagged>(&text, &[vec![]]).unwrap_err();
+        assert_eq!("column tag: unexpected NULL", err.to_string());
+    }
+
+    #[test]
+    fn test_mapping_rejected_values() {
+        #[derive(Serialize)]
+        struct Counter {
+            n: u64,
+        }
+
+        #[derive(Serialize)]
+        struct Ratio {
+            n: f64,
+        }
+
+        #[derive(Serialize)]
+        struct Small {
+            n: f32,
+        }
+
+        struct Blob;
+
+        impl Serialize for Blob {
+            fn serialize<S: ser::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                serializer.serialize_bytes(b"\x00\x01")
+            }
+        }
+
+        #[derive(Serialize)]
+        struct Data {
+            n: Blob,
+        }
+
+        let columns = [Column::new("n", DataType::Int)];
+        let max = i64::MAX as u64;
+        assert_eq!(
+            vec![Some(Value::Int(i64::MAX))],
+            to_row(&Counter { n: max }, &columns).unwrap()
+        );
+        let err = to_row(&Counter { n: max + 1 }, &columns).unwrap_err();
+        assert_eq!(
+            "column n: 9223372036854775808 is out of range",
+            err.to_string()
+        );
+
+        let err = to_row(&Ratio { n: 0.5 }, &columns).unwrap_err();
+        assert_eq!(
+            "column n: a float cannot be stored in a column",
+            err.to_string()
+        );
+        let err = to_row(&Small { n: 0.5 }, &columns).unwrap_err();
+        assert_eq!(
+            "column n: a float cannot be stored in a column",
+            err.to_string()
+        );
+        let err = to_row(&Data { n: Blob }, &columns).unwrap_err();
+        assert_eq!(
+            "column n: bytes cannot be stored in a column",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn test_mapping_sequence_lengths() {
+        let columns = vec![
+            Column::new("a", DataType::Int),
+            Column::new("b", DataType::Int),
+            Column::new("c", DataType::Int),
+        ];
+        assert_eq!(
+            vec![
+                Some(Value::Int(1)),
+                Some(Value::Int(2)),
+                Some(Value::Int(3))
+            ],
+            to_row(&vec![1, 2, 3], &columns).unwrap()
+        );
+        let err = to_row(&vec![1, 2], &columns).unwrap_err();
+        assert_eq!("expected 3 values, found 2", err.to_string());
+        let err = to_row(&vec![1, 2, 3, 4], &columns).unwrap_err();
+        assert_eq!("expected 3 values, found more", err.to_string());
+        let err = to_row(&(1, 2), &columns).unwrap_err();
+        assert_eq!("expected 3 values, found 2", err.to_string());
+
+        let row = encode(&to_row(&(1, 2, 3), &columns).unwrap());
+        assert_eq!(vec![1, 2, 3], from_row::<Vec<i64>>(&columns, &row).unwrap());
+        assert_eq!(
+            vec![1, 2],
+            from_row::<Vec<i64>>(&columns[..2], &row[..2]).unwrap()
+        );
+        let err = from_row::<(i64, i64)>(&columns, &row).unwrap_err();
+        assert_eq!("expected 2 columns, found 3", err.to_string());
+        let err = from_row::<(i64, i64, i64, i64)>(&columns, &row).unwrap_err();
+        assert_eq!("expected 4 columns, found 3", err.to_string());
+    }
+}
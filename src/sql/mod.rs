@@ -0,0 +1,17 @@
+//! A SQL front end: statements are parsed into an [`ast`], bound against the
+//! [`Catalog`](crate::catalog::Catalog) into logical plans and run through the
+//! [`Optimizer`](crate::query::optimizer::Optimizer).
+
+pub mod ast;
+mod binder;
+mod exec;
+mod lexer;
+mod mapping;
+mod parser;
+mod value;
+
+pub use binder::param_types;
+pub use exec::{describe, execute, prepare, query, schema, Output, Plan, ResultSet};
+pub use mapping::{from_row, to_row, MappingError, RowDeserializer};
+pub use parser::{is_complete, parse, parse_statements, parse_with_param_names, ParseError};
+pub use value::{ColumnDisplay, Value};
@@ -0,0 +1,836 @@
+use thiserror::Error;
+
+use super::ast::{Expr, InsertSource, Join, OrderBy, Select, SelectItem, Statement, TableRef};
+use super::lexer::{tokenize, Token};
+use super::value::Value;
+use crate::catalog::{Column, DataType};
+use crate::query::expr::BinaryOp;
+use crate::table::IndexKind;
+
+/// A syntax error, at a byte offset in the SQL text.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{message} at offset {offset}")]
+pub struct ParseError {
+    pub message: String,
+    pub offset: usize,
+}
+
+impl ParseError {
+    pub fn new(message: &str, offset: usize) -> Self {
+        Self {
+            message: message.to_string(),
+            offset,
+        }
+    }
+}
+
+/// Keywords that cannot be used as table aliases or column aliases without `AS`.
+const RESERVED: &[&str] = &[
+    "and", "as", "by", "from", "group", "inner", "join", "left", "limit", "not", "offset", "on",
+    "or", "order", "outer", "select", "set", "values", "where",
+];
+
+/// Parses a single statement, optionally followed by a semicolon.
+pub fn parse(sql: &str) -> Result<Statement, ParseError> {
+    Ok(parse_with_param_names(sql)?.0)
+}
+
+/// Parses a single statement like [`parse`], also returning the names of its `:name`
+/// parameters, in the order of their numbers.
+pub fn parse_with_param_names(sql: &str) -> Result<(Statement, Vec<String>), ParseError> {
+    let mut statements = parse_all(sql)?;
+    match statements.len() {
+        1 => Ok(statements.pop().unwrap()),
+        0 => Err(ParseError::new("empty statement", 0)),
+        _ => Err(ParseError::new("expected a single statement", 0)),
+    }
+}
+
+/// Parses statements separated by semicolons.
+pub fn parse_statements(sql: &str) -> Result<Vec<Statement>, ParseError> {
+    let statements = parse_all(sql)?;
+    Ok(statements
+        .into_iter()
+        .map(|(statement, _)| statement)
+        .collect())
+}
+
+fn parse_all(sql: &str) -> Result<Vec<(Statement, Vec<String>)>, ParseError> {
+    let mut parser = Parser {
+        tokens: tokenize(sql)?,
+        pos: 0,
+        end: sql.len(),
+        params: Params::None,
+    };
+    let mut statements = vec![];
+    loop {
+        while parser.eat_punct(";") {}
+        if parser.peek().is_none() {
+            return Ok(statements);
+        }
+        parser.params = Params::None;
+        let statement = parser.statement()?;
+        let names = match std::mem::replace(&mut parser.params, Params::None) {
+            Params::Named(names) => names,
+            _ => vec![],
+        };
+        statements.push((statement, names));
+        if parser.peek().is_some() {
+            parser.expect_punct(";")?;
+        }
+    }
+}
+
+/// Returns whether `sql` is ready to be run: it ends with a semicolon outside of any string, or
+/// has an error that more input cannot fix.
+pub fn is_complete(sql: &str) -> bool {
+    match tokenize(sql) {
+        Ok(tokens) => matches!(tokens.last(), Some((_, Token::Punct(";")))),
+        Err(err) => err.offset < sql.len(),
+    }
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    /// The length of the SQL text, the offset of errors at the end of it.
+    end: usize,
+    /// The parameters of the statement being parsed.
+    params: Params,
+}
+
+/// How the parameters of a statement are written, which is the same for all of them.
+enum Params {
+    None,
+    /// `$n`.
+    Numbered,
+    /// `?`, numbered in order, with the number of those seen so far.
+    Positional(usize),
+    /// `:name`, with the names in the order of their first use.
+    Named(Vec<String>),
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, token)| token)
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.pos)
+            .map_or(self.end, |(offset, _)| *offset)
+    }
+
+    fn error<T>(&self, expected: &str) -> Result<T, ParseError> {
+        let found = match self.peek() {
+            Some(Token::Ident(ident)) => format!("{ident:?}"),
+            Some(Token::Int(n)) => n.to_string(),
+            Some(Token::Str(s)) => format!("'{s}'"),
+            Some(Token::Param(n)) => format!("${n}"),
+            Some(Token::NamedParam(name)) => format!(":{name}"),
+            Some(Token::Punct(punct)) => format!("{punct:?}"),
+            None => "end of input".to_string(),
+        };
+        Err(ParseError::new(
+            &format!("expected {expected}, found {found}"),
+            self.offset(),
+        ))
+    }
+
+    fn peek_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Ident(ident)) if ident == keyword)
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = self.peek_keyword(keyword);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), ParseError> {
+        if !self.eat_keyword(keyword) {
+            return self.error(&keyword.to_uppercase());
+        }
+        Ok(())
+    }
+
+    fn eat_punct(&mut self, punct: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Punct(p)) if *p == punct);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<(), ParseError> {
+        if !self.eat_punct(punct) {
+            return self.error(&format!("{punct:?}"));
+        }
+        Ok(())
+    }
+
+    fn ident(&mut self) -> Result<String, ParseError> {
+        match self.peek() {
+            Some(Token::Ident(ident)) if !RESERVED.contains(&ident.as_str()) => {
+                let ident = ident.clone();
+                self.pos += 1;
+                Ok(ident)
+            }
+            _ => self.error("an identifier"),
+        }
+    }
+
+    fn usize(&mut self) -> Result<usize, ParseError> {
+        match self.peek() {
+            Some(&Token::Int(n)) => {
+                self.pos += 1;
+                Ok(n as usize)
+            }
+            _ => self.error("an integer"),
+        }
+    }
+
+    /// Parses `item [, item]...`.
+    fn list<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        let mut items = vec![item(self)?];
+        while self.eat_punct(",") {
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+
+    /// Parses `(item [, item]...)`.
+    fn parenthesized<T>(
+        &mut self,
+        item: impl FnMut(&mut Self) -> Result<T, ParseError>,
+    ) -> Result<Vec<T>, ParseError> {
+        self.expect_punct("(")?;
+        let items = self.list(item)?;
+        self.expect_punct(")")?;
+        Ok(items)
+    }
+
+    fn statement(&mut self) -> Result<Statement, ParseError> {
+        if self.eat_keyword("create") {
+            if self.eat_keyword("table") {
+                return self.create_table();
+            }
+            self.expect_keyword("unique")?;
+            self.expect_keyword("index")?;
+            return self.create_index();
+        }
+        if self.eat_keyword("insert") {
+            return self.insert();
+        }
+        if self.eat_keyword("update") {
+            return self.update();
+        }
+        if self.eat_keyword("delete") {
+            self.expect_keyword("from")?;
+            let table = self.ident()?;
+            let filter = self.filter()?;
+            return Ok(Statement::Delete { table, filter });
+        }
+        if self.peek_keyword("select") {
+            return Ok(Statement::Select(Box::new(self.select()?)));
+        }
+        if self.eat_keyword("explain") {
+            let analyze = self.eat_keyword("analyze");
+            let select = Box::new(self.select()?);
+            return Ok(Statement::Explain { analyze, select });
+        }
+        if self.eat_keyword("analyze") {
+            return Ok(Statement::Analyze);
+        }
+        self.error("a statement")
+    }
+
+    fn create_table(&mut self) -> Result<Statement, ParseError> {
+        let name = self.ident()?;
+        let mut columns = vec![];
+        let mut primary_key = vec![];
+        self.expect_punct("(")?;
+        loop {
+            if self.eat_keyword("primary") {
+                self.expect_keyword("key")?;
+                if !primary_key.is_empty() {
+                    return self.error("a single primary key");
+                }
+                primary_key = self.parenthesized(Self::ident)?;
+            } else {
+                let column = self.ident()?;
+                let data_type = self.data_type()?;
+                if self.eat_keyword("primary") {
+                    self.expect_keyword("key")?;
+                    if !primary_key.is_empty() {
+                        return self.error("a single primary key");
+                    }
+                    primary_key.push(column.clone());
+                }
+                columns.push(Column::new(&column, data_type));
+            }
+            if !self.eat_punct(",") {
+                break;
+            }
+        }
+        self.expect_punct(")")?;
+        Ok(Statement::CreateTable {
+            name,
+            columns,
+            primary_key,
+        })
+    }
+
+    fn data_type(&mut self) -> Result<DataType, ParseError> {
+        let data_type = match self.peek() {
+            Some(Token::Ident(ident)) => match ident.as_str() {
+                "int" | "integer" | "bigint" => DataType::Int,
+                "text" | "varchar" => DataType::Text,
+                "bool" | "boolean" => DataType::Bool,
+                _ => return self.error("a data type"),
+            },
+            _ => return self.error("a data type"),
+        };
+        self.pos += 1;
+        Ok(data_type)
+    }
+
+    fn create_index(&mut self) -> Result<Statement, ParseError> {
+        self.expect_keyword("on")?;
+        let table = self.ident()?;
+        let mut kind = IndexKind::BTree;
+        if self.eat_keyword("using") {
+            if self.eat_keyword("hash") {
+                kind = IndexKind::Hash;
+            } else if !self.eat_keyword("btree") {
+                return self.error("an index method");
+            }
+        }
+        let columns = self.parenthesized(Self::ident)?;
+        let include = match self.eat_keyword("include") {
+            true => self.parenthesized(Self::ident)?,
+            false => vec![],
+        };
+        Ok(Statement::CreateIndex {
+            table,
+            kind,
+            columns,
+            include,
+        })
+    }
+
+    fn insert(&mut self) -> Result<Statement, ParseError> {
+        self.expect_keyword("into")?;
+        let table = self.ident()?;
+        let columns = match self.peek() {
+            Some(Token::Punct("(")) => Some(self.parenthesized(Self::ident)?),
+            _ => None,
+        };
+        let source = if self.eat_keyword("values") {
+            InsertSource::Values(self.list(|parser| parser.parenthesized(Self::expr))?)
+        } else if self.peek_keyword("select") {
+            InsertSource::Select(Box::new(self.select()?))
+        } else {
+            return self.error("VALUES or SELECT");
+        };
+        Ok(Statement::Insert {
+            table,
+            columns,
+            source,
+        })
+    }
+
+    fn update(&mut self) -> Result<Statement, ParseError> {
+        let table = self.ident()?;
+        self.expect_keyword("set")?;
+        let assignments = self.list(|parser| {
+            let column = parser.ident()?;
+            parser.expect_punct("=")?;
+            Ok((column, parser.expr()?))
+        })?;
+        let filter = self.filter()?;
+        Ok(Statement::Update {
+            table,
+            assignments,
+            filter,
+        })
+    }
+
+    fn filter(&mut self) -> Result<Option<Expr>, ParseError> {
+        match self.eat_keyword("where") {
+            true => Ok(Some(self.expr()?)),
+            false => Ok(None),
+        }
+    }
+
+    fn select(&mut self) -> Result<Select, ParseError> {
+        self.expect_keyword("select")?;
+        let items = self.list(|parser| {
+            if parser.eat_punct("*") {
+                return Ok(SelectItem::Wildcard);
+            }
+            let expr = parser.expr()?;
+            let alias = parser.alias()?;
+            Ok(SelectItem::Expr { expr, alias })
+        })?;
+        self.expect_keyword("from")?;
+        let from = self.table_ref()?;
+        let mut joins = vec![];
+        loop {
+            if self.eat_punct(",") {
+                let table = self.table_ref()?;
+                joins.push(Join {
+                    table,
+                    cond: None,
+                    left_outer: false,
+                });
+                continue;
+            }
+            let left_outer = self.eat_keyword("left");
+            if left_outer {
+                self.eat_keyword("outer");
+            } else if !self.eat_keyword("inner") && !self.peek_keyword("join") {
+                break;
+            }
+            self.expect_keyword("join")?;
+            let table = self.table_ref()?;
+            self.expect_keyword("on")?;
+            let cond = Some(self.expr()?);
+            joins.push(Join {
+                table,
+                cond,
+                left_outer,
+            });
+        }
+        let filter = self.filter()?;
+        let mut group_by = vec![];
+        if self.eat_keyword("group") {
+            self.expect_keyword("by")?;
+            group_by = self.list(Self::expr)?;
+        }
+        let mut order_by = vec![];
+        if self.eat_keyword("order") {
+            self.expect_keyword("by")?;
+            order_by = self.list(|parser| {
+                let expr = parser.expr()?;
+                let descending = parser.eat_keyword("desc");
+                if !descending {
+                    parser.eat_keyword("asc");
+                }
+                Ok(OrderBy { expr, descending })
+            })?;
+        }
+        let limit = match self.eat_keyword("limit") {
+            true => Some(self.usize()?),
+            false => None,
+        };
+        let offset = match self.eat_keyword("offset") {
+            true => Some(self.usize()?),
+            false => None,
+        };
+        Ok(Select {
+            items,
+            from,
+            joins,
+            filter,
+            group_by,
+            order_by,
+            limit,
+            offset,
+        })
+    }
+
+    fn table_ref(&mut self) -> Result<TableRef, ParseError> {
+        let name = self.ident()?;
+        let alias = self.alias()?;
+        Ok(TableRef { name, alias })
+    }
+
+    /// Parses `[AS] alias`, where `AS` may be left out before an unreserved name.
+    fn alias(&mut self) -> Result<Option<String>, ParseError> {
+        if self.eat_keyword("as") {
+            return Ok(Some(self.ident()?));
+        }
+        match self.peek() {
+            Some(Token::Ident(ident)) if !RESERVED.contains(&ident.as_str()) => {
+                Ok(Some(self.ident()?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Parses an expression. From the loosest to the tightest, the operators bind as `OR`,
+    /// `AND`, `NOT`, comparisons, `+ - ||`, `* /` and unary minus.
+    fn expr(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.and_expr()?;
+        while self.eat_keyword("or") {
+            lhs = Expr::binary(BinaryOp::Or, lhs, self.and_expr()?);
+        }
+        Ok(lhs)
+    }
+
+    fn and_expr(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.not_expr()?;
+        while self.eat_keyword("and") {
+            lhs = Expr::binary(BinaryOp::And, lhs, self.not_expr()?);
+        }
+        Ok(lhs)
+    }
+
+    fn not_expr(&mut self) -> Result<Expr, ParseError> {
+        if self.eat_keyword("not") {
+            return Ok(Expr::Not(Box::new(self.not_expr()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ParseError> {
+        let lhs = self.additive()?;
+        let op = match self.peek() {
+            Some(Token::Punct("=")) => BinaryOp::Eq,
+            Some(Token::Punct("<>" | "!=")) => BinaryOp::Ne,
+            Some(Token::Punct("<")) => BinaryOp::Lt,
+            Some(Token::Punct("<=")) => BinaryOp::Le,
+            Some(Token::Punct(">")) => BinaryOp::Gt,
+            Some(Token::Punct(">=")) => BinaryOp::Ge,
+            _ => return Ok(lhs),
+        };
+        self.pos += 1;
+        Ok(Expr::binary(op, lhs, self.additive()?))
+    }
+
+    fn additive(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.multiplicative()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Punct("+")) => BinaryOp::Add,
+                Some(Token::Punct("-")) => BinaryOp::Sub,
+                Some(Token::Punct("||")) => BinaryOp::Concat,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            lhs = Expr::binary(op, lhs, self.multiplicative()?);
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, ParseError> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Punct("*")) => BinaryOp::Mul,
+                Some(Token::Punct("/")) => BinaryOp::Div,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            lhs = Expr::binary(op, lhs, self.unary()?);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        if !self.eat_punct("-") {
+            return self.primary();
+        }
+        match self.unary()? {
+            Expr::Literal(Value::Int(n)) => Ok(Expr::Literal(Value::Int(-n))),
+            expr => Ok(Expr::binary(
+                BinaryOp::Sub,
+                Expr::Literal(Value::Int(0)),
+                expr,
+            )),
+        }
+    }
+
+    /// Numbers a parameter from 0, checking that it is written like the others.
+    fn param(&mut self, token: Token) -> Result<Expr, ParseError> {
+        let offset = self.offset();
+        self.pos += 1;
+        let index = match (&mut self.params, token) {
+            (params @ (Params::None | Params::Numbered), Token::Param(n)) => {
+                *params = Params::Numbered;
+                n - 1
+            }
+            (params @ Params::None, Token::Punct("?")) => {
+                *params = Params::Positional(1);
+                0
+            }
+            (Params::Positional(count), Token::Punct("?")) => {
+                *count += 1;
+                *count - 1
+            }
+            (params @ Params::None, Token::NamedParam(name)) => {
+                *params = Params::Named(vec![name]);
+                0
+            }
+            (Params::Named(names), Token::NamedParam(name)) => {
+                match names.iter().position(|other| *other == name) {
+                    Some(index) => index,
+                    None => {
+                        names.push(name);
+                        names.len() - 1
+                    }
+                }
+            }
+            _ => return Err(ParseError::new("cannot mix parameter styles", offset)),
+        };
+        Ok(Expr::Param(index))
+    }
+
+    fn primary(&mut self) -> Result<Expr, ParseError> {
+        let Some(token) = self.peek().cloned() else {
+            return self.error("an expression");
+        };
+        match token {
+            Token::Int(n) => {
+                self.pos += 1;
+                Ok(Expr::Literal(Value::Int(n)))
+            }
+            Token::Str(s) => {
+                self.pos += 1;
+                Ok(Expr::Literal(Value::Text(s)))
+            }
+            Token::Param(_) | Token::Punct("?") | Token::NamedParam(_) => self.param(token),
+            Token::Punct("(") => {
+                self.pos += 1;
+                let expr = self.expr()?;
+                self.expect_punct(")")?;
+                Ok(expr)
+            }
+            Token::Ident(ident) if ident == "true" || ident == "false" => {
+                self.pos += 1;
+                Ok(Expr::Literal(Value::Bool(ident == "true")))
+            }
+            Token::Ident(ident) if RESERVED.contains(&ident.as_str()) => {
+                self.error("an expression")
+            }
+            Token::Ident(_) => {
+                let name = self.ident()?;
+                if self.eat_punct(".") {
+                    let column = self.ident()?;
+                    return Ok(Expr::Column {
+                        table: Some(name),
+                        name: column,
+                    });
+                }
+                if !self.eat_punct("(") {
+                    return Ok(Expr::Column { table: None, name });
+                }
+                if self.eat_punct("*") {
+                    self.expect_punct(")")?;
+                    return Ok(Expr::Function {
+                        name,
+                        args: vec![],
+                        star: true,
+                    });
+                }
+                let args = match self.eat_punct(")") {
+                    true => vec![],
+                    false => {
+                        let args = self.list(Self::expr)?;
+                        self.expect_punct(")")?;
+                        args
+                    }
+                };
+                Ok(Expr::Function {
+                    name,
+                    args,
+                    star: false,
+                })
+            }
+            Token::Punct(_) => self.error("an expression"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn column(name: &str) -> Expr {
+        Expr::Column {
+            table: None,
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_parse_select() {
+        let statement = parse(
+            "SELECT c.name, COUNT(*) AS n FROM customers c LEFT JOIN orders o ON c.id = o.cid \
+             WHERE NOT c.id > -1 + 2 * 3 GROUP BY c.name ORDER BY n DESC LIMIT 10;",
+        )
+        .unwrap();
+        let Statement::Select(select) = statement else {
+            panic!("not a select: {statement:?}");
+        };
+        assert_eq!(2, select.items.len());
+        assert_eq!(
+            SelectItem::Expr {
+                expr: Expr::Function {
+                    name: "count".to_string(),
+                    args: vec![],
+                    star: true
+                },
+                alias: Some("n".to_string())
+            },
+            select.items[1]
+        );
+        assert_eq!(Some("c".to_string()), select.from.alias);
+        assert!(select.joins[0].left_outer);
+        assert_eq!(
+            Some(Expr::Not(Box::new(Expr::binary(
+                BinaryOp::Gt,
+                Expr::Column {
+                    table: Some("c".to_string()),
+                    name: "id".to_string()
+                },
+                Expr::binary(
+                    BinaryOp::Add,
+                    Expr::Literal(Value::Int(-1)),
+                    Expr::binary(
+                        BinaryOp::Mul,
+                        Expr::Literal(Value::Int(2)),
+                        Expr::Literal(Value::Int(3))
+                    )
+                )
+            )))),
+            select.filter
+        );
+        assert_eq!(
+            vec![OrderBy {
+                expr: column("n"),
+                descending: true
+            }],
+            select.order_by
+        );
+        assert_eq!(Some(10), select.limit);
+    }
+
+    #[test]
+    fn test_parse_statements() {
+        let statements = parse_statements(
+            "CREATE TABLE t (id INT, name TEXT, ok BOOLEAN, PRIMARY KEY (id));
+             CREATE UNIQUE INDEX ON t (name) INCLUDE (ok);
+             INSERT INTO t (id, name) VALUES (1, 'a'), (2, 'b');
+             UPDATE t SET name = name || 'x' WHERE id = 1;
+             DELETE FROM t;
+             EXPLAIN ANALYZE SELECT * FROM t;",
+        )
+        .unwrap();
+        assert_eq!(6, statements.len());
+        assert_eq!(
+            Statement::CreateTable {
+                name: "t".to_string(),
+                columns: vec![
+                    Column::new("id", DataType::Int),
+                    Column::new("name", DataType::Text),
+                    Column::new("ok", DataType::Bool),
+                ],
+                primary_key: vec!["id".to_string()],
+            },
+            statements[0]
+        );
+        assert_eq!(
+            Statement::CreateIndex {
+                table: "t".to_string(),
+                kind: IndexKind::BTree,
+                columns: vec!["name".to_string()],
+                include: vec!["ok".to_string()],
+            },
+            statements[1]
+        );
+        assert!(matches!(
+            parse("CREATE UNIQUE INDEX ON t USING HASH (name)").unwrap(),
+            Statement::CreateIndex {
+                kind: IndexKind::Hash,
+                ..
+            }
+        ));
+        assert!(parse("CREATE UNIQUE INDEX ON t USING GIST (name)").is_err());
+        assert!(matches!(
+            &statements[2],
+            Statement::Insert { columns: Some(columns), source: InsertSource::Values(rows), .. }
+                if columns.len() == 2 && rows.len() == 2
+        ));
+        assert_eq!(
+            Statement::Delete {
+                table: "t".to_string(),
+                filter: None
+            },
+            statements[4]
+        );
+        assert!(matches!(
+            statements[5],
+            Statement::Explain { analyze: true, .. }
+        ));
+
+        let err = parse("SELECT FROM t").unwrap_err();
+        assert_eq!(7, err.offset);
+        assert_eq!(
+            "expected an expression, found \"from\" at offset 7",
+            err.to_string()
+        );
+        assert!(parse("SELECT * FROM t; SELECT * FROM t").is_err());
+    }
+
+    #[test]
+    fn test_parse_params() {
+        let param = |index| Expr::Param(index);
+        let column = |name: &str| Expr::Column {
+            table: None,
+            name: name.to_string(),
+        };
+        let Statement::Delete {
+            filter: Some(filter),
+            ..
+        } = parse("DELETE FROM t WHERE id = ? OR name = ?").unwrap()
+        else {
+            panic!("expected a delete");
+        };
+        assert_eq!(
+            Expr::binary(
+                BinaryOp::Or,
+                Expr::binary(BinaryOp::Eq, column("id"), param(0)),
+                Expr::binary(BinaryOp::Eq, column("name"), param(1)),
+            ),
+            filter
+        );
+
+        let (statement, names) =
+            parse_with_param_names("UPDATE t SET name = :name WHERE id = :id OR id = :ID").unwrap();
+        assert_eq!(vec!["name".to_string(), "id".to_string()], names);
+        let Statement::Update { filter, .. } = statement else {
+            panic!("expected an update");
+        };
+        assert_eq!(
+            Some(Expr::binary(
+                BinaryOp::Or,
+                Expr::binary(BinaryOp::Eq, column("id"), param(1)),
+                Expr::binary(BinaryOp::Eq, column("id"), param(1)),
+            )),
+            filter
+        );
+
+        // Each statement numbers its own parameters.
+        let statements =
+            parse_statements("DELETE FROM t WHERE id = ?; DELETE FROM t WHERE id = ?").unwrap();
+        assert_eq!(statements[0], statements[1]);
+
+        let err = parse("SELECT * FROM t WHERE id = $1 OR id = ?").unwrap_err();
+        assert_eq!("cannot mix parameter styles at offset 38", err.to_string());
+    }
+
+    #[test]
+    fn test_is_complete() {
+        assert!(!is_complete("SELECT *\nFROM t"));
+        assert!(is_complete("SELECT *\nFROM t;"));
+        assert!(!is_complete("SELECT 'a;"));
+        assert!(is_complete("SELECT 'a;';"));
+        assert!(is_complete("SELECT 1; -- comment"));
+        assert!(is_complete("SELECT #;"));
+    }
+}
@@ -0,0 +1,110 @@
+use std::fmt::{self, Display, Formatter};
+
+use crate::catalog::DataType;
+use crate::query::expr::{FALSE, TRUE};
+use crate::tuple;
+
+/// A typed SQL value, stored in a column as the bytes returned by [`encode`](Self::encode).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Int(i64),
+    Text(String),
+    Bool(bool),
+}
+
+impl Value {
+    pub fn data_type(&self) -> DataType {
+        match self {
+            Value::Int(_) => DataType::Int,
+            Value::Text(_) => DataType::Text,
+            Value::Bool(_) => DataType::Bool,
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        match self {
+            Value::Int(n) => tuple::encode_int(*n).to_vec(),
+            Value::Text(s) => s.as_bytes().to_vec(),
+            Value::Bool(true) => TRUE.to_vec(),
+            Value::Bool(false) => FALSE.to_vec(),
+        }
+    }
+
+    /// Decodes the bytes of a column of `data_type`. Returns `None` if they are not a value of
+    /// the type. Empty bytes are always NULL, like the columns padding a left outer join, so
+    /// the empty TEXT value reads back as NULL too.
+    pub fn decode(bytes: &[u8], data_type: DataType) -> Option<Self> {
+        if bytes.is_empty() {
+            return None;
+        }
+        match data_type {
+            DataType::Int => tuple::decode_int(bytes).map(Value::Int),
+            DataType::Text => std::str::from_utf8(bytes)
+                .ok()
+                .map(|s| Value::Text(s.to_string())),
+            DataType::Bool if bytes == TRUE => Some(Value::Bool(true)),
+            DataType::Bool if bytes == FALSE => Some(Value::Bool(false)),
+            DataType::Bool => None,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{n}"),
+            Value::Text(s) => f.write_str(s),
+            Value::Bool(b) => write!(f, "{b}"),
+        }
+    }
+}
+
+/// Formats the bytes of a column of `data_type` for display: the value if they decode as one,
+/// `NULL` if they are empty and hex bytes otherwise.
+pub struct ColumnDisplay<'a>(pub &'a [u8], pub DataType);
+
+impl<'a> Display for ColumnDisplay<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let ColumnDisplay(bytes, data_type) = *self;
+        match Value::decode(bytes, data_type) {
+            Some(value) => f.pad(&value.to_string()),
+            None if bytes.is_empty() => f.pad("NULL"),
+            None => {
+                let hex: String = bytes.iter().map(|byte| format!("{byte:02x}")).collect();
+                f.pad(&format!("0x{hex}"))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_decode() {
+        for value in [
+            Value::Int(-42),
+            Value::Text("hello".to_string()),
+            Value::Bool(true),
+        ] {
+            let bytes = value.encode();
+            assert_eq!(
+                Some(&value),
+                Value::decode(&bytes, value.data_type()).as_ref()
+            );
+        }
+        assert_eq!(None, Value::decode(b"", DataType::Int));
+        assert_eq!(None, Value::decode(b"", DataType::Text));
+        assert_eq!("NULL", ColumnDisplay(b"", DataType::Text).to_string());
+        assert_eq!("NULL", ColumnDisplay(b"", DataType::Int).to_string());
+        assert_eq!("0xff", ColumnDisplay(&[0xff], DataType::Text).to_string());
+        assert_eq!(
+            "  -42",
+            format!(
+                "{:>5}",
+                ColumnDisplay(&tuple::encode_int(-42), DataType::Int)
+            )
+        );
+    }
+}
@@ -1,8 +1,11 @@
-use crate::btree::{BTree, Iter, SearchMode};
+use crate::btree::{BTree, SearchMode};
 use crate::buffer::BufferPoolManager;
 use crate::disk::PageId;
+use crate::error::DbError;
+use crate::hash::HashIndex;
+use crate::heap::{HeapFile, RecordId};
 use crate::tuple;
-use std::error::Error;
+use serde::{Deserialize, Serialize};
 
 pub struct Table {
     pub meta_page_id: PageId,
@@ -12,17 +15,13 @@ pub struct Table {
 }
 
 impl Table {
-    pub fn create(&mut self, bufmgr: &mut BufferPoolManager) -> Result<(), Box<dyn Error>> {
+    pub fn create(&mut self, bufmgr: &mut BufferPoolManager) -> Result<(), DbError> {
         let btree = BTree::create(bufmgr)?;
         self.meta_page_id = btree.meta_page_id;
         Ok(())
     }
 
-    pub fn insert(
-        &mut self,
-        bufmgr: &mut BufferPoolManager,
-        record: &[&[u8]],
-    ) -> Result<(), Box<dyn Error>> {
+    pub fn insert(&self, bufmgr: &mut BufferPoolManager, record: &[&[u8]]) -> Result<(), DbError> {
         let btree = BTree::new(self.meta_page_id);
         let mut key = vec![];
         tuple::encode(record[..self.num_key_elems].iter(), &mut key);
@@ -30,58 +29,522 @@ impl Table {
         tuple::encode(record[self.num_key_elems..].iter(), &mut value);
         // Check unique constraints.
         for unique_index in &self.unique_index {
-            if unique_index
-                .search(bufmgr, key.clone())?
-                .next(bufmgr)?
-                .is_some()
-            {
-                return Err("Unique constraint violation".into());
+            if unique_index.contains(bufmgr, record)? {
+                return Err(DbError::UniqueViolation {
+                    index: unique_index.name.clone(),
+                });
             }
         }
         btree.insert(bufmgr, &key, &value)?;
-        for unique_index in &mut self.unique_index {
+        for unique_index in &self.unique_index {
             unique_index.insert(bufmgr, &key, record)?;
         }
         Ok(())
     }
+
+    /// Deletes the row with the primary key of `record` and its entries in the unique indexes.
+    ///
+    /// Only the primary key columns of `record` are used; the index entries are found from the
+    /// stored row, so a stale `record` cannot remove the entries of another row.
+    pub fn delete(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        record: &[impl AsRef<[u8]>],
+    ) -> Result<(), DbError> {
+        self.delete_row(bufmgr, record)?;
+        Ok(())
+    }
+
+    /// Replaces the row with the primary key of `old_record` with `new_record`, which may have
+    /// another primary key. If `new_record` violates a unique constraint, the stored row is put
+    /// back.
+    pub fn update(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        old_record: &[impl AsRef<[u8]>],
+        new_record: &[&[u8]],
+    ) -> Result<(), DbError> {
+        let old_record = self.delete_row(bufmgr, old_record)?;
+        if let Err(err) = self.insert(bufmgr, new_record) {
+            let old_record: Vec<&[u8]> = old_record.iter().map(Vec::as_slice).collect();
+            self.insert(bufmgr, &old_record)?;
+            return Err(err);
+        }
+        Ok(())
+    }
+
+    /// Deletes the row with the primary key of `record` as in [`delete`](Self::delete), and
+    /// returns the row as it was stored.
+    fn delete_row(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        record: &[impl AsRef<[u8]>],
+    ) -> Result<Vec<Vec<u8>>, DbError> {
+        let btree = BTree::new(self.meta_page_id);
+        let mut key = vec![];
+        tuple::encode(
+            record[..self.num_key_elems].iter().map(AsRef::as_ref),
+            &mut key,
+        );
+        let mut iter = btree.search(bufmgr, SearchMode::Key(key.clone()))?;
+        let stored = match iter.next(bufmgr)? {
+            Some((stored_key, value)) if stored_key == key => {
+                let mut stored = vec![];
+                tuple::decode(&stored_key, &mut stored);
+                tuple::decode(&value, &mut stored);
+                stored
+            }
+            _ => return Err(DbError::NotFound("key".to_string())),
+        };
+        // The index entries go first, so that a failure leaves the row reachable by its key.
+        for unique_index in &self.unique_index {
+            unique_index.delete(bufmgr, &stored)?;
+        }
+        btree.delete(bufmgr, &key)?;
+        Ok(stored)
+    }
+
+    /// Fills an empty table and its unique indexes from records sorted by primary key.
+    ///
+    /// The records are streamed into new pages of the table, and only the entries of the
+    /// unique indexes, which are sorted by their own keys, are kept in memory. The new pages
+    /// become part of the table and its B+ tree indexes once every key is checked, so a
+    /// duplicate primary key or unique constraint violation leaves them empty.
+    pub fn bulk_insert<R, E>(
+        &mut self,
+        bufmgr: &mut BufferPoolManager,
+        records: impl IntoIterator<Item = R>,
+        fill_factor: f64,
+    ) -> Result<(), DbError>
+    where
+        R: AsRef<[E]>,
+        E: AsRef<[u8]>,
+    {
+        let btree = BTree::new(self.meta_page_id);
+        let mut index_entries = vec![vec![]; self.unique_index.len()];
+        let pairs = records.into_iter().map(|record| {
+            let record = record.as_ref();
+            let mut key = vec![];
+            tuple::encode(record[..self.num_key_elems].iter(), &mut key);
+            let mut value = vec![];
+            tuple::encode(record[self.num_key_elems..].iter(), &mut value);
+            for (unique_index, entries) in self.unique_index.iter().zip(&mut index_entries) {
+                entries.push((
+                    unique_index.encode_skey(record),
+                    unique_index.encode_value(&key, record),
+                ));
+            }
+            (key, value)
+        });
+        let pending = btree.prepare_load(bufmgr, pairs, fill_factor)?;
+        for (unique_index, entries) in self.unique_index.iter().zip(&mut index_entries) {
+            unique_index.sort_entries(entries)?;
+        }
+        let mut pending_indexes = vec![];
+        for (unique_index, entries) in self.unique_index.iter().zip(index_entries) {
+            match unique_index.kind {
+                IndexKind::BTree => {
+                    let index_btree = BTree::new(unique_index.meta_page_id);
+                    let index_pending = index_btree.prepare_load(bufmgr, entries, fill_factor)?;
+                    pending_indexes.push((index_btree, index_pending));
+                }
+                IndexKind::Hash => unique_index.backfill(bufmgr, entries, fill_factor)?,
+            }
+        }
+        btree.finish_load(bufmgr, pending)?;
+        for (index_btree, index_pending) in pending_indexes {
+            index_btree.finish_load(bufmgr, index_pending)?;
+        }
+        Ok(())
+    }
+
+    /// Builds a unique index called `name` on the `skey` columns over the rows already in the
+    /// table and registers it. Nothing is registered if the rows have duplicate values.
+    pub fn add_unique_index(
+        &mut self,
+        bufmgr: &mut BufferPoolManager,
+        name: &str,
+        skey: Vec<usize>,
+    ) -> Result<(), DbError> {
+        self.add_unique_index_including(bufmgr, name, skey, vec![])
+    }
+
+    /// Like [`add_unique_index`](Self::add_unique_index), but also stores the `include` columns
+    /// in the index so that queries reading only indexed columns never touch the table.
+    pub fn add_unique_index_including(
+        &mut self,
+        bufmgr: &mut BufferPoolManager,
+        name: &str,
+        skey: Vec<usize>,
+        include: Vec<usize>,
+    ) -> Result<(), DbError> {
+        let unique_index = UniqueIndex {
+            name: name.to_string(),
+            kind: IndexKind::BTree,
+            meta_page_id: PageId::INVALID_PAGE_ID,
+            skey,
+            include,
+        };
+        self.add_index(bufmgr, unique_index)
+    }
+
+    /// Like [`add_unique_index`](Self::add_unique_index), but stores the index in a
+    /// [`HashIndex`], which can only be used to look up rows by all of its columns.
+    pub fn add_hash_index(
+        &mut self,
+        bufmgr: &mut BufferPoolManager,
+        name: &str,
+        skey: Vec<usize>,
+    ) -> Result<(), DbError> {
+        let unique_index = UniqueIndex {
+            name: name.to_string(),
+            kind: IndexKind::Hash,
+            meta_page_id: PageId::INVALID_PAGE_ID,
+            skey,
+            include: vec![],
+        };
+        self.add_index(bufmgr, unique_index)
+    }
+
+    fn add_index(
+        &mut self,
+        bufmgr: &mut BufferPoolManager,
+        unique_index: UniqueIndex,
+    ) -> Result<(), DbError> {
+        let mut entries = vec![];
+        let btree = BTree::new(self.meta_page_id);
+        let mut iter = btree.search(bufmgr, SearchMode::Start)?;
+        while let Some((pkey, value)) = iter.next(bufmgr)? {
+            let mut record = vec![];
+            tuple::decode(&pkey, &mut record);
+            tuple::decode(&value, &mut record);
+            entries.push(unique_index.entry(&pkey, &record)?);
+        }
+        unique_index.build(bufmgr, entries, &mut self.unique_index)
+    }
+}
+
+/// A table without a primary key, storing its rows in a [`HeapFile`] in insertion order.
+/// Rows are addressed by [`RecordId`], which the unique indexes map their `skey` columns to.
+///
+/// Suits append-heavy tables like logs, which have no natural key to cluster the rows by.
+pub struct HeapTable {
+    pub meta_page_id: PageId,
+    pub unique_index: Vec<UniqueIndex>,
+}
+
+impl HeapTable {
+    pub fn create(&mut self, bufmgr: &mut BufferPoolManager) -> Result<(), DbError> {
+        let heap = HeapFile::create(bufmgr)?;
+        self.meta_page_id = heap.meta_page_id;
+        Ok(())
+    }
+
+    pub fn insert(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        record: &[&[u8]],
+    ) -> Result<RecordId, DbError> {
+        for unique_index in &self.unique_index {
+            if unique_index.contains(bufmgr, record)? {
+                return Err(DbError::UniqueViolation {
+                    index: unique_index.name.clone(),
+                });
+            }
+        }
+        let mut bytes = vec![];
+        tuple::encode(record.iter(), &mut bytes);
+        let rid = HeapFile::new(self.meta_page_id).insert(bufmgr, &bytes)?;
+        for unique_index in &self.unique_index {
+            unique_index.insert(bufmgr, &rid.to_bytes(), record)?;
+        }
+        Ok(rid)
+    }
+
+    /// Returns the row at `rid`, or `None` if it was deleted.
+    pub fn get(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        rid: RecordId,
+    ) -> Result<Option<Vec<Vec<u8>>>, DbError> {
+        let Some(bytes) = HeapFile::new(self.meta_page_id).get(bufmgr, rid)? else {
+            return Ok(None);
+        };
+        let mut record = vec![];
+        tuple::decode(&bytes, &mut record);
+        Ok(Some(record))
+    }
+
+    /// Deletes the row at `rid` and its entries in the unique indexes.
+    pub fn delete(&self, bufmgr: &mut BufferPoolManager, rid: RecordId) -> Result<(), DbError> {
+        let record = self
+            .get(bufmgr, rid)?
+            .ok_or_else(|| DbError::NotFound("record".to_string()))?;
+        // As in `Table::delete_row`, the index entries go first, so that a failure leaves the
+        // row in place instead of entries pointing to a freed record.
+        for unique_index in &self.unique_index {
+            unique_index.delete(bufmgr, &record)?;
+        }
+        HeapFile::new(self.meta_page_id).delete(bufmgr, rid)?;
+        Ok(())
+    }
+
+    /// Replaces the row at `rid` with `new_record` and returns its new ID, which differs from
+    /// `rid` if the row had to move to another page. If `new_record` violates a unique
+    /// constraint, the row is left as it was.
+    pub fn update(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        rid: RecordId,
+        new_record: &[&[u8]],
+    ) -> Result<RecordId, DbError> {
+        let old_record = self
+            .get(bufmgr, rid)?
+            .ok_or_else(|| DbError::NotFound("record".to_string()))?;
+        for unique_index in &self.unique_index {
+            if unique_index.encode_skey(&old_record) != unique_index.encode_skey(new_record)
+                && unique_index.contains(bufmgr, new_record)?
+            {
+                return Err(DbError::UniqueViolation {
+                    index: unique_index.name.clone(),
+                });
+            }
+        }
+        let mut bytes = vec![];
+        tuple::encode(new_record.iter(), &mut bytes);
+        let new_rid = HeapFile::new(self.meta_page_id).update(bufmgr, rid, &bytes)?;
+        for unique_index in &self.unique_index {
+            unique_index.delete(bufmgr, &old_record)?;
+            unique_index.insert(bufmgr, &new_rid.to_bytes(), new_record)?;
+        }
+        Ok(new_rid)
+    }
+
+    /// Builds a unique index called `name` on the `skey` columns over the rows already in the
+    /// table and registers it. Nothing is registered if the rows have duplicate values.
+    pub fn add_unique_index(
+        &mut self,
+        bufmgr: &mut BufferPoolManager,
+        name: &str,
+        skey: Vec<usize>,
+    ) -> Result<(), DbError> {
+        let unique_index = UniqueIndex {
+            name: name.to_string(),
+            kind: IndexKind::BTree,
+            meta_page_id: PageId::INVALID_PAGE_ID,
+            skey,
+            include: vec![],
+        };
+        let mut entries = vec![];
+        let mut iter = HeapFile::new(self.meta_page_id).scan(bufmgr)?;
+        while let Some((rid, bytes)) = iter.next(bufmgr)? {
+            let mut record = vec![];
+            tuple::decode(&bytes, &mut record);
+            entries.push(unique_index.entry(&rid.to_bytes(), &record)?);
+        }
+        unique_index.build(bufmgr, entries, &mut self.unique_index)
+    }
+
+    /// Returns the ID of the row whose `skey` columns of the unique index `index` are `skey`.
+    pub fn lookup(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        index: usize,
+        skey: &[&[u8]],
+    ) -> Result<Option<RecordId>, DbError> {
+        let mut skey_bytes = vec![];
+        tuple::encode(skey.iter(), &mut skey_bytes);
+        let value = self.unique_index[index].get(bufmgr, &skey_bytes)?;
+        Ok(value.map(|value| {
+            RecordId::from_bytes(&value[..RecordId::SIZE]).expect("index value holds a record id")
+        }))
+    }
 }
 
+/// How a unique index stores its entries.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IndexKind {
+    /// A B+ tree, which can also be scanned in key order.
+    #[default]
+    BTree,
+    /// A [`HashIndex`], which only looks up whole keys but reads fewer pages doing so.
+    Hash,
+}
+
+/// A unique index mapping the `skey` columns of each row to its primary key, or its
+/// [`RecordId`] in a [`HeapTable`], followed by the values of the `include` columns.
 pub struct UniqueIndex {
+    /// The name reported when a row violates the index.
+    pub name: String,
+    pub kind: IndexKind,
     pub meta_page_id: PageId,
     pub skey: Vec<usize>,
+    pub include: Vec<usize>,
 }
 
 impl UniqueIndex {
-    pub fn create(&mut self, bufmgr: &mut BufferPoolManager) -> Result<(), Box<dyn Error>> {
-        let btree = BTree::create(bufmgr)?;
-        self.meta_page_id = btree.meta_page_id;
+    pub fn create(&mut self, bufmgr: &mut BufferPoolManager) -> Result<(), DbError> {
+        self.meta_page_id = match self.kind {
+            IndexKind::BTree => BTree::create(bufmgr)?.meta_page_id,
+            IndexKind::Hash => HashIndex::create(bufmgr)?.meta_page_id,
+        };
         Ok(())
     }
 
     pub fn insert(
-        &mut self,
+        &self,
         bufmgr: &mut BufferPoolManager,
         pkey: &[u8],
         record: &[impl AsRef<[u8]>],
-    ) -> Result<(), Box<dyn Error>> {
-        let btree = BTree::new(self.meta_page_id);
+    ) -> Result<(), DbError> {
+        let skey = self.encode_skey(record);
+        let value = self.encode_value(pkey, record);
+        match self.kind {
+            IndexKind::BTree => BTree::new(self.meta_page_id).insert(bufmgr, &skey, &value)?,
+            IndexKind::Hash => HashIndex::new(self.meta_page_id).insert(bufmgr, &skey, &value)?,
+        }
+        Ok(())
+    }
+
+    pub fn delete(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        record: &[impl AsRef<[u8]>],
+    ) -> Result<(), DbError> {
+        let skey = self.encode_skey(record);
+        match self.kind {
+            IndexKind::BTree => BTree::new(self.meta_page_id).delete(bufmgr, &skey)?,
+            IndexKind::Hash => HashIndex::new(self.meta_page_id).delete(bufmgr, &skey)?,
+        }
+        Ok(())
+    }
+
+    /// Returns an error if `record` does not have every `skey` and `include` column.
+    pub fn check_columns(&self, record: &[impl AsRef<[u8]>]) -> Result<(), DbError> {
+        match self
+            .skey
+            .iter()
+            .chain(&self.include)
+            .find(|&&index| index >= record.len())
+        {
+            Some(index) => Err(DbError::NotFound(format!("column {index}"))),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the `(skey, value)` entry of the row with `row_id` (its encoded primary key or
+    /// record id) for [`build`](Self::build), failing if `record` lacks an indexed column.
+    pub fn entry(
+        &self,
+        row_id: &[u8],
+        record: &[impl AsRef<[u8]>],
+    ) -> Result<(Vec<u8>, Vec<u8>), DbError> {
+        self.check_columns(record)?;
+        Ok((self.encode_skey(record), self.encode_value(row_id, record)))
+    }
+
+    /// Creates the index over the rows of a table from their [`entry`](Self::entry), fills it
+    /// and registers it in `indexes`. The entries are checked first, so rows with duplicate
+    /// values leave nothing allocated, as pages are never freed, and nothing registered.
+    pub fn build(
+        mut self,
+        bufmgr: &mut BufferPoolManager,
+        mut entries: Vec<(Vec<u8>, Vec<u8>)>,
+        indexes: &mut Vec<UniqueIndex>,
+    ) -> Result<(), DbError> {
+        self.sort_entries(&mut entries)?;
+        self.create(bufmgr)?;
+        self.backfill(bufmgr, entries, 1.0)?;
+        indexes.push(self);
+        Ok(())
+    }
+
+    /// Sorts `(skey, value)` entries for [`backfill`](Self::backfill), failing if two of them
+    /// have the same `skey`.
+    pub fn sort_entries(&self, entries: &mut [(Vec<u8>, Vec<u8>)]) -> Result<(), DbError> {
+        entries.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        if entries.windows(2).any(|pair| pair[0].0 == pair[1].0) {
+            return Err(DbError::UniqueViolation {
+                index: self.name.clone(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Fills the empty index from `(skey, value)` entries sorted by
+    /// [`sort_entries`](Self::sort_entries).
+    pub fn backfill(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        entries: Vec<(Vec<u8>, Vec<u8>)>,
+        fill_factor: f64,
+    ) -> Result<(), DbError> {
+        match self.kind {
+            IndexKind::BTree => BTree::new(self.meta_page_id).load(bufmgr, entries, fill_factor)?,
+            IndexKind::Hash => {
+                let hash_index = HashIndex::new(self.meta_page_id);
+                for (skey, value) in entries {
+                    hash_index.insert(bufmgr, &skey, &value)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns whether a row with the same `skey` values as `record` is already indexed.
+    pub fn contains(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        record: &[impl AsRef<[u8]>],
+    ) -> Result<bool, DbError> {
+        let skey = self.encode_skey(record);
+        Ok(self.get(bufmgr, &skey)?.is_some())
+    }
+
+    /// Returns the index value of the row with the encoded `skey` values, if any.
+    pub fn get(
+        &self,
+        bufmgr: &mut BufferPoolManager,
+        skey: &[u8],
+    ) -> Result<Option<Vec<u8>>, DbError> {
+        match self.kind {
+            IndexKind::BTree => {
+                let btree = BTree::new(self.meta_page_id);
+                let mut iter = btree.search(bufmgr, SearchMode::Key(skey.to_vec()))?;
+                Ok(iter
+                    .next(bufmgr)?
+                    .and_then(|(key, value)| (key == skey).then_some(value)))
+            }
+            IndexKind::Hash => Ok(HashIndex::new(self.meta_page_id).get(bufmgr, skey)?),
+        }
+    }
+
+    pub fn encode_skey(&self, record: &[impl AsRef<[u8]>]) -> Vec<u8> {
         let mut skey = vec![];
         tuple::encode(
             self.skey.iter().map(|&index| record[index].as_ref()),
             &mut skey,
         );
-        btree.insert(bufmgr, &skey, pkey)?;
-        Ok(())
+        skey
     }
 
-    pub fn search(
-        &self,
-        bufmgr: &mut BufferPoolManager,
-        skey: Vec<u8>,
-    ) -> Result<Iter, Box<dyn Error>> {
-        let btree = BTree::new(self.meta_page_id);
-        let iter = btree.search(bufmgr, SearchMode::Key(skey))?;
-        Ok(iter)
+    /// Encodes the index value of `record`: its encoded primary key or record ID `pkey`
+    /// followed by the `include` columns.
+    pub fn encode_value(&self, pkey: &[u8], record: &[impl AsRef<[u8]>]) -> Vec<u8> {
+        let mut value = pkey.to_vec();
+        tuple::encode(
+            self.include.iter().map(|&index| record[index].as_ref()),
+            &mut value,
+        );
+        value
+    }
+
+    /// Returns whether the index holds every one of `columns`, given the number of primary key
+    /// columns of the table.
+    pub fn covers(&self, num_key_elems: usize, columns: &[usize]) -> bool {
+        columns.iter().all(|column| {
+            *column < num_key_elems || self.skey.contains(column) || self.include.contains(column)
+        })
     }
 }
 
@@ -174,8 +637,11 @@ mod tests {
         table.create(&mut bufmgr).unwrap();
 
         let mut unique_index = UniqueIndex {
+            kind: IndexKind::BTree,
+            name: "users_first_name_last_name_key".to_string(),
             meta_page_id: PageId::INVALID_PAGE_ID,
             skey: vec![1, 2],
+            include: vec![],
         };
 
         unique_index.create(&mut bufmgr).unwrap();
@@ -253,8 +719,11 @@ mod tests {
         table.create(&mut bufmgr).unwrap();
 
         let mut unique_index = UniqueIndex {
+            kind: IndexKind::BTree,
+            name: "users_first_name_last_name_key".to_string(),
             meta_page_id: PageId::INVALID_PAGE_ID,
             skey: vec![1, 2],
+            include: vec![],
         };
 
         unique_index.create(&mut bufmgr).unwrap();
@@ -275,9 +744,319 @@ mod tests {
             .unwrap();
 
         // Try to insert a record with a duplicate unique key.
-        assert!(table
+        let err = table
             .insert(&mut bufmgr, &[b"e", b"Charlie", b"MUNGER"])
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            DbError::UniqueViolation { index } if index == "users_first_name_last_name_key"
+        ));
+        // A duplicate primary key is reported by the tree.
+        assert!(matches!(
+            table.insert(&mut bufmgr, &[b"a", b"Bruce", b"LEE"]),
+            Err(DbError::DuplicateKey)
+        ));
+    }
+
+    #[test]
+    fn test_table_bulk_insert() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let buffer_pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, buffer_pool);
+
+        let mut table = Table {
+            meta_page_id: PageId::INVALID_PAGE_ID,
+            num_key_elems: 1,
+            unique_index: vec![],
+        };
+        table.create(&mut bufmgr).unwrap();
+
+        let mut unique_index = UniqueIndex {
+            kind: IndexKind::BTree,
+            name: "users_first_name_key".to_string(),
+            meta_page_id: PageId::INVALID_PAGE_ID,
+            skey: vec![1],
+            include: vec![],
+        };
+        unique_index.create(&mut bufmgr).unwrap();
+        table.unique_index.push(unique_index);
+
+        let records = (0u64..1000).map(|i| {
+            let name = format!("name-{}", 999 - i).into_bytes();
+            vec![i.to_be_bytes().to_vec(), name]
+        });
+        table.bulk_insert(&mut bufmgr, records, 1.0).unwrap();
+
+        let btree = BTree::new(table.meta_page_id);
+        let mut iter = btree.search(&mut bufmgr, SearchMode::Start).unwrap();
+        for i in 0u64..1000 {
+            let (key, _) = iter.next(&mut bufmgr).unwrap().unwrap();
+            assert_eq!(key, get_encoded(&[&i.to_be_bytes()]));
+        }
+        assert!(iter.next(&mut bufmgr).unwrap().is_none());
+
+        let pkey = table.unique_index[0]
+            .get(&mut bufmgr, &get_encoded(&[b"name-0"]))
+            .unwrap();
+        assert_eq!(Some(get_encoded(&[&999u64.to_be_bytes()])), pkey);
+    }
+
+    #[test]
+    fn test_table_bulk_insert_unique_violation() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let buffer_pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, buffer_pool);
+
+        let mut table = Table {
+            meta_page_id: PageId::INVALID_PAGE_ID,
+            num_key_elems: 1,
+            unique_index: vec![],
+        };
+        table.create(&mut bufmgr).unwrap();
+        table
+            .add_unique_index(&mut bufmgr, "users_first_name_key", vec![1])
+            .unwrap();
+        table
+            .add_unique_index(&mut bufmgr, "users_last_name_key", vec![2])
+            .unwrap();
+
+        let records = [
+            [&b"a"[..], b"Charlie", b"MUNGER"],
+            [b"b", b"Brian", b"LEE"],
+            [b"c", b"Alice", b"LEE"],
+        ];
+        assert!(matches!(
+            table.bulk_insert(&mut bufmgr, records, 1.0),
+            Err(DbError::UniqueViolation { index }) if index == "users_last_name_key"
+        ));
+
+        // Neither the table nor the first index was filled.
+        let btree = BTree::new(table.meta_page_id);
+        let mut iter = btree.search(&mut bufmgr, SearchMode::Start).unwrap();
+        assert!(iter.next(&mut bufmgr).unwrap().is_none());
+        assert!(!table.unique_index[0]
+            .contains(&mut bufmgr, &[&b"a"[..], b"Charlie", b"MUNGER"])
+            .unwrap());
+        table
+            .bulk_insert(&mut bufmgr, [[&b"a"[..], b"Charlie", b"MUNGER"]], 1.0)
+            .unwrap();
+    }
+
+    #[test]
+    fn test_add_unique_index() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let buffer_pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, buffer_pool);
+
+        let mut table = Table {
+            meta_page_id: PageId::INVALID_PAGE_ID,
+            num_key_elems: 1,
+            unique_index: vec![],
+        };
+        table.create(&mut bufmgr).unwrap();
+
+        table
+            .insert(&mut bufmgr, &[b"a", b"Charlie", b"MUNGER"])
+            .unwrap();
+        table
+            .insert(&mut bufmgr, &[b"b", b"Brian", b"LEE"])
+            .unwrap();
+        table
+            .insert(&mut bufmgr, &[b"c", b"Alice", b"LEE"])
+            .unwrap();
+
+        // Last names are not unique, so nothing is registered or allocated.
+        let num_pages = bufmgr.num_pages();
+        assert!(table
+            .add_unique_index(&mut bufmgr, "users_last_name_key", vec![2])
+            .is_err());
+        assert!(matches!(
+            table.add_unique_index_including(&mut bufmgr, "users_key", vec![1], vec![3]),
+            Err(DbError::NotFound(column)) if column == "column 3"
+        ));
+        assert!(table.unique_index.is_empty());
+        assert_eq!(num_pages, bufmgr.num_pages());
+
+        table
+            .add_unique_index(&mut bufmgr, "users_first_name_key", vec![1])
+            .unwrap();
+        let btree = BTree::new(table.unique_index[0].meta_page_id);
+        let mut iter = btree.search(&mut bufmgr, SearchMode::Start).unwrap();
+        assert_eq!(
+            iter.next(&mut bufmgr).unwrap().unwrap(),
+            (get_encoded(&[b"Alice"]), get_encoded(&[b"c"]))
+        );
+        assert_eq!(
+            iter.next(&mut bufmgr).unwrap().unwrap(),
+            (get_encoded(&[b"Brian"]), get_encoded(&[b"b"]))
+        );
+        assert_eq!(
+            iter.next(&mut bufmgr).unwrap().unwrap(),
+            (get_encoded(&[b"Charlie"]), get_encoded(&[b"a"]))
+        );
+        assert!(iter.next(&mut bufmgr).unwrap().is_none());
+
+        // Rows inserted afterwards are checked against the new index.
+        assert!(table
+            .insert(&mut bufmgr, &[b"d", b"Alice", b"SMITH"])
             .is_err());
+        table
+            .insert(&mut bufmgr, &[b"d", b"John", b"BAKERY"])
+            .unwrap();
+    }
+
+    #[test]
+    fn test_add_unique_index_including() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let buffer_pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, buffer_pool);
+
+        let mut table = Table {
+            meta_page_id: PageId::INVALID_PAGE_ID,
+            num_key_elems: 1,
+            unique_index: vec![],
+        };
+        table.create(&mut bufmgr).unwrap();
+        table
+            .insert(&mut bufmgr, &[b"b", b"Brian", b"LEE"])
+            .unwrap();
+        table
+            .add_unique_index_including(&mut bufmgr, "users_last_name_key", vec![2], vec![1])
+            .unwrap();
+        table
+            .insert(&mut bufmgr, &[b"a", b"Charlie", b"MUNGER"])
+            .unwrap();
+
+        // The values hold the primary key followed by the included columns.
+        let btree = BTree::new(table.unique_index[0].meta_page_id);
+        let mut iter = btree.search(&mut bufmgr, SearchMode::Start).unwrap();
+        assert_eq!(
+            iter.next(&mut bufmgr).unwrap().unwrap(),
+            (get_encoded(&[b"LEE"]), get_encoded(&[b"b", b"Brian"]))
+        );
+        assert_eq!(
+            iter.next(&mut bufmgr).unwrap().unwrap(),
+            (get_encoded(&[b"MUNGER"]), get_encoded(&[b"a", b"Charlie"]))
+        );
+        assert!(iter.next(&mut bufmgr).unwrap().is_none());
+        assert!(table.unique_index[0].covers(1, &[0, 1, 2]));
+    }
+
+    #[test]
+    fn test_table_delete_and_update() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let buffer_pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, buffer_pool);
+
+        let mut table = Table {
+            meta_page_id: PageId::INVALID_PAGE_ID,
+            num_key_elems: 1,
+            unique_index: vec![],
+        };
+        table.create(&mut bufmgr).unwrap();
+        table
+            .add_unique_index(&mut bufmgr, "users_first_name_key", vec![1])
+            .unwrap();
+        let charlie: [&[u8]; 3] = [b"a", b"Charlie", b"MUNGER"];
+        let brian: [&[u8]; 3] = [b"b", b"Brian", b"LEE"];
+        table.insert(&mut bufmgr, &charlie).unwrap();
+        table.insert(&mut bufmgr, &brian).unwrap();
+
+        table.delete(&mut bufmgr, &charlie).unwrap();
+        assert!(table.delete(&mut bufmgr, &charlie).is_err());
+        // The unique key of the deleted row is free again.
+        table
+            .insert(&mut bufmgr, &[&b"c"[..], b"Charlie", b"CHAPLIN"])
+            .unwrap();
+
+        // A failed update leaves the old row in place.
+        assert!(table
+            .update(&mut bufmgr, &brian, &[b"b", b"Charlie", b"LEE"])
+            .is_err());
+        table
+            .update(&mut bufmgr, &brian, &[b"d", b"Bruce", b"LEE"])
+            .unwrap();
+
+        let btree = BTree::new(table.meta_page_id);
+        let mut iter = btree.search(&mut bufmgr, SearchMode::Start).unwrap();
+        assert_eq!(
+            iter.next(&mut bufmgr).unwrap().unwrap(),
+            (get_encoded(&[b"c"]), get_encoded(&[b"Charlie", b"CHAPLIN"]))
+        );
+        assert_eq!(
+            iter.next(&mut bufmgr).unwrap().unwrap(),
+            (get_encoded(&[b"d"]), get_encoded(&[b"Bruce", b"LEE"]))
+        );
+        assert!(iter.next(&mut bufmgr).unwrap().is_none());
+        assert!(!table.unique_index[0].contains(&mut bufmgr, &brian).unwrap());
+
+        // The index entries come from the stored row, not from the stale record passed in.
+        table
+            .delete(&mut bufmgr, &[&b"c"[..], b"Bruce", b"LEE"])
+            .unwrap();
+        let bruce: [&[u8]; 3] = [b"d", b"Bruce", b"LEE"];
+        assert!(table.unique_index[0].contains(&mut bufmgr, &bruce).unwrap());
+        assert!(!table.unique_index[0]
+            .contains(&mut bufmgr, &[&b"c"[..], b"Charlie", b"CHAPLIN"])
+            .unwrap());
+        table
+            .update(
+                &mut bufmgr,
+                &[&b"d"[..], b"Charlie", b"MUNGER"],
+                &[b"d", b"Bruce", b"WAYNE"],
+            )
+            .unwrap();
+        assert!(table.unique_index[0].contains(&mut bufmgr, &bruce).unwrap());
+    }
+
+    #[test]
+    fn test_heap_table() {
+        let disk = DiskManager::new(tempfile().unwrap()).unwrap();
+        let buffer_pool = BufferPool::new(10);
+        let mut bufmgr = BufferPoolManager::new(disk, buffer_pool);
+
+        let mut table = HeapTable {
+            meta_page_id: PageId::INVALID_PAGE_ID,
+            unique_index: vec![],
+        };
+        table.create(&mut bufmgr).unwrap();
+        let login = table
+            .insert(&mut bufmgr, &[b"1", b"login", b"alice"])
+            .unwrap();
+        let logout = table
+            .insert(&mut bufmgr, &[b"2", b"logout", b"alice"])
+            .unwrap();
+        // Users are not unique, so nothing is registered or allocated.
+        let num_pages = bufmgr.num_pages();
+        assert!(matches!(
+            table.add_unique_index(&mut bufmgr, "events_user_key", vec![2]),
+            Err(DbError::UniqueViolation { .. })
+        ));
+        assert!(table.unique_index.is_empty());
+        assert_eq!(num_pages, bufmgr.num_pages());
+        table
+            .add_unique_index(&mut bufmgr, "events_id_key", vec![0])
+            .unwrap();
+        assert_eq!(Some(logout), table.lookup(&mut bufmgr, 0, &[b"2"]).unwrap());
+        assert!(matches!(
+            table.insert(&mut bufmgr, &[b"1", b"login", b"bob"]),
+            Err(DbError::UniqueViolation { .. })
+        ));
+
+        table.delete(&mut bufmgr, login).unwrap();
+        assert_eq!(None, table.get(&mut bufmgr, login).unwrap());
+        assert_eq!(None, table.lookup(&mut bufmgr, 0, &[b"1"]).unwrap());
+        // Deleting a row leaves the ID of the next one valid.
+        assert_eq!(
+            Some(vec![b"2".to_vec(), b"logout".to_vec(), b"alice".to_vec()]),
+            table.get(&mut bufmgr, logout).unwrap()
+        );
+
+        let rid = table
+            .update(&mut bufmgr, logout, &[b"3", b"logout", b"alice"])
+            .unwrap();
+        assert_eq!(None, table.lookup(&mut bufmgr, 0, &[b"2"]).unwrap());
+        assert_eq!(Some(rid), table.lookup(&mut bufmgr, 0, &[b"3"]).unwrap());
     }
 
     fn get_encoded(record: &[&[u8]]) -> Vec<u8> {
@@ -18,6 +18,34 @@ pub fn decode(bytes: &[u8], elems: &mut Vec<Vec<u8>>) {
     }
 }
 
+/// Decodes like [`decode`], but only copies the elements whose index in `elems` is in
+/// `columns`. The others are left empty, and decoding stops after the last of `columns`.
+pub fn decode_columns(bytes: &[u8], elems: &mut Vec<Vec<u8>>, columns: &[usize]) {
+    let Some(&last) = columns.iter().max() else {
+        return;
+    };
+    let mut rest = bytes;
+    while !rest.is_empty() && elems.len() <= last {
+        let mut elem = vec![];
+        if columns.contains(&elems.len()) {
+            internal::decode(&mut rest, &mut elem);
+        } else {
+            internal::skip(&mut rest);
+        }
+        elems.push(elem);
+    }
+}
+
+/// Encodes an integer so that the encoded bytes compare in the same order as the integers.
+pub fn encode_int(n: i64) -> [u8; 8] {
+    ((n as u64) ^ (1 << 63)).to_be_bytes()
+}
+
+pub fn decode_int(bytes: &[u8]) -> Option<i64> {
+    let arr = bytes.try_into().ok()?;
+    Some((u64::from_be_bytes(arr) ^ (1 << 63)) as i64)
+}
+
 pub struct Pretty<'a, T>(pub &'a [T]);
 
 impl<'a, T: AsRef<[u8]>> Debug for Pretty<'a, T> {
@@ -77,6 +105,16 @@ mod internal {
         }
     }
 
+    pub fn skip(src: &mut &[u8]) {
+        loop {
+            let extra = src[ESCAPE_LENGTH - 1];
+            *src = &src[ESCAPE_LENGTH..];
+            if extra < ESCAPE_LENGTH as u8 {
+                break;
+            }
+        }
+    }
+
     #[cfg(test)]
     mod tests {
         use super::*;
@@ -101,3 +139,37 @@ mod internal {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_columns() {
+        let mut bytes = vec![];
+        encode(["alpha", "bravo", "charlie", "delta"].iter(), &mut bytes);
+        let mut elems = vec![b"key".to_vec()];
+        decode_columns(&bytes, &mut elems, &[2, 3]);
+        assert_eq!(
+            vec![
+                b"key".to_vec(),
+                vec![],
+                b"bravo".to_vec(),
+                b"charlie".to_vec()
+            ],
+            elems
+        );
+    }
+
+    #[test]
+    fn test_int() {
+        let ints = [i64::MIN, -300, -1, 0, 1, 255, 256, i64::MAX];
+        for pair in ints.windows(2) {
+            assert!(encode_int(pair[0]) < encode_int(pair[1]));
+        }
+        for n in ints {
+            assert_eq!(Some(n), decode_int(&encode_int(n)));
+        }
+        assert_eq!(None, decode_int(b"short"));
+    }
+}